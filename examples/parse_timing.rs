@@ -0,0 +1,36 @@
+//! Measures how long parsing long expressions takes, e.g.
+//! `cargo run --release --example parse_timing`.
+
+use rust_expression::Calculator;
+
+use std::time::Duration;
+
+const RUNS: u32 = 200;
+
+/// `terms` sums of products and powers with nested parentheses
+fn expression(terms: usize) -> String {
+    (0..terms)
+        .map(|i| format!("({}.5 * x - 2x ^ 2 / (1 + {}))", i, i % 7))
+        .collect::<Vec<_>>()
+        .join(" + ")
+}
+
+fn main() {
+    let mut calc = Calculator::new();
+    calc.execute("x := 0.5").unwrap();
+    calc.set_timing(true);
+    for terms in [10, 100, 1000] {
+        let line = expression(terms);
+        let mut parse = Duration::ZERO;
+        for _ in 0..RUNS {
+            calc.execute(&line).unwrap();
+            parse += calc.last_timing().unwrap().parse;
+        }
+        println!(
+            "{:>5} terms, {:>6} characters: {:>10.1?} per parse",
+            terms,
+            line.len(),
+            parse / RUNS
+        );
+    }
+}
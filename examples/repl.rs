@@ -1,38 +1,66 @@
-use rust_expression::{Area, Calculator, Graph, Value};
+use rust_expression::{Area, Calculator, Graph, Number, Value};
 
-use linefeed::{Interface, ReadResult};
+use linefeed::{Completer, Completion, Interface, Prompter, ReadResult, Terminal};
 
 use std::io;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
-fn draw(graph: &Graph) {
-    const WIDTH: usize = 60;
-    const HEIGHT: usize = 25;
+const WIDTH: usize = 60;
+const HEIGHT: usize = 25;
 
+fn draw(graph: &Graph) {
     let area = Area::new(-100., -100., 100., 100.);
-    let screen = Area::new(0., 0., WIDTH as f64, HEIGHT as f64);
-    let plot = graph.plot(&area, &screen);
+    // terminal characters are about twice as high as wide
+    let screen = Area::new(0., 0., WIDTH as Number * 8., HEIGHT as Number * 16.);
+    match graph.plot(&area, &screen) {
+        Ok(plot) => print!("{}", plot.render_ascii(WIDTH, HEIGHT)),
+        Err(err) => println!("{:?}", err),
+    }
+}
 
-    match plot {
-        Ok(plot) => {
-            let mut chart = vec![vec![' '; WIDTH]; HEIGHT];
+/// names and keywords of the calculator for tab completion,
+/// functions are listed with their signature
+struct CalcCompleter(Mutex<Vec<Completion>>);
+
+impl CalcCompleter {
+    fn update(&self, calc: &Calculator) {
+        *self.0.lock().unwrap() = calc
+            .complete("", 0)
+            .into_iter()
+            .map(|completion| Completion {
+                display: calc.hint(&completion.text),
+                ..Completion::simple(completion.text)
+            })
+            .collect();
+    }
+}
 
-            for w in 0..WIDTH {
-                let h = plot.points[w];
-                if let Some(h) = h {
-                    chart[HEIGHT - (h as usize)][w] = '*';
-                }
-            }
+impl<Term: Terminal> Completer<Term> for CalcCompleter {
+    fn complete(
+        &self,
+        word: &str,
+        _prompter: &Prompter<Term>,
+        _start: usize,
+        _end: usize,
+    ) -> Option<Vec<Completion>> {
+        let names = self.0.lock().unwrap();
+        Some(
+            names
+                .iter()
+                .filter(|name| name.completion.starts_with(word))
+                .cloned()
+                .collect(),
+        )
+    }
 
-            for line in chart {
-                let mut s = String::with_capacity(WIDTH as usize);
-                for ch in line {
-                    s.push(ch);
-                }
-                println!("{}", s);
-            }
-        }
-        Err(err) => println!("{:?}", err),
+    /// names are completed after operators as well, e.g. `2*pi`
+    fn word_start(&self, line: &str, end: usize, _prompter: &Prompter<Term>) -> usize {
+        line[..end]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+            .last()
+            .map_or(end, |(start, _)| start)
     }
 }
 
@@ -41,11 +69,14 @@ fn main() -> io::Result<()> {
 
     println!("This is the rust-expression repl program.");
     println!("Press Ctrl-D or \"quit\" to exit.");
-    println!("");
+    println!();
 
     interface.set_prompt("% > ")?;
 
     let mut calc = Calculator::new();
+    let completer = Arc::new(CalcCompleter(Mutex::new(Vec::new())));
+    completer.update(&calc);
+    interface.set_completer(completer.clone());
 
     while let ReadResult::Input(line) = interface.read_line()? {
         if !line.trim().is_empty() {
@@ -56,12 +87,37 @@ fn main() -> io::Result<()> {
             break;
         }
 
-        match calc.execute(&line) {
-            Ok(Value::Number(num)) => println!("{:}", num),
+        if let Some(name) = line.trim().strip_prefix("show ") {
+            match calc.show(name.trim()) {
+                Some(definition) => println!("{}", definition),
+                None => println!("Error: nothing to show for `{}`", name.trim()),
+            }
+            continue;
+        }
+
+        let result = calc.execute(&line);
+        completer.update(&calc);
+        match result {
             Ok(Value::Void) => (),
-            Ok(Value::Solved { variable, value }) => println!("{:} = {:}", variable, value),
             Ok(Value::Graph(graph)) => draw(&graph),
-            Err(err) => println!("Error: {:}", err),
+            Ok(Value::PlotSequence(sequence)) => {
+                for (value, graph) in sequence.frames() {
+                    println!("{} = {}", sequence.param(), value);
+                    draw(graph);
+                }
+            }
+            Ok(value) => println!("{}", value),
+            Err(err) => {
+                // underline the offending part of the input below the prompt
+                if let Some(span) = err.diagnostic().span {
+                    println!(
+                        "    {}{}",
+                        " ".repeat(line[..span.start].chars().count()),
+                        "^".repeat(line[span.start..span.end].chars().count().max(1))
+                    );
+                }
+                println!("Error: {:}", err)
+            }
         }
     }
 
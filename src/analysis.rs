@@ -0,0 +1,236 @@
+//! Zeros, extrema and inflection points of a graph, found by sampling and refinement.
+//!
+//! A point is only found if the samples show it, e.g. two zeros closer than the
+//! sample distance may be missed.
+
+use crate::ast::Number;
+use crate::graph::Range;
+
+/// number of intervals a range is sampled with
+const SAMPLES: usize = 1000;
+/// steps of the bisection and golden-section search
+const REFINE_STEPS: usize = 60;
+/// size relative to the largest value below which a second difference is treated as noise
+const NOISE: Number = Number::EPSILON * 64.0;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PointKind {
+    Zero,
+    Minimum,
+    Maximum,
+    Inflection,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct CriticalPoint {
+    pub kind: PointKind,
+    pub x: Number,
+    pub y: Number,
+}
+
+/// equidistant samples of `range` including both ends
+pub(crate) fn samples(range: &Range) -> Vec<Number> {
+    let step = range.get_distance() / SAMPLES as Number;
+    (0..=SAMPLES)
+        .map(|idx| range.min + idx as Number * step)
+        .collect()
+}
+
+fn finite(y: Option<Number>) -> Option<Number> {
+    y.filter(|y| y.is_finite())
+}
+
+/// root of `f` between `lo` and `hi`, where `f(lo)` has the sign of `f_lo`
+fn bisect(
+    f: &dyn Fn(Number) -> Option<Number>,
+    lo: Number,
+    hi: Number,
+    f_lo: Number,
+) -> Option<Number> {
+    let (mut lo, mut hi) = (lo, hi);
+    for _ in 0..REFINE_STEPS {
+        let mid = (lo + hi) / 2.0;
+        let f_mid = finite(f(mid))?;
+        if f_mid == 0.0 {
+            return Some(mid);
+        }
+        if (f_mid < 0.0) == (f_lo < 0.0) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    Some((lo + hi) / 2.0)
+}
+
+/// position of the largest value of `f` between `lo` and `hi`
+fn golden_section(f: &dyn Fn(Number) -> Option<Number>, lo: Number, hi: Number) -> Option<Number> {
+    let ratio = (5.0 as Number).sqrt() / 2.0 - 0.5;
+    let (mut lo, mut hi) = (lo, hi);
+    for _ in 0..REFINE_STEPS {
+        let left = hi - ratio * (hi - lo);
+        let right = lo + ratio * (hi - lo);
+        if finite(f(left))? > finite(f(right))? {
+            hi = right;
+        } else {
+            lo = left;
+        }
+    }
+    Some((lo + hi) / 2.0)
+}
+
+/// Critical points of `f`, which has the values `ys` at the sorted `xs`, ordered by `x`.
+pub(crate) fn critical_points(
+    f: &dyn Fn(Number) -> Option<Number>,
+    xs: &[Number],
+    ys: &[Option<Number>],
+) -> Vec<CriticalPoint> {
+    let ys: Vec<_> = ys.iter().map(|y| finite(*y)).collect();
+    // intervals between samples with a pole, `poles[idx]` is between `idx` and `idx + 1`
+    let mut poles = vec![false; xs.len()];
+    let mut points = Vec::new();
+    let mut push = |kind, x: Number| {
+        if let Some(y) = finite(f(x)) {
+            points.push(CriticalPoint { kind, x, y });
+        }
+    };
+
+    for idx in 0..xs.len() {
+        match (ys[idx], ys.get(idx + 1).copied().flatten()) {
+            (Some(0.0), _) => push(PointKind::Zero, xs[idx]),
+            (Some(a), Some(b)) if (a < 0.0) != (b < 0.0) && b != 0.0 => {
+                // a pole also changes the sign, but not towards zero
+                let root = bisect(f, xs[idx], xs[idx + 1], a);
+                if let Some(x) =
+                    root.filter(|x| finite(f(*x)).is_some_and(|y| y.abs() <= a.abs().min(b.abs())))
+                {
+                    push(PointKind::Zero, x);
+                } else {
+                    poles[idx] = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    for idx in 1..xs.len().saturating_sub(1) {
+        if poles[idx - 1] || poles[idx] {
+            // a sample close to a pole looks like an extremum
+            continue;
+        }
+        if let (Some(a), Some(b), Some(c)) = (ys[idx - 1], ys[idx], ys[idx + 1]) {
+            let (lo, hi) = (xs[idx - 1], xs[idx + 1]);
+            if a < b && b > c {
+                push(
+                    PointKind::Maximum,
+                    golden_section(f, lo, hi).unwrap_or(xs[idx]),
+                );
+            } else if a > b && b < c {
+                let neg = |x| f(x).map(|y| -y);
+                push(
+                    PointKind::Minimum,
+                    golden_section(&neg, lo, hi).unwrap_or(xs[idx]),
+                );
+            }
+        }
+    }
+
+    // sign changes of the second differences, skipping those too small to tell from noise
+    let h = xs.get(1).map_or(0.0, |x1| x1 - xs[0]);
+    let second = |x: Number| Some(f(x - h)? - 2.0 * f(x)? + f(x + h)?);
+    let scale = ys
+        .iter()
+        .flatten()
+        .fold(0.0 as Number, |scale, y| scale.max(y.abs()));
+    let mut last: Option<(usize, Number)> = None;
+    for idx in 1..xs.len().saturating_sub(1) {
+        if let (Some(a), Some(b), Some(c)) = (ys[idx - 1], ys[idx], ys[idx + 1]) {
+            let d2 = a - 2.0 * b + c;
+            if d2.abs() <= NOISE * scale {
+                continue;
+            }
+            if let Some((prev_idx, prev)) = last {
+                if (prev < 0.0) != (d2 < 0.0) {
+                    // near a pole the function grows beyond the samples
+                    let bound = ys[prev_idx..=idx]
+                        .iter()
+                        .flatten()
+                        .fold(0.0 as Number, |bound, y| bound.max(y.abs()));
+                    let root = bisect(&second, xs[prev_idx], xs[idx], prev);
+                    if let Some(x) =
+                        root.filter(|x| finite(f(*x)).is_some_and(|y| y.abs() <= bound))
+                    {
+                        push(PointKind::Inflection, x);
+                    }
+                }
+            }
+            last = Some((idx, d2));
+        } else {
+            last = None;
+        }
+    }
+
+    points.sort_by(|a, b| a.x.partial_cmp(&b.x).unwrap());
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::number::TEST_TOLERANCE;
+
+    fn analyze(
+        f: &dyn Fn(Number) -> Option<Number>,
+        min: Number,
+        max: Number,
+    ) -> Vec<CriticalPoint> {
+        let xs = samples(&Range::new(min, max));
+        let ys: Vec<_> = xs.iter().map(|x| f(*x)).collect();
+        critical_points(f, &xs, &ys)
+    }
+
+    fn kinds(points: &[CriticalPoint]) -> Vec<PointKind> {
+        points.iter().map(|point| point.kind).collect()
+    }
+
+    #[test]
+    fn cubic() {
+        // zeros at -1, 0 and 1, extrema at -+1/sqrt(3), inflection at 0
+        let f = |x: Number| Some(x * x * x - x);
+        let points = analyze(&f, -2.05, 1.95);
+        let third = (1.0 as Number / 3.0).sqrt();
+        let expected = [
+            (PointKind::Zero, -1.0),
+            (PointKind::Maximum, -third),
+            (PointKind::Zero, 0.0),
+            (PointKind::Inflection, 0.0),
+            (PointKind::Minimum, third),
+            (PointKind::Zero, 1.0),
+        ];
+        assert_eq!(expected.len(), points.len(), "{:?}", points);
+        for (kind, x) in expected {
+            assert!(
+                points
+                    .iter()
+                    .any(|point| point.kind == kind && (point.x - x).abs() < TEST_TOLERANCE),
+                "{:?} at {} in {:?}",
+                kind,
+                x,
+                points
+            );
+        }
+    }
+
+    #[test]
+    fn poles_and_lines() {
+        let reciprocal = |x: Number| Some(1.0 / x);
+        assert_eq!(
+            Vec::<CriticalPoint>::new(),
+            analyze(&reciprocal, -1.05, 0.95)
+        );
+        let line = |x: Number| Some(2.0 * x + 1.0);
+        let points = analyze(&line, -3.0, 3.0);
+        assert_eq!(vec![PointKind::Zero], kinds(&points));
+        assert!((points[0].x + 0.5).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,252 @@
+//! Arena representation of operands.
+//!
+//! All nodes of an operand are stored in one vector in post-order, children refer to
+//! their operands by index. So an arena is cloned with a single allocation and evaluated
+//! by one forward pass over the nodes instead of a recursive walk over boxed terms.
+//! Variables and functions are looked up once per [`ArenaEval`], not per evaluation.
+//! It is only used for functions of one argument evaluated at many points, which cannot
+//! be compiled, the parser and single evaluations keep the boxed terms.
+
+use crate::ast::*;
+use crate::budget;
+use crate::calc::{calc_operation, calc_resolved_call, CalcError, Env};
+
+/// index of a node in an [`Arena`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct NodeId(u32);
+
+#[derive(Debug, PartialEq, Clone)]
+enum Node {
+    Number(Number),
+    Symbol(String),
+    Term {
+        op: Operation,
+        lhs: NodeId,
+        rhs: NodeId,
+    },
+    FunCall {
+        name: String,
+        params: Vec<NodeId>,
+    },
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Arena {
+    nodes: Vec<Node>,
+}
+
+impl Arena {
+    fn push(&mut self, node: Node) -> NodeId {
+        self.nodes.push(node);
+        NodeId(self.nodes.len() as u32 - 1)
+    }
+
+    fn add(&mut self, op: &Operand) -> Option<NodeId> {
+        let node = match op {
+            Operand::Number(num) => Node::Number(*num),
+            Operand::Literal(lit) => Node::Number(lit.value),
+            Operand::Symbol(sym) => Node::Symbol(sym.clone()),
+            Operand::Term(term) => Node::Term {
+                op: term.op,
+                lhs: self.add(&term.lhs)?,
+                rhs: self.add(&term.rhs)?,
+            },
+            Operand::FunCall(fun_call) => Node::FunCall {
+                name: fun_call.name.clone(),
+                params: fun_call
+                    .params
+                    .iter()
+                    .map(|param| self.add(param))
+                    .collect::<Option<_>>()?,
+            },
+            // the forward pass would evaluate both branches
+            Operand::Conditional(_) => return None,
+            // the body is evaluated once per term in its own scope
+            Operand::Series(_) => return None,
+            // the body is evaluated in a scope with the bound variable
+            Operand::Let(_) => return None,
+            // vectors and matrices have no number value
+            Operand::Matrix(_) => return None,
+            // factorials have no node, they are rare in plotted functions
+            Operand::Factorial(_) => return None,
+        };
+        Some(self.push(node))
+    }
+
+    /// Returns `None` if the operand contains a conditional, whose branches must be
+    /// evaluated lazily, or a sum or product.
+    pub fn new(op: &Operand) -> Option<Arena> {
+        let mut arena = Arena { nodes: Vec::new() };
+        arena.add(op)?;
+        Some(arena)
+    }
+
+    /// the root is the last node
+    fn root(&self) -> NodeId {
+        NodeId(self.nodes.len() as u32 - 1)
+    }
+
+    #[cfg(test)]
+    fn operand(&self, id: NodeId) -> Operand {
+        match &self.nodes[id.0 as usize] {
+            Node::Number(num) => Operand::Number(*num),
+            Node::Symbol(sym) => Operand::Symbol(sym.clone()),
+            Node::Term { op, lhs, rhs } => Operand::Term(Box::new(Term {
+                op: *op,
+                lhs: self.operand(*lhs),
+                rhs: self.operand(*rhs),
+            })),
+            Node::FunCall { name, params } => Operand::FunCall(FunCall {
+                name: name.clone(),
+                params: params.iter().map(|param| self.operand(*param)).collect(),
+            }),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn to_operand(&self) -> Operand {
+        self.operand(self.root())
+    }
+
+    /// Evaluator of the operand as function of the variable `arg`,
+    /// the other variables and the called functions are looked up in `env` now.
+    pub fn evaluator<'a>(&'a self, env: &'a dyn Env, arg: &str) -> ArenaEval<'a> {
+        let mut symbols = Vec::new();
+        let mut functions = Vec::new();
+        for node in &self.nodes {
+            match node {
+                Node::Symbol(sym) if sym == arg => symbols.push(Lookup::Arg),
+                Node::Symbol(sym) => symbols.push(match env.get(sym) {
+                    Some(num) => Lookup::Number(*num),
+                    None => Lookup::Missing,
+                }),
+                Node::FunCall { name, .. } => functions.push(env.get_fun(name)),
+                Node::Number(_) | Node::Term { .. } => (),
+            }
+        }
+        ArenaEval {
+            arena: self,
+            env,
+            symbols,
+            functions,
+            values: Vec::new(),
+            params: Vec::new(),
+        }
+    }
+}
+
+/// value of a symbol node
+#[derive(Debug, Clone, Copy)]
+enum Lookup {
+    Arg,
+    Number(Number),
+    Missing,
+}
+
+/// Evaluation of an [`Arena`], the buffers are reused between evaluations.
+pub struct ArenaEval<'a> {
+    arena: &'a Arena,
+    env: &'a dyn Env,
+    /// values of the symbols in the order of their nodes
+    symbols: Vec<Lookup>,
+    /// functions of the calls in the order of their nodes, `None` if unknown
+    functions: Vec<Option<&'a Function>>,
+    values: Vec<Number>,
+    params: Vec<Number>,
+}
+
+impl<'a> ArenaEval<'a> {
+    /// Evaluates the operand with the variable set to `x`.
+    pub fn eval(&mut self, x: Number) -> Result<Number, CalcError> {
+        let (arena, env) = (self.arena, self.env);
+        budget::spend(arena.nodes.len() as u64)?;
+        self.values.clear();
+        let mut symbols = self.symbols.iter();
+        let mut functions = self.functions.iter();
+        for node in &arena.nodes {
+            let value = match node {
+                Node::Number(num) => *num,
+                Node::Symbol(name) => match symbols.next() {
+                    Some(Lookup::Arg) => x,
+                    Some(Lookup::Number(num)) => *num,
+                    _ if env.get_list(name).is_some() || env.get_matrix(name).is_some() => {
+                        return Err(CalcError::UnexpectedList(name.to_string()))
+                    }
+                    _ => return Err(CalcError::unknown_symbol(name, env)),
+                },
+                Node::Term { op, lhs, rhs } => calc_operation(
+                    *op,
+                    self.values[lhs.0 as usize],
+                    self.values[rhs.0 as usize],
+                    env,
+                )?,
+                Node::FunCall { name, params } => {
+                    let function = functions
+                        .next()
+                        .copied()
+                        .flatten()
+                        .ok_or_else(|| CalcError::unknown_function(name, env))?;
+                    self.params.clear();
+                    self.params
+                        .extend(params.iter().map(|param| self.values[param.0 as usize]));
+                    calc_resolved_call(name, function, &self.params, env)?
+                }
+            };
+            self.values.push(value);
+        }
+        Ok(self.values[arena.root().0 as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::{calc_operand, TopLevelEnv};
+    use crate::parser::parse_expression;
+
+    #[test]
+    fn arena_round_trip() {
+        let op = parse_expression("a * (x + 1) ^ 2 - f(x, sin(y))").unwrap();
+        let arena = Arena::new(&op).unwrap();
+        assert_eq!(12, arena.nodes.len());
+        assert_eq!(op, arena.to_operand());
+    }
+
+    #[test]
+    fn arena_eval_like_tree() {
+        let mut env = TopLevelEnv::default();
+        env.put("a".to_string(), 3.0).unwrap();
+        env.put("x".to_string(), 0.5).unwrap();
+        env.put_fun(
+            "f".to_string(),
+            Function::Custom(CustomFunction {
+                args: vec!["x".to_string()],
+                body: parse_expression("x + a").unwrap(),
+            }),
+        );
+        for expr in [
+            "a * (x + 1) ^ 2 - sqrt(a)",
+            "pi % x / 2",
+            "b + 1",
+            "max(a, x, 2) + f(x)",
+            "g(x) + 1",
+            "atan2(x)",
+        ] {
+            let op = parse_expression(expr).unwrap();
+            let arena = Arena::new(&op).unwrap();
+            assert_eq!(
+                calc_operand(&op, &env),
+                arena.evaluator(&env, "y").eval(1.0),
+                "{}",
+                expr
+            );
+        }
+        let op = parse_expression("a * y + f(y)").unwrap();
+        let arena = Arena::new(&op).unwrap();
+        let mut evaluator = arena.evaluator(&env, "y");
+        assert_eq!(Ok(11.0), evaluator.eval(2.0));
+        assert_eq!(Ok(15.0), evaluator.eval(3.0));
+        let op = parse_expression("if(y > 0, y, f(y))").unwrap();
+        assert_eq!(None, Arena::new(&op));
+    }
+}
@@ -0,0 +1,247 @@
+//! Text output of a [`Plot`] for terminals.
+
+use crate::ast::Number;
+use crate::graph::{Area, Direction, Labels, Marker, Plot, PlotRenderer, Range, Tic};
+
+/// Collects the characters of a plot, scaled from the screen area to `width` x `height` cells.
+struct AsciiRenderer {
+    screen: Area,
+    width: usize,
+    height: usize,
+    /// row 0 is the top line
+    cells: Vec<Vec<char>>,
+    /// row, first column and text of the tic labels, written last into blank cells
+    tic_labels: Vec<(usize, isize, String)>,
+    labels: Labels,
+}
+
+impl AsciiRenderer {
+    fn new(screen: &Area, width: usize, height: usize) -> Self {
+        AsciiRenderer {
+            screen: *screen,
+            width,
+            height,
+            cells: vec![vec![' '; width]; height],
+            tic_labels: Vec::new(),
+            labels: Labels::default(),
+        }
+    }
+
+    /// column and row of a point in screen coordinates, counted from the bottom left
+    fn position(&self, (x, y): (Number, Number)) -> (Number, Number) {
+        let columns = Range::new(0.0, self.width as Number);
+        let rows = Range::new(0.0, self.height as Number);
+        (
+            self.screen.x.project(x, &columns),
+            self.screen.y.project(y, &rows),
+        )
+    }
+
+    /// the cell of a position, the upper edges belong to the last cells
+    fn cell(&self, (column, row): (Number, Number)) -> Option<(usize, usize)> {
+        let inside = |pos: Number, count: usize| (0.0..=count as Number).contains(&pos);
+        if !(inside(column, self.width) && inside(row, self.height)) {
+            return None;
+        }
+        let column = (column as usize).min(self.width - 1);
+        let row = (row as usize).min(self.height - 1);
+        Some((column, self.height - 1 - row))
+    }
+
+    fn set(&mut self, position: (Number, Number), ch: char) {
+        if let Some((column, row)) = self.cell(position) {
+            self.cells[row][column] = ch;
+        }
+    }
+
+    /// writes `text` from `column` in `row`, unless it does not fit into blank cells
+    fn text(&mut self, row: usize, column: isize, text: &str) {
+        let len = text.chars().count();
+        let Some(line) = self.cells.get_mut(row) else {
+            return;
+        };
+        let Ok(start) = usize::try_from(column) else {
+            return;
+        };
+        match line.get(start..start + len) {
+            Some(cells) if cells.iter().all(|ch| *ch == ' ') => {
+                line[start..start + len]
+                    .iter_mut()
+                    .zip(text.chars())
+                    .for_each(|(cell, ch)| *cell = ch);
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(mut self) -> String {
+        for (row, column, text) in std::mem::take(&mut self.tic_labels) {
+            self.text(row, column, &text);
+        }
+        let mut lines = Vec::with_capacity(self.height + 3);
+        if let Some(title) = &self.labels.title {
+            lines.push(format!("{:^width$}", title, width = self.width));
+        }
+        if let Some(ylabel) = &self.labels.ylabel {
+            lines.push(ylabel.clone());
+        }
+        lines.extend(self.cells.iter().map(|row| row.iter().collect::<String>()));
+        if let Some(xlabel) = &self.labels.xlabel {
+            lines.push(format!("{:>width$}", xlabel, width = self.width));
+        }
+        lines
+            .iter()
+            .map(|line| format!("{}\n", line.trim_end()))
+            .collect()
+    }
+}
+
+impl PlotRenderer for AsciiRenderer {
+    fn draw_axis(&mut self, direction: Direction, pos: Number, range: &Range) {
+        let (from, to) = match direction {
+            Direction::Horizontal => ((range.min, pos), (range.max, pos)),
+            Direction::Vertical => ((pos, range.min), (pos, range.max)),
+        };
+        let (from, to) = (self.position(from), self.position(to));
+        let (ch, other) = match direction {
+            Direction::Horizontal => ('-', '|'),
+            Direction::Vertical => ('|', '-'),
+        };
+        let steps = (to.0 - from.0).abs().max((to.1 - from.1).abs()).ceil() as usize;
+        for step in 0..=steps {
+            let t = step as Number / steps.max(1) as Number;
+            let at = (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t);
+            if let Some((column, row)) = self.cell(at) {
+                let crossing = self.cells[row][column] == other;
+                self.cells[row][column] = if crossing { '+' } else { ch };
+            }
+        }
+    }
+
+    /// major tics are `+` on the axis with their label below or to the left of it
+    fn draw_tic(&mut self, direction: Direction, pos: Number, tic: &Tic) {
+        if tic.minor {
+            return;
+        }
+        let at = match direction {
+            Direction::Horizontal => (tic.pos, pos),
+            Direction::Vertical => (pos, tic.pos),
+        };
+        let Some((column, row)) = self.cell(self.position(at)) else {
+            return;
+        };
+        self.cells[row][column] = '+';
+        let len = tic.text.chars().count() as isize;
+        let (row, column) = match direction {
+            Direction::Horizontal => (row + 1, column as isize - len / 2),
+            Direction::Vertical => (row, column as isize - len - 1),
+        };
+        self.tic_labels.push((row, column, tic.text.clone()));
+    }
+
+    fn draw_segment(&mut self, from: (Number, Number), to: (Number, Number)) {
+        let (from, to) = (self.position(from), self.position(to));
+        // segments far beyond the screen are cut at a few screens
+        let limit = 4 * (self.width + self.height);
+        let steps = ((to.0 - from.0).abs().max((to.1 - from.1).abs()).ceil() as usize).min(limit);
+        for step in 0..=steps {
+            let t = step as Number / steps.max(1) as Number;
+            let at = (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t);
+            if let Some((column, row)) = self.cell(at) {
+                self.cells[row][column] = '*';
+            }
+        }
+    }
+
+    fn draw_point(&mut self, at: (Number, Number)) {
+        let position = self.position(at);
+        self.set(position, '*');
+    }
+
+    fn draw_labels(&mut self, labels: &Labels) {
+        self.labels = labels.clone();
+    }
+
+    fn draw_marker(&mut self, marker: &Marker) {
+        let position = self.position(marker.at);
+        self.set(position, 'o');
+    }
+}
+
+impl Plot {
+    /// Renders the plot as text of `height` lines with `width` characters, the screen area
+    /// of the plot is scaled to fit. The function is drawn by `*`, the axes by `-` and `|`
+    /// with labeled tics `+`, markers by `o`. The title and the axis labels get lines of
+    /// their own.
+    /// ```
+    /// use rust_expression::{Area, Calculator};
+    /// let mut c = Calculator::new();
+    /// let graph = c.execute("plot x ^ 2 title \"square\"").unwrap();
+    /// let area = Area::new(-2.0, -1.0, 2.0, 4.0);
+    /// let plot = graph.as_graph().unwrap().plot(&area, &Area::new(0.0, 0.0, 100.0, 50.0)).unwrap();
+    /// let text = plot.render_ascii(40, 12);
+    /// assert_eq!(13, text.lines().count());
+    /// assert!(text.starts_with("                 square") && text.contains('*'));
+    /// ```
+    pub fn render_ascii(&self, width: usize, height: usize) -> String {
+        if width == 0 || height == 0 {
+            return String::new();
+        }
+        let mut renderer = AsciiRenderer::new(&self.screen, width, height);
+        self.render(&mut renderer);
+        renderer.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::PointKind;
+    use crate::graph::Axis;
+
+    #[test]
+    fn scaled_characters() {
+        let screen = Area::new(0.0, 0.0, 160.0, 80.0);
+        let area = Area::new(-4.0, -2.0, 4.0, 2.0);
+        let plot = Plot {
+            name: "f(x)".to_string(),
+            points: vec![
+                Some((0.0, 0.0)),
+                Some((160.0, 80.0)),
+                None,
+                Some((120.0, 16.0)),
+            ],
+            samples: Vec::new(),
+            area,
+            screen,
+            x_axis: Axis::new(Some(40.0), &screen.x, &area.x, "x"),
+            y_axis: Axis::new(Some(80.0), &screen.y, &area.y, "f(x)"),
+            labels: Labels {
+                title: Some("f".to_string()),
+                xlabel: Some("x".to_string()),
+                ..Labels::default()
+            },
+            markers: vec![Marker {
+                kind: PointKind::Maximum,
+                at: (40.0, 20.0),
+            }],
+        };
+        assert_eq!(
+            concat!(
+                "         f\n",
+                "          |       **\n",
+                "          |     **\n",
+                "          |   **\n",
+                "          | **\n",
+                "+----+----**---+----\n",
+                "    -2  **|    2\n",
+                "      **  |\n",
+                "    *o    |    *\n",
+                "  **      |\n",
+                "**     -2 +\n",
+                "                   x\n",
+            ),
+            plot.render_ascii(20, 10)
+        );
+    }
+}
@@ -1,26 +1,441 @@
+//! Syntax tree of statements, e.g. for generating expressions without the parser.
+//!
+//! Operands are built with constructors and operators, statements are evaluated by
+//! [`crate::Calculator::eval_ast`]:
+//! ```
+//! use rust_expression::ast::{Operand, Statement};
+//! use rust_expression::{Calculator, Value};
+//! let mut c = Calculator::new();
+//! let x = Operand::symbol("x");
+//! let f = Statement::function("f", &["x"], x.clone().pow(Operand::number(2.0)) + x);
+//! c.eval_ast(&f).unwrap();
+//! let call = Operand::call("f", vec![Operand::number(3.0)]);
+//! assert_eq!(Ok(Value::Number(12.0)), c.eval_ast(&Statement::expression(call)));
+//! ```
+
+use crate::calc::CalcError;
+use crate::number::{real_pow, Num};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::ops;
+use std::sync::Arc;
+
+#[cfg(not(feature = "f32"))]
 pub type Number = f64;
+#[cfg(feature = "f32")]
+pub type Number = f32;
+
+// the evaluator only uses the operations of `Num`
+const _: fn() = || {
+    fn assert_num<N: Num>() {}
+    assert_num::<Number>();
+};
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub struct FunCall {
     pub name: String,
     pub params: Vec<Operand>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub enum Operand {
     Number(Number),
+    /// number literal with more digits than a [`Number`] holds, e.g. `0.1000000000000000001`
+    Literal(Box<Literal>),
     Symbol(String),
     Term(Box<Term>),
     FunCall(FunCall),
+    Conditional(Box<Conditional>),
+    Series(Box<Series>),
+    Let(Box<Let>),
+    /// `x!` of a non-negative integer `x`
+    Factorial(Box<Operand>),
+    /// vector or matrix literal of rows, e.g. `[1, 2, 3]` or `[1, 2; 3, 4]`
+    Matrix(Vec<Vec<Operand>>),
+}
+
+/// The text of a number literal and its nearest [`Number`]. Other number types
+/// (see [`crate::Calculator::evaluate_as`]) are parsed from the text.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct Literal {
+    pub value: Number,
+    pub text: String,
+}
+
+/// `if(x < 0, -x, x)`, only the chosen branch is evaluated
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct Conditional {
+    pub guard: Guard,
+    pub then: Operand,
+    pub otherwise: Operand,
+}
+
+impl Conditional {
+    pub fn operands(&self) -> [&Operand; 4] {
+        [
+            &self.guard.lhs,
+            &self.guard.rhs,
+            &self.then,
+            &self.otherwise,
+        ]
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub enum SeriesKind {
+    Sum,
+    Product,
+}
+
+impl SeriesKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SeriesKind::Sum => "sum",
+            SeriesKind::Product => "prod",
+        }
+    }
+}
+
+/// `sum(i, 1, n, i ^ 2)` or `prod(i, 1, n, i)`, the bound variable `var` runs through
+/// the integers from `from` to `to`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct Series {
+    pub kind: SeriesKind,
+    pub var: String,
+    pub from: Operand,
+    pub to: Operand,
+    pub body: Operand,
+}
+
+/// `let t := x ^ 2; t + 1 / t`, the variable `var` is bound to `value` within `body`,
+/// so `value` is evaluated only once
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct Let {
+    pub var: String,
+    pub value: Operand,
+    pub body: Operand,
+}
+
+impl Let {
+    /// the body with the value in place of the variable, e.g. for the solver
+    pub fn inlined(&self) -> Operand {
+        self.body.substitute(&self.var, &self.value)
+    }
 }
 
 impl Operand {
+    pub fn number(num: Number) -> Operand {
+        Operand::Number(num)
+    }
+
+    pub fn symbol(name: &str) -> Operand {
+        Operand::Symbol(name.to_string())
+    }
+
+    pub fn call(name: &str, params: Vec<Operand>) -> Operand {
+        Operand::FunCall(FunCall {
+            name: name.to_string(),
+            params,
+        })
+    }
+
+    /// vector or matrix of `rows`, a single row is a vector
+    pub fn matrix(rows: Vec<Vec<Operand>>) -> Operand {
+        Operand::Matrix(rows)
+    }
+
+    pub fn factorial(self) -> Operand {
+        Operand::Factorial(Box::new(self))
+    }
+
+    pub fn pow(self, rhs: Operand) -> Operand {
+        Operand::term(Operation::Pow, self, rhs)
+    }
+
+    pub fn term(op: Operation, lhs: Operand, rhs: Operand) -> Operand {
+        Operand::Term(Box::new(Term { op, lhs, rhs }))
+    }
+
+    /// true if the operand of `!` needs parenthesis, e.g. `(n - 1)!` but `n!`
+    pub fn needs_parens_in_factorial(&self) -> bool {
+        match self {
+            Operand::Number(num) => *num < 0.0,
+            Operand::Literal(lit) => lit.value < 0.0,
+            Operand::Term(_) | Operand::Let(_) => true,
+            _ => false,
+        }
+    }
+
     pub fn is_symbol(&self, sym: &str) -> bool {
         matches!(self, Operand::Symbol(s) if s == sym)
     }
+
+    /// Traverses the operand in pre-order, i.e. every node is visited before its children.
+    /// The bound variable of a sum, product or `let` is not visited within its body.
+    pub fn walk(&self, visitor: &mut impl Visitor) {
+        match self {
+            Operand::Number(num) => visitor.visit_number(*num),
+            Operand::Literal(lit) => visitor.visit_number(lit.value),
+            Operand::Symbol(sym) => visitor.visit_symbol(sym),
+            Operand::Term(term) => {
+                visitor.visit_term(term);
+                term.lhs.walk(visitor);
+                term.rhs.walk(visitor);
+            }
+            Operand::FunCall(fun_call) => {
+                visitor.visit_fun_call(fun_call);
+                for param in &fun_call.params {
+                    param.walk(visitor);
+                }
+            }
+            Operand::Conditional(cond) => {
+                for op in cond.operands() {
+                    op.walk(visitor);
+                }
+            }
+            Operand::Series(series) => {
+                series.from.walk(visitor);
+                series.to.walk(visitor);
+                series.body.walk(&mut Bound {
+                    visitor,
+                    var: &series.var,
+                });
+            }
+            Operand::Let(binding) => {
+                binding.value.walk(visitor);
+                binding.body.walk(&mut Bound {
+                    visitor,
+                    var: &binding.var,
+                });
+            }
+            Operand::Factorial(op) => op.walk(visitor),
+            Operand::Matrix(rows) => {
+                for op in rows.iter().flatten() {
+                    op.walk(visitor);
+                }
+            }
+        }
+    }
+
+    /// the operand with the free occurrences of `sym` replaced by `value`
+    pub fn substitute(&self, sym: &str, value: &Operand) -> Operand {
+        let substitute = |op: &Operand| op.substitute(sym, value);
+        match self {
+            Operand::Symbol(s) if s == sym => value.clone(),
+            Operand::Number(_) | Operand::Literal(_) | Operand::Symbol(_) => self.clone(),
+            Operand::Term(term) => Operand::Term(Box::new(Term {
+                op: term.op,
+                lhs: substitute(&term.lhs),
+                rhs: substitute(&term.rhs),
+            })),
+            Operand::FunCall(fun_call) => Operand::FunCall(FunCall {
+                name: fun_call.name.clone(),
+                params: fun_call.params.iter().map(substitute).collect(),
+            }),
+            Operand::Conditional(cond) => Operand::Conditional(Box::new(Conditional {
+                guard: Guard {
+                    lhs: substitute(&cond.guard.lhs),
+                    cmp: cond.guard.cmp,
+                    rhs: substitute(&cond.guard.rhs),
+                },
+                then: substitute(&cond.then),
+                otherwise: substitute(&cond.otherwise),
+            })),
+            Operand::Series(series) => Operand::Series(Box::new(Series {
+                kind: series.kind,
+                var: series.var.clone(),
+                from: substitute(&series.from),
+                to: substitute(&series.to),
+                body: match series.var == sym {
+                    true => series.body.clone(),
+                    false => substitute(&series.body),
+                },
+            })),
+            Operand::Let(binding) => Operand::Let(Box::new(Let {
+                var: binding.var.clone(),
+                value: substitute(&binding.value),
+                body: match binding.var == sym {
+                    true => binding.body.clone(),
+                    false => substitute(&binding.body),
+                },
+            })),
+            Operand::Factorial(op) => substitute(op).factorial(),
+            Operand::Matrix(rows) => Operand::Matrix(
+                rows.iter()
+                    .map(|row| row.iter().map(substitute).collect())
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Rebuilds the operand bottom-up: the children of a node are mapped before `f` is
+    /// applied to the node itself.
+    /// The bound variable of a sum, product or `let` is not passed to `f` within its body.
+    pub fn map(self, f: &mut impl FnMut(Operand) -> Operand) -> Operand {
+        self.map_free(&mut Vec::new(), f)
+    }
+
+    fn map_free(self, bound: &mut Vec<String>, f: &mut impl FnMut(Operand) -> Operand) -> Operand {
+        let op = match self {
+            Operand::Symbol(sym) if bound.contains(&sym) => return Operand::Symbol(sym),
+            Operand::Term(term) => {
+                let Term { op, lhs, rhs } = *term;
+                Operand::Term(Box::new(Term {
+                    op,
+                    lhs: lhs.map_free(bound, f),
+                    rhs: rhs.map_free(bound, f),
+                }))
+            }
+            Operand::FunCall(FunCall { name, params }) => Operand::FunCall(FunCall {
+                name,
+                params: params
+                    .into_iter()
+                    .map(|param| param.map_free(bound, f))
+                    .collect(),
+            }),
+            Operand::Conditional(cond) => {
+                let Conditional {
+                    guard,
+                    then,
+                    otherwise,
+                } = *cond;
+                Operand::Conditional(Box::new(Conditional {
+                    guard: Guard {
+                        lhs: guard.lhs.map_free(bound, f),
+                        cmp: guard.cmp,
+                        rhs: guard.rhs.map_free(bound, f),
+                    },
+                    then: then.map_free(bound, f),
+                    otherwise: otherwise.map_free(bound, f),
+                }))
+            }
+            Operand::Series(series) => {
+                let Series {
+                    kind,
+                    var,
+                    from,
+                    to,
+                    body,
+                } = *series;
+                Operand::Series(Box::new(Series {
+                    kind,
+                    from: from.map_free(bound, f),
+                    to: to.map_free(bound, f),
+                    body: body.map_body(&var, bound, f),
+                    var,
+                }))
+            }
+            Operand::Let(binding) => {
+                let Let { var, value, body } = *binding;
+                Operand::Let(Box::new(Let {
+                    value: value.map_free(bound, f),
+                    body: body.map_body(&var, bound, f),
+                    var,
+                }))
+            }
+            Operand::Factorial(op) => op.map_free(bound, f).factorial(),
+            Operand::Matrix(rows) => Operand::Matrix(
+                rows.into_iter()
+                    .map(|row| row.into_iter().map(|op| op.map_free(bound, f)).collect())
+                    .collect(),
+            ),
+            op => op,
+        };
+        f(op)
+    }
+
+    /// `map_free` of the body of a binder of `var`
+    fn map_body(
+        self,
+        var: &str,
+        bound: &mut Vec<String>,
+        f: &mut impl FnMut(Operand) -> Operand,
+    ) -> Operand {
+        bound.push(var.to_string());
+        let op = self.map_free(bound, f);
+        bound.pop();
+        op
+    }
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[derive(Default)]
+struct Names {
+    symbols: BTreeSet<String>,
+    functions: BTreeSet<String>,
+}
+
+impl Visitor for Names {
+    fn visit_symbol(&mut self, sym: &str) {
+        self.symbols.insert(sym.to_string());
+    }
+
+    fn visit_fun_call(&mut self, fun_call: &FunCall) {
+        self.functions.insert(fun_call.name.clone());
+    }
+}
+
+impl Operand {
+    /// names of all variables referenced in the operand
+    pub fn free_symbols(&self) -> BTreeSet<String> {
+        let mut names = Names::default();
+        self.walk(&mut names);
+        names.symbols
+    }
+
+    /// names of all functions called in the operand
+    pub fn called_functions(&self) -> BTreeSet<String> {
+        let mut names = Names::default();
+        self.walk(&mut names);
+        names.functions
+    }
+}
+
+/// hides the bound variable of a sum, product or `let` from the visitor
+struct Bound<'a> {
+    visitor: &'a mut dyn Visitor,
+    var: &'a str,
+}
+
+impl<'a> Visitor for Bound<'a> {
+    fn visit_number(&mut self, num: Number) {
+        self.visitor.visit_number(num);
+    }
+
+    fn visit_symbol(&mut self, sym: &str) {
+        if sym != self.var {
+            self.visitor.visit_symbol(sym);
+        }
+    }
+
+    fn visit_term(&mut self, term: &Term) {
+        self.visitor.visit_term(term);
+    }
+
+    fn visit_fun_call(&mut self, fun_call: &FunCall) {
+        self.visitor.visit_fun_call(fun_call);
+    }
+}
+
+/// Callbacks for [`Operand::walk`], all methods default to doing nothing.
+pub trait Visitor {
+    fn visit_number(&mut self, _num: Number) {}
+
+    fn visit_symbol(&mut self, _sym: &str) {}
+
+    fn visit_term(&mut self, _term: &Term) {}
+
+    fn visit_fun_call(&mut self, _fun_call: &FunCall) {}
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub enum Operation {
     Add,
     Sub,
@@ -30,7 +445,69 @@ pub enum Operation {
     Pow,
 }
 
+impl Operation {
+    /// binding strength of the operation, higher binds stronger
+    pub fn precedence(&self) -> u8 {
+        match self {
+            Operation::Add | Operation::Sub => 1,
+            Operation::Mul | Operation::Div | Operation::Rem => 2,
+            Operation::Pow => 3,
+        }
+    }
+
+    pub fn is_right_associative(&self) -> bool {
+        matches!(self, Operation::Pow)
+    }
+
+    /// applies the operation to two numbers
+    pub fn apply<N: Num>(&self, lhs: N, rhs: N) -> N {
+        match self {
+            Operation::Add => lhs + rhs,
+            Operation::Sub => lhs - rhs,
+            Operation::Mul => lhs * rhs,
+            Operation::Div => lhs / rhs,
+            Operation::Rem => lhs % rhs,
+            Operation::Pow => real_pow(lhs, rhs),
+        }
+    }
+
+    /// true if `child` needs parenthesis as left (`is_rhs == false`) or right operand
+    /// of a term with this operation
+    pub fn needs_parens(&self, child: &Operand, is_rhs: bool) -> bool {
+        match child {
+            Operand::Term(term) => {
+                let (prec, parent_prec) = (term.op.precedence(), self.precedence());
+                prec < parent_prec
+                    || (prec == parent_prec && (is_rhs != self.is_right_associative()))
+            }
+            // the body of a `let` extends to the end
+            Operand::Let(_) => true,
+            // a negative base, `-2 ^ 2` is `-(2 ^ 2)`
+            Operand::Number(num) => *self == Operation::Pow && !is_rhs && *num < 0.0,
+            Operand::Literal(lit) => *self == Operation::Pow && !is_rhs && lit.value < 0.0,
+            _ => false,
+        }
+    }
+}
+
+macro_rules! operand_operation {
+    ($($trait:ident $method:ident $op:ident),+) => {
+        $(
+            impl ops::$trait for Operand {
+                type Output = Operand;
+
+                fn $method(self, rhs: Operand) -> Operand {
+                    Operand::term(Operation::$op, self, rhs)
+                }
+            }
+        )+
+    };
+}
+
+operand_operation!(Add add Add, Sub sub Sub, Mul mul Mul, Div div Div, Rem rem Rem);
+
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub struct Term {
     pub op: Operation,
     pub lhs: Operand,
@@ -38,21 +515,204 @@ pub struct Term {
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub struct CustomFunction {
     pub args: Vec<String>,
     pub body: Operand,
 }
 
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub enum TextPart {
+    /// string literal without the quotes
+    Literal(String),
+    /// variable with a text or a number
+    Symbol(String),
+}
+
+/// Concatenation of string literals and variables, e.g. `"t = " + t`
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct Text {
+    pub parts: Vec<TextPart>,
+}
+
+/// Equation stored under a name, e.g. `store ohm: v = i * r`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct Equation {
+    pub lhs: Operand,
+    pub rhs: Operand,
+}
+
+/// Parameter swept by a plot, e.g. `plot f(x, a) for a in 0..5 step 1`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Sweep {
+    /// arguments of the plotted function, the parameter and the plotted variable
+    pub args: Vec<String>,
+    pub param: String,
+    pub from: Operand,
+    pub to: Operand,
+    /// `1` if not given
+    pub step: Option<Operand>,
+}
+
+/// Interval of a numeric solve or integral, e.g. `solve sin(x) = 0.5 for x in [0, 1]`
+#[derive(Debug, PartialEq, Clone)]
+pub struct Bracket {
+    pub from: Operand,
+    pub to: Operand,
+}
+
+/// Annotations of a plot, e.g. `plot f title "height" xlabel "t" ylabel "h"`
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct PlotLabels {
+    pub title: Option<Text>,
+    pub xlabel: Option<Text>,
+    pub ylabel: Option<Text>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub enum Comparison {
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Equal,
+    NotEqual,
+}
+
+/// Are `lhs` and `rhs` equal within `tolerance`? It is relative to the larger magnitude
+/// of both, but absolute for magnitudes below `1`.
+pub fn nearly_equal<N: Num>(lhs: N, rhs: N, tolerance: N) -> bool {
+    let magnitude = [lhs.abs(), rhs.abs(), N::from_f64(1.0)]
+        .into_iter()
+        .fold(N::from_f64(0.0), |max, x| if x > max { x } else { max });
+    lhs == rhs || (lhs - rhs).abs() <= tolerance * magnitude
+}
+
+impl Comparison {
+    pub fn compare<N: Num>(self, lhs: N, rhs: N) -> bool {
+        self.compare_within(lhs, rhs, N::from_f64(0.0))
+    }
+
+    /// Like [`Comparison::compare`], but numbers within `tolerance` are equal,
+    /// see [`nearly_equal`].
+    pub fn compare_within<N: Num>(self, lhs: N, rhs: N, tolerance: N) -> bool {
+        let equal = nearly_equal(lhs, rhs, tolerance);
+        match self {
+            Comparison::Less => lhs < rhs && !equal,
+            Comparison::LessEqual => lhs < rhs || equal,
+            Comparison::Greater => lhs > rhs && !equal,
+            Comparison::GreaterEqual => lhs > rhs || equal,
+            Comparison::Equal => equal,
+            Comparison::NotEqual => !equal,
+        }
+    }
+}
+
+/// condition of a clause, e.g. `x >= 0` in `f(x) := x when x >= 0`
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct Guard {
+    pub lhs: Operand,
+    pub cmp: Comparison,
+    pub rhs: Operand,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct Clause {
+    pub body: Operand,
+    /// `None` for the clause written with `otherwise`
+    pub guard: Option<Guard>,
+}
+
+/// Function defined by clauses, which are matched in order at call time, e.g.
+/// `f(x) := x when x >= 0` and `f(x) := -1 * x otherwise`.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct Piecewise {
+    pub args: Vec<String>,
+    pub clauses: Vec<Clause>,
+}
+
+impl Piecewise {
+    /// does the last clause match all arguments
+    pub fn is_complete(&self) -> bool {
+        matches!(self.clauses.last(), Some(Clause { guard: None, .. }))
+    }
+
+    /// bodies and guard operands of all clauses
+    pub fn operands(&self) -> Vec<&Operand> {
+        self.clauses
+            .iter()
+            .flat_map(|clause| {
+                let guard = clause
+                    .guard
+                    .iter()
+                    .flat_map(|guard| [&guard.lhs, &guard.rhs]);
+                std::iter::once(&clause.body).chain(guard)
+            })
+            .collect()
+    }
+}
+
+/// Function registered by the host application, see [`Calculator::register_function`](crate::Calculator::register_function).
+/// It is `Send + Sync`, so calculators can be shared by threads and plots can call it
+/// from several threads.
+pub type NativeFn = dyn Fn(&[Number]) -> Result<Number, CalcError> + Send + Sync;
+
+/// Implementation of a [`BuildInFunction`].
+#[derive(Clone)]
+pub enum BuildInBody {
+    /// function of one number like `sin`, which can be compiled
+    Unary(&'static (dyn Fn(Number) -> Number + Sync)),
+    /// function of several numbers like `atan2(y, x)`, called with the checked parameters
+    Many(&'static (dyn Fn(&[Number]) -> Number + Sync)),
+    /// closure of the host application, which may fail
+    Native(Arc<NativeFn>),
+}
+
 #[derive(Clone)]
 pub struct BuildInFunction {
     pub name: String,
-    pub arg: String,
-    pub body: &'static dyn Fn(Number) -> Number,
+    pub args: Vec<String>,
+    /// takes more parameters than `args`, e.g. `min(a, b, c)`
+    pub variadic: bool,
+    pub body: BuildInBody,
+}
+
+impl BuildInFunction {
+    /// build-in function of one number `x`
+    pub fn unary(name: &str, body: &'static (dyn Fn(Number) -> Number + Sync)) -> Self {
+        BuildInFunction {
+            name: name.to_string(),
+            args: vec!["x".to_string()],
+            variadic: false,
+            body: BuildInBody::Unary(body),
+        }
+    }
+
+    /// does the function take `count` parameters
+    pub fn accepts(&self, count: usize) -> bool {
+        count == self.args.len() || (self.variadic && count > self.args.len())
+    }
+
+    /// Evaluates the function, the number of `params` must be accepted.
+    pub fn call(&self, params: &[Number]) -> Result<Number, CalcError> {
+        match &self.body {
+            BuildInBody::Unary(body) => Ok(body(params[0])),
+            BuildInBody::Many(body) => Ok(body(params)),
+            BuildInBody::Native(body) => body(params),
+        }
+    }
 }
 
 impl PartialEq for BuildInFunction {
     fn eq(&self, other: &Self) -> bool {
-        self.name == other.name && self.arg == other.arg
+        self.name == other.name && self.args == other.args && self.variadic == other.variadic
     }
 }
 
@@ -60,15 +720,103 @@ impl std::fmt::Debug for BuildInFunction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("BuildInFunction")
             .field("name", &self.name)
-            .field("arg", &self.arg)
+            .field("args", &self.args)
+            .field("variadic", &self.variadic)
+            .finish()
+    }
+}
+
+/// Sequence defined by a recurrence like `a(n) := a(n - 1) + a(n - 2)` and initial values
+/// like `a(0) := 0`.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sequence {
+    pub arg: String,
+    pub body: Option<Operand>,
+    pub values: BTreeMap<u64, Number>,
+}
+
+/// Parameter or result of a [`DataFunction`].
+#[derive(Debug, PartialEq, Clone)]
+pub enum Data {
+    Number(Number),
+    List(Vec<Number>),
+}
+
+impl Data {
+    pub fn number(&self) -> Option<Number> {
+        match self {
+            Data::Number(num) => Some(*num),
+            Data::List(_) => None,
+        }
+    }
+
+    pub fn list(&self) -> Option<&[Number]> {
+        match self {
+            Data::Number(_) => None,
+            Data::List(list) => Some(list),
+        }
+    }
+}
+
+/// Build-in function with several parameters, which may be lists.
+/// The body checks its parameters and returns an error message for invalid ones.
+#[derive(Clone)]
+pub struct DataFunction {
+    pub name: String,
+    pub args: Vec<String>,
+    pub returns_list: bool,
+    pub body: &'static (dyn Fn(&[Data]) -> Result<Data, String> + Sync),
+}
+
+impl PartialEq for DataFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.args == other.args
+    }
+}
+
+impl std::fmt::Debug for DataFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DataFunction")
+            .field("name", &self.name)
+            .field("args", &self.args)
+            .field("returns_list", &self.returns_list)
             .finish()
     }
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "persistence", derive(serde::Serialize, serde::Deserialize))]
 pub enum Function {
     Custom(CustomFunction),
+    /// build-in functions are attached by the calculator and never saved
+    #[cfg_attr(feature = "persistence", serde(skip))]
     BuildIn(BuildInFunction),
+    #[cfg_attr(feature = "persistence", serde(skip))]
+    Data(DataFunction),
+    Sequence(Sequence),
+    Piecewise(Piecewise),
+}
+
+impl Function {
+    /// number of arguments, the minimum of variadic functions
+    pub fn arity(&self) -> usize {
+        match self {
+            Function::Custom(fun) => fun.args.len(),
+            Function::BuildIn(fun) => fun.args.len(),
+            Function::Data(fun) => fun.args.len(),
+            Function::Sequence(_) => 1,
+            Function::Piecewise(fun) => fun.args.len(),
+        }
+    }
+
+    /// does the function take `count` parameters
+    pub fn accepts(&self, count: usize) -> bool {
+        match self {
+            Function::BuildIn(fun) => fun.accepts(count),
+            fun => fun.arity() == count,
+        }
+    }
 }
 
 impl Default for Function {
@@ -93,6 +841,38 @@ pub enum Statement {
         lhs: Operand,
         rhs: Operand,
         sym: String,
+        /// interval of a numeric solution, if the equation is neither linear nor quadratic
+        bracket: Option<Bracket>,
+    },
+    /// solution set of an inequality, e.g. `solve 2 * x + 1 < 7 for x`
+    SolveInequality {
+        lhs: Operand,
+        cmp: Comparison,
+        rhs: Operand,
+        sym: String,
+    },
+    /// integral of a function of one argument, e.g. `integrate f from 0 to 2`
+    Integrate {
+        name: String,
+        bracket: Bracket,
+    },
+    /// integral of an expression over `sym`, e.g. `integrate x^2 for x in [0, 2]`
+    IntegrateExpression {
+        op: Operand,
+        sym: String,
+        bracket: Bracket,
+    },
+    /// names an equation for later solving, e.g. `store ohm: v = i * r`
+    Store {
+        name: String,
+        lhs: Operand,
+        rhs: Operand,
+    },
+    /// solves a stored equation, e.g. `solve ohm for i given v := 12, r := 4`
+    SolveStored {
+        name: String,
+        sym: String,
+        given: Vec<(String, Operand)>,
     },
     Function {
         name: String,
@@ -100,7 +880,118 @@ pub enum Statement {
     },
     Plot {
         name: String,
+        sweep: Option<Sweep>,
+        labels: PlotLabels,
+    },
+    /// plot of an expression in one free variable, e.g. `plot x^2 + 1`
+    PlotExpression {
+        op: Operand,
+        labels: PlotLabels,
+    },
+    /// polar plot of a radius, e.g. `plot polar 1 + cos(t) in [0, pi]`
+    PlotPolar {
+        op: Operand,
+        /// the angles, a full turn from `0` if not given
+        bracket: Option<Bracket>,
+        labels: PlotLabels,
+    },
+    /// zero contour of `lhs - rhs`, e.g. `plot x^2 + y^2 = 100`, or of `lhs` without
+    /// `rhs`, e.g. `plot implicit f` of a function `f(x, y)`
+    PlotImplicit {
+        lhs: Operand,
+        rhs: Option<Operand>,
+        labels: PlotLabels,
+    },
+    /// e.g. `title := "position vs time"`
+    TextAssignment {
+        sym: String,
+        text: Text,
+    },
+    TextExpression {
+        text: Text,
+    },
+    Equivalent {
+        lhs: Operand,
+        rhs: Operand,
+    },
+    /// `simplify x + x`
+    Simplify {
+        op: Operand,
+    },
+    Import {
+        path: String,
+        column: usize,
+        sym: String,
+    },
+    /// executes the statements of a script file, e.g. `run "lib/trig.calc"`
+    Run {
+        path: String,
+    },
+    FitLinear {
+        xs: String,
+        ys: String,
+        name: Option<String>,
+    },
+    SequenceValue {
+        name: String,
+        index: u64,
+        op: Operand,
     },
+    /// variable defined by a formula, e.g. `define y = x^2 + 1`
+    Define {
+        sym: String,
+        op: Operand,
+    },
+    /// explicit redefinition with `override`, required in strict mode
+    Override {
+        st: Box<Statement>,
+    },
+    /// variables and functions defined by the user, `list` or `env`
+    List,
+    /// reverts the last change of variables or functions
+    Undo,
+    /// removes the variable and function `name`, e.g. `unset a`
+    Unset {
+        name: String,
+    },
+    /// a line without statement, e.g. only a comment
+    Empty,
+}
+
+impl Statement {
+    /// evaluates `op`, e.g. `2 * x`
+    pub fn expression(op: Operand) -> Statement {
+        Statement::Expression { op }
+    }
+
+    /// assigns the value of `op` to `sym`, e.g. `a := 2 * x`
+    pub fn assignment(sym: &str, op: Operand) -> Statement {
+        Statement::Assignment {
+            sym: sym.to_string(),
+            op,
+        }
+    }
+
+    /// defines the function `name`, e.g. `f(x) := x ^ 2`
+    pub fn function(name: &str, args: &[&str], body: Operand) -> Statement {
+        Statement::Function {
+            name: name.to_string(),
+            fun: Function::Custom(CustomFunction {
+                args: args.iter().map(|arg| arg.to_string()).collect(),
+                body,
+            }),
+        }
+    }
+
+    /// solves `lhs = rhs` for `sym`, e.g. `solve 2 * x = 4 for x`
+    pub fn solve_for(lhs: Operand, rhs: Operand, sym: &str) -> Statement {
+        Statement::SolveFor {
+            lhs,
+            rhs,
+            sym: sym.to_string(),
+            bracket: None,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -113,11 +1004,217 @@ mod tests {
         Term { op, lhs, rhs }
     }
 
+    #[test]
+    fn operand_builders() {
+        let x = Operand::symbol("x");
+        assert_eq!(
+            Operand::Term(Box::new(Term {
+                op: Operation::Sub,
+                lhs: Operand::Term(Box::new(Term {
+                    op: Operation::Pow,
+                    lhs: Operand::Symbol("x".to_string()),
+                    rhs: Operand::Number(2.0),
+                })),
+                rhs: Operand::FunCall(FunCall {
+                    name: "sin".to_string(),
+                    params: vec![Operand::Symbol("x".to_string())],
+                }),
+            })),
+            x.clone().pow(Operand::number(2.0)) - Operand::call("sin", vec![x])
+        );
+    }
+
     #[test]
     fn operand_is_symbol() {
         assert!(Operand::Symbol("x".to_string()).is_symbol("x"));
     }
 
+    #[test]
+    fn operation_precedence() {
+        assert!(Operation::Add.precedence() < Operation::Mul.precedence());
+        assert!(Operation::Rem.precedence() < Operation::Pow.precedence());
+        assert_eq!(Operation::Add.precedence(), Operation::Sub.precedence());
+        assert!(Operation::Pow.is_right_associative());
+        assert!(!Operation::Div.is_right_associative());
+    }
+
+    #[test]
+    fn compare_within_tolerance() {
+        // a rounding error of one ulp
+        let sum = 1.0 + Number::EPSILON;
+        let tolerance = 4.0 * Number::EPSILON;
+        assert!(!Comparison::Equal.compare(sum, 1.0));
+        assert!(Comparison::Equal.compare_within(sum, 1.0, tolerance));
+        assert!(!Comparison::Greater.compare_within(sum, 1.0, tolerance));
+        assert!(Comparison::LessEqual.compare_within(sum, 1.0, tolerance));
+        assert!(Comparison::Less.compare_within(1.0, 2.0, tolerance));
+        // relative to large magnitudes
+        let large = 1e20 * sum;
+        assert!(Comparison::Equal.compare_within(1e20, large, tolerance));
+        assert!(!Comparison::Equal.compare_within(1e20, large, 0.0));
+        assert!(Comparison::NotEqual.compare_within(Number::NAN, Number::NAN, tolerance));
+    }
+
+    #[test]
+    fn operand_walk_pre_order() {
+        #[derive(Default)]
+        struct Names(Vec<String>);
+        impl Visitor for Names {
+            fn visit_number(&mut self, num: Number) {
+                self.0.push(num.to_string());
+            }
+            fn visit_symbol(&mut self, sym: &str) {
+                self.0.push(sym.to_string());
+            }
+            fn visit_term(&mut self, term: &Term) {
+                self.0.push(format!("{:?}", term.op));
+            }
+            fn visit_fun_call(&mut self, fun_call: &FunCall) {
+                self.0.push(fun_call.name.clone());
+            }
+        }
+
+        let op = Operand::FunCall(FunCall {
+            name: "f".to_string(),
+            params: vec![
+                Operand::Term(Box::new(create_term())),
+                Operand::Symbol("x".to_string()),
+            ],
+        });
+        let mut names = Names::default();
+        op.walk(&mut names);
+        assert_eq!(vec!["f", "Add", "1", "1", "x"], names.0);
+    }
+
+    #[test]
+    fn operand_map_replaces_symbols() {
+        let op = Operand::Term(Box::new(Term {
+            op: Operation::Mul,
+            lhs: Operand::Symbol("x".to_string()),
+            rhs: Operand::Symbol("y".to_string()),
+        }));
+        let mapped = op.map(&mut |op| match op {
+            Operand::Symbol(s) if s == "x" => Operand::Number(2.0),
+            op => op,
+        });
+        assert_eq!(
+            Operand::Term(Box::new(Term {
+                op: Operation::Mul,
+                lhs: Operand::Number(2.0),
+                rhs: Operand::Symbol("y".to_string()),
+            })),
+            mapped
+        );
+    }
+
+    #[test]
+    fn operand_map_skips_bound_variables() {
+        let binding = |value: Operand, y: Operand| {
+            Operand::Let(Box::new(Let {
+                var: "x".to_string(),
+                value,
+                body: Operand::term(Operation::Add, Operand::symbol("x"), y),
+            }))
+        };
+        let op = binding(Operand::symbol("x"), Operand::symbol("y"));
+        let mapped = op.map(&mut |op| match op {
+            Operand::Symbol(_) => Operand::Number(2.0),
+            op => op,
+        });
+        assert_eq!(binding(Operand::Number(2.0), Operand::Number(2.0)), mapped);
+    }
+
+    #[test]
+    fn operand_free_symbols_and_called_functions() {
+        let op = Operand::FunCall(FunCall {
+            name: "f".to_string(),
+            params: vec![
+                Operand::Symbol("y".to_string()),
+                Operand::FunCall(FunCall {
+                    name: "g".to_string(),
+                    params: vec![Operand::Symbol("x".to_string())],
+                }),
+                Operand::Symbol("x".to_string()),
+            ],
+        });
+        assert_eq!(
+            vec!["x".to_string(), "y".to_string()],
+            op.free_symbols().into_iter().collect::<Vec<String>>()
+        );
+        assert_eq!(
+            vec!["f".to_string(), "g".to_string()],
+            op.called_functions().into_iter().collect::<Vec<String>>()
+        );
+    }
+
+    #[test]
+    fn series_binds_its_variable() {
+        let series = |var: &str| {
+            Operand::Series(Box::new(Series {
+                kind: SeriesKind::Sum,
+                var: var.to_string(),
+                from: Operand::Number(1.0),
+                to: Operand::Symbol("n".to_string()),
+                body: Operand::Term(Box::new(Term {
+                    op: Operation::Mul,
+                    lhs: Operand::Symbol("i".to_string()),
+                    rhs: Operand::Symbol("x".to_string()),
+                })),
+            }))
+        };
+        assert_eq!(
+            vec!["n".to_string(), "x".to_string()],
+            series("i")
+                .free_symbols()
+                .into_iter()
+                .collect::<Vec<String>>()
+        );
+        assert_eq!(
+            vec!["i".to_string(), "n".to_string(), "x".to_string()],
+            series("k")
+                .free_symbols()
+                .into_iter()
+                .collect::<Vec<String>>()
+        );
+    }
+
+    #[test]
+    fn let_binds_its_variable() {
+        let sym = |name: &str| Operand::Symbol(name.to_string());
+        let binding = |value: Operand| {
+            Operand::Let(Box::new(Let {
+                var: "t".to_string(),
+                value,
+                body: Operand::Term(Box::new(Term {
+                    op: Operation::Mul,
+                    lhs: sym("t"),
+                    rhs: sym("y"),
+                })),
+            }))
+        };
+        assert_eq!(
+            vec!["t".to_string(), "y".to_string()],
+            binding(sym("t"))
+                .free_symbols()
+                .into_iter()
+                .collect::<Vec<String>>()
+        );
+        assert_eq!(
+            binding(Operand::Number(2.0)),
+            binding(sym("t")).substitute("t", &Operand::Number(2.0))
+        );
+        if let Operand::Let(binding) = binding(Operand::Number(2.0)) {
+            assert_eq!(
+                Operand::Term(Box::new(Term {
+                    op: Operation::Mul,
+                    lhs: Operand::Number(2.0),
+                    rhs: sym("y"),
+                })),
+                binding.inlined()
+            );
+        }
+    }
+
     #[test]
     fn operand_is_not_symbol() {
         assert!(!Operand::Symbol("y".to_string()).is_symbol("x"));
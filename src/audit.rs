@@ -0,0 +1,165 @@
+//! Log of the changes of variables and functions by statements.
+
+use crate::calc::{Env, TopLevelEnv};
+
+use std::fmt;
+use std::time::SystemTime;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MutationKind {
+    /// the name had no binding before
+    Defined,
+    Changed,
+    /// the variable has no value anymore, e.g. its formula cannot be evaluated
+    Removed,
+}
+
+/// Change of a single binding by a statement
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Mutation {
+    pub time: SystemTime,
+    /// the statement causing the change, as written by [`std::fmt::Display`] of the statement
+    pub statement: String,
+    pub name: String,
+    pub kind: MutationKind,
+}
+
+/// Change of the environment passed to the observers registered by
+/// [`Calculator::on_change`](crate::Calculator::on_change).
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum EnvEvent {
+    /// the variable has a new value, e.g. by `a := 1` or by recomputing its formula
+    Assigned(String),
+    /// the function was defined or redefined
+    Defined(String),
+    /// the variable or function was removed, e.g. by `unset a` or `undo`
+    Removed(String),
+}
+
+type Observer = Box<dyn Fn(&EnvEvent) + Send + Sync>;
+
+/// callbacks registered by [`Calculator::on_change`](crate::Calculator::on_change)
+#[derive(Default)]
+pub(crate) struct Observers(Vec<Observer>);
+
+impl Observers {
+    pub(crate) fn add(&mut self, observer: Observer) {
+        self.0.push(observer);
+    }
+
+    /// passes the changes from `before` to `after` to all observers
+    pub(crate) fn notify(&self, before: &TopLevelEnv, after: &TopLevelEnv) {
+        if self.0.is_empty() {
+            return;
+        }
+        for event in events(before, after) {
+            for observer in &self.0 {
+                observer(&event);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Observers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Observers({})", self.0.len())
+    }
+}
+
+/// changes of the variables, then of the functions from `before` to `after`, ordered by name
+fn events(before: &TopLevelEnv, after: &TopLevelEnv) -> Vec<EnvEvent> {
+    let vars = after
+        .changed_vars(before)
+        .into_iter()
+        .map(|name| match after.has_var(&name) {
+            true => EnvEvent::Assigned(name),
+            false => EnvEvent::Removed(name),
+        });
+    let funs = after
+        .changed_funs(before)
+        .into_iter()
+        .map(|name| match after.get_fun(&name) {
+            Some(_) => EnvEvent::Defined(name),
+            None => EnvEvent::Removed(name),
+        });
+    vars.chain(funs).collect()
+}
+
+/// changes of all bindings from `before` to `after`, ordered by name
+pub(crate) fn mutations(
+    before: &TopLevelEnv,
+    after: &TopLevelEnv,
+    statement: &str,
+    time: SystemTime,
+) -> Vec<Mutation> {
+    let mut names = after.changed_vars(before);
+    names.extend(after.changed_funs(before));
+    names.extend(after.changed_formulas(before));
+    names
+        .into_iter()
+        .map(|name| {
+            let kind = match (before.is_bound(&name), after.is_bound(&name)) {
+                (false, _) => MutationKind::Defined,
+                (true, true) => MutationKind::Changed,
+                (true, false) => MutationKind::Removed,
+            };
+            Mutation {
+                time,
+                statement: statement.to_string(),
+                name,
+                kind,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mutations_between_environments() {
+        let mut before = TopLevelEnv::default();
+        before.put("a".to_string(), 1.0).unwrap();
+        before.put("b".to_string(), 1.0).unwrap();
+        let mut after = before.clone();
+        after.put("a".to_string(), 2.0).unwrap();
+        after.remove("b").unwrap();
+        after.put_list("c".to_string(), vec![1.0]).unwrap();
+
+        let time = SystemTime::now();
+        let kinds: Vec<(String, MutationKind)> = mutations(&before, &after, "stmt", time)
+            .into_iter()
+            .map(|mutation| (mutation.name, mutation.kind))
+            .collect();
+        assert_eq!(
+            vec![
+                ("a".to_string(), MutationKind::Changed),
+                ("b".to_string(), MutationKind::Removed),
+                ("c".to_string(), MutationKind::Defined),
+            ],
+            kinds
+        );
+        assert!(mutations(&after, &after, "stmt", time).is_empty());
+    }
+
+    #[test]
+    fn events_between_environments() {
+        let mut before = TopLevelEnv::default();
+        before.put("a".to_string(), 1.0).unwrap();
+        before.put("b".to_string(), 1.0).unwrap();
+        let mut after = before.clone();
+        after.put("b".to_string(), 2.0).unwrap();
+        after.remove("a").unwrap();
+        after.put_fun("f".to_string(), before.get_fun("sin").unwrap().clone());
+        assert_eq!(
+            vec![
+                EnvEvent::Removed("a".to_string()),
+                EnvEvent::Assigned("b".to_string()),
+                EnvEvent::Defined("f".to_string()),
+            ],
+            events(&before, &after)
+        );
+        assert!(events(&after, &after).is_empty());
+    }
+}
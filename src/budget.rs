@@ -0,0 +1,147 @@
+//! Aborting evaluations that exceed the operations or the time allowed by [`Limits`].
+//!
+//! The budget is kept per thread while an evaluation is limited,
+//! otherwise spending costs a single check per operation.
+
+use crate::calc::CalcError;
+use crate::limits::{LimitError, Limits};
+
+use std::cell::Cell;
+use std::time::{Duration, Instant};
+
+/// operations between two looks at the clock
+const CLOCK_INTERVAL: u64 = 1024;
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Budget {
+    spent: u64,
+    max_operations: Option<u64>,
+    timeout: Option<Duration>,
+    start: Instant,
+    /// operations spent at the last look at the clock
+    checked: u64,
+    exceeded: Option<LimitError>,
+}
+
+thread_local! {
+    static BUDGET: Cell<Option<Budget>> = const { Cell::new(None) };
+}
+
+impl Budget {
+    fn new(limits: &Limits) -> Option<Budget> {
+        if limits.max_operations.is_none() && limits.timeout.is_none() {
+            return None;
+        }
+        Some(Budget {
+            spent: 0,
+            max_operations: limits.max_operations,
+            timeout: limits.timeout,
+            start: Instant::now(),
+            checked: 0,
+            exceeded: None,
+        })
+    }
+
+    fn spend(&mut self, n: u64) -> Result<(), LimitError> {
+        self.spent = self.spent.saturating_add(n);
+        if self.exceeded.is_none() {
+            self.exceeded = match (self.max_operations, self.timeout) {
+                (Some(max), _) if self.spent > max => Some(LimitError::TooManyOperations { max }),
+                (_, Some(timeout)) if self.spent - self.checked >= CLOCK_INTERVAL => {
+                    self.checked = self.spent;
+                    (self.start.elapsed() > timeout).then_some(LimitError::Timeout {
+                        millis: timeout.as_millis(),
+                    })
+                }
+                _ => None,
+            };
+        }
+        self.exceeded.map_or(Ok(()), Err)
+    }
+}
+
+/// spends `n` operations of the budget of this thread, if there is one
+pub(crate) fn spend(n: u64) -> Result<(), CalcError> {
+    BUDGET.with(|budget| match budget.get() {
+        Some(mut current) => {
+            let result = current.spend(n);
+            budget.set(Some(current));
+            Ok(result?)
+        }
+        None => Ok(()),
+    })
+}
+
+/// Runs `f` with the budget of `limits` and returns the exceeded limit, if any.
+/// Within an already limited evaluation, `f` spends the outer budget.
+pub(crate) fn limited<T>(limits: &Limits, f: impl FnOnce() -> T) -> (T, Option<LimitError>) {
+    let budget = match Budget::new(limits) {
+        Some(budget) if BUDGET.with(|outer| outer.get().is_none()) => budget,
+        _ => return (f(), None),
+    };
+    BUDGET.with(|current| current.set(Some(budget)));
+    let result = f();
+    let exceeded = BUDGET
+        .with(|current| current.take())
+        .and_then(|budget| budget.exceeded);
+    (result, exceeded)
+}
+
+/// the budget of this thread, to be continued on other threads by [`continued`]
+#[cfg(feature = "parallel")]
+pub(crate) fn current() -> Option<Budget> {
+    BUDGET.with(|current| current.get())
+}
+
+/// Runs `f` with a copy of `budget`, e.g. on a worker thread, and returns the operations
+/// it spent, which are then spent on the budget of the calling thread.
+#[cfg(feature = "parallel")]
+pub(crate) fn continued<T>(budget: Option<Budget>, f: impl FnOnce() -> T) -> (T, u64) {
+    let outer = BUDGET.with(|current| current.replace(budget));
+    let result = f();
+    let spent = match (BUDGET.with(|current| current.replace(outer)), budget) {
+        (Some(after), Some(before)) => after.spent - before.spent,
+        _ => 0,
+    };
+    (result, spent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spend_only_while_limited() {
+        assert_eq!(Ok(()), spend(u64::MAX));
+        let limits = Limits {
+            max_operations: Some(10),
+            ..Limits::default()
+        };
+        let (spent, exceeded) = limited(&limits, || {
+            spend(10).unwrap();
+            let (_, inner) = limited(&limits, || spend(1));
+            assert_eq!(None, inner);
+            spend(1)
+        });
+        let exceeded_ops = LimitError::TooManyOperations { max: 10 };
+        assert_eq!(Err(CalcError::LimitExceeded(exceeded_ops)), spent);
+        assert_eq!(Some(exceeded_ops), exceeded);
+        assert_eq!(Ok(()), spend(100));
+    }
+
+    #[test]
+    fn timeout() {
+        let limits = Limits {
+            timeout: Some(Duration::ZERO),
+            ..Limits::default()
+        };
+        let (_, exceeded) = limited(&limits, || spend(CLOCK_INTERVAL - 1));
+        assert_eq!(None, exceeded);
+        let (spent, exceeded) = limited(&limits, || {
+            std::thread::sleep(Duration::from_millis(1));
+            spend(CLOCK_INTERVAL)
+        });
+        assert!(spent.is_err());
+        assert_eq!(Some(LimitError::Timeout { millis: 0 }), exceeded);
+    }
+}
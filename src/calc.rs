@@ -1,12 +1,28 @@
+use crate::arena::Arena;
 use crate::ast::*;
+use crate::budget;
+use crate::compiled::CompiledFunction;
+use crate::gamma::factorial;
+use crate::higher_order::{calc_higher_order, is_higher_order};
+use crate::limits::LimitError;
+use crate::matrix::{calc_array, is_transpose_call, Array, Matrix};
+use crate::number::{real_pow, Num};
+use crate::random::{MAX_SAMPLES, SAMPLE};
+use crate::suggest::{did_you_mean, hint_suffix, similar_names};
+use crate::timing;
 
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
 use thiserror::Error;
 
 #[derive(Debug, PartialEq, Eq, Error)]
 pub enum CalcError {
-    #[error("Unknown symbol `{0}`")]
-    UnknownSymbol(String),
+    #[error("Unknown symbol `{name}`{}", hint_suffix(did_you_mean(candidates)))]
+    UnknownSymbol {
+        name: String,
+        /// variables with a similar name
+        candidates: Vec<String>,
+    },
     #[error(
         "Unexpected number of parameters for call to `{name}` - expected {exp}, but got {act}"
     )]
@@ -15,33 +31,173 @@ pub enum CalcError {
         act: usize,
         exp: usize,
     },
-    #[error("Unknown function `{0}`")]
-    UnknownFunction(String),
+    #[error(
+        "Unexpected number of parameters for call to `{name}` - expected at least {min}, but got {act}"
+    )]
+    TooFewParameters {
+        name: String,
+        act: usize,
+        min: usize,
+    },
+    #[error("Unknown function `{name}`{}", hint_suffix(did_you_mean(candidates)))]
+    UnknownFunction {
+        name: String,
+        /// functions with a similar name
+        candidates: Vec<String>,
+    },
     #[error("Cannot change value of constant `{0}`")]
     CannotChangeConstant(String),
+    #[error("Expected a number, but `{0}` is a list")]
+    UnexpectedList(String),
+    #[error("Invalid arguments for call to `{name}`: {reason}")]
+    InvalidArguments { name: String, reason: String },
+    #[error("Index of sequence `{name}` must be a non-negative integer, but is {index}")]
+    InvalidSequenceIndex { name: String, index: String },
+    #[error("No value for `{name}({index})`")]
+    MissingSequenceValue { name: String, index: u64 },
+    #[error("Computing `{name}({index})` needs more than {MAX_SEQUENCE_STEPS} steps")]
+    SequenceTooLong { name: String, index: u64 },
+    #[error("`{0}` is not a sequence")]
+    NotASequence(String),
+    #[error("`{0}` is already defined - use `override` to redefine it")]
+    AlreadyDefined(String),
+    #[error("Undefined names {}", .0.iter().map(|name| format!("`{}`", name)).collect::<Vec<_>>().join(", "))]
+    UndefinedNames(Vec<String>),
+    #[error("Cyclic definition {}", .0.iter().map(|name| format!("`{}`", name)).collect::<Vec<_>>().join(" -> "))]
+    CyclicDefinition(Vec<String>),
+    #[error("Expected a number, but `{0}` is a text")]
+    UnexpectedText(String),
+    #[error("No clause of `{name}` matches the arguments ({args})")]
+    NoMatchingClause { name: String, args: String },
+    #[error("Unknown equation `{0}`")]
+    UnknownEquation(String),
+    #[error("Cannot remove build-in function `{0}`")]
+    CannotRemoveBuildIn(String),
+    #[error("Bounds of `{name}` must be integers, but got {bound}")]
+    InvalidSeriesBound { name: String, bound: String },
+    #[error("`{name}` has more than {MAX_SERIES_TERMS} terms")]
+    SeriesTooLong { name: String },
+    #[error("Division by zero")]
+    DivisionByZero,
+    #[error("`{function}` is not defined for {argument}")]
+    DomainError { function: String, argument: String },
+    #[error("Cannot apply `{op}` to {lhs} and {rhs}")]
+    IncompatibleShapes {
+        op: Operation,
+        lhs: String,
+        rhs: String,
+    },
+    #[error("Calling `{name}` exceeds the recursion limit of {limit}")]
+    RecursionLimitExceeded { name: String, limit: usize },
+    #[error("Expected an expression of a number, but got `{0}`")]
+    NotAnExpression(String),
+    #[error("Nothing to undo")]
+    NothingToUndo,
+    #[error("`{0}` overflows the number type")]
+    Overflow(String),
+    #[error(transparent)]
+    LimitExceeded(#[from] LimitError),
+}
+
+impl CalcError {
+    /// [`CalcError::UnknownSymbol`] with the similar variables of `env`
+    pub fn unknown_symbol(name: &str, env: &dyn Env) -> Self {
+        CalcError::UnknownSymbol {
+            name: name.to_string(),
+            candidates: similar_names(name, env.var_names()),
+        }
+    }
+
+    /// [`CalcError::UnknownFunction`] with the similar functions of `env`
+    pub fn unknown_function(name: &str, env: &dyn Env) -> Self {
+        CalcError::UnknownFunction {
+            name: name.to_string(),
+            candidates: similar_names(name, env.function_names()),
+        }
+    }
 }
 
+/// maximal number of elements computed for a single sequence access
+pub const MAX_SEQUENCE_STEPS: u64 = 100_000;
+
+/// maximal number of terms of a single sum or product
+pub const MAX_SERIES_TERMS: u64 = 1_000_000;
+
+/// default maximal depth of nested calls of custom functions
+pub const DEFAULT_RECURSION_LIMIT: usize = 200;
+
+/// constants holding the last results, `ans` is the latest
+pub const ANSWERS: [&str; 3] = ["ans", "ans2", "ans3"];
+
 pub trait Env {
     fn get(&self, sym: &str) -> Option<&Number>;
 
+    fn get_list(&self, sym: &str) -> Option<&[Number]>;
+
     fn get_fun(&self, fun: &str) -> Option<&Function>;
+
+    fn get_text(&self, _sym: &str) -> Option<&str> {
+        None
+    }
+
+    fn get_matrix(&self, _sym: &str) -> Option<&Matrix> {
+        None
+    }
+
+    /// names of all variables with a number value, used for suggestions in errors
+    fn var_names(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    /// names of all functions, used for suggestions in errors
+    fn function_names(&self) -> Vec<&str> {
+        Vec::new()
+    }
+
+    /// IEEE semantics: `1 / 0` is `inf` and `sqrt(-1)` is `NaN` instead of an error
+    fn ieee(&self) -> bool {
+        false
+    }
+
+    /// tolerance of comparisons and of the solver, see [`nearly_equal`]
+    fn tolerance(&self) -> Number {
+        0.0
+    }
+
+    /// number of custom function calls being evaluated
+    fn depth(&self) -> usize {
+        0
+    }
+
+    /// maximal depth of nested calls of custom functions
+    fn recursion_limit(&self) -> usize {
+        DEFAULT_RECURSION_LIMIT
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum VarValue {
+    Number(Number),
+    List(Vec<Number>),
+    Matrix(Matrix),
+    Text(String),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 struct EnvVariable {
-    value: Number,
+    value: VarValue,
     is_const: bool,
 }
 
 impl EnvVariable {
     fn new_const(value: Number) -> EnvVariable {
         EnvVariable {
-            value,
+            value: VarValue::Number(value),
             is_const: true,
         }
     }
 
-    fn new(value: Number) -> EnvVariable {
+    fn new(value: VarValue) -> EnvVariable {
         EnvVariable {
             value,
             is_const: false,
@@ -49,77 +205,497 @@ impl EnvVariable {
     }
 }
 
+/// Global variables and functions.
+/// The maps are persistent, so cloning an environment shares its entries.
 #[derive(Debug, Clone, PartialEq)]
 pub struct TopLevelEnv {
-    vars: HashMap<String, EnvVariable>,
-    funs: HashMap<String, Function>,
+    vars: im::HashMap<String, EnvVariable>,
+    funs: im::HashMap<String, Function>,
+    /// formulas of variables, which are recomputed when their dependencies change
+    formulas: im::HashMap<String, Operand>,
+    /// equations stored by `store name: lhs = rhs`
+    equations: im::HashMap<String, Equation>,
+    ieee: bool,
+    tolerance: Number,
+    recursion_limit: usize,
 }
 
 impl TopLevelEnv {
-    pub fn put(&mut self, sym: String, num: Number) -> Result<(), CalcError> {
+    fn var(&self, sym: &str) -> Option<&EnvVariable> {
+        self.vars.get(sym)
+    }
+
+    fn fun(&self, name: &str) -> Option<&Function> {
+        self.funs.get(name)
+    }
+
+    fn put_value(&mut self, sym: String, value: VarValue) -> Result<(), CalcError> {
         if let Some(var) = self.vars.get_mut(&sym) {
             if var.is_const {
                 return Err(CalcError::CannotChangeConstant(sym));
             } else {
-                var.value = num;
+                var.value = value;
             }
         } else {
-            self.vars.insert(sym, EnvVariable::new(num));
+            self.vars.insert(sym, EnvVariable::new(value));
         }
         Ok(())
     }
 
+    pub fn put(&mut self, sym: String, num: Number) -> Result<(), CalcError> {
+        self.put_value(sym, VarValue::Number(num))
+    }
+
+    pub fn set_ieee(&mut self, ieee: bool) {
+        self.ieee = ieee;
+    }
+
+    pub fn set_tolerance(&mut self, tolerance: Number) {
+        self.tolerance = tolerance;
+    }
+
+    pub fn set_recursion_limit(&mut self, limit: usize) {
+        self.recursion_limit = limit;
+    }
+
+    pub fn put_list(&mut self, sym: String, list: Vec<Number>) -> Result<(), CalcError> {
+        self.put_value(sym, VarValue::List(list))
+    }
+
+    pub fn put_matrix(&mut self, sym: String, matrix: Matrix) -> Result<(), CalcError> {
+        self.put_value(sym, VarValue::Matrix(matrix))
+    }
+
+    pub fn put_text(&mut self, sym: String, text: String) -> Result<(), CalcError> {
+        self.put_value(sym, VarValue::Text(text))
+    }
+
+    /// stores `num` as `ans` and shifts the previous answers to `ans2` and `ans3`
+    pub fn put_answer(&mut self, num: Number) {
+        for idx in (1..ANSWERS.len()).rev() {
+            if let Some(prev) = self.var(ANSWERS[idx - 1]).cloned() {
+                self.vars.insert(ANSWERS[idx].to_string(), prev);
+            }
+        }
+        self.vars
+            .insert(ANSWERS[0].to_string(), EnvVariable::new_const(num));
+    }
+
+    /// removes the value of the variable `sym`, but keeps its formula
+    pub fn remove(&mut self, sym: &str) -> Result<(), CalcError> {
+        if self.var(sym).is_some_and(|var| var.is_const) {
+            return Err(CalcError::CannotChangeConstant(sym.to_string()));
+        }
+        self.vars.remove(sym);
+        Ok(())
+    }
+
+    pub fn put_formula(&mut self, sym: String, op: Operand) {
+        self.formulas.insert(sym, op);
+    }
+
+    pub fn remove_formula(&mut self, sym: &str) {
+        self.formulas.remove(sym);
+    }
+
+    pub fn formula(&self, sym: &str) -> Option<&Operand> {
+        self.formulas.get(sym)
+    }
+
+    pub fn formula_names(&self) -> impl Iterator<Item = &str> {
+        self.formulas.keys().map(String::as_str)
+    }
+
+    pub fn put_equation(&mut self, name: String, equation: Equation) {
+        self.equations.insert(name, equation);
+    }
+
+    pub fn equation(&self, name: &str) -> Option<&Equation> {
+        self.equations.get(name)
+    }
+
+    /// stored equations ordered by name
+    pub fn equations(&self) -> Vec<(&String, &Equation)> {
+        let mut equations: Vec<_> = self.equations.iter().collect();
+        equations.sort_by_key(|(name, _)| *name);
+        equations
+    }
+
+    /// names of functions, which differ from `before`
+    pub fn changed_funs(&self, before: &TopLevelEnv) -> BTreeSet<String> {
+        let changed = self
+            .funs
+            .iter()
+            .filter(|(name, fun)| before.fun(name) != Some(*fun))
+            .map(|(name, _)| name);
+        let removed = before.fun_names().filter(|name| self.fun(name).is_none());
+        changed
+            .map(String::as_str)
+            .chain(removed)
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// names of variables, which differ from `before`, without the answers
+    pub fn changed_vars(&self, before: &TopLevelEnv) -> BTreeSet<String> {
+        let changed = self
+            .vars
+            .iter()
+            .filter(|(name, var)| !var.is_const && before.var(name) != Some(*var))
+            .map(|(name, _)| name);
+        let removed = before.vars.keys().filter(|name| self.var(name).is_none());
+        changed.chain(removed).cloned().collect()
+    }
+
+    /// names of variables with a formula, which differs from `before`
+    pub fn changed_formulas(&self, before: &TopLevelEnv) -> BTreeSet<String> {
+        let changed = self
+            .formulas
+            .iter()
+            .filter(|(name, op)| before.formulas.get(*name) != Some(*op))
+            .map(|(name, _)| name);
+        let removed = before
+            .formulas
+            .keys()
+            .filter(|name| !self.formulas.contains_key(*name));
+        changed.chain(removed).cloned().collect()
+    }
+
+    /// has `sym` a value, e.g. a number, list or text
+    pub fn has_var(&self, sym: &str) -> bool {
+        self.var(sym).is_some()
+    }
+
+    /// is `sym` a variable, a function or has a formula
+    pub fn is_bound(&self, sym: &str) -> bool {
+        self.var(sym).is_some() || self.fun(sym).is_some() || self.formulas.contains_key(sym)
+    }
+
     pub fn put_fun(&mut self, name: String, fun: Function) {
+        // (re)defining the recurrence of a sequence keeps its initial values
+        let fun = match (fun, self.fun(&name)) {
+            (Function::Custom(mut fun), Some(Function::Sequence(seq))) if fun.args.len() == 1 => {
+                Function::Sequence(Sequence {
+                    arg: fun.args.pop().unwrap(),
+                    body: Some(fun.body),
+                    values: seq.values.clone(),
+                })
+            }
+            // clauses are added to an incomplete definition with the same arguments
+            (Function::Piecewise(mut fun), Some(Function::Piecewise(existing)))
+                if existing.args == fun.args && !existing.is_complete() =>
+            {
+                let mut clauses = existing.clauses.clone();
+                clauses.append(&mut fun.clauses);
+                Function::Piecewise(Piecewise {
+                    args: fun.args,
+                    clauses,
+                })
+            }
+            (fun, _) => fun,
+        };
         self.funs.insert(name, fun);
     }
+
+    pub fn remove_fun(&mut self, name: &str) {
+        self.funs.remove(name);
+    }
+
+    /// Removes the variable (with its formula) and the function `name` defined by the user.
+    /// A build-in function shadowed by the removed function is available again.
+    pub fn unset(&mut self, name: &str) -> Result<(), CalcError> {
+        if let Some(Function::BuildIn(_) | Function::Data(_)) = self.fun(name) {
+            return Err(CalcError::CannotRemoveBuildIn(name.to_string()));
+        }
+        let is_var = self.var(name).is_some() || self.formulas.contains_key(name);
+        let is_fun = self.fun(name).is_some();
+        if !is_var && !is_fun {
+            return Err(CalcError::unknown_symbol(name, self));
+        }
+        if is_var {
+            self.remove(name)?;
+            self.remove_formula(name);
+        }
+        if is_fun {
+            self.remove_fun(name);
+            if let Some(build_in) = TopLevelEnv::default().fun(name) {
+                self.put_fun(name.to_string(), build_in.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// Sets a value of the sequence `name`, e.g. `a(0) := 1`.
+    /// A custom function with a single argument becomes a sequence.
+    pub fn put_sequence_value(
+        &mut self,
+        name: String,
+        index: u64,
+        value: Number,
+    ) -> Result<(), CalcError> {
+        match self.funs.get_mut(&name) {
+            Some(Function::Sequence(seq)) => {
+                seq.values.insert(index, value);
+            }
+            Some(Function::Custom(fun)) if fun.args.len() == 1 => {
+                let seq = Sequence {
+                    arg: fun.args[0].clone(),
+                    body: Some(fun.body.clone()),
+                    values: [(index, value)].into_iter().collect(),
+                };
+                self.funs.insert(name, Function::Sequence(seq));
+            }
+            Some(_) => return Err(CalcError::NotASequence(name)),
+            None => {
+                let seq = Sequence {
+                    arg: "n".to_string(),
+                    body: None,
+                    values: [(index, value)].into_iter().collect(),
+                };
+                self.funs.insert(name, Function::Sequence(seq));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn fun_names(&self) -> impl Iterator<Item = &str> {
+        self.funs.keys().map(String::as_str)
+    }
+
+    /// names of the variables defined by the user
+    pub fn user_var_names(&self) -> impl Iterator<Item = &str> {
+        self.vars
+            .iter()
+            .filter(|(_, var)| !var.is_const)
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// number of variables, functions, formulas and equations defined by the user
+    pub fn binding_count(&self) -> usize {
+        let vars = self.vars.values().filter(|var| !var.is_const).count();
+        let funs = self
+            .funs
+            .values()
+            .filter(|fun| matches!(fun, Function::Custom(_) | Function::Sequence(_)))
+            .count();
+        let formulas = self
+            .formulas
+            .keys()
+            .filter(|sym| self.var(sym).is_none())
+            .count();
+        vars + funs + formulas + self.equations.len()
+    }
+
+    /// number of stored numbers in lists and values of sequences plus the bytes of texts
+    pub fn stored_numbers(&self) -> usize {
+        let lists: usize = self
+            .vars
+            .values()
+            .map(|var| match &var.value {
+                VarValue::List(list) => list.len(),
+                VarValue::Matrix(matrix) => matrix.elements().len(),
+                VarValue::Text(text) => text.len(),
+                VarValue::Number(_) => 0,
+            })
+            .sum();
+        let sequences: usize = self
+            .funs
+            .values()
+            .map(|fun| match fun {
+                Function::Sequence(seq) => seq.values.len(),
+                _ => 0,
+            })
+            .sum();
+        lists + sequences
+    }
 }
 
 impl Env for TopLevelEnv {
     fn get(&self, sym: &str) -> Option<&Number> {
-        self.vars.get(sym).map(|var| &var.value)
+        match self.var(sym).map(|var| &var.value) {
+            Some(VarValue::Number(num)) => Some(num),
+            _ => None,
+        }
+    }
+
+    fn get_list(&self, sym: &str) -> Option<&[Number]> {
+        match self.var(sym).map(|var| &var.value) {
+            Some(VarValue::List(list)) => Some(list),
+            _ => None,
+        }
+    }
+
+    fn var_names(&self) -> Vec<&str> {
+        self.vars
+            .iter()
+            .filter(|(_, var)| matches!(var.value, VarValue::Number(_)))
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+
+    fn function_names(&self) -> Vec<&str> {
+        self.fun_names().collect()
     }
 
     fn get_fun(&self, fun: &str) -> Option<&Function> {
-        self.funs.get(fun)
+        self.fun(fun)
+    }
+
+    fn get_text(&self, sym: &str) -> Option<&str> {
+        match self.var(sym).map(|var| &var.value) {
+            Some(VarValue::Text(text)) => Some(text),
+            _ => None,
+        }
     }
+
+    fn get_matrix(&self, sym: &str) -> Option<&Matrix> {
+        match self.var(sym).map(|var| &var.value) {
+            Some(VarValue::Matrix(matrix)) => Some(matrix),
+            _ => None,
+        }
+    }
+
+    fn ieee(&self) -> bool {
+        self.ieee
+    }
+
+    fn tolerance(&self) -> Number {
+        self.tolerance
+    }
+
+    fn recursion_limit(&self) -> usize {
+        self.recursion_limit
+    }
+}
+
+fn min<N: Num>(params: &[N]) -> N {
+    params[1..]
+        .iter()
+        .fold(params[0], |min, x| if *x < min { *x } else { min })
+}
+
+fn max<N: Num>(params: &[N]) -> N {
+    params[1..]
+        .iter()
+        .fold(params[0], |max, x| if *x > max { *x } else { max })
+}
+
+fn atan2<N: Num>(params: &[N]) -> N {
+    params[0].atan2(params[1])
+}
+
+fn hypot<N: Num>(params: &[N]) -> N {
+    params[0].hypot(params[1])
+}
+
+/// logarithm of `x` to the base `b`
+fn log<N: Num>(params: &[N]) -> N {
+    params[1].ln() / params[0].ln()
+}
+
+/// `n`-th root of `x`, which is real for negative `x` and odd `n`
+fn root<N: Num>(params: &[N]) -> N {
+    real_pow(params[1], N::from_f64(1.0) / params[0])
+}
+
+/// the build-in function `name` of several numbers, if it only needs the operations of `Num`
+pub(crate) fn multi_arg_function<N: Num>(name: &str) -> Option<fn(&[N]) -> N> {
+    match name {
+        "min" => Some(min),
+        "max" => Some(max),
+        "atan2" => Some(atan2),
+        "hypot" => Some(hypot),
+        "log" => Some(log),
+        "root" => Some(root),
+        _ => None,
+    }
+}
+
+fn multi_arg_functions() -> Vec<BuildInFunction> {
+    let function = |name: &str, args: &[&str], variadic, body| BuildInFunction {
+        name: name.to_string(),
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+        variadic,
+        body: BuildInBody::Many(body),
+    };
+    vec![
+        function("min", &["a"], true, &min::<Number>),
+        function("max", &["a"], true, &max::<Number>),
+        function("atan2", &["y", "x"], false, &atan2::<Number>),
+        function("hypot", &["x", "y"], false, &hypot::<Number>),
+        function("log", &["b", "x"], false, &log::<Number>),
+        function("root", &["n", "x"], false, &root::<Number>),
+    ]
 }
 
 impl Default for TopLevelEnv {
     fn default() -> Self {
         let funs = {
-            let mut funs = HashMap::new();
+            let mut funs = im::HashMap::new();
 
             macro_rules! buildin {
                 ($($id:ident) +) => {
                     $(
-                        fn $id(x: Number) -> Number { x.$id() }
-                        funs.insert(stringify!($id).to_string(), Function::BuildIn(BuildInFunction {
-                            name: stringify!($id).to_string(),
-                            arg: "x".to_string(),
-                            body: &$id,
-                        }));
+                        fn $id(x: Number) -> Number { Num::$id(x) }
+                        funs.insert(
+                            stringify!($id).to_string(),
+                            Function::BuildIn(BuildInFunction::unary(stringify!($id), &$id)),
+                        );
                     )+
                 }
             }
 
             buildin!(abs sqrt sin sinh cos cosh tan tanh exp ln log2 log10 atan atanh asin asinh acos acosh);
 
+            for fun in multi_arg_functions()
+                .into_iter()
+                .chain(crate::gamma::functions())
+            {
+                funs.insert(fun.name.clone(), Function::BuildIn(fun));
+            }
+
+            #[cfg(not(feature = "embedded"))]
+            for fun in crate::interpolation::functions()
+                .into_iter()
+                .chain(crate::linalg::functions())
+            {
+                funs.insert(fun.name.clone(), Function::Data(fun));
+            }
+            for fun in crate::random::functions()
+                .into_iter()
+                .chain(crate::higher_order::functions())
+                .chain(crate::statistics::functions())
+            {
+                funs.insert(fun.name.clone(), Function::Data(fun));
+            }
+            #[cfg(feature = "fft")]
+            for fun in crate::fft::functions() {
+                funs.insert(fun.name.clone(), Function::Data(fun));
+            }
+            #[cfg(feature = "finance")]
+            for fun in crate::finance::functions() {
+                funs.insert(fun.name.clone(), Function::Data(fun));
+            }
+
             funs
         };
 
         let vars = {
-            let mut vars = HashMap::new();
+            let mut vars = im::HashMap::new();
 
             macro_rules! buildin {
                 ($($id:ident) +) => {
                     $(
                         vars.insert(
                             stringify!($id).to_string().to_lowercase(),
-                            EnvVariable::new_const(std::f64::consts::$id));
+                            EnvVariable::new_const(Number::from_f64(std::f64::consts::$id)));
                     )+
                 }
             }
 
+            #[cfg(feature = "embedded")]
+            buildin!(E PI TAU);
+            #[cfg(not(feature = "embedded"))]
             buildin!(
                 E
                 FRAC_1_PI FRAC_1_SQRT_2 FRAC_2_PI FRAC_2_SQRT_PI FRAC_PI_2
@@ -127,47 +703,716 @@ impl Default for TopLevelEnv {
                 LN_2 LN_10 LOG2_10 LOG2_E LOG10_2 LOG10_E
                 PI SQRT_2 TAU);
 
+            for answer in ANSWERS {
+                vars.insert(answer.to_string(), EnvVariable::new_const(0.0));
+            }
+
             vars
         };
 
-        Self { vars, funs }
+        Self {
+            vars,
+            funs,
+            formulas: im::HashMap::new(),
+            equations: im::HashMap::new(),
+            ieee: false,
+            tolerance: 0.0,
+            recursion_limit: DEFAULT_RECURSION_LIMIT,
+        }
+    }
+}
+
+/// Bindings of a call of a custom function, a `let` or a series on top of `parent`.
+/// The evaluator binds numbers of its number type `N`, other evaluations, e.g. of data
+/// functions, see them through [`Env`] converted to [`Number`].
+pub(crate) struct ScopedEnv<'a, N: Num = Number> {
+    parent: &'a dyn Env,
+    /// the enclosing scope of the same evaluation
+    outer: Option<&'a ScopedEnv<'a, N>>,
+    env: HashMap<&'a str, (N, Number)>,
+    /// functions passed as arguments, e.g. `g` in `apply_twice(g, x) := g(g(x))`
+    funs: HashMap<&'a str, &'a Function>,
+    depth: usize,
+}
+
+fn convert<A: Num, B: Num>(num: A) -> B {
+    B::from_f64(num.to_f64())
+}
+
+/// `num` as number type `N`, an error if `N` has no number of its size, e.g. `1e40` as decimal
+fn number<N: Num>(num: Number) -> Result<N, CalcError> {
+    let value: N = convert(num);
+    match value.is_nan() && !num.is_nan() {
+        true => Err(CalcError::Overflow(Operand::Number(num).to_string())),
+        false => Ok(value),
+    }
+}
+
+/// an argument of a custom function call, a function is passed by its name,
+/// e.g. `sin` in `apply_twice(sin, 1)`
+#[derive(Clone, Copy)]
+enum Param<'a, N> {
+    Number(N),
+    Function(&'a str, &'a Function),
+}
+
+impl<'a, N: Num> fmt::Display for Param<'a, N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Param::Number(num) => write!(f, "{}", num),
+            Param::Function(name, _) => f.write_str(name),
+        }
+    }
+}
+
+impl<'a, N: Num> ScopedEnv<'a, N> {
+    pub(crate) fn new(parent: &'a dyn Env, vars: impl IntoIterator<Item = (&'a str, N)>) -> Self {
+        ScopedEnv {
+            parent,
+            outer: None,
+            env: vars
+                .into_iter()
+                .map(|(name, num)| (name, (num, convert(num))))
+                .collect(),
+            funs: HashMap::new(),
+            depth: parent.depth(),
+        }
+    }
+
+    /// empty scope of the body of the custom function `name`, one level deeper than `parent`
+    pub(crate) fn nested(name: &str, parent: &'a dyn Env) -> Result<Self, CalcError> {
+        ScopedEnv::new(parent, []).deeper(name)
+    }
+
+    fn deeper(mut self, name: &str) -> Result<Self, CalcError> {
+        self.depth += 1;
+        if self.depth > self.parent.recursion_limit() {
+            return Err(CalcError::RecursionLimitExceeded {
+                name: name.to_string(),
+                limit: self.parent.recursion_limit(),
+            });
+        }
+        Ok(self)
+    }
+
+    /// scope within this one binding `vars`
+    fn scope<'b>(&'b self, vars: impl IntoIterator<Item = (&'b str, N)>) -> ScopedEnv<'b, N> {
+        ScopedEnv {
+            outer: Some(self),
+            ..ScopedEnv::new(self, vars)
+        }
+    }
+
+    /// scope of the body of a call to the custom function `name` with `args` bound to `params`,
+    /// one level deeper than this scope
+    fn call<'b>(
+        &'b self,
+        name: &str,
+        args: &'b [String],
+        params: &[Param<'b, N>],
+    ) -> Result<ScopedEnv<'b, N>, CalcError> {
+        let mut scope = self.scope([]).deeper(name)?;
+        for (arg, param) in args.iter().zip(params) {
+            match *param {
+                Param::Number(num) => {
+                    scope.env.insert(arg, (num, convert(num)));
+                }
+                Param::Function(_, fun) => {
+                    scope.funs.insert(arg, fun);
+                }
+            }
+        }
+        Ok(scope)
+    }
+
+    /// the number bound to `sym`, numbers of the environment are converted to `N`
+    fn value(&self, sym: &str) -> Option<N> {
+        if let Some((num, _)) = self.env.get(sym) {
+            return Some(*num);
+        }
+        if self.funs.contains_key(sym) {
+            return None;
+        }
+        match self.outer {
+            Some(outer) => outer.value(sym),
+            None => self.parent.get(sym).map(|num| convert(*num)),
+        }
+    }
+
+    fn calc(&self, op: &Operand) -> Result<N, CalcError> {
+        budget::spend(1)?;
+        match op {
+            Operand::Number(num) => number(*num),
+            Operand::Literal(lit) => lit
+                .text
+                .parse()
+                .map_err(|_| CalcError::Overflow(lit.text.clone())),
+            Operand::Term(term) => self.term(term),
+            Operand::Symbol(sym) => self.symbol(sym),
+            Operand::FunCall(fun_call) => self.call_function(fun_call),
+            Operand::Conditional(cond) => {
+                let holds = self.holds(&cond.guard)?;
+                self.calc(if holds { &cond.then } else { &cond.otherwise })
+            }
+            Operand::Series(series) => self.series(series),
+            Operand::Let(binding) => self.binding(binding),
+            Operand::Factorial(op) => self.factorial(op),
+            Operand::Matrix(_) => Err(CalcError::UnexpectedList(op.to_string())),
+        }
+    }
+
+    fn symbol(&self, sym: &str) -> Result<N, CalcError> {
+        match self.value(sym) {
+            Some(num) => Ok(num),
+            None if self.get_list(sym).is_some() || self.get_matrix(sym).is_some() => {
+                Err(CalcError::UnexpectedList(sym.to_string()))
+            }
+            None if self.get_text(sym).is_some() => Err(CalcError::UnexpectedText(sym.to_string())),
+            None => Err(CalcError::unknown_symbol(sym, self)),
+        }
+    }
+
+    fn binding(&self, binding: &Let) -> Result<N, CalcError> {
+        let value = self.calc(&binding.value)?;
+        self.scope([(binding.var.as_str(), value)])
+            .calc(&binding.body)
+    }
+
+    fn term(&self, term: &Term) -> Result<N, CalcError> {
+        let lhs = self.calc(&term.lhs)?;
+        let rhs = self.calc(&term.rhs)?;
+        calc_operation(term.op, lhs, rhs, self)
+    }
+
+    fn holds(&self, guard: &Guard) -> Result<bool, CalcError> {
+        Ok(guard.cmp.compare_within(
+            self.calc(&guard.lhs)?,
+            self.calc(&guard.rhs)?,
+            convert(self.tolerance()),
+        ))
+    }
+
+    /// `x!` of a non-negative integer `x`, other numbers are an error unless the
+    /// environment follows IEEE semantics
+    fn factorial(&self, op: &Operand) -> Result<N, CalcError> {
+        let x = self.calc(op)?;
+        let result = factorial(x);
+        if result.is_nan() && x.is_finite() && !self.ieee() {
+            return Err(CalcError::DomainError {
+                function: "!".to_string(),
+                argument: x.to_string(),
+            });
+        }
+        Ok(result)
+    }
+
+    fn series(&self, series: &Series) -> Result<N, CalcError> {
+        let name = series.kind.name();
+        let (zero, one) = (N::from_f64(0.0), N::from_f64(1.0));
+        let bound = |op| {
+            let bound = self.calc(op)?;
+            if bound % one == zero {
+                Ok(bound)
+            } else {
+                Err(CalcError::InvalidSeriesBound {
+                    name: name.to_string(),
+                    bound: bound.to_string(),
+                })
+            }
+        };
+        let from = bound(&series.from)?;
+        let to = bound(&series.to)?;
+        if to - from >= N::from_f64(MAX_SERIES_TERMS as f64) {
+            return Err(CalcError::SeriesTooLong {
+                name: name.to_string(),
+            });
+        }
+        let mut result = match series.kind {
+            SeriesKind::Sum => zero,
+            SeriesKind::Product => one,
+        };
+        let mut index = from;
+        while index <= to {
+            let value = self
+                .scope([(series.var.as_str(), index)])
+                .calc(&series.body)?;
+            result = match series.kind {
+                SeriesKind::Sum => result + value,
+                SeriesKind::Product => result * value,
+            };
+            index = index + one;
+        }
+        Ok(result)
+    }
+
+    /// Evaluates the arguments of a call. A symbol naming a function and no variable
+    /// passes the function.
+    fn params<'b>(&'b self, params: &'b [Operand]) -> Result<Vec<Param<'b, N>>, CalcError> {
+        params
+            .iter()
+            .map(|op| match op {
+                Operand::Symbol(name)
+                    if self.value(name).is_none() && self.get_list(name).is_none() =>
+                {
+                    match self.get_fun(name) {
+                        Some(fun) => Ok(Param::Function(name, fun)),
+                        None => self.calc(op).map(Param::Number),
+                    }
+                }
+                op => self.calc(op).map(Param::Number),
+            })
+            .collect()
+    }
+
+    fn call_function(&self, fun_call: &FunCall) -> Result<N, CalcError> {
+        let name = &fun_call.name;
+        let function = self
+            .get_fun(name)
+            .ok_or_else(|| CalcError::unknown_function(name, self))?;
+        count_call(function);
+        let act = fun_call.params.len();
+        match function {
+            Function::Custom(function) => {
+                check_count(name, act, act == function.args.len(), function.args.len())?;
+                let params = self.params(&fun_call.params)?;
+                self.call(name, &function.args, &params)?
+                    .calc(&function.body)
+            }
+            Function::Piecewise(function) => {
+                check_count(name, act, act == function.args.len(), function.args.len())?;
+                let params = self.params(&fun_call.params)?;
+                self.clauses(function, name, &params)
+            }
+            Function::BuildIn(function) => {
+                check_build_in_count(name, act, function)?;
+                self.call_build_in(name, function, &fun_call.params)
+            }
+            Function::Data(function) => self.call_data(function, fun_call),
+            Function::Sequence(seq) => {
+                check_count(name, act, act == 1, 1)?;
+                self.call_sequence(seq, name, &fun_call.params[0])
+            }
+        }
+    }
+
+    // the calls of other than custom functions are kept out of `call_function`,
+    // so the stack of recursive custom functions stays small
+
+    fn call_build_in(
+        &self,
+        name: &str,
+        function: &BuildInFunction,
+        params: &[Operand],
+    ) -> Result<N, CalcError> {
+        let params = params
+            .iter()
+            .map(|param| self.calc(param))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.build_in(name, function, &params)
+    }
+
+    fn call_data(&self, function: &DataFunction, fun_call: &FunCall) -> Result<N, CalcError> {
+        let data = if is_higher_order(&function.name) {
+            calc_higher_order(fun_call, self)?
+        } else if function.returns_list {
+            return Err(CalcError::UnexpectedList(fun_call.name.clone()));
+        } else {
+            calc_data_function_call(function, fun_call, self)?
+        };
+        match data {
+            Data::Number(num) => Ok(convert(num)),
+            Data::List(_) => Err(CalcError::UnexpectedList(fun_call.name.clone())),
+        }
+    }
+
+    fn call_sequence(&self, seq: &Sequence, name: &str, index: &Operand) -> Result<N, CalcError> {
+        let index = self.calc(index)?;
+        calc_sequence(seq, name, convert(index), self).map(convert)
+    }
+
+    /// Evaluates the already resolved `function` called as `name` with the numbers `params`.
+    pub(crate) fn call_resolved(
+        &self,
+        name: &str,
+        function: &Function,
+        params: &[N],
+    ) -> Result<N, CalcError> {
+        let act = params.len();
+        match function {
+            Function::Custom(function) => {
+                check_count(name, act, act == function.args.len(), function.args.len())?;
+                let params: Vec<Param<N>> = params.iter().map(|num| Param::Number(*num)).collect();
+                self.call(name, &function.args, &params)?
+                    .calc(&function.body)
+            }
+            Function::Piecewise(function) => {
+                check_count(name, act, act == function.args.len(), function.args.len())?;
+                let params: Vec<Param<N>> = params.iter().map(|num| Param::Number(*num)).collect();
+                self.clauses(function, name, &params)
+            }
+            Function::BuildIn(function) => {
+                check_build_in_count(name, act, function)?;
+                self.build_in(name, function, params)
+            }
+            Function::Sequence(seq) => {
+                check_count(name, act, act == 1, 1)?;
+                calc_sequence(seq, name, convert(params[0]), self).map(convert)
+            }
+            Function::Data(_) => self.call_function(&FunCall {
+                name: name.to_string(),
+                params: params
+                    .iter()
+                    .map(|num| Operand::Number(convert(*num)))
+                    .collect(),
+            }),
+        }
+    }
+
+    /// evaluates the body of the first clause of `function` matching `params`
+    fn clauses(
+        &self,
+        function: &Piecewise,
+        name: &str,
+        params: &[Param<N>],
+    ) -> Result<N, CalcError> {
+        let scope = self.call(name, &function.args, params)?;
+        for clause in &function.clauses {
+            let matches = match &clause.guard {
+                Some(guard) => scope.holds(guard)?,
+                None => true,
+            };
+            if matches {
+                return scope.calc(&clause.body);
+            }
+        }
+        Err(CalcError::NoMatchingClause {
+            name: name.to_string(),
+            args: params
+                .iter()
+                .map(|param| param.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        })
+    }
+
+    fn build_in(
+        &self,
+        name: &str,
+        function: &BuildInFunction,
+        params: &[N],
+    ) -> Result<N, CalcError> {
+        let result = match (&function.body, params) {
+            (BuildInBody::Unary(_), [x]) => N::unary(&function.name, *x),
+            (BuildInBody::Many(_), params) => {
+                multi_arg_function(&function.name).map(|body| body(params))
+            }
+            _ => None,
+        };
+        let result = match result {
+            Some(result) => result,
+            // other functions, e.g. `gamma`, only exist for `Number`
+            None => {
+                let params: Vec<Number> = params.iter().map(|num| convert(*num)).collect();
+                convert(function.call(&params)?)
+            }
+        };
+        check_domain(name, params, result, self)
+    }
+}
+
+fn check_count(name: &str, act: usize, accepted: bool, exp: usize) -> Result<(), CalcError> {
+    match accepted {
+        true => Ok(()),
+        false => Err(CalcError::UnexpectedNumberOfParameters {
+            name: name.to_string(),
+            act,
+            exp,
+        }),
     }
 }
 
-struct ScopedEnv<'a> {
+/// a variadic function takes at least its arguments
+fn check_build_in_count(
+    name: &str,
+    act: usize,
+    function: &BuildInFunction,
+) -> Result<(), CalcError> {
+    match (function.accepts(act), function.variadic) {
+        (true, _) => Ok(()),
+        (false, true) => Err(CalcError::TooFewParameters {
+            name: name.to_string(),
+            act,
+            min: function.args.len(),
+        }),
+        (false, false) => check_count(name, act, false, function.args.len()),
+    }
+}
+
+impl<'a, N: Num> Env for ScopedEnv<'a, N> {
+    fn get(&self, sym: &str) -> Option<&Number> {
+        if self.funs.contains_key(sym) {
+            return None;
+        }
+        match self.env.get(sym) {
+            Some((_, num)) => Some(num),
+            None => self.parent.get(sym),
+        }
+    }
+
+    fn get_list(&self, sym: &str) -> Option<&[Number]> {
+        if self.env.contains_key(sym) {
+            None
+        } else {
+            self.parent.get_list(sym)
+        }
+    }
+
+    fn get_fun(&self, fun: &str) -> Option<&Function> {
+        match self.funs.get(fun) {
+            Some(fun) => Some(*fun),
+            None => self.parent.get_fun(fun),
+        }
+    }
+
+    fn get_text(&self, sym: &str) -> Option<&str> {
+        if self.env.contains_key(sym) {
+            None
+        } else {
+            self.parent.get_text(sym)
+        }
+    }
+
+    fn get_matrix(&self, sym: &str) -> Option<&Matrix> {
+        if self.env.contains_key(sym) {
+            None
+        } else {
+            self.parent.get_matrix(sym)
+        }
+    }
+
+    fn var_names(&self) -> Vec<&str> {
+        let mut names = self.parent.var_names();
+        names.extend(self.env.keys().copied());
+        names
+    }
+
+    fn function_names(&self) -> Vec<&str> {
+        self.parent.function_names()
+    }
+
+    fn ieee(&self) -> bool {
+        self.parent.ieee()
+    }
+
+    fn tolerance(&self) -> Number {
+        self.parent.tolerance()
+    }
+
+    fn depth(&self) -> usize {
+        self.depth
+    }
+
+    fn recursion_limit(&self) -> usize {
+        self.parent.recursion_limit()
+    }
+}
+
+/// Applies `op` to two numbers. Unless `env` follows IEEE semantics, a division by zero
+/// and an undefined result like `(-8) ^ 0.5` are errors, but an overflow to `inf` is not.
+/// Number types without `inf` like `Decimal` overflow to `NaN`, which is an error.
+pub fn calc_operation<N: Num>(
+    op: Operation,
+    lhs: N,
+    rhs: N,
+    env: &dyn Env,
+) -> Result<N, CalcError> {
+    let result = op.apply(lhs, rhs);
+    if result.is_finite() || !lhs.is_finite() || !rhs.is_finite() || env.ieee() {
+        return Ok(result);
+    }
+    let zero = N::from_f64(0.0);
+    match op {
+        Operation::Div | Operation::Rem if rhs == zero => Err(CalcError::DivisionByZero),
+        Operation::Pow if lhs == zero && rhs < zero => Err(CalcError::DivisionByZero),
+        // the arithmetic of finite numbers is only undefined if it overflows
+        Operation::Add | Operation::Sub | Operation::Mul | Operation::Div if result.is_nan() => {
+            Err(CalcError::Overflow(format!("{} {} {}", lhs, op, rhs)))
+        }
+        _ if result.is_nan() => Err(CalcError::DomainError {
+            function: op.to_string(),
+            argument: format!("{}, {}", lhs, rhs),
+        }),
+        _ => Ok(result),
+    }
+}
+
+/// Result of the build-in function `name`. Unless `env` follows IEEE semantics,
+/// a result that is not finite for finite `params` like `ln(0)` is an error.
+pub(crate) fn check_domain<N: Num>(
+    name: &str,
+    params: &[N],
+    result: N,
+    env: &dyn Env,
+) -> Result<N, CalcError> {
+    if result.is_finite() || params.iter().any(|param| !param.is_finite()) || env.ieee() {
+        Ok(result)
+    } else {
+        Err(CalcError::DomainError {
+            function: name.to_string(),
+            argument: params
+                .iter()
+                .map(|param| param.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        })
+    }
+}
+
+/// environment of a single step of a sequence evaluation,
+/// the sequence `name` only knows the values computed so far
+struct SequenceEnv<'a> {
     parent: &'a dyn Env,
-    env: HashMap<&'a str, &'a Number>,
+    name: &'a str,
+    known: Function,
 }
 
-impl<'a> Env for ScopedEnv<'a> {
+impl<'a> Env for SequenceEnv<'a> {
     fn get(&self, sym: &str) -> Option<&Number> {
-        self.env.get(sym).copied().or_else(|| self.parent.get(sym))
+        self.parent.get(sym)
+    }
+
+    fn get_list(&self, sym: &str) -> Option<&[Number]> {
+        self.parent.get_list(sym)
     }
 
     fn get_fun(&self, fun: &str) -> Option<&Function> {
-        self.parent.get_fun(fun)
+        if fun == self.name {
+            Some(&self.known)
+        } else {
+            self.parent.get_fun(fun)
+        }
+    }
+
+    fn get_text(&self, sym: &str) -> Option<&str> {
+        self.parent.get_text(sym)
+    }
+
+    fn get_matrix(&self, sym: &str) -> Option<&Matrix> {
+        self.parent.get_matrix(sym)
+    }
+
+    fn var_names(&self) -> Vec<&str> {
+        self.parent.var_names()
+    }
+
+    fn function_names(&self) -> Vec<&str> {
+        self.parent.function_names()
+    }
+
+    fn ieee(&self) -> bool {
+        self.parent.ieee()
+    }
+
+    fn tolerance(&self) -> Number {
+        self.parent.tolerance()
+    }
+
+    fn depth(&self) -> usize {
+        self.parent.depth()
+    }
+
+    fn recursion_limit(&self) -> usize {
+        self.parent.recursion_limit()
     }
 }
 
-pub fn calc_term(term: &Term, env: &dyn Env) -> Result<Number, CalcError> {
-    use self::Operation::*;
-    let lhs = calc_operand(&term.lhs, env)?;
-    let rhs = calc_operand(&term.rhs, env)?;
-    Ok(match term.op {
-        Add => lhs + rhs,
-        Sub => lhs - rhs,
-        Mul => lhs * rhs,
-        Div => lhs / rhs,
-        Rem => lhs % rhs,
-        Pow => lhs.powf(rhs),
-    })
+/// Evaluates the element `index` of a sequence. Missing elements are computed bottom up
+/// from the largest known element below `index`, so each element is computed only once.
+pub fn calc_sequence(
+    seq: &Sequence,
+    name: &str,
+    index: Number,
+    env: &dyn Env,
+) -> Result<Number, CalcError> {
+    if !(index >= 0.0 && index.fract() == 0.0 && index < u64::MAX as Number) {
+        return Err(CalcError::InvalidSequenceIndex {
+            name: name.to_string(),
+            index: index.to_string(),
+        });
+    }
+    let index = index as u64;
+    if let Some(value) = seq.values.get(&index) {
+        return Ok(*value);
+    }
+    let body = seq
+        .body
+        .as_ref()
+        .ok_or_else(|| CalcError::MissingSequenceValue {
+            name: name.to_string(),
+            index,
+        })?;
+    let start = match seq.values.range(..index).next_back() {
+        Some((known, _)) => known + 1,
+        None => index,
+    };
+    if index - start >= MAX_SEQUENCE_STEPS {
+        return Err(CalcError::SequenceTooLong {
+            name: name.to_string(),
+            index,
+        });
+    }
+
+    let mut seq_env = SequenceEnv {
+        parent: env,
+        name,
+        known: Function::Sequence(Sequence {
+            arg: seq.arg.clone(),
+            body: None,
+            values: seq.values.clone(),
+        }),
+    };
+    let mut value = 0.0;
+    for step in start..=index {
+        let arg = step as Number;
+        value = calc_operand(body, &ScopedEnv::new(&seq_env, [(seq.arg.as_str(), arg)]))?;
+        if let Function::Sequence(known) = &mut seq_env.known {
+            known.values.insert(step, value);
+        }
+    }
+    Ok(value)
 }
 
-fn calc_custom_function_call(
-    function: &CustomFunction,
+/// `sample(expr, n)`: list of `n` evaluations of `expr`
+fn calc_sample(fun_call: &FunCall, env: &dyn Env) -> Result<Vec<Number>, CalcError> {
+    let invalid = |reason: &str| CalcError::InvalidArguments {
+        name: fun_call.name.clone(),
+        reason: reason.to_string(),
+    };
+    let (expr, n) = match fun_call.params.as_slice() {
+        [expr, n] => (expr, n),
+        params => {
+            return Err(CalcError::UnexpectedNumberOfParameters {
+                name: fun_call.name.clone(),
+                act: params.len(),
+                exp: 2,
+            })
+        }
+    };
+    let n: Number = calc_operand(n, env)?;
+    if !(n >= 0.0 && n.fract() == 0.0 && n <= MAX_SAMPLES as Number) {
+        return Err(invalid(&format!(
+            "n must be an integer between 0 and {}",
+            MAX_SAMPLES
+        )));
+    }
+    (0..n as usize).map(|_| calc_operand(expr, env)).collect()
+}
+
+fn calc_data_function_call(
+    function: &DataFunction,
     fun_call: &FunCall,
     env: &dyn Env,
-) -> Result<Number, CalcError> {
+) -> Result<Data, CalcError> {
     if fun_call.params.len() != function.args.len() {
         return Err(CalcError::UnexpectedNumberOfParameters {
             name: fun_call.name.clone(),
@@ -178,61 +1423,155 @@ fn calc_custom_function_call(
     let params = fun_call
         .params
         .iter()
-        .try_fold(Vec::new(), |mut params, op| {
-            params.push(calc_operand(op, env)?);
-            Ok(params)
-        })?;
-    let fun_env: HashMap<&str, &Number> = function
-        .args
-        .iter()
-        .zip(params.iter())
-        .map(|(arg, num)| (arg.as_str(), num))
-        .collect();
-    calc_operand(
-        &function.body,
-        &ScopedEnv {
-            parent: env,
-            env: fun_env,
-        },
-    )
+        .map(|op| match calc_list(op, env) {
+            Some(list) => Ok(Data::List(list?)),
+            None => Ok(Data::Number(calc_operand(op, env)?)),
+        })
+        .collect::<Result<Vec<Data>, CalcError>>()?;
+    (function.body)(&params).map_err(|reason| CalcError::InvalidArguments {
+        name: fun_call.name.clone(),
+        reason,
+    })
 }
 
-pub fn calc_function_call(fun_call: &FunCall, env: &dyn Env) -> Result<Number, CalcError> {
-    let function = env
-        .get_fun(&fun_call.name)
-        .ok_or_else(|| CalcError::UnknownFunction(fun_call.name.to_string()))?;
+pub fn calc_function_call<N: Num>(fun_call: &FunCall, env: &dyn Env) -> Result<N, CalcError> {
+    ScopedEnv::new(env, []).call_function(fun_call)
+}
+
+fn count_call(function: &Function) {
     match function {
-        Function::Custom(function) => calc_custom_function_call(function, fun_call, env),
-        Function::BuildIn(function) => {
-            if fun_call.params.len() != 1 {
-                return Err(CalcError::UnexpectedNumberOfParameters {
-                    name: fun_call.name.clone(),
-                    act: fun_call.params.len(),
-                    exp: 1,
-                });
-            }
-            let x = calc_operand(&fun_call.params[0], env)?;
-            Ok((function.body)(x))
+        Function::Custom(_) | Function::Sequence(_) | Function::Piecewise(_) => {
+            timing::count_function_calls(1)
+        }
+        Function::BuildIn(_) | Function::Data(_) => timing::count_builtin_calls(1),
+    }
+}
+
+/// Evaluates the already resolved `function` called as `name` with the numbers `params`.
+pub(crate) fn calc_resolved_call<N: Num>(
+    name: &str,
+    function: &Function,
+    params: &[N],
+    env: &dyn Env,
+) -> Result<N, CalcError> {
+    count_call(function);
+    ScopedEnv::new(env, []).call_resolved(name, function, params)
+}
+
+/// Evaluates the function `name` for all `xs`.
+/// The function is resolved (and if possible compiled, otherwise put into an arena) only once.
+pub fn calc_function_many(
+    name: &str,
+    xs: &[Number],
+    env: &dyn Env,
+) -> Vec<Result<Number, CalcError>> {
+    let fun = match env.get_fun(name) {
+        Some(fun) => fun,
+        None => {
+            return xs
+                .iter()
+                .map(|_| Err(CalcError::unknown_function(name, env)))
+                .collect()
         }
+    };
+    if let Some(compiled) = CompiledFunction::compile(fun, env) {
+        return compiled
+            .eval_many(xs)
+            .into_iter()
+            .zip(xs)
+            .map(|(y, x)| match y.is_finite() || env.ieee() {
+                true => Ok(y),
+                // the interpreter finds the error
+                false => calc_function_call(
+                    &FunCall {
+                        name: name.to_string(),
+                        params: vec![Operand::Number(*x)],
+                    },
+                    env,
+                ),
+            })
+            .collect();
+    }
+    let arena = match fun {
+        Function::Custom(fun) if fun.args.len() == 1 => {
+            Arena::new(&fun.body).map(|body| (&fun.args[0], body))
+        }
+        _ => None,
+    };
+    match arena {
+        Some((arg, body)) => {
+            let mut evaluator = body.evaluator(env, arg);
+            xs.iter().map(|x| evaluator.eval(*x)).collect()
+        }
+        None => xs
+            .iter()
+            .map(|x| {
+                let fun_call = FunCall {
+                    name: name.to_string(),
+                    params: vec![Operand::Number(*x)],
+                };
+                calc_function_call(&fun_call, env)
+            })
+            .collect(),
     }
 }
 
-pub fn calc_operand(op: &Operand, env: &dyn Env) -> Result<Number, CalcError> {
-    use self::Operand::*;
+/// Evaluates `op` with the number type `N`, usually [`Number`]. Numbers of the syntax tree
+/// and variables of `env` are converted to `N`, calls of sequences and data functions are
+/// evaluated with [`Number`] and their results converted back.
+pub fn calc_operand<N: Num>(op: &Operand, env: &dyn Env) -> Result<N, CalcError> {
+    ScopedEnv::new(env, []).calc(op)
+}
+
+/// Evaluates a sum or product, the body is evaluated with the bound variable set to each
+/// integer from `from` to `to`. An empty range gives `0` for sums and `1` for products.
+pub fn calc_series<N: Num>(series: &Series, env: &dyn Env) -> Result<N, CalcError> {
+    ScopedEnv::new(env, []).series(series)
+}
+
+/// Evaluates list valued operands, matrices are lists of their elements in row-major order.
+/// Returns `None` if the operand does not evaluate to a list.
+pub fn calc_list(op: &Operand, env: &dyn Env) -> Option<Result<Vec<Number>, CalcError>> {
     match op {
-        Number(num) => Ok(*num),
-        Term(term) => calc_term(term, env),
-        Symbol(sym) => match env.get(sym) {
-            Some(num) => Ok(*num),
-            None => Err(CalcError::UnknownSymbol(sym.clone())),
+        Operand::Symbol(sym) => env
+            .get_list(sym)
+            .or_else(|| env.get_matrix(sym).map(Matrix::elements))
+            .map(|list| Ok(list.to_vec())),
+        Operand::Matrix(_) | Operand::Term(_) => {
+            calc_array(op, env).map(|array| array.map(Array::into_elements))
+        }
+        Operand::FunCall(fun_call) if is_transpose_call(fun_call, env) => {
+            calc_array(op, env).map(|array| array.map(Array::into_elements))
+        }
+        Operand::FunCall(fun_call) => match env.get_fun(&fun_call.name) {
+            Some(Function::Data(function)) if function.name == SAMPLE => {
+                Some(calc_sample(fun_call, env))
+            }
+            Some(Function::Data(function))
+                if function.returns_list && is_higher_order(&function.name) =>
+            {
+                Some(calc_higher_order(fun_call, env).map(|data| match data {
+                    Data::List(list) => list,
+                    Data::Number(num) => vec![num],
+                }))
+            }
+            Some(Function::Data(function)) if function.returns_list => Some(
+                calc_data_function_call(function, fun_call, env).map(|data| match data {
+                    Data::List(list) => list,
+                    Data::Number(num) => vec![num],
+                }),
+            ),
+            _ => None,
         },
-        FunCall(fun_call) => calc_function_call(fun_call, env),
+        _ => None,
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::parser::parse_expression;
+    use assert_approx_eq::assert_approx_eq;
 
     #[test]
     fn read_env_empty() {
@@ -249,6 +1588,67 @@ mod tests {
         assert_eq!(Some(&12.0), env.get("x"));
     }
 
+    #[test]
+    fn read_env_list() {
+        let mut env = TopLevelEnv::default();
+        env.put_list("xs".to_string(), vec![1.0, 2.0]).unwrap();
+
+        assert_eq!(Some(&[1.0, 2.0][..]), env.get_list("xs"));
+        assert_eq!(None, env.get("xs"));
+        assert_eq!(
+            Err(CalcError::UnexpectedList("xs".to_string())),
+            calc_operand::<Number>(&Operand::Symbol("xs".to_string()), &env)
+        );
+        assert_eq!(
+            Some(Ok(vec![1.0, 2.0])),
+            calc_list(&Operand::Symbol("xs".to_string()), &env)
+        );
+        assert_eq!(None, calc_list(&Operand::Number(1.0), &env));
+    }
+
+    #[test]
+    fn put_list_constant() {
+        let mut env = TopLevelEnv::default();
+        assert_eq!(
+            Err(CalcError::CannotChangeConstant("pi".to_string())),
+            env.put_list("pi".to_string(), vec![])
+        );
+    }
+
+    #[test]
+    fn unset_definitions() {
+        let mut env = TopLevelEnv::default();
+        let body = Operand::Symbol("x".to_string());
+        let fun = Function::Custom(CustomFunction {
+            args: vec!["x".to_string()],
+            body,
+        });
+        env.put_fun("sin".to_string(), fun.clone());
+        env.put_fun("f".to_string(), fun);
+        env.put("a".to_string(), 1.0).unwrap();
+        env.unset("a").unwrap();
+        env.unset("f").unwrap();
+        env.unset("sin").unwrap();
+        assert_eq!(None, env.get("a"));
+        assert_eq!(None, env.get_fun("f"));
+        assert!(matches!(env.get_fun("sin"), Some(Function::BuildIn(_))));
+        assert_eq!(
+            Err(CalcError::CannotRemoveBuildIn("sin".to_string())),
+            env.unset("sin")
+        );
+        assert_eq!(
+            Err(CalcError::CannotChangeConstant("pi".to_string())),
+            env.unset("pi")
+        );
+        assert_eq!(
+            Err(CalcError::UnknownSymbol {
+                name: "a".to_string(),
+                candidates: vec![],
+            }),
+            env.unset("a")
+        );
+    }
+
     #[test]
     fn calc_number_atom() {
         assert_eq!(
@@ -260,8 +1660,11 @@ mod tests {
     #[test]
     fn calc_sym_unknown() {
         assert_eq!(
-            Err(CalcError::UnknownSymbol("x".to_string())),
-            calc_operand(&Operand::Symbol("x".to_string()), &TopLevelEnv::default())
+            Err(CalcError::UnknownSymbol {
+                name: "x".to_string(),
+                candidates: vec![],
+            }),
+            calc_operand::<Number>(&Operand::Symbol("x".to_string()), &TopLevelEnv::default())
         );
     }
 
@@ -290,7 +1693,10 @@ mod tests {
         let op = Operation::Add;
         assert_eq!(
             Ok(7.0),
-            calc_term(&Term { op, lhs, rhs }, &TopLevelEnv::default())
+            calc_operand(
+                &Operand::Term(Box::new(Term { op, lhs, rhs })),
+                &TopLevelEnv::default()
+            )
         );
     }
 
@@ -301,7 +1707,10 @@ mod tests {
         let op = Operation::Sub;
         assert_eq!(
             Ok(-1.0),
-            calc_term(&Term { op, lhs, rhs }, &TopLevelEnv::default())
+            calc_operand(
+                &Operand::Term(Box::new(Term { op, lhs, rhs })),
+                &TopLevelEnv::default()
+            )
         );
     }
 
@@ -312,7 +1721,10 @@ mod tests {
         let op = Operation::Mul;
         assert_eq!(
             Ok(12.0),
-            calc_term(&Term { op, lhs, rhs }, &TopLevelEnv::default())
+            calc_operand(
+                &Operand::Term(Box::new(Term { op, lhs, rhs })),
+                &TopLevelEnv::default()
+            )
         );
     }
 
@@ -323,7 +1735,10 @@ mod tests {
         let op = Operation::Div;
         assert_eq!(
             Ok(3.0),
-            calc_term(&Term { op, lhs, rhs }, &TopLevelEnv::default())
+            calc_operand(
+                &Operand::Term(Box::new(Term { op, lhs, rhs })),
+                &TopLevelEnv::default()
+            )
         );
     }
 
@@ -334,7 +1749,10 @@ mod tests {
         let op = Operation::Rem;
         assert_eq!(
             Ok(2.0),
-            calc_term(&Term { op, lhs, rhs }, &TopLevelEnv::default())
+            calc_operand(
+                &Operand::Term(Box::new(Term { op, lhs, rhs })),
+                &TopLevelEnv::default()
+            )
         );
     }
 
@@ -345,7 +1763,10 @@ mod tests {
         let op = Operation::Pow;
         assert_eq!(
             Ok(81.0),
-            calc_term(&Term { op, lhs, rhs }, &TopLevelEnv::default())
+            calc_operand(
+                &Operand::Term(Box::new(Term { op, lhs, rhs })),
+                &TopLevelEnv::default()
+            )
         );
     }
 
@@ -364,12 +1785,8 @@ mod tests {
             args: vec!["x".to_string(), "y".to_string()],
             body: Operand::Term(Box::new(Term { lhs, rhs, op })),
         });
-        let mut funs = HashMap::new();
-        funs.insert("fun".to_string(), function);
-        let env = TopLevelEnv {
-            vars: HashMap::new(),
-            funs,
-        };
+        let mut env = TopLevelEnv::default();
+        env.put_fun("fun".to_string(), function);
         let expr = Operand::FunCall(FunCall {
             name: "fun".to_string(),
             params: vec![Operand::Number(4.0), Operand::Number(3.0)],
@@ -382,17 +1799,9 @@ mod tests {
         fn my_cos(x: Number) -> Number {
             x.cos()
         }
-        let function = Function::BuildIn(BuildInFunction {
-            name: "cos".to_string(),
-            arg: "x".to_string(),
-            body: &my_cos,
-        });
-        let mut funs = HashMap::new();
-        funs.insert("cos".to_string(), function);
-        let env = TopLevelEnv {
-            vars: HashMap::new(),
-            funs,
-        };
+        let function = Function::BuildIn(BuildInFunction::unary("cos", &my_cos));
+        let mut env = TopLevelEnv::default();
+        env.put_fun("cos".to_string(), function);
         let expr = Operand::FunCall(FunCall {
             name: "cos".to_string(),
             params: vec![Operand::Number(0.)],
@@ -400,10 +1809,187 @@ mod tests {
         assert_eq!(Ok(1.0), calc_operand(&expr, &env));
     }
 
+    #[test]
+    fn top_level_env_by_symbol() {
+        let mut env = TopLevelEnv::default();
+        env.put("x".to_string(), 1.0).unwrap();
+        env.put("y".to_string(), 2.0).unwrap();
+        let mut other = TopLevelEnv::default();
+        other.put("y".to_string(), 2.0).unwrap();
+        other.put("x".to_string(), 1.0).unwrap();
+        assert_eq!(env, other);
+        other.put("z".to_string(), 3.0).unwrap();
+        other.remove("x").unwrap();
+        assert_eq!(
+            BTreeSet::from(["x".to_string(), "z".to_string()]),
+            other.changed_vars(&env)
+        );
+        assert_eq!(Some(&3.0), other.get("z"));
+        assert_eq!(
+            Err(CalcError::CannotChangeConstant("pi".to_string())),
+            other.remove("pi")
+        );
+    }
+
     #[test]
     fn top_level_env_build_ins() {
         let env = TopLevelEnv::default();
         assert!(env.get_fun("sin").is_some());
         assert!(env.get_fun("cos").is_some());
     }
+
+    #[test]
+    fn multi_arg_build_ins() {
+        let env = TopLevelEnv::default();
+        let calc = |s: &str| calc_operand(&parse_expression(s).unwrap(), &env);
+        assert_eq!(Ok(1.0), calc("min(3, 1, 2)"));
+        assert_eq!(Ok(3.0), calc("max(3, 1, 2)"));
+        assert_eq!(Ok(2.0), calc("max(1, 2)"));
+        assert_eq!(Ok(5.0), calc("hypot(3, 4)"));
+        assert_approx_eq!(
+            Number::from_f64(std::f64::consts::FRAC_PI_2),
+            calc("atan2(1, 0)").unwrap()
+        );
+        assert_approx_eq!(3.0, calc("log(2, 8)").unwrap());
+        assert_eq!(Ok(1.0), calc("min(1)"));
+        assert_eq!(
+            Err(CalcError::TooFewParameters {
+                name: "max".to_string(),
+                act: 0,
+                min: 1,
+            }),
+            calc("max()")
+        );
+        assert_eq!(
+            "Unexpected number of parameters for call to `max` - expected at least 1, but got 0",
+            calc("max()").unwrap_err().to_string()
+        );
+        assert_eq!(
+            Err(CalcError::UnexpectedNumberOfParameters {
+                name: "atan2".to_string(),
+                act: 3,
+                exp: 2,
+            }),
+            calc("atan2(1, 2, 3)")
+        );
+    }
+
+    #[test]
+    fn unknown_name_suggestions() {
+        let mut env = TopLevelEnv::default();
+        env.put("speed".to_string(), 2.0).unwrap();
+        let calc = |s: &str| calc_operand::<Number>(&parse_expression(s).unwrap(), &env);
+        let err = calc("sni(1)").unwrap_err();
+        assert_eq!(
+            CalcError::UnknownFunction {
+                name: "sni".to_string(),
+                candidates: vec!["sin".to_string()],
+            },
+            err
+        );
+        assert_eq!(
+            "Unknown function `sni` - did you mean `sin`?",
+            err.to_string()
+        );
+        assert_eq!(
+            "Unknown symbol `sped` - did you mean `speed`?",
+            calc("2 * sped").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Unknown function `sinn` - did you mean `sin` or `sinh`?",
+            calc("sinn(1)").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "Unknown symbol `qwerty`",
+            calc("qwerty").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn domain_errors() {
+        let mut env = TopLevelEnv::default();
+        let calc = |s: &str, env: &TopLevelEnv| calc_operand(&parse_expression(s).unwrap(), env);
+        assert_eq!(Err(CalcError::DivisionByZero), calc("1 / 0", &env));
+        assert_eq!(Err(CalcError::DivisionByZero), calc("5 % 0", &env));
+        assert_eq!(Err(CalcError::DivisionByZero), calc("0 ^ -1", &env));
+        assert_eq!(
+            Err(CalcError::DomainError {
+                function: "sqrt".to_string(),
+                argument: "-1".to_string(),
+            }),
+            calc("sqrt(-1)", &env)
+        );
+        assert_eq!(
+            Err(CalcError::DomainError {
+                function: "log".to_string(),
+                argument: "2, 0".to_string(),
+            }),
+            calc("log(2, 0)", &env)
+        );
+        assert!(matches!(
+            calc("(-8) ^ 0.5", &env),
+            Err(CalcError::DomainError { .. })
+        ));
+        assert_eq!(Ok(Number::INFINITY), calc("10 ^ 400", &env));
+        assert_eq!(Ok(-2.0), calc("(-8) ^ (1 / 3)", &env));
+        assert_eq!(Ok(-3.0), calc("root(3, -27)", &env));
+        assert!(matches!(
+            calc("root(2, -4)", &env),
+            Err(CalcError::DomainError { .. })
+        ));
+
+        env.set_ieee(true);
+        assert_eq!(Ok(Number::INFINITY), calc("1 / 0", &env));
+        assert_eq!(Ok(Number::NEG_INFINITY), calc("ln(0)", &env));
+        assert!(calc("sqrt(-1)", &env).unwrap().is_nan());
+    }
+
+    #[test]
+    fn other_number_type() {
+        let mut env = TopLevelEnv::default();
+        env.put("a".to_string(), 0.5).unwrap();
+        let calc_as = |line: &str| calc_operand::<f32>(&parse_expression(line).unwrap(), &env);
+        for line in [
+            "a * 2 ^ 3 - sqrt(16)",
+            "max(1, a, -2) + sum(i, 1, 4, i) + 3!",
+            "let t := a + 1; if(t > 1, t ^ 2, 0)",
+            "hypot(3, 4) + abs(-a)",
+        ] {
+            let expected = calc_operand::<Number>(&parse_expression(line).unwrap(), &env).unwrap();
+            assert_eq!(Ok(expected.to_f64() as f32), calc_as(line), "{}", line);
+        }
+        assert_eq!(
+            Ok(1.0 / 3.0),
+            calc_operand::<f64>(&parse_expression("1 / 3").unwrap(), &env)
+        );
+        assert_eq!(Ok(1.0 / 3.0), calc_as("1 / 3"));
+    }
+
+    #[test]
+    fn functions_with_other_number_type() {
+        let mut c = crate::Calculator::new();
+        for line in [
+            "f(x) := x / 3",
+            "g(h, x) := h(h(x))",
+            "sign(x) := -1 when x < 0",
+            "sign(x) := 1 otherwise",
+            "r(x) := r(x)",
+        ] {
+            c.execute(line).unwrap();
+        }
+        assert_eq!(Ok(0.5f32), c.evaluate_as("f(1.5)"));
+        assert_eq!(Ok(1.0f32), c.evaluate_as("g(f, 9)"));
+        assert_eq!(Ok(-1.0f32), c.evaluate_as("sign(-2)"));
+        assert_eq!(Ok(24.0f32), c.evaluate_as("gamma(5)"));
+        let error = |line| match c.evaluate_as::<f32>(line) {
+            Err(crate::Error::CalcError(error)) => error,
+            result => panic!("expected an error, got {:?}", result),
+        };
+        assert_eq!(CalcError::DivisionByZero, error("f(1) / 0"));
+        assert!(matches!(error("sqrt(-1)"), CalcError::DomainError { .. }));
+        assert!(matches!(
+            error("r(1)"),
+            CalcError::RecursionLimitExceeded { .. }
+        ));
+    }
 }
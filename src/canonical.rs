@@ -0,0 +1,219 @@
+//! Canonical (normal) form of operands.
+//!
+//! Sums and products are flattened, their operands sorted and constants folded,
+//! `a - b` is represented as `a + -1 * b`. So e.g. `b + a * 2 - 1` and `2 * a + (b - 1)`
+//! have the same canonical form.
+
+use crate::ast::*;
+
+use std::cmp::Ordering;
+
+fn rank(op: &Operand) -> u8 {
+    match op {
+        Operand::Number(_) | Operand::Literal(_) => 0,
+        Operand::Symbol(_) => 1,
+        Operand::FunCall(_) => 2,
+        Operand::Term(_) => 3,
+        Operand::Conditional(_) => 4,
+        Operand::Series(_) => 5,
+        Operand::Let(_) => 6,
+        Operand::Matrix(_) => 7,
+        Operand::Factorial(_) => 8,
+    }
+}
+
+/// total order of operands used to sort commutative operands
+fn compare(lhs: &Operand, rhs: &Operand) -> Ordering {
+    match (lhs, rhs) {
+        (Operand::Number(lhs), Operand::Number(rhs)) => lhs.total_cmp(rhs),
+        (Operand::Literal(lhs), Operand::Literal(rhs)) => lhs.value.total_cmp(&rhs.value),
+        (Operand::Symbol(lhs), Operand::Symbol(rhs)) => lhs.cmp(rhs),
+        (Operand::FunCall(lhs), Operand::FunCall(rhs)) => lhs
+            .name
+            .cmp(&rhs.name)
+            .then_with(|| compare_all(&lhs.params, &rhs.params)),
+        (Operand::Term(lhs), Operand::Term(rhs)) => (lhs.op as u8)
+            .cmp(&(rhs.op as u8))
+            .then_with(|| compare(&lhs.lhs, &rhs.lhs))
+            .then_with(|| compare(&lhs.rhs, &rhs.rhs)),
+        (Operand::Factorial(lhs), Operand::Factorial(rhs)) => compare(lhs, rhs),
+        (lhs, rhs) => rank(lhs).cmp(&rank(rhs)),
+    }
+}
+
+fn compare_all(lhs: &[Operand], rhs: &[Operand]) -> Ordering {
+    lhs.iter()
+        .zip(rhs.iter())
+        .map(|(lhs, rhs)| compare(lhs, rhs))
+        .find(|ord| ord.is_ne())
+        .unwrap_or_else(|| lhs.len().cmp(&rhs.len()))
+}
+
+fn term(lhs: Operand, op: Operation, rhs: Operand) -> Operand {
+    Operand::Term(Box::new(Term { op, lhs, rhs }))
+}
+
+fn negate(op: Operand) -> Operand {
+    term(Operand::Number(-1.0), Operation::Mul, op)
+}
+
+/// collects the operands of nested `+`/`-` (for `Add`) or `*` (for `Mul`) terms
+fn flatten(op: Operand, chain: Operation, operands: &mut Vec<Operand>) {
+    match op {
+        Operand::Term(t) if t.op == chain => {
+            let Term { lhs, rhs, .. } = *t;
+            flatten(lhs, chain, operands);
+            flatten(rhs, chain, operands);
+        }
+        Operand::Term(t) if chain == Operation::Add && t.op == Operation::Sub => {
+            let Term { lhs, rhs, .. } = *t;
+            flatten(lhs, chain, operands);
+            flatten(negate(rhs), chain, operands);
+        }
+        op => operands.push(op.canonicalize()),
+    }
+}
+
+fn combine(chain: Operation, operands: Vec<Operand>) -> Operand {
+    let (neutral, fold): (Number, fn(Number, Number) -> Number) = match chain {
+        Operation::Add => (0.0, |a, b| a + b),
+        _ => (1.0, |a, b| a * b),
+    };
+
+    let mut constant = neutral;
+    let mut rest = Vec::new();
+    let mut operands = operands;
+    while let Some(op) = operands.pop() {
+        match op {
+            Operand::Number(num) => constant = fold(constant, num),
+            // nested chains may appear after canonicalization of the children
+            Operand::Term(t) if t.op == chain => {
+                let Term { lhs, rhs, .. } = *t;
+                operands.push(lhs);
+                operands.push(rhs);
+            }
+            op => rest.push(op),
+        }
+    }
+    if chain == Operation::Mul && constant == 0.0 {
+        return Operand::Number(0.0);
+    }
+    rest.sort_by(compare);
+    if constant != neutral || rest.is_empty() {
+        rest.insert(0, Operand::Number(constant));
+    }
+
+    let mut it = rest.into_iter();
+    let first = it.next().unwrap();
+    it.fold(first, |lhs, rhs| term(lhs, chain, rhs))
+}
+
+impl Operand {
+    /// Returns the canonical form of the operand, semantically equal operands
+    /// (modulo commutativity, associativity and constant folding) have equal canonical forms.
+    pub fn canonicalize(&self) -> Operand {
+        match self {
+            Operand::Term(t) => match t.op {
+                Operation::Add | Operation::Sub | Operation::Mul => {
+                    let chain = if t.op == Operation::Mul {
+                        Operation::Mul
+                    } else {
+                        Operation::Add
+                    };
+                    let mut operands = Vec::new();
+                    flatten(self.clone(), chain, &mut operands);
+                    combine(chain, operands)
+                }
+                op => match (t.lhs.canonicalize(), t.rhs.canonicalize()) {
+                    (Operand::Number(lhs), Operand::Number(rhs))
+                        if op != Operation::Div || rhs != 0.0 =>
+                    {
+                        Operand::Number(op.apply(lhs, rhs))
+                    }
+                    (lhs, rhs) => term(lhs, op, rhs),
+                },
+            },
+            Operand::FunCall(fun_call) => Operand::FunCall(FunCall {
+                name: fun_call.name.clone(),
+                params: fun_call.params.iter().map(Operand::canonicalize).collect(),
+            }),
+            Operand::Conditional(cond) => Operand::Conditional(Box::new(Conditional {
+                guard: Guard {
+                    lhs: cond.guard.lhs.canonicalize(),
+                    cmp: cond.guard.cmp,
+                    rhs: cond.guard.rhs.canonicalize(),
+                },
+                then: cond.then.canonicalize(),
+                otherwise: cond.otherwise.canonicalize(),
+            })),
+            Operand::Series(series) => Operand::Series(Box::new(Series {
+                kind: series.kind,
+                var: series.var.clone(),
+                from: series.from.canonicalize(),
+                to: series.to.canonicalize(),
+                body: series.body.canonicalize(),
+            })),
+            Operand::Let(binding) => Operand::Let(Box::new(Let {
+                var: binding.var.clone(),
+                value: binding.value.canonicalize(),
+                body: binding.body.canonicalize(),
+            })),
+            Operand::Factorial(op) => op.canonicalize().factorial(),
+            Operand::Matrix(rows) => Operand::Matrix(
+                rows.iter()
+                    .map(|row| row.iter().map(Operand::canonicalize).collect())
+                    .collect(),
+            ),
+            op => op.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::{Operand, Statement};
+    use crate::parser::parse;
+
+    fn canonical(s: &str) -> Operand {
+        match parse(s).unwrap() {
+            Statement::Expression { op } => op.canonicalize(),
+            _ => panic!("not an expression"),
+        }
+    }
+
+    #[test]
+    fn canonical_commutative() {
+        assert_eq!(canonical("a + b"), canonical("b + a"));
+        assert_eq!(canonical("a * b * c"), canonical("c * (b * a)"));
+        assert_ne!(canonical("a - b"), canonical("b - a"));
+    }
+
+    #[test]
+    fn canonical_constants_folded() {
+        assert_eq!(canonical("6 * x"), canonical("2 * x * 3"));
+        assert_eq!(canonical("x"), canonical("x + 1 - 1"));
+        assert_eq!(canonical("0"), canonical("0 * sin(x)"));
+        assert_eq!(canonical("9"), canonical("3 ^ 2"));
+    }
+
+    #[test]
+    fn canonical_subtraction_as_negative_addition() {
+        assert_eq!(canonical("a + -1 * b"), canonical("a - b"));
+        assert_eq!(canonical("2 * a + (b - 1)"), canonical("b + a * 2 - 1"));
+    }
+
+    #[test]
+    fn canonical_function_params() {
+        assert_eq!(canonical("f(b + a, 2)"), canonical("f(a + b, 1 + 1)"));
+    }
+
+    #[test]
+    fn canonical_nested_chain_after_simplification() {
+        assert_eq!(canonical("a + 1"), canonical("1 * (a + 1) + 0"));
+    }
+
+    #[test]
+    fn canonical_keeps_division_by_zero() {
+        assert_eq!(canonical("1 / 0"), canonical("(0 + 1) / 0"));
+    }
+}
@@ -0,0 +1,360 @@
+//! Message templates of errors keyed by their [`Diagnostic`](crate::Diagnostic) code.
+//!
+//! Templates refer to the arguments of a diagnostic by name, e.g. `{name}`.
+//! Applications translate the messages by filling a catalog in their language.
+
+use std::collections::HashMap;
+
+const ENGLISH: &[(&str, &str)] = &[
+    ("P0001", "Invalid number - expected a floating number `{input}`"),
+    ("P0002", "Invalid operation - expected +, -, *, /, %, or ^ `{input}`"),
+    ("P0003", "Invalid operand - expected variable, number or term, but got `{input}`"),
+    ("P0004", "Invalid expression - expected variable, number or term, but got `{input}`"),
+    ("P0005", "Invalid symbol - expected  `{input}`"),
+    (
+        "P0006",
+        "Invalid statement - expected assignment, expression, or solve statement, but got `{input}`",
+    ),
+    ("P0007", "Expected statement, but got an empty line"),
+    ("P0008", "Missing assignment target - expected symbol, but got `{input}`"),
+    ("P0009", "Expected an assignment `:=`, but got `{input}`"),
+    ("P0010", "Expected an expression, but got `{input}`"),
+    ("P0011", "Expected expression in solve left from the `=`, but got `{input}`"),
+    ("P0012", "Expected expression in solve right from the `=`, but got `{input}`"),
+    ("P0013", "Expected variable name after `for`, but got `{input}`"),
+    ("P0014", "No function name found"),
+    ("P0015", "Expected expression as function body, but got nothing"),
+    ("P0016", "Expected expression as parameter value, but got `{input}`"),
+    ("P0017", "Plot is missing a function name, but got nothing"),
+    ("P0018", "Expected function name, but got {input}"),
+    ("P0019", "Expected expression in `is` left from the `==`, but got `{input}`"),
+    ("P0020", "Expected expression in `is` right from the `==`, but got `{input}`"),
+    ("P0021", "Expected a file name in quotes after `import`, but got `{input}`"),
+    ("P0022", "Expected a column number after `column`, but got `{input}`"),
+    ("P0023", "Expected a non-negative integer as index, but got `{input}`"),
+    ("P0024", "Expected two list variables after `fit linear`, but got `{input}`"),
+    ("P0025", "Not enough operands on the stack for `{input}`"),
+    ("P0026", "Expected a single result, but {count} operands are left on the stack"),
+    ("P0027", "Expected a file name in quotes after `run`, but got `{input}`"),
+    ("P0028", "Expected a comparison like `x >= 0` after `when`, but got `{input}`"),
+    ("P0029", "Plot has more than one `{input}`"),
+    (
+        "P0030",
+        "Expected the swept parameter and one plotted variable as arguments, but got `{input}`",
+    ),
+    (
+        "P0031",
+        "Expected a name and an equation like `store ohm: v = i * r`, but got `{input}`",
+    ),
+    ("P0032", "Syntax error at `{found}` - {expected}"),
+    (
+        "P0033",
+        "Expected rows with the same number of elements, but got `{input}`",
+    ),
+    ("C0001", "Unknown symbol `{name}`"),
+    (
+        "C0002",
+        "Unexpected number of parameters for call to `{name}` - expected {exp}, but got {act}",
+    ),
+    ("C0003", "Unknown function `{name}`"),
+    ("C0004", "Cannot change value of constant `{name}`"),
+    ("C0005", "Expected a number, but `{name}` is a list"),
+    ("C0006", "Invalid arguments for call to `{name}`: {reason}"),
+    ("C0007", "Index of sequence `{name}` must be a non-negative integer, but is {index}"),
+    ("C0008", "No value for `{name}({index})`"),
+    ("C0009", "Computing `{name}({index})` needs more than {max} steps"),
+    ("C0010", "`{name}` is not a sequence"),
+    ("C0011", "`{name}` is already defined - use `override` to redefine it"),
+    ("C0012", "Undefined names {names}"),
+    ("C0013", "Cyclic definition {names}"),
+    ("C0014", "No clause of `{name}` matches the arguments ({args})"),
+    ("C0015", "Expected a number, but `{name}` is a text"),
+    ("C0016", "Unknown equation `{name}`"),
+    ("C0017", "Cannot remove build-in function `{name}`"),
+    ("C0018", "Bounds of `{name}` must be integers, but got {bound}"),
+    ("C0019", "`{name}` has more than {max} terms"),
+    ("C0020", "Division by zero"),
+    ("C0021", "`{function}` is not defined for {argument}"),
+    ("C0022", "Calling `{name}` exceeds the recursion limit of {limit}"),
+    ("C0023", "Cannot apply `{op}` to {lhs} and {rhs}"),
+    ("C0024", "Expected an expression of a number, but got `{line}`"),
+    ("C0025", "Nothing to undo"),
+    ("C0026", "`{expr}` overflows the number type"),
+    (
+        "C0027",
+        "Unexpected number of parameters for call to `{name}` - expected at least {min}, but got {act}",
+    ),
+    ("S0001", "Unknown variable `{name}` in `solve ... for ...`"),
+    (
+        "S0002",
+        "Unsupported power above `^2` of variable to solve for in `solve ... for ...`",
+    ),
+    ("S0003", "Unsupported variable in denominator in `solve ... for ...`"),
+    ("S0004", "Unsupported % with solve for variable in `solve ... for ...`"),
+    ("S0005", "Unsupported power in `solve ... for ...`"),
+    ("S0006", "`solve ... for ...` contains no variable (after simplification)"),
+    (
+        "S0007",
+        "Unsupported variable `{variable}` in the arguments of function `{function}` in `solve ... for ...`",
+    ),
+    (
+        "S0008",
+        "Both sides of `solve ... for {variable} in [{from}, {to}]` differ with the same sign at both ends of the interval",
+    ),
+    (
+        "S0009",
+        "Expected a finite interval `[{from}, {to}]` with a lower bound smaller than the upper bound in `solve ... for {variable} in [...]`",
+    ),
+    ("G0001", "Unknown function `{name}` to plot"),
+    (
+        "G0002",
+        "Expected a sweep with a positive step and at most 1000 values, but got `{range}`",
+    ),
+    (
+        "G0003",
+        "Expected at most one free variable in the plotted expression, but got {names}",
+    ),
+    (
+        "G0004",
+        "Expected finite angles `[from, to]` with `from` smaller than `to`, but got `{angles}`",
+    ),
+    (
+        "G0005",
+        "Expected a finite range `min..max` with `min` smaller than `max`, but got `{range}`",
+    ),
+    (
+        "G0006",
+        "Expected an equation of at most two variables or a function of two arguments, but got `{plot}`",
+    ),
+    ("I0001", "Cannot read `{path}`: {message}"),
+    ("I0002", "Columns are counted from 1, but got column 0"),
+    ("I0003", "Line {line} has no column {column}"),
+    ("I0004", "Expected a number in line {line}, but got `{value}`"),
+    ("I0005", "No data found"),
+    ("F0001", "Expected a list, but `{name}` is not a list"),
+    ("F0002", "Both lists must have the same length, but have {xs} and {ys} elements"),
+    ("F0003", "At least two points are needed for a fit, but got {count}"),
+    ("F0004", "Cannot fit data with constant x values"),
+    ("N0001", "Expected finite bounds, but got `{from}` and `{to}`"),
+    ("N0002", "The integrand is not finite at {at}"),
+    ("N0003", "The integral does not converge to the tolerance {tolerance}"),
+    ("R0001", "Script `{name}` runs itself: {scripts}"),
+    ("R0002", "{path}:{line}: {error}"),
+    ("R0003", "line {line}, statement {statement}: {error}"),
+    ("R0004", "Script `{path}` is outside of the directory `{base}`"),
+    ("R0005", "{path}:{line}: Invalid statement"),
+    ("R0006", "File access is disabled, `{path}` is not read"),
+    (
+        "L0001",
+        "Too many definitions - at most {max} variables and functions are allowed",
+    ),
+    (
+        "L0002",
+        "Statement is too large - it has {nodes} nodes, but at most {max} are allowed",
+    ),
+    (
+        "L0003",
+        "Too much data - {count} numbers are stored, but at most {max} are allowed",
+    ),
+    (
+        "L0004",
+        "Evaluation takes too long - it needs more than {max} operations",
+    ),
+    ("L0005", "Evaluation takes too long - it needs more than {millis} ms"),
+    ("T0001", "Invalid saved state: {message}"),
+    (
+        "U0001",
+        "Cannot combine `{lhs}` with `{rhs}` - the units have different dimensions",
+    ),
+    (
+        "U0002",
+        "Expected an argument without unit for `{name}`, but got `{unit}`",
+    ),
+    ("U0003", "Expected an exponent without unit, but got `{unit}`"),
+    ("U0004", "Cannot raise `{unit}` to the power {exp}"),
+    (
+        "U0005",
+        "Variables hold numbers without unit, but `{sym}` would be in `{unit}`",
+    ),
+];
+
+/// Message templates by error code
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Catalog {
+    templates: HashMap<String, String>,
+}
+
+impl Catalog {
+    /// catalog without any templates
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the default catalog with the English messages of the errors
+    pub fn english() -> Self {
+        let mut catalog = Self::new();
+        for (code, template) in ENGLISH {
+            catalog.insert(code, template);
+        }
+        catalog
+    }
+
+    /// adds or replaces the template for `code`
+    pub fn insert(&mut self, code: &str, template: &str) {
+        self.templates
+            .insert(code.to_string(), template.to_string());
+    }
+
+    pub fn template(&self, code: &str) -> Option<&str> {
+        self.templates.get(code).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Operation;
+    use crate::calc::CalcError;
+    use crate::fit::FitError;
+    use crate::graph::GraphError;
+    use crate::import::ImportError;
+    use crate::limits::LimitError;
+    use crate::parser::ParserError;
+    use crate::solver::SolverError;
+    use crate::units::UnitError;
+    use crate::{Diagnostic, Error};
+
+    #[test]
+    fn english_matches_error_messages() {
+        let catalog = Catalog::english();
+        let errors: Vec<Error> = vec![
+            ParserError::InvalidNumber("1.2.3".to_string()).into(),
+            ParserError::EmptyStatement.into(),
+            ParserError::RpnUnconsumedOperands(2).into(),
+            crate::parser::parse("1 + * 2").unwrap_err().into(),
+            CalcError::UnexpectedNumberOfParameters {
+                name: "f".to_string(),
+                act: 1,
+                exp: 2,
+            }
+            .into(),
+            CalcError::SequenceTooLong {
+                name: "a".to_string(),
+                index: 7,
+            }
+            .into(),
+            CalcError::UndefinedNames(vec!["a".to_string(), "b".to_string()]).into(),
+            CalcError::SeriesTooLong {
+                name: "sum".to_string(),
+            }
+            .into(),
+            CalcError::DivisionByZero.into(),
+            CalcError::DomainError {
+                function: "ln".to_string(),
+                argument: "0".to_string(),
+            }
+            .into(),
+            CalcError::RecursionLimitExceeded {
+                name: "f".to_string(),
+                limit: 200,
+            }
+            .into(),
+            CalcError::IncompatibleShapes {
+                op: Operation::Mul,
+                lhs: "a 2x3 matrix".to_string(),
+                rhs: "a vector of 2 elements".to_string(),
+            }
+            .into(),
+            CalcError::NotAnExpression("a := 1".to_string()).into(),
+            CalcError::NothingToUndo.into(),
+            CalcError::Overflow("1e40 * 10".to_string()).into(),
+            CalcError::TooFewParameters {
+                name: "max".to_string(),
+                act: 0,
+                min: 1,
+            }
+            .into(),
+            SolverError::VariableInFunctionCall("x".to_string(), "sin".to_string()).into(),
+            SolverError::NoSignChange {
+                variable: "x".to_string(),
+                from: "0".to_string(),
+                to: "1".to_string(),
+            }
+            .into(),
+            SolverError::InvalidInterval {
+                variable: "x".to_string(),
+                from: "1".to_string(),
+                to: "0".to_string(),
+            }
+            .into(),
+            ImportError::MissingColumn { line: 2, column: 3 }.into(),
+            FitError::LengthMismatch(2, 3).into(),
+            crate::IntegrationError::NotFinite {
+                at: "0".to_string(),
+            }
+            .into(),
+            LimitError::TooManyNodes { nodes: 5, max: 4 }.into(),
+            LimitError::TooManyOperations { max: 1000 }.into(),
+            LimitError::Timeout { millis: 250 }.into(),
+            GraphError::InvalidSweepRange("0..5 step 0".to_string()).into(),
+            GraphError::AmbiguousVariable(vec!["x".to_string(), "y".to_string()]).into(),
+            GraphError::InvalidAngles("[1, 0]".to_string()).into(),
+            GraphError::InvalidRange("1..0".to_string()).into(),
+            GraphError::NotImplicit("a + b = c".to_string()).into(),
+            #[cfg(feature = "persistence")]
+            crate::state::StateError::InvalidState("EOF".to_string()).into(),
+            UnitError::IncompatibleUnits {
+                lhs: "m".to_string(),
+                rhs: "s".to_string(),
+            }
+            .into(),
+            UnitError::FractionalPower {
+                unit: "m".to_string(),
+                exp: "0.5".to_string(),
+            }
+            .into(),
+            crate::ScriptError::OutsideBase {
+                path: "../a.calc".to_string(),
+                base: "/calc".to_string(),
+            }
+            .into(),
+            crate::ScriptError::InvalidStatement {
+                path: "a.calc".to_string(),
+                line: 1,
+            }
+            .into(),
+            crate::ScriptError::FileAccessDisabled("a.calc".to_string()).into(),
+            crate::ScriptError::Failed {
+                line: 2,
+                statement: 3,
+                error: Box::new(
+                    CalcError::UnknownSymbol {
+                        name: "a".to_string(),
+                        candidates: vec![],
+                    }
+                    .into(),
+                ),
+            }
+            .into(),
+        ];
+        for err in errors {
+            assert_eq!(err.to_string(), err.diagnostic().localized(&catalog));
+        }
+    }
+
+    #[test]
+    fn translated_message() {
+        let mut catalog = Catalog::new();
+        catalog.insert("C0003", "Unbekannte Funktion `{name}`");
+        let diagnostic = Diagnostic::from(&CalcError::UnknownFunction {
+            name: "f".to_string(),
+            candidates: vec![],
+        });
+        assert_eq!("Unbekannte Funktion `f`", diagnostic.localized(&catalog));
+        let diagnostic = Diagnostic::from(&CalcError::UnknownSymbol {
+            name: "a".to_string(),
+            candidates: vec![],
+        });
+        assert_eq!("Unknown symbol `a`", diagnostic.localized(&catalog));
+    }
+}
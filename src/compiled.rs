@@ -0,0 +1,405 @@
+//! Compilation of functions and expressions into a flat stack program.
+//!
+//! Evaluating the program avoids walking the AST and looking up symbols for every
+//! x value. Inputs are processed in chunks of [`LANES`] values; with feature `simd`
+//! the arithmetic of a chunk runs in SIMD registers.
+
+use crate::ast::*;
+use crate::budget;
+use crate::calc::{calc_operand, CalcError, Env, ScopedEnv, TopLevelEnv};
+use crate::suggest::similar_names;
+use crate::timing;
+
+use std::cell::RefCell;
+
+/// number of x values evaluated at once
+pub const LANES: usize = 4;
+
+/// maximal depth of nested calls of custom functions, which are inlined
+const MAX_INLINE_DEPTH: usize = 16;
+
+#[cfg(all(feature = "simd", not(feature = "f32")))]
+type Lanes = wide::f64x4;
+#[cfg(all(feature = "simd", feature = "f32"))]
+type Lanes = wide::f32x4;
+
+#[cfg(not(feature = "simd"))]
+mod lanes {
+    use crate::ast::Number;
+    use crate::compiled::LANES;
+
+    use std::ops;
+
+    /// portable fallback for the SIMD vectors of `wide`
+    #[derive(Debug, Clone, Copy)]
+    pub struct Lanes([Number; LANES]);
+
+    impl Lanes {
+        pub fn splat(num: Number) -> Lanes {
+            Lanes([num; LANES])
+        }
+
+        pub fn to_array(self) -> [Number; LANES] {
+            self.0
+        }
+    }
+
+    impl From<[Number; LANES]> for Lanes {
+        fn from(lanes: [Number; LANES]) -> Lanes {
+            Lanes(lanes)
+        }
+    }
+
+    macro_rules! lanes_operation {
+        ($($trait:ident $method:ident),+) => {
+            $(
+                impl ops::$trait for Lanes {
+                    type Output = Lanes;
+
+                    fn $method(self, rhs: Lanes) -> Lanes {
+                        let mut lanes = self.0;
+                        for (lane, rhs) in lanes.iter_mut().zip(rhs.0) {
+                            *lane = ops::$trait::$method(*lane, rhs);
+                        }
+                        Lanes(lanes)
+                    }
+                }
+            )+
+        };
+    }
+
+    lanes_operation!(Add add, Sub sub, Mul mul, Div div);
+}
+
+#[cfg(not(feature = "simd"))]
+use lanes::Lanes;
+
+#[derive(Clone)]
+enum Instr {
+    Number(Number),
+    /// the argument with the index
+    Arg(usize),
+    Op(Operation),
+    Call(&'static (dyn Fn(Number) -> Number + Sync)),
+}
+
+/// A function of one variable compiled to a stack program.
+#[derive(Clone)]
+pub struct CompiledFunction {
+    code: Vec<Instr>,
+    /// maximal stack depth of the program
+    depth: usize,
+}
+
+/// Compiles `op` with the variables `args`, other variables are constants of `env`.
+/// Calls of custom functions are inlined.
+fn compile_operand(
+    op: &Operand,
+    args: &[&str],
+    env: &dyn Env,
+    depth: usize,
+    code: &mut Vec<Instr>,
+) -> Option<()> {
+    match op {
+        Operand::Number(num) => code.push(Instr::Number(*num)),
+        Operand::Literal(lit) => code.push(Instr::Number(lit.value)),
+        Operand::Symbol(sym) => match args.iter().position(|arg| arg == sym) {
+            Some(index) => code.push(Instr::Arg(index)),
+            None => code.push(Instr::Number(*env.get(sym)?)),
+        },
+        Operand::Term(term) => {
+            compile_operand(&term.lhs, args, env, depth, code)?;
+            compile_operand(&term.rhs, args, env, depth, code)?;
+            code.push(Instr::Op(term.op));
+        }
+        Operand::FunCall(fun_call) => match (env.get_fun(&fun_call.name)?, &fun_call.params[..]) {
+            (
+                Function::BuildIn(BuildInFunction {
+                    body: BuildInBody::Unary(body),
+                    ..
+                }),
+                [param],
+            ) => {
+                compile_operand(param, args, env, depth, code)?;
+                code.push(Instr::Call(*body));
+            }
+            (Function::Custom(fun), params)
+                if fun.args.len() == params.len() && depth < MAX_INLINE_DEPTH =>
+            {
+                let body = fun.body.clone().map(&mut |op| match op {
+                    Operand::Symbol(sym) => match fun.args.iter().position(|arg| *arg == sym) {
+                        Some(index) => params[index].clone(),
+                        None => Operand::Symbol(sym),
+                    },
+                    op => op,
+                });
+                compile_operand(&body, args, env, depth + 1, code)?;
+            }
+            _ => return None,
+        },
+        // the value is computed for every use of the variable
+        Operand::Let(binding) => compile_operand(&binding.inlined(), args, env, depth, code)?,
+        Operand::Conditional(_)
+        | Operand::Series(_)
+        | Operand::Factorial(_)
+        | Operand::Matrix(_) => return None,
+    }
+    Some(())
+}
+
+/// maximal stack depth of a program
+fn stack_depth(code: &[Instr]) -> usize {
+    let mut depth = 0;
+    let mut max_depth = 0;
+    for instr in code {
+        match instr {
+            Instr::Number(_) | Instr::Arg(_) => depth += 1,
+            Instr::Op(_) => depth -= 1,
+            Instr::Call(_) => (),
+        }
+        max_depth = max_depth.max(depth);
+    }
+    max_depth
+}
+
+impl CompiledFunction {
+    /// Compiles a function of one variable. Returns `None` if the function contains
+    /// anything but arithmetic, known variables and calls of build-in functions.
+    pub fn compile(fun: &Function, env: &dyn Env) -> Option<CompiledFunction> {
+        let mut code = Vec::new();
+        match fun {
+            Function::Custom(fun) if fun.args.len() == 1 => {
+                compile_operand(&fun.body, &[&fun.args[0]], env, 0, &mut code)?
+            }
+            Function::BuildIn(BuildInFunction {
+                body: BuildInBody::Unary(body),
+                ..
+            }) => code.extend([Instr::Arg(0), Instr::Call(*body)]),
+            _ => return None,
+        }
+        let depth = stack_depth(&code);
+        Some(CompiledFunction { code, depth })
+    }
+
+    fn eval_lanes(&self, x: Lanes, stack: &mut Vec<Lanes>) -> Lanes {
+        stack.clear();
+        for instr in &self.code {
+            match instr {
+                Instr::Number(num) => stack.push(Lanes::splat(*num)),
+                Instr::Arg(_) => stack.push(x),
+                Instr::Op(op) => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    stack.push(match op {
+                        Operation::Add => lhs + rhs,
+                        Operation::Sub => lhs - rhs,
+                        Operation::Mul => lhs * rhs,
+                        Operation::Div => lhs / rhs,
+                        op => {
+                            let (lhs, rhs) = (lhs.to_array(), rhs.to_array());
+                            Lanes::from(std::array::from_fn(|i| op.apply(lhs[i], rhs[i])))
+                        }
+                    });
+                }
+                Instr::Call(fun) => {
+                    let param = stack.pop().unwrap().to_array();
+                    stack.push(Lanes::from(param.map(fun)));
+                }
+            }
+        }
+        stack.pop().unwrap()
+    }
+
+    /// evaluates the function for all `xs`, all are `NaN` if the budget is exhausted
+    pub fn eval_many(&self, xs: &[Number]) -> Vec<Number> {
+        if budget::spend((self.code.len() * xs.len()) as u64).is_err() {
+            return vec![Number::NAN; xs.len()];
+        }
+        let calls = self
+            .code
+            .iter()
+            .filter(|instr| matches!(instr, Instr::Call(_)))
+            .count();
+        timing::count_builtin_calls((calls * xs.len()) as u64);
+        let mut stack = Vec::with_capacity(self.depth);
+        let mut ys = Vec::with_capacity(xs.len());
+        for chunk in xs.chunks(LANES) {
+            let mut lanes = [0.0; LANES];
+            lanes[..chunk.len()].copy_from_slice(chunk);
+            let result = self.eval_lanes(Lanes::from(lanes), &mut stack).to_array();
+            ys.extend_from_slice(&result[..chunk.len()]);
+        }
+        ys
+    }
+}
+
+/// An expression compiled by [`Calculator::compile`](crate::Calculator::compile) for
+/// repeated evaluation with different values of its variables.
+#[derive(Clone)]
+pub struct CompiledExpression {
+    /// `None` if the expression needs the interpreter, e.g. for conditionals
+    code: Option<Vec<Instr>>,
+    stack: RefCell<Vec<Number>>,
+    variables: Vec<String>,
+    op: Operand,
+    env: TopLevelEnv,
+}
+
+impl CompiledExpression {
+    /// Compiles `op`, its variables are the symbols not defined in `env`.
+    /// Defined variables and functions are resolved now, later changes do not apply.
+    pub(crate) fn new(op: Operand, env: &TopLevelEnv) -> CompiledExpression {
+        let variables: Vec<String> = op
+            .free_symbols()
+            .into_iter()
+            .filter(|sym| !env.is_bound(sym))
+            .collect();
+        let args: Vec<&str> = variables.iter().map(String::as_str).collect();
+        let mut code = Vec::new();
+        let code = compile_operand(&op, &args, env, 0, &mut code).map(|_| code);
+        let depth = code.as_deref().map_or(0, stack_depth);
+        CompiledExpression {
+            code,
+            stack: RefCell::new(Vec::with_capacity(depth)),
+            variables,
+            op,
+            env: env.clone(),
+        }
+    }
+
+    /// names of the variables, which must be given to [`CompiledExpression::eval`]
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    /// Evaluates the expression with the values of its variables,
+    /// values of other names are ignored.
+    pub fn eval(&self, values: &[(&str, Number)]) -> Result<Number, CalcError> {
+        let mut args = Vec::with_capacity(self.variables.len());
+        for var in &self.variables {
+            match values.iter().find(|(name, _)| name == var) {
+                Some((_, value)) => args.push(*value),
+                None => {
+                    return Err(CalcError::UnknownSymbol {
+                        name: var.clone(),
+                        candidates: similar_names(var, values.iter().map(|(name, _)| *name)),
+                    })
+                }
+            }
+        }
+        if let Some(code) = &self.code {
+            let result = self.run(code, &args);
+            // the interpreter finds the error of a result, which is not finite
+            if result.is_finite() || self.env.ieee() {
+                return Ok(result);
+            }
+        }
+        let scope = self
+            .variables
+            .iter()
+            .map(String::as_str)
+            .zip(args.iter().copied());
+        calc_operand(&self.op, &ScopedEnv::new(&self.env, scope))
+    }
+
+    fn run(&self, code: &[Instr], args: &[Number]) -> Number {
+        let mut stack = self.stack.borrow_mut();
+        stack.clear();
+        for instr in code {
+            match instr {
+                Instr::Number(num) => stack.push(*num),
+                Instr::Arg(index) => stack.push(args[*index]),
+                Instr::Op(op) => {
+                    let rhs = stack.pop().unwrap();
+                    let lhs = stack.pop().unwrap();
+                    stack.push(op.apply(lhs, rhs));
+                }
+                Instr::Call(fun) => {
+                    let param = stack.pop().unwrap();
+                    stack.push(fun(param));
+                }
+            }
+        }
+        stack.pop().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::{calc_operand, ScopedEnv, TopLevelEnv};
+    use crate::parser::parse;
+
+    fn function(definition: &str) -> Function {
+        match parse(definition).unwrap() {
+            Statement::Function { fun, .. } => fun,
+            _ => panic!("not a function definition"),
+        }
+    }
+
+    #[test]
+    fn compiled_matches_interpreter() {
+        let mut env = TopLevelEnv::default();
+        env.put("a".to_string(), 3.0).unwrap();
+        let fun = function("f(x) := a * x ^ 2 - sin(x) / 2 + x % 3");
+        let compiled = CompiledFunction::compile(&fun, &env).unwrap();
+        let body = match &fun {
+            Function::Custom(fun) => &fun.body,
+            _ => unreachable!(),
+        };
+
+        let xs: Vec<Number> = (-10..=10).map(|x| x as Number * 0.7).collect();
+        let ys = compiled.eval_many(&xs);
+        assert_eq!(xs.len(), ys.len());
+        for (x, y) in xs.iter().zip(ys) {
+            let exp = calc_operand::<Number>(body, &ScopedEnv::new(&env, [("x", *x)])).unwrap();
+            assert_eq!(exp, y);
+        }
+    }
+
+    #[test]
+    fn compiled_build_in() {
+        let env = TopLevelEnv::default();
+        let compiled = CompiledFunction::compile(env.get_fun("sqrt").unwrap(), &env).unwrap();
+        assert_eq!(vec![0.0, 1.0, 2.0], compiled.eval_many(&[0.0, 1.0, 4.0]));
+    }
+
+    #[test]
+    fn compiled_expression() {
+        let mut env = TopLevelEnv::default();
+        env.put("a".to_string(), 2.0).unwrap();
+        let fun = function("sq(t) := t * t + a");
+        if let Function::Custom(_) = fun {
+            env.put_fun("sq".to_string(), fun);
+        }
+        let op = crate::parser::parse_expression("sq(x) - y * sin(0)").unwrap();
+        let compiled = CompiledExpression::new(op, &env);
+        assert!(compiled.code.is_some());
+        assert_eq!(vec!["x".to_string(), "y".to_string()], compiled.variables());
+        assert_eq!(
+            Ok(11.0),
+            compiled.eval(&[("y", 1.0), ("x", 3.0), ("a", 5.0)])
+        );
+        assert_eq!(
+            Err(CalcError::UnknownSymbol {
+                name: "y".to_string(),
+                candidates: vec![],
+            }),
+            compiled.eval(&[("x", 3.0)])
+        );
+
+        let op = crate::parser::parse_expression("if(x < 0, -x, sq(x))").unwrap();
+        let interpreted = CompiledExpression::new(op, &env);
+        assert!(interpreted.code.is_none());
+        assert_eq!(Ok(3.0), interpreted.eval(&[("x", -3.0)]));
+        assert_eq!(Ok(6.0), interpreted.eval(&[("x", 2.0)]));
+    }
+
+    #[test]
+    fn compiled_unsupported() {
+        let env = TopLevelEnv::default();
+        assert!(CompiledFunction::compile(&function("f(x) := x + b"), &env).is_none());
+        assert!(CompiledFunction::compile(&function("f(x, y) := x + y"), &env).is_none());
+        assert!(CompiledFunction::compile(&function("f(x) := g(x)"), &env).is_none());
+    }
+}
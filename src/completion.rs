@@ -0,0 +1,185 @@
+//! Completion of names and keywords for frontends, e.g. a REPL or the web app.
+
+use crate::ast::Function;
+use crate::calc::{Env, TopLevelEnv};
+use crate::diagnostic::Span;
+
+/// keywords of statements in the grammar
+const KEYWORDS: &[&str] = &[
+    "as",
+    "column",
+    "define",
+    "env",
+    "fit",
+    "for",
+    "from",
+    "given",
+    "if",
+    "implicit",
+    "import",
+    "in",
+    "integrate",
+    "is",
+    "let",
+    "list",
+    "otherwise",
+    "override",
+    "plot",
+    "polar",
+    "prod",
+    "run",
+    "simplify",
+    "solve",
+    "step",
+    "store",
+    "sum",
+    "title",
+    "to",
+    "undo",
+    "unset",
+    "when",
+    "xlabel",
+    "ylabel",
+];
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord)]
+pub enum CompletionKind {
+    /// variable defined by the user
+    Variable,
+    /// build-in constant, e.g. `pi`
+    Constant,
+    /// build-in or user function
+    Function,
+    Keyword,
+}
+
+/// Candidate for the name before the cursor, see [`crate::Calculator::complete`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Completion {
+    pub text: String,
+    pub kind: CompletionKind,
+    /// the part of the line `text` replaces
+    pub span: Span,
+}
+
+/// start of the name that ends at `cursor`
+fn name_start(line: &str, cursor: usize) -> usize {
+    line[..cursor]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+        .last()
+        .map_or(cursor, |(start, _)| start)
+}
+
+/// Names and keywords starting with the name before `cursor` ordered by text,
+/// nothing after a number. `cursor` is a byte offset, beyond the line it is its end.
+pub fn complete(line: &str, cursor: usize, env: &TopLevelEnv) -> Vec<Completion> {
+    let mut cursor = cursor.min(line.len());
+    while !line.is_char_boundary(cursor) {
+        cursor -= 1;
+    }
+    let start = name_start(line, cursor);
+    let prefix = &line[start..cursor];
+    if prefix.starts_with(|c: char| c.is_ascii_digit()) {
+        return Vec::new();
+    }
+    let user_vars: Vec<&str> = env.user_var_names().collect();
+    let constants = env
+        .var_names()
+        .into_iter()
+        .filter(|name| !user_vars.contains(name))
+        .map(|name| (name, CompletionKind::Constant));
+    let candidates = user_vars
+        .iter()
+        .map(|name| (*name, CompletionKind::Variable))
+        .chain(constants)
+        .chain(env.fun_names().map(|name| (name, CompletionKind::Function)))
+        .chain(KEYWORDS.iter().map(|name| (*name, CompletionKind::Keyword)));
+    let mut completions: Vec<Completion> = candidates
+        .filter(|(name, _)| name.starts_with(prefix))
+        .map(|(name, kind)| Completion {
+            text: name.to_string(),
+            kind,
+            span: Span { start, end: cursor },
+        })
+        .collect();
+    completions.sort_by(|lhs, rhs| (&lhs.text, lhs.kind).cmp(&(&rhs.text, rhs.kind)));
+    completions.dedup_by(|rhs, lhs| lhs.text == rhs.text);
+    completions
+}
+
+/// Signature of the function `name`, e.g. `atan2(y, x)` or `min(a, ...)`.
+pub fn hint(name: &str, env: &dyn Env) -> Option<String> {
+    let args = match env.get_fun(name)? {
+        Function::Custom(fun) => fun.args.clone(),
+        Function::BuildIn(fun) if fun.variadic => [&fun.args[..], &["...".to_string()]].concat(),
+        Function::BuildIn(fun) => fun.args.clone(),
+        Function::Data(fun) => fun.args.clone(),
+        Function::Sequence(seq) => vec![seq.arg.clone()],
+        Function::Piecewise(fun) => fun.args.clone(),
+    };
+    Some(format!("{}({})", name, args.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(line: &str, cursor: usize, env: &TopLevelEnv) -> Vec<(String, CompletionKind)> {
+        complete(line, cursor, env)
+            .into_iter()
+            .map(|completion| (completion.text, completion.kind))
+            .collect()
+    }
+
+    #[test]
+    fn completes_name_before_cursor() {
+        let mut env = TopLevelEnv::default();
+        env.put("pitch".to_string(), 1.0).unwrap();
+        assert_eq!(
+            vec![
+                ("pi".to_string(), CompletionKind::Constant),
+                ("pitch".to_string(), CompletionKind::Variable),
+            ],
+            texts("2 * pi", 6, &env)
+        );
+        assert_eq!(
+            vec![("plot".to_string(), CompletionKind::Keyword)],
+            texts("plo", 3, &env)
+        );
+        #[cfg(not(feature = "embedded"))] // only basic constants
+        assert_eq!(
+            vec![
+                ("sqrt".to_string(), CompletionKind::Function),
+                ("sqrt_2".to_string(), CompletionKind::Constant),
+            ],
+            texts("sqr + 1", 3, &env)
+        );
+        assert_eq!(
+            Span { start: 0, end: 3 },
+            complete("sqr + 1", 3, &env)[0].span
+        );
+        assert_eq!(texts("1 + sqr", 7, &env), texts("1 + sqr", 100, &env));
+        assert!(complete("12", 2, &env).is_empty());
+    }
+
+    #[test]
+    fn functions_before_keywords() {
+        let env = TopLevelEnv::default();
+        let completions = texts("sum", 3, &env);
+        assert_eq!(
+            vec![("sum".to_string(), CompletionKind::Function)],
+            completions
+        );
+    }
+
+    #[test]
+    fn signatures() {
+        let env = TopLevelEnv::default();
+        assert_eq!(Some("atan2(y, x)".to_string()), hint("atan2", &env));
+        assert_eq!(Some("min(a, ...)".to_string()), hint("min", &env));
+        assert_eq!(Some("mean(xs)".to_string()), hint("mean", &env));
+        assert_eq!(None, hint("pi", &env));
+    }
+}
@@ -0,0 +1,286 @@
+//! Decimal number type for [`crate::Calculator::evaluate_as`] (requires feature `decimal`).
+//!
+//! Decimals have 28 significant digits, so `0.1 + 0.2` is exactly `0.3`, and literals keep
+//! all of their digits. A result which is no decimal number, e.g. of `sqrt(-1)`, is `NaN`,
+//! an overflow is an error. Functions which `rust_decimal` does not provide, e.g. `atan`,
+//! are computed with `f64`. Only expressions evaluated by `evaluate_as` use decimals,
+//! variables and functions are still defined with [`crate::ast::Number`].
+//! ```
+//! use rust_expression::{Calculator, Decimal};
+//! let c = Calculator::new();
+//! let interest = c.evaluate_as::<Decimal>("1000 * 1.05 ^ 3").unwrap();
+//! assert_eq!("1157.625000", interest.to_string());
+//! ```
+
+use crate::number::Num;
+
+use rust_decimal::prelude::{FromPrimitive, MathematicalOps, ToPrimitive};
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+use std::str::FromStr;
+
+/// Decimal number or `NaN`, which is neither equal to nor ordered with any number.
+#[derive(Debug, Clone, Copy)]
+pub struct Decimal(Option<rust_decimal::Decimal>);
+
+impl Decimal {
+    pub const NAN: Decimal = Decimal(None);
+
+    /// the decimal number, `None` for `NaN`
+    pub fn value(self) -> Option<rust_decimal::Decimal> {
+        self.0
+    }
+
+    fn map(self, f: impl FnOnce(rust_decimal::Decimal) -> Option<rust_decimal::Decimal>) -> Self {
+        Decimal(self.0.and_then(f))
+    }
+
+    fn via_f64(self, f: impl FnOnce(f64) -> f64) -> Self {
+        Decimal::from_f64(f(self.to_f64()))
+    }
+}
+
+impl From<rust_decimal::Decimal> for Decimal {
+    fn from(value: rust_decimal::Decimal) -> Self {
+        Decimal(Some(value))
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        matches!((self.0, other.0), (Some(lhs), Some(rhs)) if lhs == rhs)
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.0.zip(other.0).map(|(lhs, rhs)| lhs.cmp(&rhs))
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(value) => value.fmt(f),
+            None => f.write_str("NaN"),
+        }
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = rust_decimal::Error;
+
+    /// parses decimals like `1.25` or `1e-3` and `NaN`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "NaN" {
+            return Ok(Decimal::NAN);
+        }
+        rust_decimal::Decimal::from_str(s)
+            .or_else(|_| rust_decimal::Decimal::from_scientific(s))
+            .map(Decimal::from)
+    }
+}
+
+macro_rules! checked_operation {
+    ($($trait:ident $method:ident $checked:ident),+) => {
+        $(
+            impl $trait for Decimal {
+                type Output = Decimal;
+
+                fn $method(self, rhs: Decimal) -> Decimal {
+                    Decimal(self.0.zip(rhs.0).and_then(|(lhs, rhs)| lhs.$checked(rhs)))
+                }
+            }
+        )+
+    };
+}
+
+checked_operation!(
+    Add add checked_add,
+    Sub sub checked_sub,
+    Mul mul checked_mul,
+    Div div checked_div,
+    Rem rem checked_rem
+);
+
+impl Neg for Decimal {
+    type Output = Decimal;
+
+    fn neg(self) -> Decimal {
+        Decimal(self.0.map(|value| -value))
+    }
+}
+
+macro_rules! via_f64 {
+    ($($fun:ident) +) => {
+        $(
+            fn $fun(self) -> Self {
+                self.via_f64(f64::$fun)
+            }
+        )+
+    };
+}
+
+impl Num for Decimal {
+    /// the shortest representation of `value`, so `0.1` is exactly `0.1`
+    fn from_f64(value: f64) -> Self {
+        Decimal(
+            value
+                .to_string()
+                .parse()
+                .ok()
+                .or_else(|| rust_decimal::Decimal::from_f64(value)),
+        )
+    }
+
+    fn to_f64(self) -> f64 {
+        self.0.and_then(|value| value.to_f64()).unwrap_or(f64::NAN)
+    }
+
+    fn pow(self, exp: Self) -> Self {
+        let exp_value = exp.0;
+        self.map(|x| {
+            let exp = exp_value?;
+            match exp.to_i64() {
+                Some(n) if exp.fract().is_zero() => x.checked_powi(n),
+                _ => x.checked_powd(exp),
+            }
+        })
+    }
+
+    fn atan2(self, x: Self) -> Self {
+        Decimal::from_f64(self.to_f64().atan2(x.to_f64()))
+    }
+
+    fn hypot(self, other: Self) -> Self {
+        (self * self + other * other).sqrt()
+    }
+
+    fn is_finite(self) -> bool {
+        self.0.is_some()
+    }
+
+    fn is_nan(self) -> bool {
+        self.0.is_none()
+    }
+
+    fn abs(self) -> Self {
+        Decimal(self.0.map(|value| value.abs()))
+    }
+
+    fn sqrt(self) -> Self {
+        self.map(|value| value.sqrt())
+    }
+
+    fn exp(self) -> Self {
+        self.map(|value| value.checked_exp())
+    }
+
+    fn ln(self) -> Self {
+        self.map(|value| value.checked_ln())
+    }
+
+    fn log10(self) -> Self {
+        self.map(|value| value.checked_log10())
+    }
+
+    fn sin(self) -> Self {
+        self.map(|value| value.checked_sin())
+    }
+
+    fn cos(self) -> Self {
+        self.map(|value| value.checked_cos())
+    }
+
+    fn tan(self) -> Self {
+        self.map(|value| value.checked_tan())
+    }
+
+    via_f64!(sinh cosh tanh log2 atan atanh asin asinh acos acosh);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CalcError, Calculator, Error};
+
+    fn decimal(s: &str) -> Decimal {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn exact_arithmetic() {
+        let mut c = Calculator::new();
+        c.execute("price := 19.99").unwrap();
+        assert_eq!(Ok(decimal("0.3")), c.evaluate_as("0.1 + 0.2"));
+        assert_eq!(Ok(decimal("59.97")), c.evaluate_as("3 * price"));
+        assert_eq!(Ok(decimal("1.21")), c.evaluate_as("1.1 ^ 2"));
+        assert_eq!(Ok(decimal("0.5")), c.evaluate_as("2 ^ -1"));
+        assert_eq!(Ok(decimal("1.5")), c.evaluate_as("max(1, 1.5) % 2"));
+        assert_eq!(Ok(decimal("3")), c.evaluate_as("sqrt(9)"));
+        assert_eq!(Ok(decimal("1e-3")), c.evaluate_as("0.001"));
+        assert_eq!(
+            Ok(decimal("123456789.123456789")),
+            c.evaluate_as("123456789.123456789 * 1")
+        );
+        assert_eq!(
+            Ok(decimal("-0.1000000000000000000000000001")),
+            c.evaluate_as("-0.1000000000000000000000000001")
+        );
+        assert_eq!(
+            Ok(decimal("0.3333333333333333333333333333")),
+            c.evaluate_as("1 / 3")
+        );
+    }
+
+    #[test]
+    fn not_a_decimal() {
+        let c = Calculator::new();
+        assert_eq!(
+            Err(Error::CalcError(CalcError::DivisionByZero)),
+            c.evaluate_as::<Decimal>("1 / 0")
+        );
+        assert!(matches!(
+            c.evaluate_as::<Decimal>("sqrt(-1)"),
+            Err(Error::CalcError(CalcError::DomainError { .. }))
+        ));
+        assert!(matches!(
+            c.evaluate_as::<Decimal>("10 ^ 40"),
+            Err(Error::CalcError(CalcError::DomainError { .. }))
+        ));
+        assert_eq!(
+            Err(Error::CalcError(CalcError::Overflow(
+                "10000000000000000000000000000 * 10".to_string()
+            ))),
+            c.evaluate_as::<Decimal>("10000000000000000000000000000 * 10")
+        );
+        for line in ["100000000000000000000000000000 * 10", "1e40", "1e999"] {
+            assert!(
+                matches!(
+                    c.evaluate_as::<Decimal>(line),
+                    Err(Error::CalcError(CalcError::Overflow(_)))
+                ),
+                "{}",
+                line
+            );
+        }
+        assert_ne!(Decimal::NAN, Decimal::NAN);
+        assert_eq!(None, Decimal::NAN.partial_cmp(&decimal("1")));
+        assert_eq!("NaN", Decimal::from_f64(f64::INFINITY).to_string());
+    }
+
+    #[test]
+    fn functions_of_decimals() {
+        let c = Calculator::new();
+        let approx = |line, expected: f64| {
+            let value = c.evaluate_as::<Decimal>(line).unwrap().to_f64();
+            assert!((value - expected).abs() < 1e-12, "{} = {}", line, value);
+        };
+        approx("sin(pi / 2)", 1.0);
+        approx("ln(e)", 1.0);
+        approx("atan(1) * 4", std::f64::consts::PI);
+        approx("hypot(3, 4)", 5.0);
+        approx("gamma(5)", 24.0);
+    }
+}
@@ -0,0 +1,178 @@
+//! Analysis which variables and functions a definition references.
+
+use crate::ast::{Function, Operand};
+use crate::calc::{Env, TopLevelEnv};
+
+use std::collections::BTreeSet;
+
+/// Variables and functions a function references, directly or through called functions.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Dependencies {
+    pub variables: BTreeSet<String>,
+    pub functions: BTreeSet<String>,
+}
+
+fn collect(name: &str, env: &TopLevelEnv, deps: &mut Dependencies) {
+    let (args, bodies) = match env.get_fun(name) {
+        Some(Function::Custom(fun)) => (fun.args.as_slice(), vec![&fun.body]),
+        Some(Function::Sequence(seq)) => match &seq.body {
+            Some(body) => (std::slice::from_ref(&seq.arg), vec![body]),
+            None => return,
+        },
+        Some(Function::Piecewise(fun)) => (fun.args.as_slice(), fun.operands()),
+        _ => return,
+    };
+    for body in bodies {
+        deps.variables.extend(
+            body.free_symbols()
+                .into_iter()
+                .filter(|sym| !args.contains(sym)),
+        );
+        for called in body.called_functions() {
+            if deps.functions.insert(called.clone()) {
+                collect(&called, env, deps);
+            }
+        }
+    }
+}
+
+/// transitive dependencies of the function `name`, `None` if there is no such function
+pub fn dependencies_of(name: &str, env: &TopLevelEnv) -> Option<Dependencies> {
+    env.get_fun(name)?;
+    let mut deps = Dependencies::default();
+    collect(name, env, &mut deps);
+    Some(deps)
+}
+
+/// names of all functions depending (transitively) on the variable or function `name`
+pub fn dependents_of(name: &str, env: &TopLevelEnv) -> BTreeSet<String> {
+    env.fun_names()
+        .filter(|fun| {
+            dependencies_of(fun, env)
+                .is_some_and(|deps| deps.variables.contains(name) || deps.functions.contains(name))
+        })
+        .map(str::to_string)
+        .collect()
+}
+
+fn undefined(deps: Dependencies, env: &TopLevelEnv) -> BTreeSet<String> {
+    let variables = deps
+        .variables
+        .into_iter()
+        // functions passed by name, e.g. `f` in `map(f, xs)`
+        .filter(|sym| {
+            env.get(sym).is_none()
+                && env.get_list(sym).is_none()
+                && env.get_matrix(sym).is_none()
+                && env.get_text(sym).is_none()
+                && env.get_fun(sym).is_none()
+        });
+    let functions = deps
+        .functions
+        .into_iter()
+        .filter(|fun| env.get_fun(fun).is_none());
+    variables.chain(functions).collect()
+}
+
+/// variables and functions `op` references, directly or through called functions
+pub fn dependencies_of_operand(op: &Operand, env: &TopLevelEnv) -> Dependencies {
+    let mut deps = Dependencies {
+        variables: op.free_symbols(),
+        functions: BTreeSet::new(),
+    };
+    for called in op.called_functions() {
+        if deps.functions.insert(called.clone()) {
+            collect(&called, env, &mut deps);
+        }
+    }
+    deps
+}
+
+/// undefined variables and functions `op` references, directly or through called functions
+pub fn undefined_names(op: &Operand, env: &TopLevelEnv) -> BTreeSet<String> {
+    undefined(dependencies_of_operand(op, env), env)
+}
+
+/// undefined variables and functions the function `name` references
+pub fn undefined_names_of(name: &str, env: &TopLevelEnv) -> BTreeSet<String> {
+    dependencies_of(name, env)
+        .map(|deps| undefined(deps, env))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::{CustomFunction, Statement};
+    use crate::parser::parse;
+
+    fn env_with(definitions: &[&str]) -> TopLevelEnv {
+        let mut env = TopLevelEnv::default();
+        for definition in definitions {
+            if let Statement::Function { name, fun } = parse(definition).unwrap() {
+                env.put_fun(name, fun);
+            }
+        }
+        env
+    }
+
+    fn names(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn dependencies_transitive() {
+        let env = env_with(&["f(x) := g(x) + a", "g(y) := sin(y) * b + y"]);
+        assert_eq!(
+            Some(Dependencies {
+                variables: names(&["a", "b"]),
+                functions: names(&["g", "sin"]),
+            }),
+            dependencies_of("f", &env)
+        );
+    }
+
+    #[test]
+    fn dependencies_cyclic() {
+        let env = env_with(&["f(x) := g(x)", "g(x) := f(x) + c"]);
+        assert_eq!(
+            Some(Dependencies {
+                variables: names(&["c"]),
+                functions: names(&["f", "g"]),
+            }),
+            dependencies_of("f", &env)
+        );
+    }
+
+    #[test]
+    fn dependencies_unknown_and_build_in() {
+        let env = env_with(&[]);
+        assert_eq!(None, dependencies_of("f", &env));
+        assert_eq!(Some(Dependencies::default()), dependencies_of("sin", &env));
+    }
+
+    #[test]
+    fn dependents() {
+        let mut env = env_with(&["f(x) := g(x) + a", "g(y) := y * b", "h() := a"]);
+        env.put_fun(
+            "k".to_string(),
+            Function::Custom(CustomFunction {
+                args: vec!["b".to_string()],
+                body: crate::ast::Operand::Symbol("b".to_string()),
+            }),
+        );
+        assert_eq!(names(&["f", "h"]), dependents_of("a", &env));
+        assert_eq!(names(&["f", "g"]), dependents_of("b", &env));
+        assert_eq!(names(&["f"]), dependents_of("g", &env));
+    }
+
+    #[test]
+    fn undefined_references() {
+        let mut env = env_with(&["f(x) := g(x) + a", "g(y) := y * b"]);
+        env.put("a".to_string(), 1.0).unwrap();
+        let op = crate::parser::parse_expression("f(c) + h(2) * pi").unwrap();
+        assert_eq!(names(&["b", "c", "h"]), undefined_names(&op, &env));
+        assert_eq!(names(&["b"]), undefined_names_of("f", &env));
+        assert!(undefined_names_of("sin", &env).is_empty());
+    }
+}
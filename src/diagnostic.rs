@@ -0,0 +1,547 @@
+//! Uniform presentation of all errors.
+//!
+//! Every error converts into a [`Diagnostic`] with a stable code, so frontends
+//! render errors in one place and can match on codes instead of messages.
+
+use crate::calc::{CalcError, MAX_SEQUENCE_STEPS, MAX_SERIES_TERMS};
+use crate::catalog::Catalog;
+use crate::fit::FitError;
+use crate::graph::GraphError;
+use crate::import::ImportError;
+use crate::integrate::IntegrationError;
+use crate::limits::LimitError;
+use crate::parser::ParserError;
+use crate::script::ScriptError;
+use crate::solver::{unknown_variable_hint, SolverError};
+#[cfg(feature = "persistence")]
+use crate::state::StateError;
+use crate::suggest::did_you_mean;
+use crate::units::UnitError;
+use crate::Error;
+
+use std::fmt;
+
+/// byte range in the input line
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Severity {
+    Error,
+    /// the statement was executed, but the result may not be what was intended
+    Warning,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// stable error code, e.g. `C0001`, warnings start with `W`
+    pub code: &'static str,
+    pub message: String,
+    /// named arguments of the message, e.g. `("name", "a")` for `{name}` in a template
+    pub args: Vec<(&'static str, String)>,
+    /// location of the error in the input, if known
+    pub span: Option<Span>,
+    pub help: Option<String>,
+}
+
+impl Diagnostic {
+    fn new(code: &'static str, message: String) -> Self {
+        Diagnostic {
+            severity: Severity::Error,
+            code,
+            message,
+            args: Vec::new(),
+            span: None,
+            help: None,
+        }
+    }
+
+    pub(crate) fn warning(code: &'static str, message: String) -> Self {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::new(code, message)
+        }
+    }
+
+    pub(crate) fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
+
+    fn arg(mut self, name: &'static str, value: impl ToString) -> Self {
+        self.args.push((name, value.to_string()));
+        self
+    }
+
+    /// help for solver errors about equations, which are neither linear nor quadratic
+    fn with_linear_help(self) -> Self {
+        match self.code {
+            "S0002" | "S0003" | "S0004" | "S0005" | "S0007" => self.with_help(
+                "only linear and quadratic equations can be solved exactly - add `in [a, b]` to search a solution numerically",
+            ),
+            _ => self,
+        }
+    }
+
+    /// The message from the template for the code of the diagnostic in `catalog`.
+    /// Falls back to [`Diagnostic::message`], if the catalog has no template.
+    pub fn localized(&self, catalog: &Catalog) -> String {
+        match catalog.template(self.code) {
+            Some(template) => self
+                .args
+                .iter()
+                .fold(template.to_string(), |msg, (name, value)| {
+                    msg.replace(&format!("{{{}}}", name), value)
+                }),
+            None => self.message.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}[{}]: {}", severity, self.code, self.message)?;
+        if let Some(help) = &self.help {
+            write!(f, "\nhelp: {}", help)?;
+        }
+        Ok(())
+    }
+}
+
+fn quoted(names: &[String], separator: &str) -> String {
+    names
+        .iter()
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+impl From<&ParserError> for Diagnostic {
+    fn from(err: &ParserError) -> Self {
+        use ParserError::*;
+        let (code, input) = match err {
+            InvalidNumber(input) => ("P0001", Some(input)),
+            InvalidOperation(input) => ("P0002", Some(input)),
+            InvalidOperand(input) => ("P0003", Some(input)),
+            InvalidExpression(input) => ("P0004", Some(input)),
+            InvalidSymbol(input) => ("P0005", Some(input)),
+            InvalidStatement(input) => ("P0006", Some(input)),
+            EmptyStatement => ("P0007", None),
+            MissingAssignmentTarget(input) => ("P0008", Some(input)),
+            MissingAssignment(input) => ("P0009", Some(input)),
+            MissingAssignmentExpression(input) => ("P0010", Some(input)),
+            MissingSolveForLeftExpression(input) => ("P0011", Some(input)),
+            MissingSolveForRightExpression(input) => ("P0012", Some(input)),
+            MissingSolveForSymbol(input) => ("P0013", Some(input)),
+            MissingFunctionName => ("P0014", None),
+            MissingFunctionBody => ("P0015", None),
+            ExpectedParamExpression(input) => ("P0016", Some(input)),
+            PlotMissingFunction => ("P0017", None),
+            PlotUnexpectedSymbol(input) => ("P0018", Some(input)),
+            MissingEquivalentLeftExpression(input) => ("P0019", Some(input)),
+            MissingEquivalentRightExpression(input) => ("P0020", Some(input)),
+            MissingImportPath(input) => ("P0021", Some(input)),
+            MissingRunPath(input) => ("P0027", Some(input)),
+            MissingGuard(input) => ("P0028", Some(input)),
+            DuplicatePlotLabel(input) => ("P0029", Some(input)),
+            InvalidSweep(input) => ("P0030", Some(input)),
+            MissingStoredEquation(input) => ("P0031", Some(input)),
+            InvalidColumn(input) => ("P0022", Some(input)),
+            InvalidSequenceIndex(input) => ("P0023", Some(input)),
+            MissingFitData(input) => ("P0024", Some(input)),
+            RpnStackUnderflow(input) => ("P0025", Some(input)),
+            RaggedMatrix(input) => ("P0033", Some(input)),
+            RpnUnconsumedOperands(count) => {
+                return Diagnostic::new("P0026", err.to_string()).arg("count", count)
+            }
+            Syntax {
+                found,
+                expected,
+                span,
+            } => {
+                return Diagnostic {
+                    span: Some(*span),
+                    ..Diagnostic::new("P0032", err.to_string())
+                        .arg("found", found)
+                        .arg("expected", expected)
+                }
+            }
+        };
+        let mut diagnostic = Diagnostic::new(code, err.to_string());
+        if let Some(input) = input {
+            diagnostic = diagnostic.arg("input", input);
+        }
+        match err {
+            MissingAssignment(_) => diagnostic.with_help("assignments are written `a := 1`"),
+            MissingSolveForSymbol(_) => {
+                diagnostic.with_help("write the equation as `solve 2 * x = 4 for x`")
+            }
+            _ => diagnostic,
+        }
+    }
+}
+
+impl From<&CalcError> for Diagnostic {
+    fn from(err: &CalcError) -> Self {
+        use CalcError::*;
+        let diagnostic = |code| Diagnostic::new(code, err.to_string());
+        match err {
+            UnknownSymbol { name, candidates } => {
+                diagnostic("C0001")
+                    .arg("name", name)
+                    .arg("candidates", quoted(candidates, " or "))
+                    .with_help(did_you_mean(candidates).unwrap_or_else(|| {
+                        format!("define the variable first, e.g. `{} := 1`", name)
+                    }))
+            }
+            UnexpectedNumberOfParameters { name, act, exp } => diagnostic("C0002")
+                .arg("name", name)
+                .arg("act", act)
+                .arg("exp", exp),
+            TooFewParameters { name, act, min } => diagnostic("C0027")
+                .arg("name", name)
+                .arg("act", act)
+                .arg("min", min),
+            UnknownFunction { name, candidates } => {
+                let diagnostic = diagnostic("C0003")
+                    .arg("name", name)
+                    .arg("candidates", quoted(candidates, " or "));
+                match did_you_mean(candidates) {
+                    Some(hint) => diagnostic.with_help(hint),
+                    None => diagnostic,
+                }
+            }
+            CannotChangeConstant(name) => diagnostic("C0004").arg("name", name),
+            UnexpectedList(name) => diagnostic("C0005").arg("name", name),
+            UnexpectedText(name) => diagnostic("C0015").arg("name", name),
+            CannotRemoveBuildIn(name) => diagnostic("C0017").arg("name", name),
+            InvalidSeriesBound { name, bound } => {
+                diagnostic("C0018").arg("name", name).arg("bound", bound)
+            }
+            SeriesTooLong { name } => diagnostic("C0019")
+                .arg("name", name)
+                .arg("max", MAX_SERIES_TERMS),
+            DivisionByZero => diagnostic("C0020"),
+            DomainError { function, argument } => diagnostic("C0021")
+                .arg("function", function)
+                .arg("argument", argument),
+            UnknownEquation(name) => diagnostic("C0016").arg("name", name).with_help(format!(
+                "store the equation first, e.g. `store {}: v = i * r`",
+                name
+            )),
+            InvalidArguments { name, reason } => {
+                diagnostic("C0006").arg("name", name).arg("reason", reason)
+            }
+            InvalidSequenceIndex { name, index } => {
+                diagnostic("C0007").arg("name", name).arg("index", index)
+            }
+            MissingSequenceValue { name, index } => diagnostic("C0008")
+                .arg("name", name)
+                .arg("index", index)
+                .with_help(format!(
+                    "define an initial value, e.g. `{}({}) := 1`",
+                    name, index
+                )),
+            SequenceTooLong { name, index } => diagnostic("C0009")
+                .arg("name", name)
+                .arg("index", index)
+                .arg("max", MAX_SEQUENCE_STEPS),
+            NotASequence(name) => diagnostic("C0010").arg("name", name),
+            AlreadyDefined(name) => diagnostic("C0011").arg("name", name),
+            UndefinedNames(names) => diagnostic("C0012").arg("names", quoted(names, ", ")),
+            CyclicDefinition(names) => diagnostic("C0013").arg("names", quoted(names, " -> ")),
+            NoMatchingClause { name, args } => diagnostic("C0014")
+                .arg("name", name)
+                .arg("args", args)
+                .with_help(format!("add a clause `{}(...) := ... otherwise`", name)),
+            RecursionLimitExceeded { name, limit } => diagnostic("C0022")
+                .arg("name", name)
+                .arg("limit", limit)
+                .with_help(format!(
+                    "make sure `{}` stops calling itself, e.g. with `if(...)`",
+                    name
+                )),
+            IncompatibleShapes { op, lhs, rhs } => diagnostic("C0023")
+                .arg("op", op)
+                .arg("lhs", lhs)
+                .arg("rhs", rhs),
+            NotAnExpression(line) => diagnostic("C0024").arg("line", line),
+            NothingToUndo => diagnostic("C0025"),
+            Overflow(expr) => diagnostic("C0026").arg("expr", expr),
+            LimitExceeded(err) => err.into(),
+        }
+    }
+}
+
+impl From<&SolverError> for Diagnostic {
+    fn from(err: &SolverError) -> Self {
+        use SolverError::*;
+        let diagnostic = |code| Diagnostic::new(code, err.to_string());
+        match err {
+            UnknownVariable {
+                name,
+                candidates,
+                misspelled_for,
+            } => {
+                let hint = unknown_variable_hint(name, candidates, misspelled_for.as_deref());
+                let diagnostic = diagnostic("S0001")
+                    .arg("name", name)
+                    .arg("candidates", quoted(candidates, " or "));
+                match hint {
+                    Some(hint) => diagnostic.with_help(hint),
+                    None => diagnostic,
+                }
+            }
+            UnsupportedDegree => diagnostic("S0002"),
+            UnsupportedXDenominator => diagnostic("S0003"),
+            UnsupportedRemainder => diagnostic("S0004"),
+            UnsupportedPower => diagnostic("S0005"),
+            NoVariable => diagnostic("S0006"),
+            VariableInFunctionCall(var, fun) => diagnostic("S0007")
+                .arg("variable", var)
+                .arg("function", fun),
+            NoSignChange { variable, from, to } => diagnostic("S0008")
+                .arg("variable", variable)
+                .arg("from", from)
+                .arg("to", to),
+            InvalidInterval { variable, from, to } => diagnostic("S0009")
+                .arg("variable", variable)
+                .arg("from", from)
+                .arg("to", to),
+            FunctionCallError(err) => err.into(),
+        }
+        .with_linear_help()
+    }
+}
+
+impl From<&GraphError> for Diagnostic {
+    fn from(err: &GraphError) -> Self {
+        match err {
+            GraphError::UnknownFunction(name) => {
+                Diagnostic::new("G0001", err.to_string()).arg("name", name)
+            }
+            GraphError::InvalidSweepRange(range) => {
+                Diagnostic::new("G0002", err.to_string()).arg("range", range)
+            }
+            GraphError::AmbiguousVariable(names) => {
+                Diagnostic::new("G0003", err.to_string()).arg("names", quoted(names, ", "))
+            }
+            GraphError::InvalidAngles(angles) => {
+                Diagnostic::new("G0004", err.to_string()).arg("angles", angles)
+            }
+            GraphError::InvalidRange(range) => {
+                Diagnostic::new("G0005", err.to_string()).arg("range", range)
+            }
+            GraphError::NotImplicit(plot) => {
+                Diagnostic::new("G0006", err.to_string()).arg("plot", plot)
+            }
+            GraphError::LimitExceeded(err) => err.into(),
+        }
+    }
+}
+
+impl From<&ImportError> for Diagnostic {
+    fn from(err: &ImportError) -> Self {
+        use ImportError::*;
+        let diagnostic = |code| Diagnostic::new(code, err.to_string());
+        match err {
+            Io { path, message } => diagnostic("I0001")
+                .arg("path", path)
+                .arg("message", message),
+            InvalidColumn => diagnostic("I0002"),
+            MissingColumn { line, column } => {
+                diagnostic("I0003").arg("line", line).arg("column", column)
+            }
+            InvalidNumber { line, value } => {
+                diagnostic("I0004").arg("line", line).arg("value", value)
+            }
+            NoData => diagnostic("I0005"),
+        }
+    }
+}
+
+impl From<&FitError> for Diagnostic {
+    fn from(err: &FitError) -> Self {
+        use FitError::*;
+        let diagnostic = |code| Diagnostic::new(code, err.to_string());
+        match err {
+            NotAList(name) => diagnostic("F0001").arg("name", name),
+            LengthMismatch(xs, ys) => diagnostic("F0002").arg("xs", xs).arg("ys", ys),
+            TooFewPoints(count) => diagnostic("F0003").arg("count", count),
+            ConstantX => diagnostic("F0004"),
+        }
+    }
+}
+
+impl From<&IntegrationError> for Diagnostic {
+    fn from(err: &IntegrationError) -> Self {
+        use IntegrationError::*;
+        let diagnostic = |code| Diagnostic::new(code, err.to_string());
+        match err {
+            InvalidBounds { from, to } => diagnostic("N0001").arg("from", from).arg("to", to),
+            NotFinite { at } => diagnostic("N0002").arg("at", at),
+            NotConverged { tolerance } => diagnostic("N0003")
+                .arg("tolerance", tolerance)
+                .with_help("the integrand may have a singularity, try a larger tolerance"),
+            CalcError(err) => err.into(),
+        }
+    }
+}
+
+impl From<&LimitError> for Diagnostic {
+    fn from(err: &LimitError) -> Self {
+        use LimitError::*;
+        let diagnostic = |code| Diagnostic::new(code, err.to_string());
+        match err {
+            TooManyBindings { max } => diagnostic("L0001").arg("max", max),
+            TooManyNodes { nodes, max } => diagnostic("L0002").arg("nodes", nodes).arg("max", max),
+            TooManyNumbers { count, max } => {
+                diagnostic("L0003").arg("count", count).arg("max", max)
+            }
+            TooManyOperations { max } => diagnostic("L0004").arg("max", max),
+            Timeout { millis } => diagnostic("L0005").arg("millis", millis),
+        }
+    }
+}
+
+impl From<&ScriptError> for Diagnostic {
+    fn from(err: &ScriptError) -> Self {
+        let diagnostic = |code| Diagnostic::new(code, err.to_string());
+        match err {
+            ScriptError::RecursiveRun(scripts) => diagnostic("R0001")
+                .arg("scripts", scripts.join(" -> "))
+                .arg(
+                    "name",
+                    scripts.last().map(String::as_str).unwrap_or_default(),
+                ),
+            ScriptError::Statement { path, line, error } => diagnostic("R0002")
+                .arg("path", path)
+                .arg("line", line)
+                .arg("error", error),
+            ScriptError::Failed {
+                line,
+                statement,
+                error,
+            } => diagnostic("R0003")
+                .arg("line", line)
+                .arg("statement", statement)
+                .arg("error", error),
+            ScriptError::OutsideBase { path, base } => {
+                diagnostic("R0004").arg("path", path).arg("base", base)
+            }
+            ScriptError::InvalidStatement { path, line } => {
+                diagnostic("R0005").arg("path", path).arg("line", line)
+            }
+            ScriptError::FileAccessDisabled(path) => diagnostic("R0006").arg("path", path),
+        }
+    }
+}
+
+impl From<&UnitError> for Diagnostic {
+    fn from(err: &UnitError) -> Self {
+        use UnitError::*;
+        let diagnostic = |code| Diagnostic::new(code, err.to_string());
+        match err {
+            IncompatibleUnits { lhs, rhs } => diagnostic("U0001").arg("lhs", lhs).arg("rhs", rhs),
+            UnitInArgument { name, unit } => {
+                diagnostic("U0002").arg("name", name).arg("unit", unit)
+            }
+            UnitInExponent(unit) => diagnostic("U0003").arg("unit", unit),
+            FractionalPower { unit, exp } => diagnostic("U0004").arg("unit", unit).arg("exp", exp),
+            UnitInVariable { sym, unit } => diagnostic("U0005")
+                .arg("sym", sym)
+                .arg("unit", unit)
+                .with_help("divide by the unit to store the number, e.g. `d := 3 km / 1 m`"),
+        }
+    }
+}
+
+#[cfg(feature = "persistence")]
+impl From<&StateError> for Diagnostic {
+    fn from(err: &StateError) -> Self {
+        match err {
+            StateError::InvalidState(message) => {
+                Diagnostic::new("T0001", err.to_string()).arg("message", message)
+            }
+        }
+    }
+}
+
+impl From<&Error> for Diagnostic {
+    fn from(err: &Error) -> Self {
+        match err {
+            Error::ParserError(err) => err.into(),
+            Error::CalcError(err) => err.into(),
+            Error::SolverError(err) => err.into(),
+            Error::GraphError(err) => err.into(),
+            Error::ImportError(err) => err.into(),
+            Error::FitError(err) => err.into(),
+            Error::IntegrationError(err) => err.into(),
+            Error::LimitError(err) => err.into(),
+            Error::ScriptError(err) => err.into(),
+            #[cfg(feature = "persistence")]
+            Error::StateError(err) => err.into(),
+            Error::UnitError(err) => err.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn diagnostic_of_syntax_error() {
+        let err = Error::ParserError(crate::parser::parse("sin(1))").unwrap_err());
+        let diagnostic = err.diagnostic();
+        assert_eq!("P0032", diagnostic.code);
+        assert_eq!(Some(Span { start: 6, end: 7 }), diagnostic.span);
+    }
+
+    #[test]
+    fn diagnostic_of_calc_error() {
+        let err = Error::CalcError(CalcError::UnknownSymbol {
+            name: "a".to_string(),
+            candidates: vec![],
+        });
+        let diagnostic = Diagnostic::from(&err);
+        assert_eq!("C0001", diagnostic.code);
+        assert_eq!("Unknown symbol `a`", diagnostic.message);
+        assert_eq!(None, diagnostic.span);
+        assert_eq!(
+            "error[C0001]: Unknown symbol `a`\nhelp: define the variable first, e.g. `a := 1`",
+            diagnostic.to_string()
+        );
+    }
+
+    #[test]
+    fn diagnostic_suggests_similar_names() {
+        let err = CalcError::UnknownSymbol {
+            name: "sped".to_string(),
+            candidates: vec!["speed".to_string()],
+        };
+        let diagnostic = Diagnostic::from(&err);
+        assert_eq!(Some("did you mean `speed`?".to_string()), diagnostic.help);
+        assert!(diagnostic
+            .args
+            .contains(&("candidates", "`speed`".to_string())));
+    }
+
+    #[test]
+    fn solver_forwards_calc_error() {
+        let err = SolverError::FunctionCallError(CalcError::UnknownFunction {
+            name: "f".to_string(),
+            candidates: vec![],
+        });
+        assert_eq!("C0003", Diagnostic::from(&err).code);
+        assert_eq!("S0006", Diagnostic::from(&SolverError::NoVariable).code);
+    }
+}
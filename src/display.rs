@@ -0,0 +1,570 @@
+//! Formatting of the AST as source text and of results for humans.
+//!
+//! The output of the AST uses as few parenthesis as possible, but parsing it again
+//! yields the same AST.
+
+use crate::ast::*;
+use crate::equivalence::Equivalence;
+use crate::fit::LinearFit;
+use crate::listing::Listing;
+use crate::matrix::Matrix;
+use crate::solver::Interval;
+use crate::Value;
+
+use std::fmt;
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Operation::Add => "+",
+            Operation::Sub => "-",
+            Operation::Mul => "*",
+            Operation::Div => "/",
+            Operation::Rem => "%",
+            Operation::Pow => "^",
+        })
+    }
+}
+
+fn fmt_child(
+    f: &mut fmt::Formatter<'_>,
+    op: &Operand,
+    parent: Operation,
+    is_rhs: bool,
+) -> fmt::Result {
+    if parent.needs_parens(op, is_rhs) {
+        write!(f, "({})", op)
+    } else {
+        write!(f, "{}", op)
+    }
+}
+
+fn fmt_list<T: fmt::Display>(f: &mut fmt::Formatter<'_>, items: &[T]) -> fmt::Result {
+    for (idx, item) in items.iter().enumerate() {
+        if idx > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+/// rows separated by `;`, e.g. `[1, 2; 3, 4]`
+fn fmt_rows<'a, T: fmt::Display + 'a>(
+    f: &mut fmt::Formatter<'_>,
+    rows: impl Iterator<Item = &'a [T]>,
+) -> fmt::Result {
+    f.write_str("[")?;
+    for (idx, row) in rows.enumerate() {
+        if idx > 0 {
+            f.write_str("; ")?;
+        }
+        fmt_list(f, row)?;
+    }
+    f.write_str("]")
+}
+
+impl fmt::Display for Matrix {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_rows(f, (0..self.rows()).map(|row| self.row(row)))
+    }
+}
+
+impl fmt::Display for Term {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt_child(f, &self.lhs, self.op, false)?;
+        write!(f, " {} ", self.op)?;
+        fmt_child(f, &self.rhs, self.op, true)
+    }
+}
+
+impl fmt::Display for FunCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}(", self.name)?;
+        fmt_list(f, &self.params)?;
+        f.write_str(")")
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            // infinity is not a valid literal, but overflows to it
+            Operand::Number(num) if num.is_infinite() => {
+                f.write_str(if *num > 0.0 { "1e999" } else { "-1e999" })
+            }
+            Operand::Number(num) => write!(f, "{}", num),
+            Operand::Literal(lit) => f.write_str(&lit.text),
+            Operand::Symbol(sym) => f.write_str(sym),
+            Operand::Term(term) => write!(f, "{}", term),
+            Operand::FunCall(fun_call) => write!(f, "{}", fun_call),
+            Operand::Conditional(cond) => write!(
+                f,
+                "if({} {} {}, {}, {})",
+                cond.guard.lhs, cond.guard.cmp, cond.guard.rhs, cond.then, cond.otherwise
+            ),
+            Operand::Series(series) => write!(
+                f,
+                "{}({}, {}, {}, {})",
+                series.kind.name(),
+                series.var,
+                series.from,
+                series.to,
+                series.body
+            ),
+            Operand::Let(binding) => write!(
+                f,
+                "let {} := {}; {}",
+                binding.var, binding.value, binding.body
+            ),
+            Operand::Factorial(op) if op.needs_parens_in_factorial() => write!(f, "({})!", op),
+            Operand::Factorial(op) => write!(f, "{}!", op),
+            Operand::Matrix(rows) => fmt_rows(f, rows.iter().map(Vec::as_slice)),
+        }
+    }
+}
+
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, part) in self.parts.iter().enumerate() {
+            if idx > 0 {
+                f.write_str(" + ")?;
+            }
+            match part {
+                TextPart::Literal(literal) => write!(f, "\"{}\"", literal)?,
+                TextPart::Symbol(sym) => f.write_str(sym)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Comparison::Less => "<",
+            Comparison::LessEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterEqual => ">=",
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+        })
+    }
+}
+
+/// the labels with a leading space, e.g. ` title "t" xlabel "x"`
+impl fmt::Display for PlotLabels {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let labels = [
+            ("title", &self.title),
+            ("xlabel", &self.xlabel),
+            ("ylabel", &self.ylabel),
+        ];
+        for (keyword, text) in labels {
+            if let Some(text) = text {
+                write!(f, " {} {}", keyword, text)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A function with its name as definition, see [`Function::definition`].
+pub struct Definition<'a> {
+    name: &'a str,
+    fun: &'a Function,
+}
+
+impl Function {
+    /// the definition `name(args) := body` of the function
+    pub fn definition<'a>(&'a self, name: &'a str) -> Definition<'a> {
+        Definition { name, fun: self }
+    }
+}
+
+impl fmt::Display for Definition<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.name;
+        match self.fun {
+            Function::Custom(fun) => {
+                write!(f, "{}(", name)?;
+                fmt_list(f, &fun.args)?;
+                write!(f, ") := {}", fun.body)
+            }
+            Function::BuildIn(fun) => {
+                write!(f, "{}(", name)?;
+                fmt_list(f, &fun.args)?;
+                if fun.variadic {
+                    f.write_str(", ...")?;
+                }
+                f.write_str(") := <build-in>")
+            }
+            Function::Data(fun) => {
+                write!(f, "{}(", name)?;
+                fmt_list(f, &fun.args)?;
+                write!(f, ") := <build-in>")
+            }
+            Function::Sequence(seq) => match &seq.body {
+                Some(body) => write!(f, "{}({}) := {}", name, seq.arg, body),
+                None => write!(f, "{}({}) := <sequence>", name, seq.arg),
+            },
+            // one statement per line and clause
+            Function::Piecewise(fun) => {
+                for (idx, clause) in fun.clauses.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str("\n")?;
+                    }
+                    write!(f, "{}(", name)?;
+                    fmt_list(f, &fun.args)?;
+                    write!(f, ") := {}", clause.body)?;
+                    match &clause.guard {
+                        Some(guard) => {
+                            write!(f, " when {} {} {}", guard.lhs, guard.cmp, guard.rhs)?
+                        }
+                        None => f.write_str(" otherwise")?,
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Statement::Expression { op } => write!(f, "{}", op),
+            Statement::Assignment { sym, op } => write!(f, "{} := {}", sym, op),
+            Statement::SolveFor {
+                lhs,
+                rhs,
+                sym,
+                bracket,
+            } => {
+                write!(f, "solve {} = {} for {}", lhs, rhs, sym)?;
+                match bracket {
+                    Some(bracket) => write!(f, " in [{}, {}]", bracket.from, bracket.to),
+                    None => Ok(()),
+                }
+            }
+            Statement::SolveInequality { lhs, cmp, rhs, sym } => {
+                write!(f, "solve {} {} {} for {}", lhs, cmp, rhs, sym)
+            }
+            Statement::Integrate { name, bracket } => write!(
+                f,
+                "integrate {} from {} to {}",
+                name, bracket.from, bracket.to
+            ),
+            Statement::IntegrateExpression { op, sym, bracket } => write!(
+                f,
+                "integrate {} for {} in [{}, {}]",
+                op, sym, bracket.from, bracket.to
+            ),
+            Statement::Store { name, lhs, rhs } => write!(f, "store {}: {} = {}", name, lhs, rhs),
+            Statement::SolveStored { name, sym, given } => {
+                write!(f, "solve {} for {}", name, sym)?;
+                for (idx, (sym, op)) in given.iter().enumerate() {
+                    let sep = if idx == 0 { " given" } else { "," };
+                    write!(f, "{} {} := {}", sep, sym, op)?;
+                }
+                Ok(())
+            }
+            Statement::Function { name, fun } => write!(f, "{}", fun.definition(name)),
+            Statement::Plot {
+                name,
+                sweep,
+                labels,
+            } => {
+                write!(f, "plot {}", name)?;
+                if let Some(sweep) = sweep {
+                    write!(
+                        f,
+                        "({}) for {} in {}..{}",
+                        sweep.args.join(", "),
+                        sweep.param,
+                        sweep.from,
+                        sweep.to
+                    )?;
+                    if let Some(step) = &sweep.step {
+                        write!(f, " step {}", step)?;
+                    }
+                }
+                write!(f, "{}", labels)
+            }
+            Statement::PlotExpression { op, labels } => write!(f, "plot {}{}", op, labels),
+            Statement::PlotPolar {
+                op,
+                bracket,
+                labels,
+            } => {
+                write!(f, "plot polar {}", op)?;
+                if let Some(bracket) = bracket {
+                    write!(f, " in [{}, {}]", bracket.from, bracket.to)?;
+                }
+                write!(f, "{}", labels)
+            }
+            Statement::PlotImplicit {
+                lhs,
+                rhs: Some(rhs),
+                labels,
+            } => write!(f, "plot {} = {}{}", lhs, rhs, labels),
+            Statement::PlotImplicit {
+                lhs,
+                rhs: None,
+                labels,
+            } => write!(f, "plot implicit {}{}", lhs, labels),
+            Statement::TextAssignment { sym, text } => write!(f, "{} := {}", sym, text),
+            Statement::TextExpression { text } => write!(f, "{}", text),
+            Statement::Equivalent { lhs, rhs } => write!(f, "is {} == {}", lhs, rhs),
+            Statement::Simplify { op } => write!(f, "simplify {}", op),
+            Statement::Import { path, column, sym } => {
+                write!(f, "import \"{}\" column {} as {}", path, column, sym)
+            }
+            Statement::Run { path } => write!(f, "run \"{}\"", path),
+            Statement::SequenceValue { name, index, op } => {
+                write!(f, "{}({}) := {}", name, index, op)
+            }
+            Statement::Define { sym, op } => write!(f, "define {} = {}", sym, op),
+            Statement::Override { st } => write!(f, "override {}", st),
+            Statement::List => f.write_str("list"),
+            Statement::Undo => f.write_str("undo"),
+            Statement::Empty => Ok(()),
+            Statement::Unset { name } => write!(f, "unset {}", name),
+            Statement::FitLinear { xs, ys, name } => {
+                write!(f, "fit linear {}, {}", xs, ys)?;
+                match name {
+                    Some(name) => write!(f, " as {}", name),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+fn fmt_numbers(f: &mut fmt::Formatter<'_>, nums: &[Number]) -> fmt::Result {
+    f.write_str("[")?;
+    fmt_list(f, nums)?;
+    f.write_str("]")
+}
+
+impl fmt::Display for Equivalence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Equivalence::Proven => f.write_str("equivalent"),
+            Equivalence::Probable { samples } => {
+                write!(f, "probably equivalent ({} samples agree)", samples)
+            }
+            Equivalence::NotEquivalent { counterexample } => {
+                f.write_str("not equivalent")?;
+                for (idx, (sym, num)) in counterexample.iter().enumerate() {
+                    let sep = if idx == 0 { " for" } else { "," };
+                    write!(f, "{} {} = {}", sep, sym, num)?;
+                }
+                Ok(())
+            }
+            Equivalence::Undetermined => f.write_str("undetermined"),
+        }
+    }
+}
+
+impl fmt::Display for LinearFit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "slope = {}, intercept = {}, R² = {}",
+            self.slope, self.intercept, self.r_squared
+        )
+    }
+}
+
+/// one line per variable and function clause
+impl fmt::Display for Listing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = Vec::new();
+        for var in &self.variables {
+            lines.push(match (&var.formula, &var.value) {
+                (Some(formula), Value::Void) => format!("{} = {}", var.name, formula),
+                (Some(formula), value) => format!("{} = {} = {}", var.name, formula, value),
+                (None, value) => format!("{} = {}", var.name, value),
+            });
+        }
+        lines.extend(self.functions.iter().map(|fun| fun.definition.clone()));
+        f.write_str(&lines.join("\n"))
+    }
+}
+
+/// results for humans, graphs are only described
+/// e.g. `x < 3`, `1 <= x < 3` or `x = 2`
+fn fmt_interval(f: &mut fmt::Formatter<'_>, variable: &str, interval: &Interval) -> fmt::Result {
+    let bound = |closed| if closed { "<=" } else { "<" };
+    match (interval.from.is_finite(), interval.to.is_finite()) {
+        (true, true) if interval.from == interval.to => {
+            write!(f, "{} = {}", variable, interval.from)
+        }
+        (true, true) => write!(
+            f,
+            "{} {} {} {} {}",
+            interval.from,
+            bound(interval.from_closed),
+            variable,
+            bound(interval.to_closed),
+            interval.to
+        ),
+        (true, false) => write!(
+            f,
+            "{} {} {}",
+            variable,
+            if interval.from_closed { ">=" } else { ">" },
+            interval.from
+        ),
+        (false, true) => write!(
+            f,
+            "{} {} {}",
+            variable,
+            bound(interval.to_closed),
+            interval.to
+        ),
+        (false, false) => write!(f, "any {}", variable),
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Void => Ok(()),
+            Value::Number(num) => write!(f, "{}", num),
+            Value::List(list) => fmt_numbers(f, list),
+            Value::Matrix(matrix) => write!(f, "{}", matrix),
+            Value::Solved { variable, value } => write!(f, "{} = {}", variable, value),
+            Value::Solutions(solutions) if solutions.is_empty() => f.write_str("no solution"),
+            Value::Solutions(solutions) => fmt_numbers(f, solutions),
+            Value::Intervals { intervals, .. } if intervals.is_empty() => {
+                f.write_str("no solution")
+            }
+            Value::Intervals {
+                variable,
+                intervals,
+            } => {
+                for (i, interval) in intervals.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" or ")?;
+                    }
+                    fmt_interval(f, variable, interval)?;
+                }
+                Ok(())
+            }
+            Value::Graph(_) => f.write_str("<graph>"),
+            Value::PlotSequence(sequence) => write!(
+                f,
+                "<{} graphs for {}>",
+                sequence.frames().len(),
+                sequence.param()
+            ),
+            Value::Surface(surface) => write!(f, "<surface {}>", surface.name()),
+            Value::Equivalence(equivalence) => write!(f, "{}", equivalence),
+            Value::Expression(op) => write!(f, "{}", op),
+            Value::Fit(fit) => write!(f, "{}", fit),
+            Value::Text(text) => f.write_str(text),
+            Value::Listing(listing) => write!(f, "{}", listing),
+            Value::Quantity(quantity) => write!(f, "{}", quantity),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn assert_round_trip(exp: &str, input: &str) {
+        let st = parse(input).unwrap();
+        let printed = st.to_string();
+        assert_eq!(exp, printed);
+        assert_eq!(Ok(st), parse(&printed));
+    }
+
+    #[test]
+    fn display_minimal_parenthesis() {
+        assert_round_trip("1 + 2 * x", "1 + (2 * x)");
+        assert_round_trip("(1 + 2) * x", "(1 + 2) * x");
+        assert_round_trip("a - b - c", "(a - b) - c");
+        assert_round_trip("a - (b - c)", "a - (b - c)");
+        assert_round_trip("a + (b + c)", "a + (b + c)");
+        assert_round_trip("a / (b * c)", "a / (b * c)");
+        assert_round_trip("a ^ b ^ c", "a ^ (b ^ c)");
+        assert_round_trip("(a ^ b) ^ c", "(a ^ b) ^ c");
+        assert_round_trip("(-2) ^ 2 % 3", "((-2) ^ 2) % 3");
+        assert_round_trip("-1 * 2 ^ 2", "-2 ^ 2");
+    }
+
+    #[test]
+    fn display_numbers() {
+        assert_round_trip("0.1 + 3 * -4", "0.1 + 3 * -4");
+        assert_round_trip("1e999", "1e999");
+        assert_round_trip("0.000001", "1e-6");
+    }
+
+    #[test]
+    fn display_factorials() {
+        assert_round_trip("10! / (3! * 7!)", "10!/(3!*7!)");
+        assert_round_trip("(n - 1)! * f(n)!", "(n-1)!*f(n)!");
+        assert_round_trip("2 ^ n! + (-3)!", "2^n! + (-3)!");
+    }
+
+    #[test]
+    fn display_statements() {
+        assert_round_trip("a := sin(x) + f()", "a:=sin( x )+f()");
+        assert_round_trip("f(x, y) := x * (y + 1)", "f(x,y) := x*(y+1)");
+        assert_round_trip("solve 2 * x = 4 for x", "solve 2*x = 4 for x");
+        assert_round_trip("solve 2 * x + 1 < 7 for x", "solve 2*x+1<7 for x");
+        assert_round_trip("simplify x * (x + 1)", "simplify x*(x+1)");
+        assert_round_trip("store ohm: v = i * r", "store ohm : v=i*r");
+        assert_round_trip(
+            "solve ohm for i given v := 12, r := 2 * 2",
+            "solve ohm for i given v:=12,r:=2*2",
+        );
+        assert_round_trip("plot f", "plot   f");
+        assert_round_trip(
+            "plot f(x, a) for a in 0..5 step 0.5 title \"f\"",
+            "plot f(x,a) for a in 0..5 step 0.5 title \"f\"",
+        );
+        assert_round_trip(
+            "plot polar 1 + cos(t) in [0, pi] title \"cardioid\"",
+            "plot polar 1+cos(t) in [0,pi] title \"cardioid\"",
+        );
+        assert_round_trip("plot x ^ 2 + y ^ 2 = 100", "plot x^2+y^2=100");
+        assert_round_trip(
+            "plot implicit f title \"f\"",
+            "plot implicit  f title \"f\"",
+        );
+        assert_round_trip("is x + x == 2 * x", "is x+x == 2*x");
+        assert_round_trip("2 * if(x >= 1, x ^ 2, 0)", "2*if(x>=1,x^2,0)");
+        assert_round_trip("sum(i, 1, n + 1, i ^ 2) / 2", "sum(i,1,n+1,i^2)/2");
+        assert_round_trip("2 * (let t := x ^ 2; t + 1 / t)", "2*(let t:=x^2;t+1/t)");
+        assert_round_trip("[1, 2; 3, a + 1] * [x, -1]", "[1,2;3,a+1]*[x,-1]");
+        assert_round_trip(
+            "import \"a.csv\" column 1 as xs",
+            "import \"a.csv\"  column 1 as  xs",
+        );
+    }
+
+    #[test]
+    fn display_values() {
+        assert_eq!("[1, 2.5]", Value::List(vec![1.0, 2.5]).to_string());
+        let matrix = Matrix::new(2, vec![1.0, 2.0, 3.0, 4.5]).unwrap();
+        assert_eq!("[1, 2; 3, 4.5]", Value::Matrix(matrix).to_string());
+        assert_eq!("no solution", Value::Solutions(vec![]).to_string());
+        assert_eq!(
+            "x = -2",
+            Value::Solved {
+                variable: "x".to_string(),
+                value: -2.0
+            }
+            .to_string()
+        );
+        let equivalence = Equivalence::NotEquivalent {
+            counterexample: vec![("x".to_string(), 1.0), ("y".to_string(), 0.5)],
+        };
+        assert_eq!("not equivalent for x = 1, y = 0.5", equivalence.to_string());
+    }
+}
@@ -0,0 +1,188 @@
+//! Writing the environment as a script of statements, which recreates it when executed.
+
+use crate::ast::{Function, Number, Operand, Operation, Statement, Term, Text, TextPart};
+use crate::calc::{Env, TopLevelEnv};
+use crate::reactive::topological_order;
+
+use std::collections::BTreeSet;
+
+/// the number as operand, which parses to the same value
+fn number(num: Number) -> Operand {
+    if num.is_nan() {
+        Operand::Term(Box::new(Term {
+            op: Operation::Div,
+            lhs: Operand::Number(0.0),
+            rhs: Operand::Number(0.0),
+        }))
+    } else {
+        Operand::Number(num)
+    }
+}
+
+/// custom functions and sequences, every function after the functions it calls
+fn function_order(env: &TopLevelEnv) -> Vec<String> {
+    fn visit(
+        name: &str,
+        env: &TopLevelEnv,
+        visited: &mut BTreeSet<String>,
+        order: &mut Vec<String>,
+    ) {
+        let bodies = match env.get_fun(name) {
+            Some(Function::Custom(fun)) => vec![&fun.body],
+            Some(Function::Sequence(seq)) => seq.body.iter().collect(),
+            Some(Function::Piecewise(fun)) => fun.operands(),
+            _ => return,
+        };
+        if visited.insert(name.to_string()) {
+            let mut called: Vec<String> = bodies
+                .into_iter()
+                .flat_map(|body| body.called_functions())
+                .collect();
+            called.sort();
+            for called in called {
+                visit(&called, env, visited, order);
+            }
+            order.push(name.to_string());
+        }
+    }
+    let mut names: Vec<&str> = env.fun_names().collect();
+    names.sort();
+    let mut visited = BTreeSet::new();
+    let mut order = Vec::new();
+    for name in names {
+        visit(name, env, &mut visited, &mut order);
+    }
+    order
+}
+
+/// Statements defining all variables, functions and formulas of `env`.
+pub fn dump(env: &TopLevelEnv) -> Vec<Statement> {
+    let mut statements = Vec::new();
+
+    let mut vars: Vec<&str> = env
+        .user_var_names()
+        .filter(|sym| env.formula(sym).is_none())
+        .collect();
+    vars.sort();
+    for sym in vars {
+        if let Some(num) = env.get(sym) {
+            statements.push(Statement::Assignment {
+                sym: sym.to_string(),
+                op: number(*num),
+            });
+        } else if let Some(list) = env.get_list(sym) {
+            statements.push(Statement::Assignment {
+                sym: sym.to_string(),
+                op: Operand::Matrix(vec![list.iter().copied().map(number).collect()]),
+            });
+        } else if let Some(matrix) = env.get_matrix(sym) {
+            let rows = (0..matrix.rows()).map(|row| matrix.row(row).iter().copied().map(number));
+            statements.push(Statement::Assignment {
+                sym: sym.to_string(),
+                op: Operand::Matrix(rows.map(Iterator::collect).collect()),
+            });
+        } else if let Some(text) = env.get_text(sym) {
+            statements.push(Statement::TextAssignment {
+                sym: sym.to_string(),
+                text: Text {
+                    parts: vec![TextPart::Literal(text.to_string())],
+                },
+            });
+        }
+    }
+
+    for name in function_order(env) {
+        match env.get_fun(&name) {
+            Some(Function::Sequence(seq)) => {
+                if seq.body.is_some() {
+                    statements.push(Statement::Function {
+                        name: name.clone(),
+                        fun: Function::Sequence(seq.clone()),
+                    });
+                }
+                for (index, value) in &seq.values {
+                    statements.push(Statement::SequenceValue {
+                        name: name.clone(),
+                        index: *index,
+                        op: number(*value),
+                    });
+                }
+            }
+            Some(fun) => statements.push(Statement::Function {
+                name,
+                fun: fun.clone(),
+            }),
+            None => {}
+        }
+    }
+
+    for sym in topological_order(env) {
+        if let Some(op) = env.formula(&sym) {
+            statements.push(Statement::Define {
+                op: op.clone(),
+                sym,
+            });
+        }
+    }
+
+    for (name, equation) in env.equations() {
+        statements.push(Statement::Store {
+            name: name.clone(),
+            lhs: equation.lhs.clone(),
+            rhs: equation.rhs.clone(),
+        });
+    }
+    statements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::calc_operand;
+    use crate::matrix::Matrix;
+    use crate::parser::{parse, parse_expression};
+
+    fn script(env: &TopLevelEnv) -> Vec<String> {
+        dump(env).iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn dump_in_dependency_order() {
+        let mut env = TopLevelEnv::default();
+        for line in [
+            "f(x) := g(x) ^ 2",
+            "g(x) := x * b",
+            "a(n) := a(n - 1) + 1",
+            "a(0) := -1.5",
+        ] {
+            match parse(line).unwrap() {
+                Statement::Function { name, fun } => env.put_fun(name, fun),
+                Statement::SequenceValue { name, index, op } => {
+                    let num = calc_operand(&op, &env).unwrap();
+                    env.put_sequence_value(name, index, num).unwrap()
+                }
+                _ => unreachable!(),
+            }
+        }
+        env.put("b".to_string(), Number::NAN).unwrap();
+        env.put("c".to_string(), Number::INFINITY).unwrap();
+        env.put_list("xs".to_string(), vec![1.0]).unwrap();
+        let m = Matrix::new(2, vec![1.0, 2.0, 3.0, Number::NAN]).unwrap();
+        env.put_matrix("m".to_string(), m).unwrap();
+        env.put_formula("d".to_string(), parse_expression("b + 1").unwrap());
+        assert_eq!(
+            vec![
+                "b := 0 / 0",
+                "c := 1e999",
+                "m := [1, 2; 3, 0 / 0]",
+                "xs := [1]",
+                "a(n) := a(n - 1) + 1",
+                "a(0) := -1.5",
+                "g(x) := x * b",
+                "f(x) := g(x) ^ 2",
+                "define d = b + 1",
+            ],
+            script(&env)
+        );
+    }
+}
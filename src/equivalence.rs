@@ -0,0 +1,131 @@
+//! Checks whether two expressions are equivalent.
+//!
+//! First the canonical forms of both expressions are compared. If they differ, both
+//! expressions are evaluated for random values of their free variables (variables not
+//! defined in the environment).
+
+use crate::ast::*;
+use crate::calc::{calc_operand, Env, ScopedEnv};
+use crate::random::Rng;
+
+/// number of random samples evaluated if the canonical forms differ
+const SAMPLES: usize = 32;
+
+/// Answer of an equivalence check.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Equivalence {
+    /// both expressions have the same canonical form
+    Proven,
+    /// both expressions agree on all `samples` random probes
+    Probable { samples: usize },
+    /// both expressions differ for the given values of the free variables
+    NotEquivalent {
+        counterexample: Vec<(String, Number)>,
+    },
+    /// no probe could be evaluated for both expressions (e.g. out of the domain)
+    Undetermined,
+}
+
+/// relative difference of probes which is treated as rounding error
+const AGREEMENT: Number = if cfg!(feature = "f32") { 1e-4 } else { 1e-9 };
+
+fn agree(lhs: Number, rhs: Number) -> bool {
+    (lhs - rhs).abs() <= AGREEMENT * lhs.abs().max(rhs.abs()).max(1.0)
+}
+
+pub fn equivalent(lhs: &Operand, rhs: &Operand, env: &dyn Env) -> Equivalence {
+    if lhs.canonicalize() == rhs.canonicalize() {
+        return Equivalence::Proven;
+    }
+
+    let symbols: Vec<String> = lhs
+        .free_symbols()
+        .union(&rhs.free_symbols())
+        .filter(|sym| env.get(sym).is_none())
+        .cloned()
+        .collect();
+
+    let mut rng = Rng::default();
+    let mut samples = 0;
+    for _ in 0..SAMPLES {
+        let values: Vec<Number> = symbols.iter().map(|_| rng.uniform(-10.0, 10.0)).collect();
+        let scope = ScopedEnv::new(env, symbols.iter().map(String::as_str).zip(values.clone()));
+        match (
+            calc_operand::<Number>(lhs, &scope),
+            calc_operand::<Number>(rhs, &scope),
+        ) {
+            (Ok(l), Ok(r)) if l.is_finite() && r.is_finite() => {
+                if !agree(l, r) {
+                    return Equivalence::NotEquivalent {
+                        counterexample: symbols.iter().cloned().zip(values).collect(),
+                    };
+                }
+                samples += 1;
+            }
+            _ => (),
+        }
+    }
+
+    if samples == 0 {
+        Equivalence::Undetermined
+    } else {
+        Equivalence::Probable { samples }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::TopLevelEnv;
+    use crate::parser::parse;
+
+    fn expr(s: &str) -> Operand {
+        match parse(s).unwrap() {
+            Statement::Expression { op } => op,
+            _ => panic!("not an expression"),
+        }
+    }
+
+    fn check(lhs: &str, rhs: &str) -> Equivalence {
+        equivalent(&expr(lhs), &expr(rhs), &TopLevelEnv::default())
+    }
+
+    #[test]
+    fn equivalent_proven() {
+        assert_eq!(Equivalence::Proven, check("a + 2 * b", "b * 2 + a"));
+    }
+
+    #[test]
+    fn equivalent_probable() {
+        assert_eq!(
+            Equivalence::Probable { samples: SAMPLES },
+            check("x ^ 2 - 1", "(x - 1) * (x + 1)")
+        );
+    }
+
+    #[test]
+    fn equivalent_not() {
+        assert!(matches!(
+            check("x ^ 2", "2 * x"),
+            Equivalence::NotEquivalent { counterexample } if counterexample[0].0 == "x"
+        ));
+    }
+
+    #[test]
+    fn equivalent_uses_env() {
+        let mut env = TopLevelEnv::default();
+        env.put("a".to_string(), 2.0).unwrap();
+        assert_eq!(
+            Equivalence::Probable { samples: SAMPLES },
+            equivalent(&expr("a * x"), &expr("x + x"), &env)
+        );
+    }
+
+    #[test]
+    fn equivalent_undetermined() {
+        assert_eq!(
+            Equivalence::Undetermined,
+            check("sqrt(-1 - x ^ 2)", "ln(-1 - x ^ 2)")
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Data output of a [`Plot`] for other tools, e.g. spreadsheets or gnuplot.
+
+use crate::ast::Number;
+use crate::graph::Plot;
+
+use std::fmt::Write;
+
+/// `text` as double quoted gnuplot string
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+impl Plot {
+    /// `pos` on the screen in the coordinates of the plotted area
+    fn unproject(&self, (x, y): (Number, Number)) -> (Number, Number) {
+        (
+            self.screen.x.project(x, &self.area.x),
+            self.screen.y.project(y, &self.area.y),
+        )
+    }
+
+    /// The points of the function in area coordinates as CSV with the header `x,y`.
+    /// Gaps, where the function is not defined or jumps, are left out.
+    /// ```
+    /// use rust_expression::{Area, Calculator};
+    /// let mut c = Calculator::new();
+    /// let graph = c.execute("plot 2 * x").unwrap();
+    /// let area = Area::new(0.0, 0.0, 4.0, 8.0);
+    /// let plot = graph.as_graph().unwrap().plot(&area, &Area::new(0.0, 0.0, 4.0, 8.0)).unwrap();
+    /// assert_eq!("x,y\n0,0\n1,2\n2,4\n3,6\n", plot.to_csv());
+    /// ```
+    pub fn to_csv(&self) -> String {
+        let mut csv = "x,y\n".to_string();
+        for (x, y) in self.points.iter().flatten().map(|at| self.unproject(*at)) {
+            writeln!(csv, "{},{}", x, y).unwrap();
+        }
+        csv
+    }
+
+    /// A gnuplot script drawing the points of the function in area coordinates with
+    /// the labels and markers of the plot. The data is inline, gaps are blank lines.
+    /// ```
+    /// use rust_expression::{Area, Calculator};
+    /// let mut c = Calculator::new();
+    /// let graph = c.execute("plot x ^ 2 title \"square\"").unwrap();
+    /// let area = Area::new(-2.0, -1.0, 2.0, 4.0);
+    /// let plot = graph.as_graph().unwrap().plot(&area, &Area::new(0.0, 0.0, 100.0, 50.0)).unwrap();
+    /// let script = plot.to_gnuplot();
+    /// assert!(script.contains("set title \"square\"") && script.contains("plot $data"));
+    /// ```
+    pub fn to_gnuplot(&self) -> String {
+        let mut script = String::new();
+        let labels = [
+            ("title", &self.labels.title),
+            ("xlabel", &self.labels.xlabel),
+            ("ylabel", &self.labels.ylabel),
+        ];
+        for (name, label) in labels {
+            if let Some(label) = label {
+                writeln!(script, "set {} {}", name, quote(label)).unwrap();
+            }
+        }
+        writeln!(
+            script,
+            "set xrange [{}:{}]",
+            self.area.x.min, self.area.x.max
+        )
+        .unwrap();
+        writeln!(
+            script,
+            "set yrange [{}:{}]",
+            self.area.y.min, self.area.y.max
+        )
+        .unwrap();
+        script.push_str("$data << EOD\n");
+        for point in &self.points {
+            match point {
+                Some(at) => {
+                    let (x, y) = self.unproject(*at);
+                    writeln!(script, "{} {}", x, y).unwrap();
+                }
+                None => script.push('\n'),
+            }
+        }
+        script.push_str("EOD\n");
+        if self.markers.is_empty() {
+            script.push_str("plot $data with lines notitle\n");
+        } else {
+            script.push_str("$markers << EOD\n");
+            for marker in &self.markers {
+                let (x, y) = self.unproject(marker.at);
+                writeln!(script, "{} {}", x, y).unwrap();
+            }
+            script.push_str("EOD\n");
+            script.push_str(
+                "plot $data with lines notitle, $markers with points pointtype 7 notitle\n",
+            );
+        }
+        script
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::PointKind;
+    use crate::graph::{Area, Labels, Marker};
+
+    fn plot() -> Plot {
+        Plot {
+            name: "f(x)".to_string(),
+            points: vec![Some((0.0, 0.0)), Some((1.0, 5.0)), None, Some((3.0, 10.0))],
+            samples: Vec::new(),
+            area: Area::new(-2.0, -1.0, 2.0, 1.0),
+            screen: Area::new(0.0, 0.0, 4.0, 10.0),
+            x_axis: None,
+            y_axis: None,
+            labels: Labels {
+                title: Some("say \"hi\"".to_string()),
+                ..Labels::default()
+            },
+            markers: vec![Marker {
+                kind: PointKind::Maximum,
+                at: (1.0, 5.0),
+            }],
+        }
+    }
+
+    #[test]
+    fn csv_in_area_coordinates() {
+        assert_eq!("x,y\n-2,-1\n-1,0\n1,1\n", plot().to_csv());
+    }
+
+    #[test]
+    fn gnuplot_script() {
+        assert_eq!(
+            concat!(
+                "set title \"say \\\"hi\\\"\"\n",
+                "set xrange [-2:2]\n",
+                "set yrange [-1:1]\n",
+                "$data << EOD\n",
+                "-2 -1\n",
+                "-1 0\n",
+                "\n",
+                "1 1\n",
+                "EOD\n",
+                "$markers << EOD\n",
+                "-1 0\n",
+                "EOD\n",
+                "plot $data with lines notitle, $markers with points pointtype 7 notitle\n",
+            ),
+            plot().to_gnuplot()
+        );
+    }
+}
@@ -0,0 +1,200 @@
+//! Discrete Fourier transform of lists (requires feature `fft`).
+//!
+//! `fft(values)` returns the magnitudes, `fft_phase(values)` the phases of the spectrum.
+//! `ifft(magnitudes, phases)` transforms a spectrum back into (real) values.
+//! Lists are padded with zeros to the next power of two.
+
+use crate::ast::{Data, DataFunction, Number};
+use crate::number::Num;
+
+use num::complex::Complex;
+
+type Complex64 = Complex<Number>;
+
+/// in-place iterative radix-2 Cooley-Tukey transform, `values.len()` must be a power of two
+fn transform(values: &mut [Complex64], inverse: bool) {
+    let n = values.len();
+    if n <= 1 {
+        // a single value is its own spectrum
+        return;
+    }
+    let bits = n.trailing_zeros();
+    for i in 0..n {
+        let j = i.reverse_bits() >> (usize::BITS - bits);
+        if i < j {
+            values.swap(i, j);
+        }
+    }
+
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let tau = Number::from_f64(std::f64::consts::TAU);
+        let step = Complex64::from_polar(1.0, sign * tau / len as Number);
+        for chunk in values.chunks_mut(len) {
+            let mut w = Complex64::new(1.0, 0.0);
+            let (lo, hi) = chunk.split_at_mut(len / 2);
+            for (a, b) in lo.iter_mut().zip(hi.iter_mut()) {
+                let t = *b * w;
+                *b = *a - t;
+                *a += t;
+                w *= step;
+            }
+        }
+        len *= 2;
+    }
+
+    if inverse {
+        for value in values.iter_mut() {
+            *value /= n as Number;
+        }
+    }
+}
+
+fn padded(values: impl Iterator<Item = Complex64>, len: usize) -> Vec<Complex64> {
+    let mut values: Vec<Complex64> = values.collect();
+    values.resize(len.next_power_of_two(), Complex64::new(0.0, 0.0));
+    values
+}
+
+pub fn spectrum(values: &[Number]) -> Vec<Complex64> {
+    let mut spectrum = padded(
+        values.iter().map(|re| Complex64::new(*re, 0.0)),
+        values.len(),
+    );
+    transform(&mut spectrum, false);
+    spectrum
+}
+
+pub fn inverse(spectrum: &[Complex64]) -> Vec<Number> {
+    let mut values = padded(spectrum.iter().copied(), spectrum.len());
+    transform(&mut values, true);
+    values.iter().map(|value| value.re).collect()
+}
+
+fn values(params: &[Data]) -> Result<&[Number], String> {
+    match params[0].list() {
+        Some([]) => Err("values must not be empty".to_string()),
+        Some(values) => Ok(values),
+        None => Err("values must be a list".to_string()),
+    }
+}
+
+fn fft_fn(params: &[Data]) -> Result<Data, String> {
+    let spectrum = spectrum(values(params)?);
+    Ok(Data::List(spectrum.iter().map(|c| c.norm()).collect()))
+}
+
+fn fft_phase_fn(params: &[Data]) -> Result<Data, String> {
+    let spectrum = spectrum(values(params)?);
+    Ok(Data::List(spectrum.iter().map(|c| c.arg()).collect()))
+}
+
+fn ifft_fn(params: &[Data]) -> Result<Data, String> {
+    let magnitudes = params[0].list().ok_or("magnitudes must be a list")?;
+    let phases = params[1].list().ok_or("phases must be a list")?;
+    if magnitudes.len() != phases.len() {
+        return Err(format!(
+            "magnitudes and phases must have the same length, but have {} and {} elements",
+            magnitudes.len(),
+            phases.len()
+        ));
+    }
+    if magnitudes.is_empty() {
+        return Err("magnitudes must not be empty".to_string());
+    }
+    let spectrum: Vec<Complex64> = magnitudes
+        .iter()
+        .zip(phases.iter())
+        .map(|(r, theta)| Complex64::from_polar(*r, *theta))
+        .collect();
+    Ok(Data::List(inverse(&spectrum)))
+}
+
+pub(crate) fn functions() -> Vec<DataFunction> {
+    vec![
+        DataFunction {
+            name: "fft".to_string(),
+            args: vec!["values".to_string()],
+            returns_list: true,
+            body: &fft_fn,
+        },
+        DataFunction {
+            name: "fft_phase".to_string(),
+            args: vec!["values".to_string()],
+            returns_list: true,
+            body: &fft_phase_fn,
+        },
+        DataFunction {
+            name: "ifft".to_string(),
+            args: vec!["magnitudes".to_string(), "phases".to_string()],
+            returns_list: true,
+            body: &ifft_fn,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn fft_constant_signal() {
+        let spectrum = spectrum(&[1.0, 1.0, 1.0, 1.0]);
+        assert_approx_eq!(4.0, spectrum[0].norm());
+        for c in &spectrum[1..] {
+            assert_approx_eq!(0.0, c.norm());
+        }
+    }
+
+    #[test]
+    fn fft_cosine_peak() {
+        let n = 8;
+        let signal: Vec<Number> = (0..n)
+            .map(|k| {
+                (Number::from_f64(std::f64::consts::TAU) * 2.0 * k as Number / n as Number).cos()
+            })
+            .collect();
+        let magnitudes = match fft_fn(&[Data::List(signal)]).unwrap() {
+            Data::List(magnitudes) => magnitudes,
+            _ => panic!("expected a list"),
+        };
+        for (k, magnitude) in magnitudes.iter().enumerate() {
+            let exp = if k == 2 || k == 6 { 4.0 } else { 0.0 };
+            assert_approx_eq!(exp, *magnitude);
+        }
+    }
+
+    #[test]
+    fn fft_round_trip_with_padding() {
+        let signal = [1.0, -2.0, 3.5];
+        let values = inverse(&spectrum(&signal));
+        assert_eq!(4, values.len());
+        for (exp, act) in signal.iter().chain([0.0].iter()).zip(values.iter()) {
+            assert_approx_eq!(*exp, *act);
+        }
+    }
+
+    #[test]
+    fn fft_single_value() {
+        assert_eq!(vec![Complex64::new(-2.5, 0.0)], spectrum(&[-2.5]));
+        assert_eq!(vec![-2.5], inverse(&spectrum(&[-2.5])));
+        assert_eq!(
+            Ok(Data::List(vec![0.0])),
+            fft_phase_fn(&[Data::List(vec![1.0])])
+        );
+    }
+
+    #[test]
+    fn fft_invalid_params() {
+        assert_eq!(
+            Err("values must not be empty".to_string()),
+            fft_fn(&[Data::List(vec![])])
+        );
+        assert_eq!(
+            Err("values must be a list".to_string()),
+            fft_fn(&[Data::Number(1.0)])
+        );
+    }
+}
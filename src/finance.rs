@@ -0,0 +1,190 @@
+//! Financial functions (requires feature `finance`).
+//!
+//! Signs follow the usual spreadsheet convention: money paid is negative, money received
+//! positive. Cash flows are lists starting at period 0.
+
+use crate::ast::{Data, DataFunction, Number};
+
+/// maximal number of Newton iterations for [`irr`]
+const MAX_ITERATIONS: usize = 100;
+
+fn number(params: &[Data], idx: usize, name: &str) -> Result<Number, String> {
+    params[idx]
+        .number()
+        .ok_or_else(|| format!("{} must be a number", name))
+}
+
+fn cashflows(params: &[Data], idx: usize) -> Result<&[Number], String> {
+    match params[idx].list() {
+        Some([]) => Err("cashflows must not be empty".to_string()),
+        Some(cashflows) => Ok(cashflows),
+        None => Err("cashflows must be a list".to_string()),
+    }
+}
+
+/// net present value of `cashflows`, the first one is not discounted
+pub fn npv(rate: Number, cashflows: &[Number]) -> Number {
+    cashflows
+        .iter()
+        .rev()
+        .fold(0.0, |acc, cashflow| acc / (1.0 + rate) + cashflow)
+}
+
+/// derivative of [`npv`] with respect to `rate`
+fn npv_derivative(rate: Number, cashflows: &[Number]) -> Number {
+    cashflows
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(t, cashflow)| -(t as Number) * cashflow / (1.0 + rate).powi(t as i32 + 1))
+        .sum()
+}
+
+/// periodic payment to pay off the present value `pv` in `nper` periods
+pub fn pmt(rate: Number, nper: Number, pv: Number) -> Number {
+    if rate == 0.0 {
+        -pv / nper
+    } else {
+        -pv * rate / (1.0 - (1.0 + rate).powf(-nper))
+    }
+}
+
+/// future value after `nper` periods with payments `pmt` and present value `pv`
+pub fn fv(rate: Number, nper: Number, pmt: Number, pv: Number) -> Number {
+    if rate == 0.0 {
+        -(pv + pmt * nper)
+    } else {
+        let growth = (1.0 + rate).powf(nper);
+        -(pv * growth + pmt * (growth - 1.0) / rate)
+    }
+}
+
+/// internal rate of return, i.e. the rate for which the [`npv`] is zero
+pub fn irr(cashflows: &[Number]) -> Option<Number> {
+    let positive = cashflows.iter().any(|cashflow| *cashflow > 0.0);
+    let negative = cashflows.iter().any(|cashflow| *cashflow < 0.0);
+    if !positive || !negative {
+        return None;
+    }
+
+    let mut rate = 0.1;
+    for _ in 0..MAX_ITERATIONS {
+        let value = npv(rate, cashflows);
+        if value.abs() < 1e-10 {
+            return Some(rate);
+        }
+        let next = rate - value / npv_derivative(rate, cashflows);
+        if !next.is_finite() || next <= -1.0 {
+            return None;
+        }
+        rate = next;
+    }
+    None
+}
+
+fn npv_fn(params: &[Data]) -> Result<Data, String> {
+    let rate = number(params, 0, "rate")?;
+    Ok(Data::Number(npv(rate, cashflows(params, 1)?)))
+}
+
+fn pmt_fn(params: &[Data]) -> Result<Data, String> {
+    let nper = number(params, 1, "nper")?;
+    if nper <= 0.0 {
+        return Err("nper must be positive".to_string());
+    }
+    Ok(Data::Number(pmt(
+        number(params, 0, "rate")?,
+        nper,
+        number(params, 2, "pv")?,
+    )))
+}
+
+fn fv_fn(params: &[Data]) -> Result<Data, String> {
+    Ok(Data::Number(fv(
+        number(params, 0, "rate")?,
+        number(params, 1, "nper")?,
+        number(params, 2, "pmt")?,
+        number(params, 3, "pv")?,
+    )))
+}
+
+fn irr_fn(params: &[Data]) -> Result<Data, String> {
+    irr(cashflows(params, 0)?)
+        .map(Data::Number)
+        .ok_or_else(|| "no internal rate of return found".to_string())
+}
+
+pub(crate) fn functions() -> Vec<DataFunction> {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect();
+    vec![
+        DataFunction {
+            name: "npv".to_string(),
+            args: args(&["rate", "cashflows"]),
+            returns_list: false,
+            body: &npv_fn,
+        },
+        DataFunction {
+            name: "pmt".to_string(),
+            args: args(&["rate", "nper", "pv"]),
+            returns_list: false,
+            body: &pmt_fn,
+        },
+        DataFunction {
+            name: "fv".to_string(),
+            args: args(&["rate", "nper", "pmt", "pv"]),
+            returns_list: false,
+            body: &fv_fn,
+        },
+        DataFunction {
+            name: "irr".to_string(),
+            args: args(&["cashflows"]),
+            returns_list: false,
+            body: &irr_fn,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::number::TEST_TOLERANCE;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn finance_npv() {
+        assert_approx_eq!(-100.0 + 110.0 / 1.1, npv(0.1, &[-100.0, 110.0]));
+        assert_approx_eq!(20.0, npv(0.0, &[-100.0, 60.0, 60.0]));
+    }
+
+    #[test]
+    fn finance_pmt_and_fv() {
+        assert_approx_eq!(-12.1 / 0.21, pmt(0.1, 2.0, 100.0), TEST_TOLERANCE);
+        assert_approx_eq!(-10.0, pmt(0.0, 10.0, 100.0), TEST_TOLERANCE);
+        assert_approx_eq!(-1102.5, fv(0.05, 2.0, 0.0, 1000.0), TEST_TOLERANCE);
+        assert_approx_eq!(-300.0, fv(0.0, 3.0, 100.0, 0.0), TEST_TOLERANCE);
+        // paying off a loan leaves nothing
+        let payment = pmt(0.01, 12.0, 1000.0);
+        assert_approx_eq!(0.0, fv(0.01, 12.0, payment, 1000.0), TEST_TOLERANCE);
+    }
+
+    #[test]
+    fn finance_irr() {
+        let cashflows = [-1000.0, 300.0, 400.0, 500.0];
+        let rate = irr(&cashflows).unwrap();
+        assert_approx_eq!(0.0, npv(rate, &cashflows));
+        assert_approx_eq!(0.1, irr(&[-100.0, 110.0]).unwrap());
+        assert_eq!(None, irr(&[100.0, 110.0]));
+    }
+
+    #[test]
+    fn finance_invalid_params() {
+        assert_eq!(
+            Err("nper must be positive".to_string()),
+            pmt_fn(&[Data::Number(0.1), Data::Number(0.0), Data::Number(1.0)])
+        );
+        assert_eq!(
+            Err("cashflows must be a list".to_string()),
+            npv_fn(&[Data::Number(0.1), Data::Number(1.0)])
+        );
+    }
+}
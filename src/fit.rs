@@ -0,0 +1,121 @@
+//! Least squares fits of list data.
+
+use crate::ast::*;
+
+use thiserror::Error;
+
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum FitError {
+    #[error("Expected a list, but `{0}` is not a list")]
+    NotAList(String),
+    #[error("Both lists must have the same length, but have {0} and {1} elements")]
+    LengthMismatch(usize, usize),
+    #[error("At least two points are needed for a fit, but got {0}")]
+    TooFewPoints(usize),
+    #[error("Cannot fit data with constant x values")]
+    ConstantX,
+}
+
+/// Result of a linear least squares fit `y = slope * x + intercept`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct LinearFit {
+    pub slope: Number,
+    pub intercept: Number,
+    /// coefficient of determination, `1` for a perfect fit
+    pub r_squared: Number,
+}
+
+impl LinearFit {
+    /// the fitted line as function of `arg`
+    pub(crate) fn function(&self, arg: &str) -> Function {
+        Function::Custom(CustomFunction {
+            args: vec![arg.to_string()],
+            body: Operand::Term(Box::new(Term {
+                op: Operation::Add,
+                lhs: Operand::Term(Box::new(Term {
+                    op: Operation::Mul,
+                    lhs: Operand::Number(self.slope),
+                    rhs: Operand::Symbol(arg.to_string()),
+                })),
+                rhs: Operand::Number(self.intercept),
+            })),
+        })
+    }
+}
+
+fn mean(values: &[Number]) -> Number {
+    values.iter().sum::<Number>() / values.len() as Number
+}
+
+pub fn fit_linear(xs: &[Number], ys: &[Number]) -> Result<LinearFit, FitError> {
+    if xs.len() != ys.len() {
+        return Err(FitError::LengthMismatch(xs.len(), ys.len()));
+    }
+    if xs.len() < 2 {
+        return Err(FitError::TooFewPoints(xs.len()));
+    }
+
+    let (mx, my) = (mean(xs), mean(ys));
+    let (mut sxx, mut sxy, mut syy) = (0.0, 0.0, 0.0);
+    for (x, y) in xs.iter().zip(ys.iter()) {
+        sxx += (x - mx) * (x - mx);
+        sxy += (x - mx) * (y - my);
+        syy += (y - my) * (y - my);
+    }
+    if sxx == 0.0 {
+        return Err(FitError::ConstantX);
+    }
+
+    let slope = sxy / sxx;
+    let intercept = my - slope * mx;
+    // constant y values are fitted perfectly by a horizontal line
+    let r_squared = if syy == 0.0 {
+        1.0
+    } else {
+        sxy * sxy / (sxx * syy)
+    };
+    Ok(LinearFit {
+        slope,
+        intercept,
+        r_squared,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn fit_exact_line() {
+        assert_eq!(
+            Ok(LinearFit {
+                slope: 2.0,
+                intercept: 1.0,
+                r_squared: 1.0
+            }),
+            fit_linear(&[0.0, 1.0, 2.0], &[1.0, 3.0, 5.0])
+        );
+    }
+
+    #[test]
+    fn fit_noisy_data() {
+        let fit = fit_linear(&[1.0, 2.0, 3.0, 4.0], &[2.0, 4.0, 5.0, 8.0]).unwrap();
+        assert_approx_eq!(1.9, fit.slope);
+        assert_approx_eq!(0.0, fit.intercept);
+        assert!(fit.r_squared > 0.9 && fit.r_squared < 1.0);
+    }
+
+    #[test]
+    fn fit_errors() {
+        assert_eq!(
+            Err(FitError::LengthMismatch(2, 1)),
+            fit_linear(&[1.0, 2.0], &[1.0])
+        );
+        assert_eq!(Err(FitError::TooFewPoints(1)), fit_linear(&[1.0], &[1.0]));
+        assert_eq!(
+            Err(FitError::ConstantX),
+            fit_linear(&[1.0, 1.0], &[1.0, 2.0])
+        );
+    }
+}
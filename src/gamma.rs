@@ -0,0 +1,127 @@
+//! Factorial and gamma function, e.g. `5!`, `gamma(0.5)` or `lgamma(200)`.
+//!
+//! The gamma function uses the Lanczos approximation, integers are computed exactly as
+//! long as the result is representable.
+
+use crate::ast::{BuildInFunction, Number};
+use crate::number::Num;
+use std::f64::consts::PI;
+
+/// coefficients of the Lanczos approximation with `g = 7`
+const LANCZOS: [f64; 9] = [
+    0.999_999_999_999_809_9,
+    676.520_368_121_885_1,
+    -1_259.139_216_722_402_8,
+    771.323_428_777_653_1,
+    -176.615_029_162_140_6,
+    12.507_343_278_686_905,
+    -0.138_571_095_265_720_12,
+    9.984_369_578_019_572e-6,
+    1.505_632_735_149_311_6e-7,
+];
+const G: f64 = 7.0;
+
+/// `x!` of a non-negative integer, `NaN` for other numbers
+pub fn factorial<N: Num>(x: N) -> N {
+    let one = N::from_f64(1.0);
+    if x < N::from_f64(0.0) || x % one != N::from_f64(0.0) {
+        return N::from_f64(f64::NAN);
+    }
+    let mut result = one;
+    let mut i = one + one;
+    while i <= x && result.is_finite() {
+        result = result * i;
+        i = i + one;
+    }
+    result
+}
+
+/// sum of the Lanczos series and `t` for `x >= 0.5`
+fn lanczos(x: f64) -> (f64, f64) {
+    let x = x - 1.0;
+    let sum = LANCZOS[1..]
+        .iter()
+        .enumerate()
+        .fold(LANCZOS[0], |sum, (i, c)| sum + c / (x + i as f64 + 1.0));
+    (sum, x + G + 0.5)
+}
+
+fn is_pole(x: f64) -> bool {
+    x <= 0.0 && x.fract() == 0.0
+}
+
+fn gamma_f64(x: f64) -> f64 {
+    if x < 0.5 {
+        // reflection formula
+        PI / ((PI * x).sin() * gamma_f64(1.0 - x))
+    } else {
+        let (sum, t) = lanczos(x);
+        (2.0 * PI).sqrt() * t.powf(x - 0.5) * (-t).exp() * sum
+    }
+}
+
+fn lgamma_f64(x: f64) -> f64 {
+    if x < 0.5 {
+        (PI / (PI * x).sin().abs()).ln() - lgamma_f64(1.0 - x)
+    } else {
+        let (sum, t) = lanczos(x);
+        0.5 * (2.0 * PI).ln() + (x - 0.5) * t.ln() - t + sum.ln()
+    }
+}
+
+/// `Γ(x)`, i.e. `(x - 1)!` for positive integers, `NaN` at its poles `0, -1, -2, ...`
+pub fn gamma(x: Number) -> Number {
+    let x64 = x.to_f64();
+    if is_pole(x64) {
+        Number::NAN
+    } else if x > 0.0 && x.fract() == 0.0 {
+        factorial(x - 1.0)
+    } else {
+        Number::from_f64(gamma_f64(x64))
+    }
+}
+
+/// `ln |Γ(x)|`, which stays finite for large `x`, `inf` at the poles of `Γ`
+pub fn lgamma(x: Number) -> Number {
+    let x64 = x.to_f64();
+    if is_pole(x64) {
+        Number::INFINITY
+    } else {
+        Number::from_f64(lgamma_f64(x64))
+    }
+}
+
+pub fn functions() -> Vec<BuildInFunction> {
+    vec![
+        BuildInFunction::unary("gamma", &gamma),
+        BuildInFunction::unary("lgamma", &lgamma),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    #[test]
+    fn factorials() {
+        assert_eq!(1.0, factorial(0.0));
+        assert_eq!(120.0, factorial(5.0));
+        assert_eq!(3_628_800.0, factorial(10.0));
+        assert!(factorial(-1.0).is_nan());
+        assert!(factorial(1.5).is_nan());
+        assert_eq!(Number::INFINITY, factorial(1e9));
+    }
+
+    #[test]
+    fn gamma_function() {
+        assert_eq!(24.0, gamma(5.0));
+        assert_approx_eq!(Number::from_f64(PI.sqrt()), gamma(0.5));
+        assert_approx_eq!(-2.0 * Number::from_f64(PI.sqrt()), gamma(-0.5));
+        assert!(gamma(0.0).is_nan());
+        assert!(gamma(-2.0).is_nan());
+        assert_approx_eq!(Number::from_f64(24.0f64.ln()), lgamma(5.0));
+        assert_approx_eq!(863.232, lgamma(201.0), 1e-3);
+        assert_eq!(Number::INFINITY, lgamma(-1.0));
+    }
+}
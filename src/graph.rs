@@ -1,10 +1,14 @@
 use crate::{
-    ast::{Function, Number},
-    calc::{calc_operand, Env, TopLevelEnv},
+    analysis::{self, CriticalPoint, PointKind},
+    ast::{CustomFunction, Data, FunCall, Function, Number, Operand, Operation},
+    budget,
+    calc::{Env, ScopedEnv, TopLevelEnv},
+    compiled::CompiledFunction,
+    limits::{LimitError, Limits},
+    number::Num,
+    timing::{self, Timing},
 };
 
-use num::iter::range_step_from;
-
 use thiserror::Error;
 
 use std::cmp::PartialEq;
@@ -14,71 +18,621 @@ use std::fmt::Debug;
 pub enum GraphError {
     #[error("Unknown function `{0}` to plot")]
     UnknownFunction(String),
+    #[error(
+        "Expected a sweep with a positive step and at most {MAX_FRAMES} values, but got `{0}`"
+    )]
+    InvalidSweepRange(String),
+    #[error("Expected at most one free variable in the plotted expression, but got {}", .0.iter().map(|name| format!("`{}`", name)).collect::<Vec<_>>().join(", "))]
+    AmbiguousVariable(Vec<String>),
+    #[error("Expected finite angles `[from, to]` with `from` smaller than `to`, but got `{0}`")]
+    InvalidAngles(String),
+    #[error("Expected a finite range `min..max` with `min` smaller than `max`, but got `{0}`")]
+    InvalidRange(String),
+    #[error(
+        "Expected an equation of at most two variables or a function of two arguments, but got `{0}`"
+    )]
+    NotImplicit(String),
+    #[error(transparent)]
+    LimitExceeded(#[from] LimitError),
 }
 
-struct ArgEnv<'a> {
-    name: &'a str,
-    value: Number,
-    env: &'a dyn Env,
+/// most graphs of a [`PlotSequence`]
+pub const MAX_FRAMES: usize = 1000;
+
+/// most bisections of the interval between two screen columns
+const MAX_REFINEMENTS: usize = 6;
+/// distance in pixels of a plot from the straight line between its samples, above which
+/// the interval between the samples is refined
+const TOLERANCE: Number = 0.5;
+/// samples of a polar plot per full turn
+const POLAR_SAMPLES: Number = 720.0;
+/// most samples of a polar plot
+const MAX_POLAR_SAMPLES: usize = 100_000;
+/// size in pixels of the grid cells of an implicit plot
+const IMPLICIT_CELL: Number = 4.0;
+
+/// arguments and values of a function, see [`Plot::samples`]
+type Samples = Vec<(Number, Option<Number>)>;
+
+/// Title and axis labels given by `plot f title "..." xlabel "..." ylabel "..."`
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Labels {
+    pub title: Option<String>,
+    pub xlabel: Option<String>,
+    pub ylabel: Option<String>,
 }
 
-impl<'a> Env for ArgEnv<'a> {
-    fn get(&self, sym: &str) -> Option<&Number> {
-        if sym == self.name {
-            Some(&self.value)
-        } else {
-            self.env.get(sym)
-        }
-    }
-
-    fn get_fun(&self, fun: &str) -> Option<&Function> {
-        self.env.get_fun(fun)
-    }
+/// How the variable and the value of a [`Graph`] are mapped to points.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum PlotKind {
+    /// `y = f(x)`
+    #[default]
+    Cartesian,
+    /// radius `r = f(theta)` for the angles from `from` to `to`, e.g. `plot polar 1 + cos(t)`
+    Polar { from: Number, to: Number },
+    /// zero contour of `f(x, y)`, e.g. `plot x^2 + y^2 = 100`
+    Implicit,
 }
 
 #[derive(Debug, PartialEq)]
 pub struct Graph {
     env: TopLevelEnv,
     fun: Function,
+    /// area shown by [`Graph::plot_view`], changed by zooming and panning;
+    /// boxed like the settings
+    view: Box<Area>,
+    /// boxed, so that a graph stays small as a [`Value`](crate::Value)
+    settings: Box<Settings>,
+}
+
+/// kind, name, labels and limits of a graph
+#[derive(Debug, PartialEq, Default)]
+struct Settings {
+    kind: PlotKind,
+    /// name of the plotted series, e.g. `f(x)`
+    name: String,
+    labels: Labels,
+    /// budget of plotting the graph
+    limits: Limits,
 }
 
 impl Graph {
     pub fn new(name: &str, env: &TopLevelEnv) -> Result<Graph, GraphError> {
         let env = env.clone();
-        let graph = Graph {
+        let mut graph = Graph {
             fun: env
                 .get_fun(name)
                 .ok_or_else(|| GraphError::UnknownFunction(name.to_string()))?
                 .clone(),
             env,
+            view: Box::default(),
+            settings: Box::default(),
         };
+        graph.settings.name = format!("{}({})", name, graph.args().join(", "));
 
         Ok(graph)
     }
 
+    /// Polar graph of the radius `op` for the angles from `from` to `to`, `op` is
+    /// the name of a function or an expression like in [`Graph::of_expression`].
+    pub fn polar(
+        op: &Operand,
+        (from, to): (Number, Number),
+        env: &TopLevelEnv,
+    ) -> Result<Graph, GraphError> {
+        if !(from.is_finite() && to.is_finite() && from < to) {
+            return Err(GraphError::InvalidAngles(format!("[{}, {}]", from, to)));
+        }
+        let mut graph = match op {
+            Operand::Symbol(name) if env.get_fun(name).is_some() => Graph::new(name, env)?,
+            _ => Graph::of_expression(op, env)?,
+        };
+        graph.settings.kind = PlotKind::Polar { from, to };
+        Ok(graph)
+    }
+
+    /// Zero contour of `lhs - rhs`, or of `lhs` without `rhs`. `lhs` is the name of a
+    /// function of two arguments or an expression of at most two free variables, which
+    /// are completed by `x` and `y`. `x` is the horizontal and `y` the vertical axis.
+    pub fn implicit(
+        lhs: &Operand,
+        rhs: Option<&Operand>,
+        env: &TopLevelEnv,
+    ) -> Result<Graph, GraphError> {
+        let mut graph = match (lhs, rhs) {
+            (Operand::Symbol(name), None) if env.get_fun(name).is_some() => {
+                let mut graph = Graph::new(name, env)?;
+                if graph.args().len() != 2 {
+                    return Err(GraphError::NotImplicit(name.clone()));
+                }
+                graph.settings.name.push_str(" = 0");
+                graph
+            }
+            _ => {
+                let (body, name) = match rhs {
+                    Some(rhs) => (
+                        Operand::term(Operation::Sub, lhs.clone(), rhs.clone()),
+                        format!("{} = {}", lhs, rhs),
+                    ),
+                    None => (lhs.clone(), format!("{} = 0", lhs)),
+                };
+                let mut args: Vec<String> = body
+                    .free_symbols()
+                    .into_iter()
+                    .filter(|sym| !env.is_bound(sym))
+                    .collect();
+                if args.len() > 2 {
+                    return Err(GraphError::NotImplicit(name));
+                }
+                for axis in ["x", "y"] {
+                    if args.len() < 2 && !args.iter().any(|arg| arg == axis) {
+                        args.push(axis.to_string());
+                    }
+                }
+                if args[0] == "y" || args[1] == "x" {
+                    args.swap(0, 1);
+                }
+                Graph {
+                    env: env.clone(),
+                    fun: Function::Custom(CustomFunction { args, body }),
+                    view: Box::default(),
+                    settings: Box::new(Settings {
+                        name,
+                        ..Settings::default()
+                    }),
+                }
+            }
+        };
+        graph.settings.kind = PlotKind::Implicit;
+        Ok(graph)
+    }
+
+    /// Graph of an expression, which is wrapped in a function of its free variable
+    /// (`x` if there is none).
+    pub fn of_expression(op: &Operand, env: &TopLevelEnv) -> Result<Graph, GraphError> {
+        let mut free: Vec<String> = op
+            .free_symbols()
+            .into_iter()
+            .filter(|sym| !env.is_bound(sym))
+            .collect();
+        if free.len() > 1 {
+            return Err(GraphError::AmbiguousVariable(free));
+        }
+        Ok(Graph {
+            env: env.clone(),
+            fun: Function::Custom(CustomFunction {
+                args: vec![free.pop().unwrap_or_else(|| "x".to_string())],
+                body: op.clone(),
+            }),
+            view: Box::default(),
+            settings: Box::new(Settings {
+                name: op.to_string(),
+                ..Settings::default()
+            }),
+        })
+    }
+
+    /// Graph of `name` called with `args`, where `param` is fixed to `value`
+    /// and the other argument is the plotted variable.
+    fn partial(
+        name: &str,
+        args: &[String],
+        param: &str,
+        value: Number,
+        env: &TopLevelEnv,
+    ) -> Result<Graph, GraphError> {
+        let mut graph = Graph::new(name, env)?;
+        let params = args
+            .iter()
+            .map(|arg| match arg == param {
+                true => Operand::Number(value),
+                false => Operand::Symbol(arg.clone()),
+            })
+            .collect();
+        let bound: Vec<String> = args
+            .iter()
+            .map(|arg| match arg == param {
+                true => format!("{} = {}", arg, value),
+                false => arg.clone(),
+            })
+            .collect();
+        graph.settings.name = format!("{}({})", name, bound.join(", "));
+        graph.fun = Function::Custom(CustomFunction {
+            args: args.iter().filter(|arg| *arg != param).cloned().collect(),
+            body: Operand::FunCall(FunCall {
+                name: name.to_string(),
+                params,
+            }),
+        });
+        Ok(graph)
+    }
+
+    pub(crate) fn with_labels(mut self, labels: Labels) -> Self {
+        self.settings.labels = labels;
+        self
+    }
+
+    pub(crate) fn with_limits(mut self, limits: Limits) -> Self {
+        self.settings.limits = limits;
+        self
+    }
+
+    /// runs `f` within the operations and time allowed by the limits of the graph
+    fn limited<T>(&self, f: impl FnOnce() -> Result<T, GraphError>) -> Result<T, GraphError> {
+        match budget::limited(&self.settings.limits, f) {
+            (_, Some(exceeded)) => Err(exceeded.into()),
+            (result, None) => result,
+        }
+    }
+
+    pub fn kind(&self) -> PlotKind {
+        self.settings.kind
+    }
+
+    pub fn labels(&self) -> &Labels {
+        &self.settings.labels
+    }
+
+    /// name of the plotted series, e.g. `f(x)`, `f(x, a = 2)` or `x ^ 2`
+    pub fn name(&self) -> &str {
+        &self.settings.name
+    }
+
+    /// title given by `plot f title "..."`
+    pub fn title(&self) -> Option<&str> {
+        self.settings.labels.title.as_deref()
+    }
+
+    fn args(&self) -> &[String] {
+        match self.fun {
+            Function::Custom(ref fun) => &fun.args,
+            Function::BuildIn(ref fun) => &fun.args,
+            Function::Data(ref fun) => &fun.args,
+            Function::Sequence(ref seq) => std::slice::from_ref(&seq.arg),
+            Function::Piecewise(ref fun) => &fun.args,
+        }
+    }
+
+    /// number of arguments of the function
+    pub(crate) fn arity(&self) -> usize {
+        self.args().len()
+    }
+
     fn x_name(&self) -> &str {
+        &self.args()[0]
+    }
+
+    /// value of the function at `params`, `None` where it is not defined
+    fn calc_with(&self, params: &[Number]) -> Option<Number> {
         match self.fun {
-            Function::Custom(ref fun) => &fun.args[0],
-            Function::BuildIn(ref fun) => &fun.arg,
+            Function::Data(ref fun) if fun.args.len() == params.len() => {
+                let params: Vec<Data> = params.iter().map(|num| Data::Number(*num)).collect();
+                (fun.body)(&params).ok()?.number()
+            }
+            Function::Data(_) => None,
+            // the name of the function is only used in errors
+            ref fun => ScopedEnv::new(&self.env, [])
+                .call_resolved("", fun, params)
+                .ok(),
         }
     }
 
+    /// value of a function of two arguments at `(x, y)`, `None` where it is not defined
+    fn calc_at(&self, x: Number, y: Number) -> Option<Number> {
+        self.calc_with(&[x, y])
+    }
+
+    /// finite values of a function of two arguments at `(xs[column], ys[row])` by rows,
+    /// `None` where it is not defined
+    fn calc_grid(&self, xs: &[Number], ys: &[Number]) -> Vec<Vec<Option<Number>>> {
+        timing::count_function_calls((xs.len() * ys.len()) as u64);
+        ys.iter()
+            .map(|y| {
+                xs.iter()
+                    .map(|x| self.calc_at(*x, *y).filter(|z| z.is_finite()))
+                    .collect()
+            })
+            .collect()
+    }
+
     fn calc(&self, x: Number) -> Option<Number> {
-        match self.fun {
-            Function::Custom(ref fun) => {
-                let call_env = ArgEnv {
-                    name: self.x_name(),
-                    value: x,
-                    env: &self.env,
-                };
-                calc_operand(&fun.body, &call_env).ok()
+        self.calc_with(&[x])
+    }
+
+    /// Evaluates the function for all `xs`, `None` where it is not defined.
+    /// Functions of plain arithmetic are compiled once and evaluated in batches,
+    /// others are evaluated in parallel with the `parallel` feature.
+    pub fn eval_many(&self, xs: &[Number]) -> Vec<Option<Number>> {
+        let n = xs.len() as u64;
+        match CompiledFunction::compile(&self.fun, &self.env) {
+            Some(compiled) => {
+                if let Function::Custom(_) = self.fun {
+                    timing::count_function_calls(n);
+                }
+                compiled.eval_many(xs).into_iter().map(Some).collect()
+            }
+            None => {
+                match self.fun {
+                    Function::Custom(_) | Function::Sequence(_) | Function::Piecewise(_) => {
+                        timing::count_function_calls(n)
+                    }
+                    Function::BuildIn(_) | Function::Data(_) => timing::count_builtin_calls(n),
+                }
+                self.calc_all(xs)
             }
-            Function::BuildIn(ref fun) => Some((fun.body)(x)),
         }
     }
 
+    #[cfg(not(feature = "parallel"))]
+    fn calc_all(&self, xs: &[Number]) -> Vec<Option<Number>> {
+        xs.iter().map(|x| self.calc(*x)).collect()
+    }
+
+    /// evaluates the points on the threads of rayon, their calls are counted
+    /// and their operations are spent on this thread
+    #[cfg(feature = "parallel")]
+    fn calc_all(&self, xs: &[Number]) -> Vec<Option<Number>> {
+        use rayon::prelude::*;
+        let budget = budget::current();
+        let (ys, timings): (Vec<_>, Vec<_>) = xs
+            .par_iter()
+            .map(|x| timing::isolated(|| budget::continued(budget, || self.calc(*x))))
+            .unzip();
+        for timing in timings {
+            timing::count_function_calls(timing.function_calls);
+            timing::count_builtin_calls(timing.builtin_calls);
+        }
+        ys.into_iter()
+            .map(|(y, spent)| {
+                let _ = budget::spend(spent);
+                y
+            })
+            .collect()
+    }
+
+    /// Plots the function within `area` onto `screen`, aborted with
+    /// [`GraphError::LimitExceeded`] if it exceeds the limits of the calculator.
     pub fn plot(&self, area: &Area, screen: &Area) -> Result<Plot, GraphError> {
-        Plot::new(self, area, screen)
+        self.limited(|| Plot::new(self, area, screen))
+    }
+
+    /// area currently shown, `-10..10` in both directions until zoomed or panned
+    pub fn view(&self) -> &Area {
+        &self.view
+    }
+
+    pub fn set_view(&mut self, view: Area) {
+        *self.view = view;
+    }
+
+    /// Scales the view by `factor` around `center`, which stays in place, e.g. under
+    /// the mouse pointer. A factor above 1 zooms out, one that is not positive is ignored.
+    pub fn zoom(&mut self, factor: Number, center: (Number, Number)) {
+        if factor > 0.0 && factor.is_finite() {
+            self.view.zoom_at(factor, center);
+        }
+    }
+
+    /// Moves the view by `dx` and `dy`.
+    pub fn pan(&mut self, dx: Number, dy: Number) {
+        self.view.move_by(dx, dy);
+    }
+
+    /// Plots the current view onto `screen`.
+    pub fn plot_view(&self, screen: &Area) -> Result<Plot, GraphError> {
+        self.plot(&self.view, screen)
+    }
+
+    /// Zeros, local extrema and inflection points within `range`, ordered by `x`.
+    /// They are found by sampling the range, so points closer than the sample
+    /// distance may be missed.
+    pub fn analyze(&self, range: &Range) -> Vec<CriticalPoint> {
+        let xs = analysis::samples(range);
+        let ys = self.eval_many(&xs);
+        analysis::critical_points(&|x| self.calc(x), &xs, &ys)
+    }
+
+    /// Like [`Graph::plot`], but with the critical points within `area` as markers.
+    /// Polar and implicit graphs have no markers.
+    pub fn plot_analyzed(&self, area: &Area, screen: &Area) -> Result<Plot, GraphError> {
+        self.limited(|| {
+            let mut plot = self.plot(area, screen)?;
+            if let PlotKind::Polar { .. } | PlotKind::Implicit = self.settings.kind {
+                return Ok(plot);
+            }
+            plot.markers = self
+                .analyze(&area.x)
+                .into_iter()
+                .filter_map(|point| {
+                    let x = area.x.project_inclusive(point.x, &screen.x)?;
+                    let y = area.y.project_inclusive(point.y, &screen.y)?;
+                    Some(Marker {
+                        kind: point.kind,
+                        at: (x, y),
+                    })
+                })
+                .collect();
+            Ok(plot)
+        })
+    }
+
+    /// Like [`Graph::plot`], but also measures the duration and counts the function calls.
+    pub fn plot_with_timing(
+        &self,
+        area: &Area,
+        screen: &Area,
+    ) -> (Result<Plot, GraphError>, Timing) {
+        let mut timing = Timing::default();
+        let start = std::time::Instant::now();
+        let plot = timing::counted(&mut timing, || self.plot(area, screen));
+        timing.plot = start.elapsed();
+        (plot, timing)
+    }
+}
+
+/// Function of two arguments, e.g. `plot f` of `f(x, y) := x * y`, which frontends
+/// sample into a [`SurfacePlot`] to render it as heatmap or surface.
+#[derive(Debug, PartialEq)]
+pub struct Surface {
+    graph: Graph,
+}
+
+impl Surface {
+    /// `graph` of a function of two arguments
+    pub(crate) fn new(graph: Graph) -> Surface {
+        Surface { graph }
+    }
+
+    /// name of the function with its arguments, e.g. `f(x, y)`
+    pub fn name(&self) -> &str {
+        self.graph.name()
+    }
+
+    pub fn labels(&self) -> &Labels {
+        self.graph.labels()
+    }
+
+    /// Samples the function at `columns` times `rows` evenly spaced points of `area`,
+    /// including its edges; at least two in each direction.
+    pub fn sample(
+        &self,
+        area: &Area,
+        columns: usize,
+        rows: usize,
+    ) -> Result<SurfacePlot, GraphError> {
+        self.graph.limited(|| {
+            area.validate()?;
+            let points = |range: &Range, count: usize| {
+                let steps = count.max(2) - 1;
+                (0..=steps)
+                    .map(|idx| range.min + range.get_distance() * idx as Number / steps as Number)
+                    .collect::<Vec<Number>>()
+            };
+            let (xs, ys) = (points(&area.x, columns), points(&area.y, rows));
+            let z = self.graph.calc_grid(&xs, &ys);
+            let z_range = z
+                .iter()
+                .flatten()
+                .flatten()
+                .fold(None, |range, z| match range {
+                    None => Some(Range { min: *z, max: *z }),
+                    Some(Range { min, max }) => Some(Range {
+                        min: min.min(*z),
+                        max: max.max(*z),
+                    }),
+                });
+            let z_tics = match z_range {
+                Some(range) if range.min < range.max => {
+                    let options = TicOptions {
+                        spacing: range.get_distance() / 5.0,
+                        minor: false,
+                        precision: None,
+                    };
+                    Tic::create_tics_with(&range, &range, &options)
+                }
+                Some(range) => vec![Tic::new(range.min, range.min)],
+                None => Vec::new(),
+            };
+            let args = self.graph.args();
+            Ok(SurfacePlot {
+                name: self.name().to_string(),
+                x_label: args[0].clone(),
+                y_label: args[1].clone(),
+                xs,
+                ys,
+                z,
+                z_range,
+                z_tics,
+                labels: self.labels().clone(),
+            })
+        })
+    }
+}
+
+/// Heights of a [`Surface`] sampled on a grid.
+#[derive(Debug, PartialEq)]
+pub struct SurfacePlot {
+    /// name of the function, see [`Surface::name`]
+    pub name: String,
+    /// the first argument of the function
+    pub x_label: String,
+    /// the second argument of the function
+    pub y_label: String,
+    /// arguments of the columns in increasing order
+    pub xs: Vec<Number>,
+    /// arguments of the rows in increasing order
+    pub ys: Vec<Number>,
+    /// `z[row][column]` is the value at `(xs[column], ys[row])`, `None` where it is not
+    /// defined or not finite
+    pub z: Vec<Vec<Option<Number>>>,
+    /// smallest and largest value, equal for a constant function, `None` without values
+    pub z_range: Option<Range>,
+    /// major tics of `z_range`, their positions are values
+    pub z_tics: Vec<Tic>,
+    pub labels: Labels,
+}
+
+/// Graphs of a function for every value of a swept parameter,
+/// e.g. `plot f(x, a) for a in 0..5 step 1`.
+#[derive(Debug, PartialEq)]
+pub struct PlotSequence {
+    param: String,
+    frames: Vec<(Number, Graph)>,
+}
+
+impl PlotSequence {
+    /// `from..to` includes `to` if it is hit by a step.
+    pub(crate) fn new(
+        name: &str,
+        args: &[String],
+        param: &str,
+        (from, to, step): (Number, Number, Number),
+        labels: &Labels,
+        env: &TopLevelEnv,
+    ) -> Result<PlotSequence, GraphError> {
+        let count = ((to - from) / step + 1e-9).floor() + 1.0;
+        if !(step > 0.0 && (1.0..=MAX_FRAMES as Number).contains(&count)) {
+            let range = format!("{}..{} step {}", from, to, step);
+            return Err(GraphError::InvalidSweepRange(range));
+        }
+        let frames = (0..count as usize)
+            .map(|idx| {
+                let value = from + idx as Number * step;
+                let graph = Graph::partial(name, args, param, value, env)?;
+                Ok((value, graph.with_labels(labels.clone())))
+            })
+            .collect::<Result<_, GraphError>>()?;
+        Ok(PlotSequence {
+            param: param.to_string(),
+            frames,
+        })
+    }
+
+    pub(crate) fn with_limits(mut self, limits: Limits) -> Self {
+        self.frames = self
+            .frames
+            .into_iter()
+            .map(|(value, graph)| (value, graph.with_limits(limits)))
+            .collect();
+        self
+    }
+
+    /// name of the swept parameter
+    pub fn param(&self) -> &str {
+        &self.param
+    }
+
+    /// value of the parameter and the graph for it, in increasing order
+    pub fn frames(&self) -> &[(Number, Graph)] {
+        &self.frames
+    }
+
+    /// Plots every frame into the same area.
+    pub fn plot(&self, area: &Area, screen: &Area) -> Result<Vec<Plot>, GraphError> {
+        self.frames
+            .iter()
+            .map(|(_, graph)| graph.plot(area, screen))
+            .collect()
     }
 }
 
@@ -96,6 +650,26 @@ impl Range {
         Range { min, max }
     }
 
+    /// Like [`Range::new`], but fails instead of panicking if `min` is not smaller than `max`
+    /// or they are not finite.
+    pub fn try_new(min: Number, max: Number) -> Result<Range, GraphError> {
+        let range = Range { min, max };
+        range.validate()?;
+        Ok(range)
+    }
+
+    /// checks a range, whose bounds may have been changed directly or by zooming
+    fn validate(&self) -> Result<(), GraphError> {
+        if self.min.is_finite() && self.max.is_finite() && self.min < self.max {
+            Ok(())
+        } else {
+            Err(GraphError::InvalidRange(format!(
+                "{}..{}",
+                self.min, self.max
+            )))
+        }
+    }
+
     pub fn contains(&self, pos: Number) -> bool {
         (self.min..self.max).contains(&pos)
     }
@@ -128,6 +702,12 @@ impl Range {
         self.min -= diff;
         self.max += diff;
     }
+
+    /// scales the distances to `center` by `factor`
+    pub fn zoom_at(&mut self, factor: Number, center: Number) {
+        self.min = center - (center - self.min) * factor;
+        self.max = center + (self.max - center) * factor;
+    }
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -144,6 +724,24 @@ impl Area {
         }
     }
 
+    /// Like [`Area::new`], but fails instead of panicking for an invalid range.
+    pub fn try_new(
+        x_min: Number,
+        y_min: Number,
+        x_max: Number,
+        y_max: Number,
+    ) -> Result<Area, GraphError> {
+        Ok(Area {
+            x: Range::try_new(x_min, x_max)?,
+            y: Range::try_new(y_min, y_max)?,
+        })
+    }
+
+    fn validate(&self) -> Result<(), GraphError> {
+        self.x.validate()?;
+        self.y.validate()
+    }
+
     pub fn move_by(&mut self, x_delta: Number, y_delta: Number) {
         self.x.move_by(x_delta);
         self.y.move_by(y_delta);
@@ -153,42 +751,110 @@ impl Area {
         self.x.zoom_by(factor);
         self.y.zoom_by(factor);
     }
+
+    pub fn zoom_at(&mut self, factor: Number, (x, y): (Number, Number)) {
+        self.x.zoom_at(factor, x);
+        self.y.zoom_at(factor, y);
+    }
+}
+
+/// `-10..10` in both directions
+impl Default for Area {
+    fn default() -> Self {
+        Area::new(-10., -10., 10., 10.)
+    }
+}
+
+/// Spacing and labels of tics, see [`Tic::create_tics_with`].
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TicOptions {
+    /// least distance of major tics on the screen
+    pub spacing: Number,
+    /// with minor tics between the major tics
+    pub minor: bool,
+    /// digits after the decimal point of the labels, by default as many as the step needs
+    pub precision: Option<usize>,
+}
+
+impl Default for TicOptions {
+    fn default() -> Self {
+        TicOptions {
+            spacing: 40.0,
+            minor: true,
+            precision: None,
+        }
+    }
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Tic {
     pub pos: Number,
     pub label: Number,
+    /// the label formatted for display, empty for minor tics
+    pub text: String,
+    /// minor tics are between the labeled major tics
+    pub minor: bool,
+}
+
+/// The smallest step of 1, 2 or 5 times a power of ten, which is at least `min`,
+/// and the number of parts minor tics divide it into.
+fn nice_step(min: Number) -> (Number, usize) {
+    let power = (10.0 as Number).powf(min.log10().floor());
+    [(1.0, 5), (2.0, 4), (5.0, 5)]
+        .into_iter()
+        .map(|(mantissa, parts)| (mantissa * power, parts))
+        .find(|(step, _)| *step >= min)
+        .unwrap_or((10.0 * power, 5))
 }
 
 impl Tic {
+    /// major tic, the text is the label without the noise of repeated float steps,
+    /// e.g. `0.3` for `0.30000000000000004`
     pub fn new(pos: Number, label: Number) -> Tic {
-        Tic { pos, label }
+        let rounded = (label * 1e9).round() / 1e9;
+        Tic {
+            pos,
+            label,
+            text: format!("{}", rounded),
+            minor: false,
+        }
     }
 
+    /// tics with the default [`TicOptions`]
     pub fn create_tics(screen: &Range, area: &Range) -> Vec<Tic> {
-        let width = area.max - area.min;
-        let step = 10f64.powf((width.log10() - 1.0).round());
-        if area.contains(0.0) {
-            let left: Vec<Tic> = range_step_from(0f64, -step)
-                .take_while(|label| label > &area.min)
-                .map(|label| Tic::new(area.project_inclusive(label, screen).unwrap(), label))
-                .collect();
-
-            let right: Vec<Tic> = range_step_from(step, step)
-                .take_while(|label| label < &area.max)
-                .map(|label| Tic::new(area.project_inclusive(label, screen).unwrap(), label))
-                .collect();
-
-            left.iter().rev().chain(right.iter()).copied().collect()
-        } else {
-            let start = (area.min / step).ceil() * step;
+        Tic::create_tics_with(screen, area, &TicOptions::default())
+    }
 
-            range_step_from(start, step)
-                .take_while(|label| label < &area.max)
-                .map(|label| Tic::new(area.project_inclusive(label, screen).unwrap(), label))
-                .collect()
-        }
+    /// Tics of `area` projected onto `screen`. Major tics are at steps of 1, 2 or 5 times
+    /// a power of ten, the smallest step at least `options.spacing` apart on the screen.
+    /// Minor tics divide a step into 5 parts, or 4 parts for steps of 2.
+    pub fn create_tics_with(screen: &Range, area: &Range, options: &TicOptions) -> Vec<Tic> {
+        let min_step = area.get_distance() * options.spacing.max(1.0) / screen.get_distance();
+        let (step, parts) = nice_step(min_step);
+        let parts = if options.minor { parts as i64 } else { 1 };
+        let digits = options
+            .precision
+            .unwrap_or_else(|| (-step.log10().floor()).max(0.0) as usize);
+        let minor_step = step / parts as Number;
+        let first = (area.min / minor_step).ceil() as i64;
+        let last = (area.max / minor_step).ceil() as i64;
+        (first..last)
+            .filter_map(|idx| {
+                let label = idx as Number * minor_step;
+                let pos = area.project_inclusive(label, screen)?;
+                let minor = idx % parts != 0;
+                let text = match minor {
+                    true => String::new(),
+                    false => format!("{:.*}", digits, label),
+                };
+                Some(Tic {
+                    pos,
+                    label,
+                    text,
+                    minor,
+                })
+            })
+            .collect()
     }
 }
 
@@ -196,43 +862,336 @@ impl Tic {
 pub struct Axis {
     pub pos: Number,
     pub tics: Vec<Tic>,
+    /// the argument on the horizontal axis, the function on the vertical one
+    pub label: String,
 }
 
 impl Axis {
-    pub fn new(pos: Option<Number>, screen: &Range, area: &Range) -> Option<Axis> {
+    pub fn new(pos: Option<Number>, screen: &Range, area: &Range, label: &str) -> Option<Axis> {
         pos.map(|pos| Axis {
             pos,
             tics: Tic::create_tics(screen, area),
+            label: label.to_string(),
         })
     }
 }
 
+/// direction of an axis on the screen
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Direction {
+    Horizontal,
+    Vertical,
+}
+
+/// Backend drawing a [`Plot`], all positions are screen coordinates.
+pub trait PlotRenderer {
+    /// axis along `direction` at `pos` on the other coordinate spanning `range`
+    fn draw_axis(&mut self, direction: Direction, pos: Number, range: &Range);
+    /// tic on the axis along `direction` at `pos` on the other coordinate
+    fn draw_tic(&mut self, direction: Direction, pos: Number, tic: &Tic);
+    /// line between two neighboring points of the function
+    fn draw_segment(&mut self, from: (Number, Number), to: (Number, Number));
+    /// point of the function without defined neighbors
+    fn draw_point(&mut self, at: (Number, Number));
+    /// title and axis labels, drawn first; ignored by default
+    fn draw_labels(&mut self, _labels: &Labels) {}
+    /// critical point of the function, drawn last; ignored by default
+    fn draw_marker(&mut self, _marker: &Marker) {}
+}
+
+/// Critical point of a [`Plot`] in screen coordinates.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Marker {
+    pub kind: PointKind,
+    pub at: (Number, Number),
+}
+
+/// Does the sample `m` in the middle of `a` and `b` deviate from the straight line between them,
+/// unless all are beyond the same edge of the `screen`.
+fn bends(a: Option<Number>, m: Option<Number>, b: Option<Number>, screen: &Range) -> bool {
+    match (a, m, b) {
+        (Some(a), Some(m), Some(b)) => {
+            let above = a > screen.max && m > screen.max && b > screen.max;
+            let below = a < screen.min && m < screen.min && b < screen.min;
+            (m - (a + b) / 2.0).abs() > TOLERANCE && !above && !below
+        }
+        _ => false,
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Plot {
-    pub points: Vec<Option<Number>>,
+    /// name of the plotted series, see [`Graph::name`]
+    pub name: String,
+    /// points of the function in screen coordinates in drawing order, which is by `x`
+    /// except for polar plots, `None` where it is not defined or jumps; for implicit
+    /// plots the segments of the contour, each followed by `None`
+    pub points: Vec<Option<(Number, Number)>>,
+    /// sampled arguments and values of the function in drawing order, `None` where it is
+    /// not defined; for polar plots the angles and radii, empty for implicit plots.
+    /// Unlike `points` they are not projected, so values beyond the area are kept.
+    pub samples: Vec<(Number, Option<Number>)>,
+    /// plotted area of the function, projected to `screen`
+    pub area: Area,
     pub screen: Area,
     pub x_axis: Option<Axis>,
     pub y_axis: Option<Axis>,
+    pub labels: Labels,
+    /// only filled by [`Graph::plot_analyzed`]
+    pub markers: Vec<Marker>,
 }
 
 impl Plot {
+    /// Fails for an `area` or `screen` with an empty or infinite range.
     pub fn new(graph: &Graph, area: &Area, screen: &Area) -> Result<Plot, GraphError> {
-        let points = ((screen.x.min as i32)..(screen.x.max as i32))
-            .map(|w| {
-                let x = screen.x.project_inclusive(w as f64, &area.x).unwrap();
-                graph.calc(x).map(|y| area.y.project(y, &screen.y))
-            })
-            .collect();
-        let x_axis = Axis::new(area.y.project_inclusive(0., &screen.y), &screen.x, &area.x);
-        let y_axis = Axis::new(area.x.project_inclusive(0., &screen.x), &screen.y, &area.y);
+        area.validate()?;
+        screen.validate()?;
+        let (points, samples) = match graph.settings.kind {
+            PlotKind::Cartesian => Plot::cartesian(graph, area, screen),
+            PlotKind::Polar { from, to } => Plot::polar(graph, (from, to), area, screen),
+            PlotKind::Implicit => (Plot::implicit(graph, area, screen), Vec::new()),
+        };
+        let (x_label, y_label) = match graph.settings.kind {
+            PlotKind::Cartesian => (graph.x_name(), graph.name()),
+            PlotKind::Polar { .. } => ("x", "y"),
+            PlotKind::Implicit => (graph.x_name(), graph.args()[1].as_str()),
+        };
+        let x_axis = Axis::new(
+            area.y.project_inclusive(0., &screen.y),
+            &screen.x,
+            &area.x,
+            x_label,
+        );
+        let y_axis = Axis::new(
+            area.x.project_inclusive(0., &screen.x),
+            &screen.y,
+            &area.y,
+            y_label,
+        );
 
         Ok(Plot {
+            name: graph.name().to_string(),
             points,
+            samples,
+            area: *area,
             screen: *screen,
             x_axis,
             y_axis,
+            labels: graph.settings.labels.clone(),
+            markers: Vec::new(),
         })
     }
+
+    /// Samples the function at every screen column, intervals where the function bends
+    /// or becomes undefined are bisected up to 6 times. Intervals that still bend
+    /// after that are jumps or poles like `1 / x` at `0`, they are broken by `None`.
+    /// Returns the points and the samples.
+    fn cartesian(
+        graph: &Graph,
+        area: &Area,
+        screen: &Area,
+    ) -> (Vec<Option<(Number, Number)>>, Samples) {
+        let columns: Vec<Number> = ((screen.x.min as i32)..(screen.x.max as i32))
+            .map(|w| w as Number)
+            .collect();
+        let to_screen = |y: Option<Number>| y.map(|y| area.y.project(y, &screen.y));
+        let ys = Plot::sample(graph, &columns, area, screen);
+        let mut samples: Vec<(Number, Option<Number>)> = columns.into_iter().zip(ys).collect();
+        // the first bisection looks for spikes between all columns
+        let mut refine: Vec<bool> = samples
+            .windows(2)
+            .map(|pair| pair[0].1.is_some() || pair[1].1.is_some())
+            .collect();
+        for _ in 0..MAX_REFINEMENTS {
+            let mids: Vec<Number> = samples
+                .windows(2)
+                .zip(&refine)
+                .filter(|(_, needed)| **needed)
+                .map(|(pair, _)| (pair[0].0 + pair[1].0) / 2.0)
+                .collect();
+            if mids.is_empty() {
+                break;
+            }
+            let mut refined = Vec::with_capacity(samples.len() + mids.len());
+            let mut next = Vec::with_capacity(refine.len() + mids.len());
+            let ys = Plot::sample(graph, &mids, area, screen);
+            let mut mids = mids.into_iter().zip(ys);
+            for (pair, needed) in samples.windows(2).zip(&refine) {
+                refined.push(pair[0]);
+                match if *needed { mids.next() } else { None } {
+                    Some(mid) => {
+                        let (a, m, b) =
+                            (to_screen(pair[0].1), to_screen(mid.1), to_screen(pair[1].1));
+                        let bent = bends(a, m, b, &screen.y);
+                        let left = bent || a.is_some() != m.is_some();
+                        let right = bent || m.is_some() != b.is_some();
+                        if left || right {
+                            refined.push(mid);
+                            next.extend([left, right]);
+                        } else {
+                            next.push(false);
+                        }
+                    }
+                    None => next.push(false),
+                }
+            }
+            refined.extend(samples.last());
+            samples = refined;
+            refine = next;
+        }
+        let mut points = Vec::with_capacity(samples.len());
+        for (idx, (x, y)) in samples.iter().enumerate() {
+            let y = to_screen(*y);
+            if idx > 0 && refine[idx - 1] {
+                if let (Some(prev), Some(y)) = (to_screen(samples[idx - 1].1), y) {
+                    if (y - prev).abs() > 2.0 * TOLERANCE {
+                        points.push(None);
+                    }
+                }
+            }
+            points.push(y.map(|y| (*x, y)));
+        }
+        let samples = samples
+            .into_iter()
+            .map(|(x, y)| (screen.x.project(x, &area.x), y))
+            .collect();
+        (points, samples)
+    }
+
+    /// Samples the radius at evenly spaced angles, the points follow the angle.
+    /// Returns the points and the samples.
+    fn polar(
+        graph: &Graph,
+        (from, to): (Number, Number),
+        area: &Area,
+        screen: &Area,
+    ) -> (Vec<Option<(Number, Number)>>, Samples) {
+        let turns = (to - from) / Number::from_f64(std::f64::consts::TAU);
+        let count = ((turns * POLAR_SAMPLES).ceil() as usize).clamp(1, MAX_POLAR_SAMPLES);
+        let angles: Vec<Number> = (0..=count)
+            .map(|idx| from + (to - from) * idx as Number / count as Number)
+            .collect();
+        let samples: Samples = graph
+            .eval_many(&angles)
+            .into_iter()
+            .zip(angles)
+            .map(|(r, angle)| (angle, r.filter(|r| r.is_finite())))
+            .collect();
+        let points = samples
+            .iter()
+            .map(|(angle, r)| {
+                let r = (*r)?;
+                let x = area.x.project(r * angle.cos(), &screen.x);
+                let y = area.y.project(r * angle.sin(), &screen.y);
+                Some((x, y))
+            })
+            .collect();
+        (points, samples)
+    }
+
+    /// Evaluates the function on a grid of cells of 4 pixels and connects the points,
+    /// where it changes its sign along the edges of a cell (marching squares).
+    /// Returns the segments of the contour, each followed by `None`.
+    fn implicit(graph: &Graph, area: &Area, screen: &Area) -> Vec<Option<(Number, Number)>> {
+        let cells = |range: &Range| {
+            let count = (range.get_distance().abs() / IMPLICIT_CELL).ceil().max(1.0) as usize;
+            (0..=count)
+                .map(|idx| range.min + range.get_distance() * idx as Number / count as Number)
+                .collect::<Vec<Number>>()
+        };
+        let (columns, rows) = (cells(&screen.x), cells(&screen.y));
+        let xs: Vec<Number> = columns
+            .iter()
+            .map(|x| screen.x.project(*x, &area.x))
+            .collect();
+        let ys: Vec<Number> = rows.iter().map(|y| screen.y.project(*y, &area.y)).collect();
+        let values = graph.calc_grid(&xs, &ys);
+        let mut points = Vec::new();
+        for j in 0..rows.len() - 1 {
+            for i in 0..columns.len() - 1 {
+                // corners counterclockwise, edge `k` runs from corner `k` to corner `k + 1`
+                let corners = [(i, j), (i + 1, j), (i + 1, j + 1), (i, j + 1)];
+                let Some(vs) = corners
+                    .iter()
+                    .map(|(i, j)| values[*j][*i])
+                    .collect::<Option<Vec<Number>>>()
+                else {
+                    continue;
+                };
+                let crossing = |k: usize| {
+                    let (a, b) = (k, (k + 1) % 4);
+                    if (vs[a] > 0.0) == (vs[b] > 0.0) {
+                        return None;
+                    }
+                    let t = vs[a] / (vs[a] - vs[b]);
+                    let ((ia, ja), (ib, jb)) = (corners[a], corners[b]);
+                    Some((
+                        columns[ia] + (columns[ib] - columns[ia]) * t,
+                        rows[ja] + (rows[jb] - rows[ja]) * t,
+                    ))
+                };
+                let edges: Vec<(Number, Number)> = (0..4).filter_map(crossing).collect();
+                let segments = match edges.len() {
+                    2 => vec![(edges[0], edges[1])],
+                    4 => {
+                        // a saddle, the center decides which opposite corners are connected
+                        let center = vs.iter().sum::<Number>() / 4.0;
+                        if (center > 0.0) == (vs[0] > 0.0) {
+                            vec![(edges[0], edges[1]), (edges[2], edges[3])]
+                        } else {
+                            vec![(edges[3], edges[0]), (edges[1], edges[2])]
+                        }
+                    }
+                    _ => Vec::new(),
+                };
+                for (from, to) in segments {
+                    points.extend([Some(from), Some(to), None]);
+                }
+            }
+        }
+        points
+    }
+
+    /// values of the function at the screen columns `xs`, `None` where it is not defined
+    /// or not finite
+    fn sample(graph: &Graph, xs: &[Number], area: &Area, screen: &Area) -> Vec<Option<Number>> {
+        let xs: Vec<Number> = xs.iter().map(|x| screen.x.project(*x, &area.x)).collect();
+        graph
+            .eval_many(&xs)
+            .into_iter()
+            .map(|y| y.filter(|y| y.is_finite()))
+            .collect()
+    }
+
+    /// Draws the labels, the axes with their tics, the function, then the markers.
+    /// Neighboring points are connected by segments.
+    pub fn render(&self, renderer: &mut impl PlotRenderer) {
+        renderer.draw_labels(&self.labels);
+        let axes = [
+            (Direction::Horizontal, &self.x_axis, &self.screen.x),
+            (Direction::Vertical, &self.y_axis, &self.screen.y),
+        ];
+        for (direction, axis, range) in axes {
+            if let Some(axis) = axis {
+                renderer.draw_axis(direction, axis.pos, range);
+                for tic in &axis.tics {
+                    renderer.draw_tic(direction, axis.pos, tic);
+                }
+            }
+        }
+
+        let point = |idx: usize| self.points.get(idx).copied().flatten();
+        for idx in 0..self.points.len() {
+            match (point(idx), point(idx + 1)) {
+                (Some(from), Some(to)) => renderer.draw_segment(from, to),
+                (Some(at), None) if idx == 0 || point(idx - 1).is_none() => renderer.draw_point(at),
+                _ => {}
+            }
+        }
+        for marker in &self.markers {
+            renderer.draw_marker(marker);
+        }
+    }
 }
 
 #[cfg(test)]
@@ -248,11 +1207,7 @@ mod tests {
         env.put("x".to_string(), -19.0).unwrap();
         let name = "x";
         let value = 42.0;
-        let env = ArgEnv {
-            name,
-            value,
-            env: &env,
-        };
+        let env = ScopedEnv::new(&env, [(name, value)]);
         assert_eq!(Some(&42.0), env.get("x"));
     }
 
@@ -262,14 +1217,97 @@ mod tests {
         env.put("y".to_string(), -19.0).unwrap();
         let name = "x";
         let value = 42.0;
-        let env = ArgEnv {
-            name,
-            value,
-            env: &env,
-        };
+        let env = ScopedEnv::new(&env, [(name, value)]);
         assert_eq!(Some(&-19.0), env.get("y"));
     }
 
+    #[derive(Default)]
+    struct Recorder(Vec<String>);
+
+    impl PlotRenderer for Recorder {
+        fn draw_axis(&mut self, direction: Direction, pos: Number, range: &Range) {
+            self.0.push(format!(
+                "axis {:?} {} {}..{}",
+                direction, pos, range.min, range.max
+            ));
+        }
+
+        fn draw_tic(&mut self, direction: Direction, pos: Number, tic: &Tic) {
+            self.0.push(format!(
+                "tic {:?} {} {} {}",
+                direction, pos, tic.pos, tic.label
+            ));
+        }
+
+        fn draw_segment(&mut self, from: (Number, Number), to: (Number, Number)) {
+            self.0.push(format!("segment {:?} {:?}", from, to));
+        }
+
+        fn draw_point(&mut self, at: (Number, Number)) {
+            self.0.push(format!("point {:?}", at));
+        }
+
+        fn draw_marker(&mut self, marker: &Marker) {
+            self.0
+                .push(format!("marker {:?} {:?}", marker.kind, marker.at));
+        }
+
+        fn draw_labels(&mut self, labels: &Labels) {
+            self.0.push(format!(
+                "labels {:?} {:?} {:?}",
+                labels.title, labels.xlabel, labels.ylabel
+            ));
+        }
+    }
+
+    #[test]
+    fn render_plot() {
+        let plot = Plot {
+            name: "f(x)".to_string(),
+            points: vec![
+                Some((10.0, 1.0)),
+                Some((11.0, 2.0)),
+                None,
+                Some((13.0, 3.0)),
+                None,
+                Some((15.0, 4.0)),
+                Some((16.0, 5.0)),
+            ],
+            samples: Vec::new(),
+            area: Area::default(),
+            screen: Area::new(10.0, 0.0, 17.0, 10.0),
+            x_axis: Some(Axis {
+                pos: 5.0,
+                tics: vec![Tic::new(12.0, 1.0)],
+                label: "x".to_string(),
+            }),
+            y_axis: None,
+            labels: Labels {
+                title: Some("height".to_string()),
+                xlabel: Some("t".to_string()),
+                ylabel: None,
+            },
+            markers: vec![Marker {
+                kind: PointKind::Maximum,
+                at: (16.0, 5.0),
+            }],
+        };
+        let mut recorder = Recorder::default();
+        plot.render(&mut recorder);
+        assert_eq!(
+            vec![
+                "labels Some(\"height\") Some(\"t\") None",
+                "axis Horizontal 5 10..17",
+                "tic Horizontal 5 12 1",
+                "segment (10.0, 1.0) (11.0, 2.0)",
+                "point (13.0, 3.0)",
+                "segment (15.0, 4.0) (16.0, 5.0)",
+                "marker Maximum (16.0, 5.0)",
+            ],
+            recorder.0
+        );
+    }
+
     #[test]
     fn function_call() {
         let fun = Function::Custom(CustomFunction {
@@ -277,16 +1315,62 @@ mod tests {
             body: Operand::Symbol("x".to_string()),
         });
         let env = TopLevelEnv::default();
-        let graph = Graph { fun: fun, env };
+        let graph = Graph {
+            fun,
+            env,
+            view: Box::default(),
+            settings: Box::default(),
+        };
         assert_eq!(Some(1.0), graph.calc(1.0));
     }
 
+    #[test]
+    fn eval_many_of_interpreted_function() {
+        let mut env = TopLevelEnv::default();
+        env.put_fun(
+            "f".to_string(),
+            Function::Custom(CustomFunction {
+                args: vec!["x".to_string()],
+                body: crate::parser::parse_expression("if(x > 0, sqrt(x), 0)").unwrap(),
+            }),
+        );
+        let graph = Graph::new("f", &env).unwrap();
+        let xs: Vec<Number> = (-50..50).map(|x| x as Number).collect();
+        let mut timing = Timing::default();
+        let ys = timing::counted(&mut timing, || graph.eval_many(&xs));
+        let expected: Vec<_> = xs.iter().map(|x| graph.calc(*x)).collect();
+        assert_eq!(expected, ys);
+        assert_eq!(100, timing.function_calls);
+        assert_eq!(49, timing.builtin_calls);
+    }
+
     #[test]
     #[should_panic(expected = "min 4.0 must be smaller than max 3.0")]
     fn range_construct_failure() {
         let _ = Range::new(4., 3.);
     }
 
+    #[test]
+    fn invalid_ranges() {
+        assert_eq!(Ok(Range::new(1., 2.)), Range::try_new(1., 2.));
+        assert_eq!(
+            Err(GraphError::InvalidRange("4..3".to_string())),
+            Range::try_new(4., 3.)
+        );
+        assert!(Range::try_new(Number::NAN, 1.).is_err());
+        assert!(Area::try_new(0., 0., Number::INFINITY, 1.).is_err());
+
+        let graph =
+            Graph::of_expression(&Operand::Symbol("x".to_string()), &TopLevelEnv::default())
+                .unwrap();
+        let mut area = Area::default();
+        area.zoom_by(-1.);
+        assert_eq!(
+            Err(GraphError::InvalidRange("10..-10".to_string())),
+            graph.plot(&area, &Area::default())
+        );
+    }
+
     #[test]
     fn range_distance_f64() {
         assert_eq!(4.0, Range::new(10.0, 14.0).get_distance());
@@ -355,9 +1439,74 @@ mod tests {
         assert_eq!(20., plot.x_axis.unwrap().pos);
         assert_eq!(20., plot.y_axis.unwrap().pos);
         assert_eq!(40, plot.points.len());
-        assert_eq!(Some(-20.), plot.points[0]);
-        assert_eq!(Some(18.), plot.points[19]);
-        assert_eq!(Some(58.), plot.points[39]);
+        assert_eq!(Some((0., -20.)), plot.points[0]);
+        assert_eq!(Some((19., 18.)), plot.points[19]);
+        assert_eq!(Some((39., 58.)), plot.points[39]);
+    }
+
+    fn plot_of(expr: &str, area: &Area, screen: &Area) -> Plot {
+        let mut env = TopLevelEnv::default();
+        env.put_fun(
+            "f".to_string(),
+            Function::Custom(CustomFunction {
+                args: vec!["x".to_string()],
+                body: crate::parser::parse_expression(expr).unwrap(),
+            }),
+        );
+        Graph::new("f", &env).unwrap().plot(area, screen).unwrap()
+    }
+
+    #[test]
+    fn plot_breaks_at_poles() {
+        // the pole at `x = 0` is at 3.5 on the screen, between two columns
+        let screen = Area::new(0., 0., 7., 100.);
+        let plot = plot_of("1 / x", &Area::new(-1., -10., 1., 10.), &screen);
+        let across = plot.points.windows(2).filter(|pair| match pair {
+            [Some((a, _)), Some((b, _))] => *a < 3.5 && *b > 3.5,
+            _ => false,
+        });
+        assert_eq!(0, across.count());
+        assert_eq!(
+            1,
+            plot.points.iter().filter(|point| point.is_none()).count()
+        );
+        assert!(plot.points.windows(2).all(|pair| match pair {
+            [Some((a, _)), Some((b, _))] => a < b,
+            _ => true,
+        }));
+    }
+
+    #[test]
+    fn plot_refines_spikes() {
+        // the columns are at odd tenths, the spike at 0 is between them
+        let area = Area::new(-0.9, 0., 1.1, 1.);
+        let screen = Area::new(0., 0., 10., 100.);
+        let plot = plot_of("1 / (1 + (20 * x) ^ 2)", &area, &screen);
+        let top = plot
+            .points
+            .iter()
+            .flatten()
+            .map(|(_, y)| *y)
+            .fold(0., Number::max);
+        assert!(top > 95., "{}", top);
+        let line = plot_of("x", &area, &screen);
+        assert_eq!(10, line.points.len());
+    }
+
+    #[test]
+    fn plot_keeps_samples() {
+        let area = Area::new(-4., -1., 4., 1.);
+        let screen = Area::new(0., 0., 8., 100.);
+        let plot = plot_of("x ^ 2 - 5", &area, &screen);
+        assert_eq!((-4., Some(11.)), plot.samples[0]);
+        assert!(plot.samples.contains(&(0., Some(-5.))));
+        assert!(plot.samples.iter().all(|(x, y)| *y == Some(x * x - 5.)));
+        let plot = plot_of("sqrt(x)", &area, &screen);
+        assert_eq!((-4., None), plot.samples[0]);
+        assert_eq!(
+            (3., Some(Number::sqrt(3.))),
+            plot.samples[plot.samples.len() - 1]
+        );
     }
 
     #[test]
@@ -394,6 +1543,21 @@ mod tests {
         assert_approx_eq!(13., r.max);
     }
 
+    #[test]
+    fn graph_zoom_and_pan() {
+        let mut graph =
+            Graph::of_expression(&Operand::Symbol("x".to_string()), &TopLevelEnv::default())
+                .unwrap();
+        assert_eq!(&Area::default(), graph.view());
+        graph.zoom(0.5, (10., 0.));
+        assert_eq!(&Area::new(0., -5., 10., 5.), graph.view());
+        graph.zoom(0., (0., 0.));
+        graph.pan(-5., 5.);
+        assert_eq!(&Area::new(-5., 0., 5., 10.), graph.view());
+        let plot = graph.plot_view(&Area::new(0., 0., 10., 10.)).unwrap();
+        assert_eq!(Some((0., -5.)), plot.points[0]);
+    }
+
     #[test]
     fn range_zoom_by_in() {
         let mut r = Range::new(2., 12.);
@@ -402,80 +1566,76 @@ mod tests {
         assert_approx_eq!(11., r.max);
     }
 
+    fn majors(tics: &[Tic]) -> Vec<(Number, &str)> {
+        tics.iter()
+            .filter(|tic| !tic.minor)
+            .map(|tic| (tic.pos, tic.text.as_str()))
+            .collect()
+    }
+
     #[test]
     fn create_tics_with_zero() {
-        use float_cmp::approx_eq;
-
-        let act = Tic::create_tics(&Range::new(-100., 100.), &Range::new(-5., 15.));
-        let exp: Vec<Tic> = range_step_from(-90., 10.)
-            .zip(range_step_from(-4., 1.))
-            .take(19)
-            .map(|(pos, label)| Tic::new(pos, label))
-            .collect();
-
-        assert_eq!(exp.len(), act.len());
-        assert!(exp.iter().zip(act.iter()).all(|(exp, act)| approx_eq!(
-            f64,
-            exp.pos,
-            act.pos,
-            epsilon = 0.00001
-        ) && approx_eq!(
-            f64,
-            exp.label,
-            act.label,
-            epsilon = 0.00001
-        )));
+        let tics = Tic::create_tics(&Range::new(-100., 100.), &Range::new(-5., 15.));
+        assert_eq!(
+            vec![(-100., "-5"), (-50., "0"), (0., "5"), (50., "10")],
+            majors(&tics)
+        );
+        assert_eq!(20, tics.len());
     }
 
     #[test]
     fn create_tics_above_zero() {
-        use float_cmp::approx_eq;
-
-        let act = Tic::create_tics(&Range::new(0., 400.), &Range::new(3., 19.));
-        let exp: Vec<Tic> = range_step_from(0., 25.)
-            .zip(range_step_from(3., 1.))
-            .take(16)
-            .map(|(pos, label)| Tic::new(pos, label))
+        let tics = Tic::create_tics(&Range::new(0., 400.), &Range::new(3., 19.));
+        let labels: Vec<String> = (4..=18).step_by(2).map(|label| label.to_string()).collect();
+        let exp: Vec<(Number, &str)> = labels
+            .iter()
+            .map(|label| {
+                (
+                    (label.parse::<Number>().unwrap() - 3.) * 25.,
+                    label.as_str(),
+                )
+            })
             .collect();
-
-        assert_eq!(exp.len(), act.len());
-        println!("{:?}", act);
-        assert!(exp.iter().zip(act.iter()).all(|(exp, act)| approx_eq!(
-            f64,
-            exp.pos,
-            act.pos,
-            epsilon = 0.00001
-        ) && approx_eq!(
-            f64,
-            exp.label,
-            act.label,
-            epsilon = 0.00001
-        )));
+        assert_eq!(exp, majors(&tics));
+        // minor tics every 0.5 from 3 to 18.5
+        assert_eq!(32, tics.len());
     }
 
     #[test]
     fn create_tics_below_zero() {
-        use float_cmp::approx_eq;
-
-        let act = Tic::create_tics(&Range::new(0., 400.), &Range::new(-19., -3.));
-        let exp: Vec<Tic> = range_step_from(0., 25.)
-            .zip(range_step_from(-19., 1.))
-            .take(16)
-            .map(|(pos, label)| Tic::new(pos, label))
+        let tics = Tic::create_tics(&Range::new(0., 400.), &Range::new(-19., -3.));
+        let labels: Vec<String> = (-18..=-4)
+            .step_by(2)
+            .map(|label| label.to_string())
+            .collect();
+        let exp: Vec<(Number, &str)> = labels
+            .iter()
+            .map(|label| {
+                (
+                    (label.parse::<Number>().unwrap() + 19.) * 25.,
+                    label.as_str(),
+                )
+            })
             .collect();
+        assert_eq!(exp, majors(&tics));
+    }
 
-        assert_eq!(exp.len(), act.len());
-        println!("{:?}", act);
-        assert!(exp.iter().zip(act.iter()).all(|(exp, act)| approx_eq!(
-            f64,
-            exp.pos,
-            act.pos,
-            epsilon = 0.00001
-        ) && approx_eq!(
-            f64,
-            exp.label,
-            act.label,
-            epsilon = 0.00001
-        )));
+    #[test]
+    fn tic_labels() {
+        let options = TicOptions {
+            minor: false,
+            ..TicOptions::default()
+        };
+        let tics = Tic::create_tics_with(&Range::new(0., 100.), &Range::new(0., 1.), &options);
+        assert_eq!(vec![(0., "0.0"), (50., "0.5")], majors(&tics));
+        assert_eq!(2, tics.len());
+        let options = TicOptions {
+            precision: Some(2),
+            ..options
+        };
+        let tics = Tic::create_tics_with(&Range::new(0., 100.), &Range::new(0., 1.), &options);
+        assert_eq!("0.50", tics[1].text);
+        assert_eq!("0.3", Tic::new(0., 0.1 + 0.2).text);
+        assert_eq!("-20", Tic::new(0., -20.).text);
     }
 }
@@ -0,0 +1,177 @@
+//! `map(f, xs)`, `filter(p, xs)` and `reduce(f, init, xs)`, which take a function by its name.
+//!
+//! Like `sample`, they are evaluated by the calculator itself, because the function
+//! argument is a name and no value.
+
+use crate::ast::{Data, DataFunction, FunCall, Number, Operand};
+use crate::calc::{
+    calc_function_call, calc_function_many, calc_list, calc_operand, CalcError, Env,
+};
+
+pub const MAP: &str = "map";
+pub const FILTER: &str = "filter";
+pub const REDUCE: &str = "reduce";
+
+fn lazy_fn(_: &[Data]) -> Result<Data, String> {
+    Err("higher-order functions must be evaluated lazily".to_string())
+}
+
+pub(crate) fn functions() -> Vec<DataFunction> {
+    let function = |name: &str, args: &[&str], returns_list| DataFunction {
+        name: name.to_string(),
+        args: args.iter().map(|arg| arg.to_string()).collect(),
+        returns_list,
+        body: &lazy_fn,
+    };
+    vec![
+        function(MAP, &["f", "xs"], true),
+        function(FILTER, &["p", "xs"], true),
+        function(REDUCE, &["f", "init", "xs"], false),
+    ]
+}
+
+/// is `name` one of the higher-order functions
+pub fn is_higher_order(name: &str) -> bool {
+    matches!(name, MAP | FILTER | REDUCE)
+}
+
+/// the name of the function in `op`, which must take `count` parameters
+fn function_name<'a>(
+    fun_call: &FunCall,
+    op: &'a Operand,
+    count: usize,
+    env: &dyn Env,
+) -> Result<&'a str, CalcError> {
+    match op {
+        Operand::Symbol(name) => match env.get_fun(name) {
+            Some(fun) if fun.accepts(count) => Ok(name),
+            Some(fun) => Err(CalcError::InvalidArguments {
+                name: fun_call.name.clone(),
+                reason: format!(
+                    "expected a function of {} parameters, but `{}` takes {}",
+                    count,
+                    name,
+                    fun.arity()
+                ),
+            }),
+            None => Err(CalcError::unknown_function(name, env)),
+        },
+        op => Err(CalcError::InvalidArguments {
+            name: fun_call.name.clone(),
+            reason: format!("expected a function name, but got `{}`", op),
+        }),
+    }
+}
+
+fn list(op: &Operand, env: &dyn Env) -> Result<Vec<Number>, CalcError> {
+    match calc_list(op, env) {
+        Some(list) => list,
+        None => Ok(vec![calc_operand(op, env)?]),
+    }
+}
+
+fn call(name: &str, params: &[Number], env: &dyn Env) -> Result<Number, CalcError> {
+    let fun_call = FunCall {
+        name: name.to_string(),
+        params: params.iter().map(|param| Operand::Number(*param)).collect(),
+    };
+    calc_function_call(&fun_call, env)
+}
+
+/// Evaluates a call of `map`, `filter` or `reduce`.
+pub fn calc_higher_order(fun_call: &FunCall, env: &dyn Env) -> Result<Data, CalcError> {
+    let exp = if fun_call.name == REDUCE { 3 } else { 2 };
+    if fun_call.params.len() != exp {
+        return Err(CalcError::UnexpectedNumberOfParameters {
+            name: fun_call.name.clone(),
+            act: fun_call.params.len(),
+            exp,
+        });
+    }
+    // the reducer takes the accumulator and an element
+    let name = function_name(fun_call, &fun_call.params[0], exp - 1, env)?;
+    let xs = list(&fun_call.params[exp - 1], env)?;
+    match fun_call.name.as_str() {
+        MAP => calc_function_many(name, &xs, env)
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .map(Data::List),
+        FILTER => {
+            let keep = calc_function_many(name, &xs, env);
+            xs.into_iter()
+                .zip(keep)
+                .filter_map(|(x, keep)| match keep {
+                    Ok(keep) => (keep != 0.0).then_some(Ok(x)),
+                    Err(err) => Some(Err(err)),
+                })
+                .collect::<Result<_, _>>()
+                .map(Data::List)
+        }
+        _ => {
+            let init = calc_operand(&fun_call.params[1], env)?;
+            xs.into_iter()
+                .try_fold(init, |acc, x| call(name, &[acc, x], env))
+                .map(Data::Number)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calc::{CalcError, TopLevelEnv};
+    use crate::parser::parse_expression;
+    use crate::{Calculator, Value};
+
+    #[test]
+    fn map_filter_reduce() {
+        let mut calc = Calculator::new();
+        calc.load_csv_str("1\n2\n3\n4", 1, "xs").unwrap();
+        calc.execute("sq(x) := x ^ 2").unwrap();
+        calc.execute("odd(x) := x % 2").unwrap();
+        calc.execute("add(a, b) := a + b").unwrap();
+        assert_eq!(
+            Ok(Value::List(vec![1.0, 4.0, 9.0, 16.0])),
+            calc.execute("map(sq, xs)")
+        );
+        assert_eq!(
+            Ok(Value::List(vec![1.0, 3.0])),
+            calc.execute("filter(odd, xs)")
+        );
+        assert_eq!(Ok(Value::Number(10.0)), calc.execute("reduce(add, 0, xs)"));
+        assert_eq!(Ok(Value::Number(4.0)), calc.execute("reduce(max, 0, xs)"));
+        assert_eq!(
+            Ok(Value::Number(35.0)),
+            calc.execute("reduce(add, 0, map(sq, filter(odd, xs))) + 25")
+        );
+    }
+
+    #[test]
+    fn invalid_function_arguments() {
+        let env = TopLevelEnv::default();
+        let calc = |s: &str| {
+            crate::calc::calc_operand::<crate::ast::Number>(&parse_expression(s).unwrap(), &env)
+        };
+        assert_eq!(
+            Err(CalcError::UnknownFunction {
+                name: "g".to_string(),
+                candidates: vec![],
+            }),
+            calc("reduce(g, 0, 1)")
+        );
+        assert!(matches!(
+            calc("reduce(1 + 2, 0, 1)"),
+            Err(CalcError::InvalidArguments { .. })
+        ));
+        assert_eq!(
+            Err(CalcError::InvalidArguments {
+                name: "reduce".to_string(),
+                reason: "expected a function of 2 parameters, but `sin` takes 1".to_string(),
+            }),
+            calc("reduce(sin, 0, 1)")
+        );
+        assert!(matches!(
+            calc("reduce(sin, 0)"),
+            Err(CalcError::UnexpectedNumberOfParameters { exp: 3, act: 2, .. })
+        ));
+    }
+}
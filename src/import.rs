@@ -0,0 +1,123 @@
+//! Import of numeric data from CSV files into list variables.
+
+use crate::ast::Number;
+
+use thiserror::Error;
+
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum ImportError {
+    #[error("Cannot read `{path}`: {message}")]
+    Io { path: String, message: String },
+    #[error("Columns are counted from 1, but got column 0")]
+    InvalidColumn,
+    #[error("Line {line} has no column {column}")]
+    MissingColumn { line: usize, column: usize },
+    #[error("Expected a number in line {line}, but got `{value}`")]
+    InvalidNumber { line: usize, value: String },
+    #[error("No data found")]
+    NoData,
+}
+
+fn separator(line: &str) -> char {
+    [',', ';', '\t']
+        .into_iter()
+        .find(|sep| line.contains(*sep))
+        .unwrap_or(',')
+}
+
+/// Reads the numbers of `column` (counted from 1) of CSV `content`.
+/// Fields may be separated by `,`, `;` or tabs. A non-numeric first line is skipped as header.
+pub fn read_csv_column(content: &str, column: usize) -> Result<Vec<Number>, ImportError> {
+    if column == 0 {
+        return Err(ImportError::InvalidColumn);
+    }
+    let mut lines = content
+        .lines()
+        .enumerate()
+        .map(|(idx, line)| (idx + 1, line))
+        .filter(|(_, line)| !line.trim().is_empty())
+        .peekable();
+    let sep = match lines.peek() {
+        Some((_, line)) => separator(line),
+        None => return Err(ImportError::NoData),
+    };
+
+    let mut values = Vec::new();
+    for (idx, (line, text)) in lines.enumerate() {
+        let value = text
+            .split(sep)
+            .nth(column - 1)
+            .ok_or(ImportError::MissingColumn { line, column })?
+            .trim()
+            .trim_matches('"')
+            .trim();
+        match value.parse::<Number>() {
+            Ok(num) => values.push(num),
+            Err(_) if idx == 0 => (),
+            Err(_) => {
+                return Err(ImportError::InvalidNumber {
+                    line,
+                    value: value.to_string(),
+                })
+            }
+        }
+    }
+
+    if values.is_empty() {
+        Err(ImportError::NoData)
+    } else {
+        Ok(values)
+    }
+}
+
+/// Reads the numbers of `column` (counted from 1) of the CSV file `path`.
+pub fn read_csv_file(path: &str, column: usize) -> Result<Vec<Number>, ImportError> {
+    let content = std::fs::read_to_string(path).map_err(|err| ImportError::Io {
+        path: path.to_string(),
+        message: err.to_string(),
+    })?;
+    read_csv_column(&content, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_with_header() {
+        let csv = "t, h\n0, 1.5\n1, 2.5\n\n2, \"3.5\"\n";
+        assert_eq!(Ok(vec![0.0, 1.0, 2.0]), read_csv_column(csv, 1));
+        assert_eq!(Ok(vec![1.5, 2.5, 3.5]), read_csv_column(csv, 2));
+    }
+
+    #[test]
+    fn csv_semicolon_without_header() {
+        assert_eq!(Ok(vec![2.0, 4.0]), read_csv_column("1;2\n3;4", 2));
+    }
+
+    #[test]
+    fn csv_errors() {
+        assert_eq!(Err(ImportError::InvalidColumn), read_csv_column("1", 0));
+        assert_eq!(Err(ImportError::NoData), read_csv_column("\n\n", 1));
+        assert_eq!(Err(ImportError::NoData), read_csv_column("a,b", 1));
+        assert_eq!(
+            Err(ImportError::MissingColumn { line: 2, column: 2 }),
+            read_csv_column("1,2\n3", 2)
+        );
+        assert_eq!(
+            Err(ImportError::InvalidNumber {
+                line: 3,
+                value: "x".to_string()
+            }),
+            read_csv_column("a\n1\nx", 1)
+        );
+    }
+
+    #[test]
+    fn csv_file_missing() {
+        assert!(matches!(
+            read_csv_file("does/not/exist.csv", 1),
+            Err(ImportError::Io { path, .. }) if path == "does/not/exist.csv"
+        ));
+    }
+}
@@ -0,0 +1,187 @@
+//! Numeric definite integrals by adaptive Simpson quadrature,
+//! e.g. `integrate f from 0 to 2` or `integrate x^2 for x in [0, 2]`.
+
+use crate::ast::{FunCall, Number, Operand};
+use crate::calc::{calc_function_call, calc_operand, CalcError, Env, ScopedEnv};
+
+use thiserror::Error;
+
+/// default bound of the absolute error of an integral
+pub const DEFAULT_INTEGRATION_TOLERANCE: Number = 1e-8;
+/// bisections of the interval before giving up
+const MAX_DEPTH: u32 = 50;
+const MAX_EVALUATIONS: usize = 1 << 20;
+
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum IntegrationError {
+    #[error("Expected finite bounds, but got `{from}` and `{to}`")]
+    InvalidBounds { from: String, to: String },
+    #[error("The integrand is not finite at {at}")]
+    NotFinite { at: String },
+    #[error("The integral does not converge to the tolerance {tolerance}")]
+    NotConverged { tolerance: String },
+    #[error(transparent)]
+    CalcError(#[from] CalcError),
+}
+
+/// Simpson's rule on `[a, b]` evaluates the integrand at the midpoint `m` only once.
+struct Panel {
+    a: (Number, Number),
+    m: (Number, Number),
+    b: (Number, Number),
+    area: Number,
+}
+
+struct Quadrature<F> {
+    integrand: F,
+    evaluations: usize,
+    tolerance: Number,
+}
+
+impl<F: FnMut(Number) -> Result<Number, CalcError>> Quadrature<F> {
+    fn eval(&mut self, x: Number) -> Result<(Number, Number), IntegrationError> {
+        self.evaluations += 1;
+        if self.evaluations > MAX_EVALUATIONS {
+            return Err(self.not_converged());
+        }
+        let y = (self.integrand)(x)?;
+        if !y.is_finite() {
+            return Err(IntegrationError::NotFinite { at: x.to_string() });
+        }
+        Ok((x, y))
+    }
+
+    fn not_converged(&self) -> IntegrationError {
+        IntegrationError::NotConverged {
+            tolerance: self.tolerance.to_string(),
+        }
+    }
+
+    fn panel(
+        &mut self,
+        a: (Number, Number),
+        b: (Number, Number),
+    ) -> Result<Panel, IntegrationError> {
+        let m = self.eval((a.0 + b.0) / 2.0)?;
+        let area = (b.0 - a.0) / 6.0 * (a.1 + 4.0 * m.1 + b.1);
+        Ok(Panel { a, m, b, area })
+    }
+
+    /// Splits the panel until both halves agree within `tolerance`, the difference of
+    /// both estimates is 15 times the error of the finer one.
+    fn adapt(
+        &mut self,
+        panel: Panel,
+        tolerance: Number,
+        depth: u32,
+    ) -> Result<Number, IntegrationError> {
+        let left = self.panel(panel.a, panel.m)?;
+        let right = self.panel(panel.m, panel.b)?;
+        let area = left.area + right.area;
+        let delta = area - panel.area;
+        // the interval cannot be split any further
+        let tiny = left.m.0 == panel.a.0 || right.m.0 == panel.b.0;
+        if delta.abs() <= 15.0 * tolerance.max(Number::EPSILON * area.abs()) || tiny {
+            return Ok(area + delta / 15.0);
+        }
+        if depth == 0 {
+            return Err(self.not_converged());
+        }
+        Ok(self.adapt(left, tolerance / 2.0, depth - 1)?
+            + self.adapt(right, tolerance / 2.0, depth - 1)?)
+    }
+}
+
+/// Integral of `integrand` from `from` to `to`, negative if `from` is greater than `to`.
+fn integrate(
+    integrand: impl FnMut(Number) -> Result<Number, CalcError>,
+    (from, to): (Number, Number),
+    tolerance: Number,
+) -> Result<Number, IntegrationError> {
+    if !from.is_finite() || !to.is_finite() {
+        return Err(IntegrationError::InvalidBounds {
+            from: from.to_string(),
+            to: to.to_string(),
+        });
+    }
+    let mut quadrature = Quadrature {
+        integrand,
+        evaluations: 0,
+        tolerance,
+    };
+    let (a, b) = (quadrature.eval(from)?, quadrature.eval(to)?);
+    let panel = quadrature.panel(a, b)?;
+    quadrature.adapt(panel, tolerance, MAX_DEPTH)
+}
+
+/// integral of the function `name` of one argument, e.g. `integrate f from 0 to 2`
+pub fn integrate_function(
+    name: &str,
+    bounds: (Number, Number),
+    tolerance: Number,
+    env: &dyn Env,
+) -> Result<Number, IntegrationError> {
+    let integrand = |x| {
+        let fun_call = FunCall {
+            name: name.to_string(),
+            params: vec![Operand::Number(x)],
+        };
+        calc_function_call(&fun_call, env)
+    };
+    integrate(integrand, bounds, tolerance)
+}
+
+/// integral of `op` over `sym`, e.g. `integrate x^2 for x in [0, 2]`
+pub fn integrate_expression(
+    op: &Operand,
+    sym: &str,
+    bounds: (Number, Number),
+    tolerance: Number,
+    env: &dyn Env,
+) -> Result<Number, IntegrationError> {
+    let integrand = |x: Number| calc_operand(op, &ScopedEnv::new(env, [(sym, x)]));
+    integrate(integrand, bounds, tolerance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::TopLevelEnv;
+    use crate::parser::parse_expression;
+
+    fn integral(expr: &str, bounds: (Number, Number)) -> Result<Number, IntegrationError> {
+        let op = parse_expression(expr).unwrap();
+        let env = TopLevelEnv::default();
+        integrate_expression(&op, "x", bounds, DEFAULT_INTEGRATION_TOLERANCE, &env)
+    }
+
+    #[test]
+    fn smooth_integrands() {
+        assert_eq!(Ok(8.0 / 3.0), integral("x ^ 2", (0.0, 2.0)));
+        assert_eq!(Ok(-8.0 / 3.0), integral("x ^ 2", (2.0, 0.0)));
+        assert_eq!(Ok(0.0), integral("x", (1.0, 1.0)));
+        let sin = integral("sin(x)", (0.0, std::f64::consts::PI as Number)).unwrap();
+        assert!((sin - 2.0).abs() < 1e-6);
+        let gauss = integral("exp(0 - x ^ 2)", (-6.0, 6.0)).unwrap();
+        assert!((gauss - (std::f64::consts::PI as Number).sqrt()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn invalid_integrals() {
+        assert!(matches!(
+            integral("x", (0.0, Number::INFINITY)),
+            Err(IntegrationError::InvalidBounds { .. })
+        ));
+        assert_eq!(
+            Err(IntegrationError::CalcError(CalcError::DivisionByZero)),
+            integral("1 / x", (0.0, 1.0))
+        );
+        assert_eq!(
+            Err(IntegrationError::CalcError(CalcError::UnknownSymbol {
+                name: "y".to_string(),
+                candidates: vec![],
+            })),
+            integral("x * y", (0.0, 1.0))
+        );
+    }
+}
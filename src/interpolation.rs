@@ -0,0 +1,161 @@
+//! Interpolation in tables of data points, e.g. `interp(xs, ys, 2.5)`.
+//!
+//! The x values must be strictly increasing. Outside of the table the first or last
+//! y value is returned.
+
+use crate::ast::{Data, DataFunction, Number};
+
+/// checks the parameters `(xs, ys, x)`
+fn table(params: &[Data]) -> Result<(&[Number], &[Number], Number), String> {
+    let xs = params[0].list().ok_or("xs must be a list")?;
+    let ys = params[1].list().ok_or("ys must be a list")?;
+    let x = params[2].number().ok_or("x must be a number")?;
+    if xs.len() != ys.len() {
+        return Err(format!(
+            "xs and ys must have the same length, but have {} and {} elements",
+            xs.len(),
+            ys.len()
+        ));
+    }
+    if xs.len() < 2 {
+        return Err("at least two data points are needed".to_string());
+    }
+    if xs.windows(2).any(|w| w[0] >= w[1]) {
+        return Err("xs must be strictly increasing".to_string());
+    }
+    Ok((xs, ys, x))
+}
+
+/// index `i` of the interval `xs[i]..=xs[i + 1]` containing `x`
+fn interval(xs: &[Number], x: Number) -> usize {
+    xs.partition_point(|xi| *xi <= x).clamp(1, xs.len() - 1) - 1
+}
+
+fn clamped(xs: &[Number], ys: &[Number], x: Number, inner: impl Fn(usize) -> Number) -> Number {
+    if x <= xs[0] {
+        ys[0]
+    } else if x >= xs[xs.len() - 1] {
+        ys[ys.len() - 1]
+    } else {
+        inner(interval(xs, x))
+    }
+}
+
+pub fn linear(xs: &[Number], ys: &[Number], x: Number) -> Number {
+    clamped(xs, ys, x, |i| {
+        ys[i] + (ys[i + 1] - ys[i]) * (x - xs[i]) / (xs[i + 1] - xs[i])
+    })
+}
+
+/// second derivatives of the natural cubic spline through the points
+fn spline_moments(xs: &[Number], ys: &[Number]) -> Vec<Number> {
+    let n = xs.len();
+    let h: Vec<Number> = xs.windows(2).map(|w| w[1] - w[0]).collect();
+    let mut moments = vec![0.0; n];
+    if n < 3 {
+        return moments;
+    }
+
+    // tridiagonal system for the inner moments, solved with the Thomas algorithm
+    let mut diag = vec![0.0; n];
+    let mut rhs = vec![0.0; n];
+    for i in 1..n - 1 {
+        diag[i] = 2.0 * (h[i - 1] + h[i]);
+        rhs[i] = 6.0 * ((ys[i + 1] - ys[i]) / h[i] - (ys[i] - ys[i - 1]) / h[i - 1]);
+        if i > 1 {
+            let factor = h[i - 1] / diag[i - 1];
+            diag[i] -= factor * h[i - 1];
+            rhs[i] -= factor * rhs[i - 1];
+        }
+    }
+    for i in (1..n - 1).rev() {
+        moments[i] = (rhs[i] - h[i] * moments[i + 1]) / diag[i];
+    }
+    moments
+}
+
+pub fn spline(xs: &[Number], ys: &[Number], x: Number) -> Number {
+    let m = spline_moments(xs, ys);
+    clamped(xs, ys, x, |i| {
+        let h = xs[i + 1] - xs[i];
+        let (a, b) = (xs[i + 1] - x, x - xs[i]);
+        m[i] * a.powi(3) / (6.0 * h)
+            + m[i + 1] * b.powi(3) / (6.0 * h)
+            + (ys[i] / h - m[i] * h / 6.0) * a
+            + (ys[i + 1] / h - m[i + 1] * h / 6.0) * b
+    })
+}
+
+fn interp_fn(params: &[Data]) -> Result<Data, String> {
+    let (xs, ys, x) = table(params)?;
+    Ok(Data::Number(linear(xs, ys, x)))
+}
+
+fn spline_fn(params: &[Data]) -> Result<Data, String> {
+    let (xs, ys, x) = table(params)?;
+    Ok(Data::Number(spline(xs, ys, x)))
+}
+
+pub(crate) fn functions() -> Vec<DataFunction> {
+    let args = || vec!["xs".to_string(), "ys".to_string(), "x".to_string()];
+    vec![
+        DataFunction {
+            name: "interp".to_string(),
+            args: args(),
+            returns_list: false,
+            body: &interp_fn,
+        },
+        DataFunction {
+            name: "spline".to_string(),
+            args: args(),
+            returns_list: false,
+            body: &spline_fn,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    const XS: [Number; 4] = [0.0, 1.0, 2.0, 4.0];
+    const YS: [Number; 4] = [0.0, 2.0, 3.0, 1.0];
+
+    #[test]
+    fn interp_linear() {
+        assert_eq!(1.0, linear(&XS, &YS, 0.5));
+        assert_eq!(2.0, linear(&XS, &YS, 3.0));
+        assert_eq!(3.0, linear(&XS, &YS, 2.0));
+        assert_eq!(0.0, linear(&XS, &YS, -1.0));
+        assert_eq!(1.0, linear(&XS, &YS, 5.0));
+    }
+
+    #[test]
+    fn interp_spline_through_points() {
+        for (x, y) in XS.iter().zip(YS.iter()) {
+            assert_approx_eq!(*y, spline(&XS, &YS, *x));
+        }
+        // a spline through points on a line is the line
+        assert_approx_eq!(2.5, spline(&[0.0, 1.0, 3.0], &[0.0, 1.0, 3.0], 2.5));
+    }
+
+    #[test]
+    fn interp_spline_smooth() {
+        let y = spline(&XS, &YS, 1.5);
+        assert!(y > linear(&XS, &YS, 1.5));
+    }
+
+    #[test]
+    fn interp_invalid_table() {
+        let list = |l: &[Number]| Data::List(l.to_vec());
+        assert_eq!(
+            Err("xs must be strictly increasing".to_string()),
+            interp_fn(&[list(&[1.0, 1.0]), list(&[1.0, 2.0]), Data::Number(1.0)])
+        );
+        assert_eq!(
+            Err("x must be a number".to_string()),
+            interp_fn(&[list(&XS), list(&YS), list(&XS)])
+        );
+    }
+}
@@ -0,0 +1,530 @@
+#![allow(clippy::upper_case_acronyms)]
+
+//! Secondary input syntax accepting a subset of LaTeX math and Unicode math symbols.
+//!
+//! Supported are numbers, single letter variables (with optional subscript like `x_1`),
+//! greek letters (`\pi`, `π`), `+`, `-`, `−`, `*`, `\cdot`, `\times`, `×`, `/`, `\div`, `÷`,
+//! powers `^` and superscripts like `x²`, `\frac{..}{..}`, `\sqrt{..}`, `\sqrt[n]{..}`,
+//! `√`, `∛`, `∜`, `|..|`, parenthesis (also `\left( .. \right)`), the usual functions
+//! (`\sin`, `\ln`, ...), calls of known functions like `f(2)`, implicit multiplication
+//! like `2x` and definitions like `f(x) := \frac{1}{x}`.
+
+use crate::ast::*;
+use crate::calc::Env;
+use crate::parser::{syntax_error, ParserError};
+
+use pest::{
+    iterators::{Pair, Pairs},
+    Parser,
+};
+use pest_derive::Parser;
+
+#[derive(Parser)]
+#[grammar = "latex.pest"]
+struct LatexParser;
+
+const SUPERSCRIPTS: &str = "⁰¹²³⁴⁵⁶⁷⁸⁹⁺⁻";
+const DIGITS: &str = "0123456789+-";
+
+/// the known functions, which are called by `name(..)` instead of multiplied
+struct Context<'a> {
+    env: &'a dyn Env,
+    /// the function being defined, so it may call itself
+    defining: Option<&'a str>,
+}
+
+impl Context<'_> {
+    fn is_function(&self, name: &str) -> bool {
+        self.defining == Some(name) || self.env.get_fun(name).is_some()
+    }
+}
+
+fn new_operand_term(lhs: Operand, op: Operation, rhs: Operand) -> Operand {
+    Operand::Term(Box::new(Term { op, lhs, rhs }))
+}
+
+fn parse_num(pair: Pair<Rule>) -> Result<Operand, ParserError> {
+    match pair.as_str().parse::<Number>() {
+        Ok(num) => Ok(Operand::Number(num)),
+        Err(_) => Err(ParserError::InvalidNumber(pair.as_str().to_string())),
+    }
+}
+
+/// the number of superscript digits with an optional sign like `⁻³`
+fn parse_superscript(pair: Pair<Rule>) -> Result<Operand, ParserError> {
+    let digits: String = pair
+        .as_str()
+        .chars()
+        .map(|c| {
+            SUPERSCRIPTS
+                .chars()
+                .zip(DIGITS.chars())
+                .find_map(|(sup, digit)| (sup == c).then_some(digit))
+                .unwrap_or(c)
+        })
+        .collect();
+    match digits.parse::<Number>() {
+        Ok(num) => Ok(Operand::Number(num)),
+        Err(_) => Err(ParserError::InvalidNumber(pair.as_str().to_string())),
+    }
+}
+
+fn parse_letter(pair: Pair<Rule>) -> Operand {
+    let name: String = pair
+        .as_str()
+        .chars()
+        .filter(|c| !matches!(c, '{' | '}'))
+        .collect();
+    Operand::Symbol(name)
+}
+
+fn parse_fun_name(pair: Pair<Rule>) -> String {
+    match pair.as_str().trim_start_matches('\\') {
+        "arcsin" => "asin",
+        "arccos" => "acos",
+        "arctan" => "atan",
+        "log" => "log10",
+        name => name,
+    }
+    .to_string()
+}
+
+fn next_operand(it: &mut Pairs<Rule>, context: &str, cx: &Context) -> Result<Operand, ParserError> {
+    parse_atom(
+        it.next()
+            .ok_or_else(|| ParserError::InvalidOperand(context.to_string()))?,
+        cx,
+    )
+}
+
+fn parse_atom(pair: Pair<Rule>, cx: &Context) -> Result<Operand, ParserError> {
+    let text = pair.as_str().to_string();
+    match pair.as_rule() {
+        Rule::num | Rule::digit => parse_num(pair),
+        Rule::letter => Ok(parse_letter(pair)),
+        Rule::greek => Ok(Operand::Symbol(
+            match text.as_str() {
+                "π" => "pi",
+                "τ" => "tau",
+                text => text.trim_start_matches('\\').trim_start_matches("var"),
+            }
+            .to_string(),
+        )),
+        Rule::expr => parse_expr(pair.into_inner(), cx),
+        Rule::power => parse_power(pair.into_inner(), cx),
+        Rule::frac => {
+            let mut it = pair.into_inner();
+            let nominator = next_operand(&mut it, &text, cx)?;
+            let denominator = next_operand(&mut it, &text, cx)?;
+            Ok(new_operand_term(nominator, Operation::Div, denominator))
+        }
+        Rule::sqrt => {
+            let mut it = pair.into_inner().peekable();
+            let index = match it.peek().map(|p| p.as_rule()) {
+                Some(Rule::root_index) => {
+                    let index = it.next().unwrap().into_inner();
+                    Some(parse_expr(index.peek().unwrap().into_inner(), cx)?)
+                }
+                _ => None,
+            };
+            let radicand = parse_atom(
+                it.next()
+                    .ok_or_else(|| ParserError::InvalidOperand(text.clone()))?,
+                cx,
+            )?;
+            Ok(match index {
+                None => Operand::call("sqrt", vec![radicand]),
+                Some(index) => new_operand_term(
+                    radicand,
+                    Operation::Pow,
+                    new_operand_term(Operand::Number(1.0), Operation::Div, index),
+                ),
+            })
+        }
+        Rule::radical => {
+            let mut it = pair.into_inner();
+            let sign = it.next().ok_or(ParserError::MissingFunctionName)?;
+            let radicand = next_operand(&mut it, &text, cx)?;
+            Ok(match sign.as_str() {
+                "∛" => Operand::call("root", vec![Operand::Number(3.0), radicand]),
+                "∜" => Operand::call("root", vec![Operand::Number(4.0), radicand]),
+                _ => Operand::call("sqrt", vec![radicand]),
+            })
+        }
+        Rule::abs => {
+            let mut it = pair.into_inner();
+            Ok(Operand::call(
+                "abs",
+                vec![next_operand(&mut it, &text, cx)?],
+            ))
+        }
+        Rule::fun_call => {
+            let mut it = pair.into_inner();
+            let name = parse_fun_name(it.next().ok_or(ParserError::MissingFunctionName)?);
+            let param = next_operand(&mut it, &text, cx)?;
+            Ok(Operand::FunCall(FunCall {
+                name,
+                params: vec![param],
+            }))
+        }
+        Rule::call => {
+            let mut it = pair.into_inner();
+            let name = it.next().ok_or(ParserError::MissingFunctionName)?.as_str();
+            let mut params = it
+                .map(|param| parse_expr(param.into_inner(), cx))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(match params.len() {
+                // implicit multiplication like `x(x + 1)`
+                1 if !cx.is_function(name) => {
+                    new_operand_term(Operand::symbol(name), Operation::Mul, params.remove(0))
+                }
+                _ => Operand::call(name, params),
+            })
+        }
+        _ => Err(ParserError::InvalidOperand(text)),
+    }
+}
+
+fn parse_power(power: Pairs<Rule>, cx: &Context) -> Result<Operand, ParserError> {
+    let text = power.as_str().to_string();
+    let mut it = power;
+    let base = next_operand(&mut it, &text, cx)?;
+    match it.next() {
+        Some(exponent) => {
+            let exponent = match exponent.as_rule() {
+                Rule::superscript => parse_superscript(exponent)?,
+                _ => parse_atom(exponent, cx)?,
+            };
+            Ok(new_operand_term(base, Operation::Pow, exponent))
+        }
+        None => Ok(base),
+    }
+}
+
+fn parse_product(product: Pairs<Rule>, cx: &Context) -> Result<Operand, ParserError> {
+    let text = product.as_str().to_string();
+    let mut it = product;
+    let mut lhs = next_operand(&mut it, &text, cx)?;
+    while let Some(pair) = it.next() {
+        let (op, rhs) = match pair.as_rule() {
+            Rule::multiply => (Operation::Mul, next_operand(&mut it, &text, cx)?),
+            Rule::divide => (Operation::Div, next_operand(&mut it, &text, cx)?),
+            // implicit multiplication like `2x`
+            _ => (Operation::Mul, parse_atom(pair, cx)?),
+        };
+        lhs = new_operand_term(lhs, op, rhs);
+    }
+    Ok(lhs)
+}
+
+fn parse_expr(expr: Pairs<Rule>, cx: &Context) -> Result<Operand, ParserError> {
+    let mut it = expr.peekable();
+    let negate = matches!(it.peek().map(|p| p.as_rule()), Some(Rule::subtract));
+    if negate {
+        it.next();
+    }
+    let first = it
+        .next()
+        .ok_or_else(|| ParserError::InvalidExpression("expected expression".to_string()))?;
+    let mut lhs = match parse_product(first.into_inner(), cx)? {
+        Operand::Number(num) if negate => Operand::Number(-num),
+        op if negate => new_operand_term(Operand::Number(-1.0), Operation::Mul, op),
+        op => op,
+    };
+    while let Some(op) = it.next() {
+        let op = match op.as_rule() {
+            Rule::add => Operation::Add,
+            Rule::subtract => Operation::Sub,
+            _ => return Err(ParserError::InvalidOperation(op.as_str().to_string())),
+        };
+        let rhs = parse_product(
+            it.next()
+                .ok_or_else(|| ParserError::InvalidOperand("expected operand".to_string()))?
+                .into_inner(),
+            cx,
+        )?;
+        lhs = new_operand_term(lhs, op, rhs);
+    }
+    Ok(lhs)
+}
+
+fn parse_statement(statement: Pair<Rule>, env: &dyn Env) -> Result<Statement, ParserError> {
+    let rule = statement.as_rule();
+    let mut it = statement.into_inner();
+    match rule {
+        Rule::function => {
+            let name = it.next().ok_or(ParserError::MissingFunctionName)?.as_str();
+            let cx = Context {
+                env,
+                defining: Some(name),
+            };
+            let mut args = Vec::new();
+            for p in it {
+                match p.as_rule() {
+                    Rule::name => args.push(p.as_str().to_string()),
+                    _ => {
+                        let body = parse_expr(p.into_inner(), &cx)?;
+                        return Ok(Statement::Function {
+                            name: name.to_string(),
+                            fun: Function::Custom(CustomFunction { args, body }),
+                        });
+                    }
+                }
+            }
+            Err(ParserError::MissingFunctionBody)
+        }
+        Rule::assignment => {
+            let sym = it.next().ok_or(ParserError::EmptyStatement)?.as_str();
+            let op = next_operand(
+                &mut it,
+                sym,
+                &Context {
+                    env,
+                    defining: None,
+                },
+            )?;
+            Ok(Statement::Assignment {
+                sym: sym.to_string(),
+                op,
+            })
+        }
+        _ => Ok(Statement::Expression {
+            op: parse_expr(
+                it,
+                &Context {
+                    env,
+                    defining: None,
+                },
+            )?,
+        }),
+    }
+}
+
+/// parses a LaTeX math expression or definition, `env` tells the known functions
+pub fn parse_latex(cmd: &str, env: &dyn Env) -> Result<Statement, ParserError> {
+    match LatexParser::parse(Rule::statement, cmd) {
+        Ok(mut rules) => {
+            let statement = rules.next().ok_or(ParserError::EmptyStatement)?;
+            parse_statement(statement, env)
+        }
+        Err(e) => Err(syntax_error(cmd, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::TopLevelEnv;
+
+    fn term(lhs: Operand, op: Operation, rhs: Operand) -> Operand {
+        new_operand_term(lhs, op, rhs)
+    }
+
+    fn num(num: Number) -> Operand {
+        Operand::Number(num)
+    }
+
+    fn sym(sym: &str) -> Operand {
+        Operand::Symbol(sym.to_string())
+    }
+
+    fn expr(s: &str) -> Operand {
+        match parse_latex(s, &TopLevelEnv::default()).unwrap() {
+            Statement::Expression { op } => op,
+            st => panic!("unexpected statement {:?}", st),
+        }
+    }
+
+    #[test]
+    fn latex_frac() {
+        assert_eq!(
+            term(num(1.0), Operation::Div, num(2.0)),
+            expr("\\frac{1}{2}")
+        );
+    }
+
+    #[test]
+    fn latex_frac_plus_sqrt() {
+        let sqrt = Operand::FunCall(FunCall {
+            name: "sqrt".to_string(),
+            params: vec![sym("x")],
+        });
+        assert_eq!(
+            term(
+                term(num(1.0), Operation::Div, num(2.0)),
+                Operation::Add,
+                sqrt
+            ),
+            expr("\\frac{1}{2} + \\sqrt{x}")
+        );
+    }
+
+    #[test]
+    fn latex_nth_root() {
+        assert_eq!(
+            term(
+                num(8.0),
+                Operation::Pow,
+                term(num(1.0), Operation::Div, num(3.0))
+            ),
+            expr("\\sqrt[3]{8}")
+        );
+    }
+
+    #[test]
+    fn latex_implicit_multiplication_and_power() {
+        assert_eq!(
+            term(
+                num(2.0),
+                Operation::Mul,
+                term(sym("x"), Operation::Pow, num(2.0))
+            ),
+            expr("2x^2")
+        );
+    }
+
+    #[test]
+    fn latex_power_group() {
+        assert_eq!(
+            term(
+                sym("e"),
+                Operation::Pow,
+                term(sym("x"), Operation::Add, num(1.0))
+            ),
+            expr("e^{x + 1}")
+        );
+    }
+
+    #[test]
+    fn latex_cdot_and_greek() {
+        assert_eq!(
+            term(num(2.0), Operation::Mul, sym("pi")),
+            expr("2 \\cdot \\pi")
+        );
+    }
+
+    #[test]
+    fn latex_function_and_paren() {
+        let sin = Operand::FunCall(FunCall {
+            name: "sin".to_string(),
+            params: vec![term(sym("x"), Operation::Add, num(1.0))],
+        });
+        assert_eq!(sin, expr("\\sin\\left(x + 1\\right)"));
+    }
+
+    #[test]
+    fn latex_negation() {
+        assert_eq!(
+            term(
+                term(num(-1.0), Operation::Mul, sym("x")),
+                Operation::Add,
+                num(3.0)
+            ),
+            expr("-x + 3")
+        );
+    }
+
+    #[test]
+    fn latex_subscript() {
+        assert_eq!(
+            term(sym("x_1"), Operation::Sub, sym("x_12")),
+            expr("x_1 - x_{12}")
+        );
+    }
+
+    #[test]
+    fn latex_single_character_arguments() {
+        assert_eq!(term(num(1.0), Operation::Div, sym("x")), expr("\\frac1x"));
+        assert_eq!(Operand::call("sqrt", vec![num(2.0)]), expr("\\sqrt2"));
+    }
+
+    #[test]
+    fn unicode_symbols() {
+        assert_eq!(
+            term(
+                term(num(2.0), Operation::Mul, sym("pi")),
+                Operation::Div,
+                term(num(-1.0), Operation::Mul, sym("tau"))
+            ),
+            expr("2 × π ÷ (−τ)")
+        );
+        assert_eq!(
+            term(
+                term(sym("x"), Operation::Pow, num(2.0)),
+                Operation::Add,
+                term(num(10.0), Operation::Pow, num(-3.0))
+            ),
+            expr("x² + 10⁻³")
+        );
+        assert_eq!(
+            term(
+                Operand::call("sqrt", vec![term(sym("x"), Operation::Add, num(1.0))]),
+                Operation::Add,
+                Operand::call("root", vec![num(3.0), num(8.0)])
+            ),
+            expr("√(x + 1) + ∛8")
+        );
+    }
+
+    #[test]
+    fn latex_calls_of_known_functions() {
+        assert_eq!(
+            Operand::call("cos", vec![term(num(2.0), Operation::Mul, sym("pi"))]),
+            expr("cos(2π)")
+        );
+        assert_eq!(
+            term(
+                sym("x"),
+                Operation::Mul,
+                term(sym("x"), Operation::Add, num(1.0))
+            ),
+            expr("x(x + 1)")
+        );
+        assert_eq!(
+            Operand::call("g", vec![sym("a"), sym("b")]),
+            expr("g(a, b)")
+        );
+        assert_eq!(
+            term(
+                num(-1.0),
+                Operation::Mul,
+                Operand::call("abs", vec![num(-3.0)])
+            ),
+            expr("-|-3|")
+        );
+    }
+
+    #[test]
+    fn latex_definitions() {
+        let env = TopLevelEnv::default();
+        assert_eq!(
+            Ok(Statement::Assignment {
+                sym: "r".to_string(),
+                op: term(num(1.0), Operation::Div, num(2.0)),
+            }),
+            parse_latex("r := \\frac{1}{2}", &env)
+        );
+        assert_eq!(
+            Ok(Statement::Function {
+                name: "f".to_string(),
+                fun: Function::Custom(CustomFunction {
+                    args: vec!["n".to_string()],
+                    body: term(
+                        sym("n"),
+                        Operation::Mul,
+                        Operand::call("f", vec![term(sym("n"), Operation::Sub, num(1.0))])
+                    ),
+                }),
+            }),
+            parse_latex("f(n) := n \\cdot f(n - 1)", &env)
+        );
+    }
+
+    #[test]
+    fn latex_invalid() {
+        assert!(matches!(
+            parse_latex("\\frac{1}", &TopLevelEnv::default()),
+            Err(ParserError::Syntax { .. })
+        ));
+    }
+}
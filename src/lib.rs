@@ -1,17 +1,122 @@
-mod ast;
+mod analysis;
+mod arena;
+mod ascii;
+pub mod ast;
+mod audit;
+mod budget;
 mod calc;
+mod canonical;
+mod catalog;
+mod compiled;
+mod completion;
+#[cfg(feature = "decimal")]
+mod decimal;
+mod dependencies;
+mod diagnostic;
+mod display;
+mod dump;
+mod equivalence;
+mod export;
+#[cfg(feature = "fft")]
+mod fft;
+#[cfg(feature = "finance")]
+mod finance;
+mod fit;
+mod gamma;
 mod graph;
+mod higher_order;
+mod import;
+mod integrate;
+#[cfg(not(feature = "embedded"))]
+mod interpolation;
+#[cfg(feature = "latex")]
+mod latex;
+mod limits;
+#[cfg(not(feature = "embedded"))]
+mod linalg;
+mod listing;
+mod mathml;
+mod matrix;
+mod number;
 mod parser;
+mod random;
+mod reactive;
+mod rewrite;
+mod rpn;
+mod script;
 mod solver;
+#[cfg(feature = "persistence")]
+mod state;
+mod statistics;
+mod stats;
+mod suggest;
+#[cfg(feature = "svg")]
+mod svg;
+mod text;
+mod timing;
+mod unicode;
+mod units;
+mod warning;
+#[cfg(feature = "wasm")]
+mod wasm;
 
+pub use crate::analysis::{CriticalPoint, PointKind};
 pub use crate::ast::Number;
-use crate::ast::Statement;
-use crate::calc::{calc_operand, CalcError, TopLevelEnv};
+use crate::ast::{
+    nearly_equal, Bracket, BuildInBody, BuildInFunction, Equation, Function, Operand, PlotLabels,
+    Sequence, Statement, Text, TextPart,
+};
+use crate::audit::Observers;
+pub use crate::audit::{EnvEvent, Mutation, MutationKind};
+pub use crate::calc::CalcError;
+use crate::calc::{calc_function_many, calc_operand, Env, ScopedEnv, TopLevelEnv};
+pub use crate::catalog::Catalog;
+pub use crate::compiled::CompiledExpression;
+use crate::completion::{complete, hint};
+pub use crate::completion::{Completion, CompletionKind};
+#[cfg(feature = "decimal")]
+pub use crate::decimal::Decimal;
+pub use crate::dependencies::Dependencies;
+use crate::dependencies::{dependencies_of, dependents_of, undefined_names, undefined_names_of};
+pub use crate::diagnostic::{Diagnostic, Severity, Span};
+use crate::equivalence::equivalent;
+pub use crate::equivalence::Equivalence;
+pub use crate::fit::LinearFit;
+use crate::fit::{fit_linear, FitError};
 use crate::graph::GraphError;
-pub use crate::graph::{Area, Graph, Range};
-use crate::parser::{parse, ParserError};
-use crate::solver::{solve_for, SolverError};
+pub use crate::graph::{
+    Area, Axis, Direction, Graph, Labels, Marker, Plot, PlotKind, PlotRenderer, PlotSequence,
+    Range, Surface, SurfacePlot, Tic, TicOptions,
+};
+use crate::import::{read_csv_column, read_csv_file, ImportError};
+pub use crate::integrate::IntegrationError;
+use crate::integrate::{integrate_expression, integrate_function, DEFAULT_INTEGRATION_TOLERANCE};
+pub use crate::limits::{LimitError, Limits};
+pub use crate::listing::{FunctionEntry, Listing, VariableEntry};
+pub use crate::matrix::Matrix;
+use crate::matrix::{calc_array, Array};
+pub use crate::number::Num;
+use crate::parser::{parse, parse_expression, ParserError};
+use crate::random::{with_generator, Rng};
+pub use crate::rewrite::{Pattern, RewriteRule, Rewriter};
+use crate::rpn::parse_rpn;
+pub use crate::script::ScriptError;
+pub use crate::solver::Interval;
+use crate::solver::{solve_for, solve_in, solve_inequality, Solution, SolverError};
+#[cfg(feature = "persistence")]
+pub use crate::state::StateError;
+pub use crate::stats::ExpressionStats;
+use crate::text::{as_text, calc_text};
+pub use crate::timing::Timing;
+use crate::units::{calc_quantity, has_units};
+pub use crate::units::{Quantity, Unit, UnitError};
+#[cfg(feature = "wasm")]
+pub use crate::wasm::{JsCalculator, JsPlot, JsResult};
 
+use std::collections::{BTreeSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Instant, SystemTime};
 use thiserror::Error;
 
 /// Calculator error
@@ -29,16 +134,278 @@ pub enum Error {
     /// errors derived from graph
     #[error(transparent)]
     GraphError(#[from] GraphError),
+    /// errors derived from data import
+    #[error(transparent)]
+    ImportError(#[from] ImportError),
+    /// errors derived from fitting data
+    #[error(transparent)]
+    FitError(#[from] FitError),
+    /// errors derived from numeric integration
+    #[error(transparent)]
+    IntegrationError(#[from] IntegrationError),
+    /// errors derived from exceeded resource limits
+    #[error(transparent)]
+    LimitError(#[from] LimitError),
+    /// errors derived from running script files
+    #[error(transparent)]
+    ScriptError(#[from] ScriptError),
+    /// errors derived from loading a saved state
+    #[cfg(feature = "persistence")]
+    #[error(transparent)]
+    StateError(#[from] StateError),
+    /// errors derived from quantities with units
+    #[error(transparent)]
+    UnitError(#[from] UnitError),
+}
+
+impl Error {
+    /// The error as [`Diagnostic`] with a stable error code.
+    /// ```
+    /// use rust_expression::Calculator;
+    /// let mut c = Calculator::new();
+    /// let err = c.execute("a + 1").unwrap_err();
+    /// assert_eq!("C0001", err.diagnostic().code);
+    /// ```
+    pub fn diagnostic(&self) -> Diagnostic {
+        self.into()
+    }
 }
 
 #[derive(Debug, PartialEq)]
+#[non_exhaustive]
 pub enum Value {
     Void,
     Number(Number),
-    Solved { variable: String, value: Number },
+    /// list or vector, e.g. `[1, 2, 3]`
+    List(Vec<Number>),
+    /// matrix of at least two rows, e.g. `[1, 2; 3, 4]`
+    Matrix(Matrix),
+    Solved {
+        variable: String,
+        value: Number,
+    },
+    /// real solutions of a quadratic equation in ascending order
+    Solutions(Vec<Number>),
+    /// solution set of an inequality as ascending disjoint intervals, e.g. `x < 3`
+    Intervals {
+        variable: String,
+        intervals: Vec<Interval>,
+    },
     Graph(Graph),
+    PlotSequence(PlotSequence),
+    /// function of two arguments, e.g. `plot f` of `f(x, y) := x * y`
+    Surface(Surface),
+    Equivalence(Equivalence),
+    /// symbolic result, e.g. of `simplify x + x`
+    Expression(Operand),
+    Fit(LinearFit),
+    Text(String),
+    Listing(Listing),
+    /// number with a unit, e.g. `3.2 m`
+    Quantity(Quantity),
+}
+
+/// Kind of a [`Value`] without its content
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[non_exhaustive]
+pub enum ValueKind {
+    Void,
+    Number,
+    List,
+    Matrix,
+    Solved,
+    Solutions,
+    Intervals,
+    Graph,
+    PlotSequence,
+    Surface,
+    Equivalence,
+    Expression,
+    Fit,
+    Text,
+    Listing,
+    Quantity,
+}
+
+impl Value {
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            Value::Void => ValueKind::Void,
+            Value::Number(_) => ValueKind::Number,
+            Value::List(_) => ValueKind::List,
+            Value::Matrix(_) => ValueKind::Matrix,
+            Value::Solved { .. } => ValueKind::Solved,
+            Value::Solutions(_) => ValueKind::Solutions,
+            Value::Intervals { .. } => ValueKind::Intervals,
+            Value::Graph(_) => ValueKind::Graph,
+            Value::PlotSequence(_) => ValueKind::PlotSequence,
+            Value::Surface(_) => ValueKind::Surface,
+            Value::Equivalence(_) => ValueKind::Equivalence,
+            Value::Expression(_) => ValueKind::Expression,
+            Value::Fit(_) => ValueKind::Fit,
+            Value::Text(_) => ValueKind::Text,
+            Value::Listing(_) => ValueKind::Listing,
+            Value::Quantity(_) => ValueKind::Quantity,
+        }
+    }
+
+    /// The number of a result or solution.
+    /// ```
+    /// use rust_expression::Calculator;
+    /// let mut c = Calculator::new();
+    /// assert_eq!(Some(3.0), c.execute("1 + 2").unwrap().as_number());
+    /// assert_eq!(Some(2.0), c.execute("solve 2 * x = 4 for x").unwrap().as_number());
+    /// ```
+    pub fn as_number(&self) -> Option<Number> {
+        match self {
+            Value::Number(num) | Value::Solved { value: num, .. } => Some(*num),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Number]> {
+        match self {
+            Value::List(list) | Value::Solutions(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    /// The solution set of an inequality.
+    /// ```
+    /// use rust_expression::Calculator;
+    /// let mut c = Calculator::new();
+    /// let solution = c.execute("solve x ^ 2 > 4 for x").unwrap();
+    /// let intervals = solution.as_intervals().unwrap();
+    /// assert!(intervals[0].contains(-3.0) && !intervals[0].contains(-2.0));
+    /// assert_eq!("x < -2 or x > 2", solution.to_string());
+    /// ```
+    pub fn as_intervals(&self) -> Option<&[Interval]> {
+        match self {
+            Value::Intervals { intervals, .. } => Some(intervals),
+            _ => None,
+        }
+    }
+
+    pub fn as_matrix(&self) -> Option<&Matrix> {
+        match self {
+            Value::Matrix(matrix) => Some(matrix),
+            _ => None,
+        }
+    }
+
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            Value::Text(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// The reduced expression of `simplify`.
+    /// ```
+    /// use rust_expression::{ast::Operand, Calculator};
+    /// let mut c = Calculator::new();
+    /// let value = c.execute("simplify 2 * x + 3 * x").unwrap();
+    /// let exp = Operand::number(5.0) * Operand::symbol("x");
+    /// assert_eq!(Some(&exp), value.as_expression());
+    /// ```
+    pub fn as_expression(&self) -> Option<&Operand> {
+        match self {
+            Value::Expression(op) => Some(op),
+            _ => None,
+        }
+    }
+
+    pub fn as_graph(&self) -> Option<&Graph> {
+        match self {
+            Value::Graph(graph) => Some(graph),
+            _ => None,
+        }
+    }
+
+    pub fn as_plot_sequence(&self) -> Option<&PlotSequence> {
+        match self {
+            Value::PlotSequence(sequence) => Some(sequence),
+            _ => None,
+        }
+    }
+
+    pub fn as_surface(&self) -> Option<&Surface> {
+        match self {
+            Value::Surface(surface) => Some(surface),
+            _ => None,
+        }
+    }
+
+    pub fn as_listing(&self) -> Option<&Listing> {
+        match self {
+            Value::Listing(listing) => Some(listing),
+            _ => None,
+        }
+    }
+
+    /// The quantity of a result with unit.
+    /// ```
+    /// use rust_expression::Calculator;
+    /// let mut c = Calculator::new();
+    /// let length = c.execute("3 m + 20 cm").unwrap();
+    /// assert_eq!("3.2 m", length.as_quantity().unwrap().to_string());
+    /// ```
+    pub fn as_quantity(&self) -> Option<&Quantity> {
+        match self {
+            Value::Quantity(quantity) => Some(quantity),
+            _ => None,
+        }
+    }
+}
+
+fn solved(variable: String, solution: Solution) -> Value {
+    match solution {
+        Solution::Single(value) => Value::Solved { variable, value },
+        Solution::Quadratic(values) => Value::Solutions(values),
+    }
+}
+
+/// the numbers of `value` with the fewest significant digits within `tolerance`
+fn rounded(value: Value, tolerance: Number) -> Value {
+    let round = |num: Number| {
+        (0..=17)
+            .filter_map(|digits| format!("{:.*e}", digits, num).parse::<Number>().ok())
+            .find(|candidate| nearly_equal(*candidate, num, tolerance))
+            .unwrap_or(num)
+    };
+    match value {
+        Value::Number(num) => Value::Number(round(num)),
+        Value::Solved { variable, value } => Value::Solved {
+            variable,
+            value: round(value),
+        },
+        Value::Solutions(nums) => Value::Solutions(nums.into_iter().map(round).collect()),
+        value => value,
+    }
+}
+
+/// State of a [`Calculator`] captured by [`Calculator::snapshot`].
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    env: TopLevelEnv,
+}
+
+/// Result of [`Calculator::execute_with_warnings`]
+#[derive(Debug, PartialEq)]
+pub struct Output {
+    pub value: Value,
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// Statements parsed once by [`Calculator::compile_script`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    statements: Vec<Statement>,
 }
 
+/// most changes, which can be reverted by `undo`
+const MAX_UNDO: usize = 32;
+
 /// # Calculator
 ///
 /// See it in action on [https://msuesskraut.github.io/calc/index.html](https://msuesskraut.github.io/calc/index.html).
@@ -46,6 +413,39 @@ pub enum Value {
 #[derive(Debug, Default)]
 pub struct Calculator {
     env: TopLevelEnv,
+    rpn: bool,
+    /// accept LaTeX commands and Unicode math symbols
+    #[cfg(feature = "latex")]
+    math_input: bool,
+    strict: bool,
+    declared_only: bool,
+    echo: bool,
+    reactive: bool,
+    /// width of the interval, at which a numeric solve stops
+    solve_tolerance: Number,
+    /// bound of the error of `integrate`, the default if not set
+    integration_tolerance: Option<Number>,
+    /// timing of the last statement, if timing is enabled
+    timing: Option<Timing>,
+    /// variables changed by the last statement
+    changed: BTreeSet<String>,
+    limits: Limits,
+    /// changes of bindings, if auditing is enabled
+    audit: Option<Vec<Mutation>>,
+    /// directory of the scripts executed by `run`, the working directory if empty
+    base_path: PathBuf,
+    /// scripts currently executed by `run`, the innermost last
+    running: Vec<PathBuf>,
+    /// `run` and `import` fail instead of reading files
+    sandboxed: bool,
+    /// environments before the last changes, the latest last
+    history: VecDeque<TopLevelEnv>,
+    /// round results within the tolerance
+    round_results: bool,
+    /// callbacks registered by `on_change`
+    observers: Observers,
+    /// generator of `randn()`, `randexp(lambda)` and `sample`, advanced by evaluations
+    rng: Mutex<Rng>,
 }
 
 impl Calculator {
@@ -84,6 +484,15 @@ impl Calculator {
     ///   assert_eq!(Ok(Value::Void), c.execute("fun(x, y) := y - x"));
     ///   assert_eq!(Ok(Value::Number(2.0)), c.execute("fun(1.5 * 2, 3 + a) - 4"));
     ///   ```
+    /// - Sequences defined by a recurrence and initial values:
+    ///   ```
+    ///   # use rust_expression::{Calculator, Value};
+    ///   # let mut c = Calculator::new();
+    ///   assert_eq!(Ok(Value::Void), c.execute("fib(n) := fib(n - 1) + fib(n - 2)"));
+    ///   assert_eq!(Ok(Value::Void), c.execute("fib(0) := 0"));
+    ///   assert_eq!(Ok(Value::Void), c.execute("fib(1) := 1"));
+    ///   assert_eq!(Ok(Value::Number(55.0)), c.execute("fib(10)"));
+    ///   ```
     /// - Create a plot:
     ///   ```
     ///   # use rust_expression::{Calculator, Value};
@@ -96,88 +505,3357 @@ impl Calculator {
     ///           let area = Area::new(-100., -100., 100., 100.);
     ///           let screen = Area::new(0., 0., 60., 40.);
     ///           let plot = graph.plot(&area, &screen).unwrap();
-    ///           assert_eq!(Some(20.), plot.points[30]);
+    ///           assert!(plot.points.contains(&Some((30., 20.))));
     ///       }
     ///       // ...
     ///   #   _ => unimplemented!(),
     ///   }
     ///   ```
+    /// - Expression in postfix notation (see also [`Calculator::set_rpn`]):
+    ///   ```
+    ///   # use rust_expression::{Calculator, Value};
+    ///   # let mut c = Calculator::new();
+    ///   assert_eq!(Ok(Value::Number(14.0)), c.execute("rpn 3 4 + 2 *"));
+    ///   ```
+    /// - Linear regression of list variables, optionally defining the fitted function:
+    ///   ```
+    ///   # use rust_expression::{Calculator, LinearFit, Value};
+    ///   # let mut c = Calculator::new();
+    ///   c.load_csv_str("0,1\n1,3\n2,5", 1, "xs").unwrap();
+    ///   c.load_csv_str("0,1\n1,3\n2,5", 2, "ys").unwrap();
+    ///   assert_eq!(
+    ///       Ok(Value::Fit(LinearFit { slope: 2.0, intercept: 1.0, r_squared: 1.0 })),
+    ///       c.execute("fit linear xs, ys as fit1")
+    ///   );
+    ///   assert_eq!(Ok(Value::Number(7.0)), c.execute("fit1(3)"));
+    ///   ```
+    /// - Check equivalence of expressions:
+    ///   ```
+    ///   # use rust_expression::{Calculator, Equivalence, Value};
+    ///   # let mut c = Calculator::new();
+    ///   assert_eq!(
+    ///       Ok(Value::Equivalence(Equivalence::Probable { samples: 32 })),
+    ///       c.execute("is x^2 - 1 == (x - 1) * (x + 1)")
+    ///   );
+    ///   ```
     pub fn execute(&mut self, line: &str) -> Result<Value, Error> {
-        let st = parse(line)?;
-        match st {
-            Statement::Expression { op } => Ok(Value::Number(calc_operand(&op, &self.env)?)),
-            Statement::Assignment { sym, op } => {
-                self.env.put(sym, calc_operand(&op, &self.env)?)?;
-                Ok(Value::Void)
+        if self.timing.is_none() {
+            let st = self.parse_line(line)?;
+            return self.execute_statement(st);
+        }
+        let start = Instant::now();
+        let st = self.parse_line(line);
+        let parsed = Instant::now();
+        let mut timing = Timing {
+            parse: parsed - start,
+            ..Timing::default()
+        };
+        self.timing = Some(timing);
+        let st = st?;
+        let (is_solve, is_plot) = (
+            matches!(st, Statement::SolveFor { .. }),
+            matches!(
+                st,
+                Statement::Plot { .. }
+                    | Statement::PlotExpression { .. }
+                    | Statement::PlotPolar { .. }
+                    | Statement::PlotImplicit { .. }
+            ),
+        );
+        let result = timing::counted(&mut timing, || self.execute_statement(st));
+        let elapsed = parsed.elapsed();
+        if is_solve {
+            timing.solve = elapsed;
+        } else if is_plot {
+            timing.plot = elapsed;
+        } else {
+            timing.eval = elapsed;
+        }
+        self.timing = Some(timing);
+        result
+    }
+
+    /// Enables measuring durations and counting function calls of [`Calculator::execute`].
+    /// A plot is only evaluated when drawn, see [`Graph::plot_with_timing`].
+    /// ```
+    /// use rust_expression::Calculator;
+    /// let mut c = Calculator::new();
+    /// c.set_timing(true);
+    /// c.execute("f(x) := sin(x) + x").unwrap();
+    /// c.execute("f(1) + f(2)").unwrap();
+    /// let timing = c.last_timing().unwrap();
+    /// assert_eq!(2, timing.function_calls);
+    /// assert_eq!(2, timing.builtin_calls);
+    /// ```
+    pub fn set_timing(&mut self, timing: bool) {
+        self.timing = timing.then(Timing::default);
+    }
+
+    /// Caps the resources used by statements, e.g. for untrusted input.
+    /// A statement exceeding a limit fails and leaves the environment unchanged.
+    /// ```
+    /// use rust_expression::{Calculator, Error, LimitError, Limits};
+    /// let mut c = Calculator::new();
+    /// c.set_limits(Limits {
+    ///     max_bindings: Some(1),
+    ///     ..Limits::default()
+    /// });
+    /// c.execute("a := 1").unwrap();
+    /// assert_eq!(
+    ///     Err(Error::LimitError(LimitError::TooManyBindings { max: 1 })),
+    ///     c.execute("b := 2")
+    /// );
+    /// assert!(c.execute("b").is_err());
+    /// ```
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Timing of the last statement executed with timing enabled.
+    pub fn last_timing(&self) -> Option<&Timing> {
+        self.timing.as_ref()
+    }
+
+    /// Like [`Calculator::execute`], but also returns warnings about a successful result,
+    /// e.g. when the result is not finite or a function shadows a build-in function.
+    /// ```
+    /// use rust_expression::{Calculator, Number, Value};
+    /// let mut c = Calculator::new();
+    /// let output = c.execute_with_warnings("10 ^ 400").unwrap();
+    /// assert_eq!(Value::Number(Number::INFINITY), output.value);
+    /// assert_eq!("W0001", output.warnings[0].code);
+    /// ```
+    pub fn execute_with_warnings(&mut self, line: &str) -> Result<Output, Error> {
+        let st = self.parse_line(line)?;
+        let mut warnings = warning::before(&st, &self.env);
+        let value = self.execute_statement(st.clone())?;
+        warnings.extend(warning::after(&st, &value, &self.env));
+        Ok(Output { value, warnings })
+    }
+
+    /// Executes a statement built with the [`ast`] module like a parsed line.
+    /// ```
+    /// use rust_expression::ast::{Operand, Statement};
+    /// use rust_expression::{Calculator, Value};
+    /// let mut c = Calculator::new();
+    /// let x = Operand::symbol("x");
+    /// let st = Statement::solve_for(Operand::number(2.0) * x, Operand::number(4.0), "x");
+    /// assert_eq!(
+    ///     Ok(Value::Solved { variable: "x".to_string(), value: 2.0 }),
+    ///     c.eval_ast(&st)
+    /// );
+    /// ```
+    pub fn eval_ast(&mut self, st: &Statement) -> Result<Value, Error> {
+        self.execute_statement(st.clone())
+    }
+
+    /// Evaluates an expression without changing the calculator, so it can be shared
+    /// by several readers, also on several threads. Fails for statements, e.g. assignments, and for results
+    /// other than a number.
+    /// ```
+    /// use rust_expression::{CalcError, Calculator, Error};
+    /// let mut c = Calculator::new();
+    /// c.execute("f(x) := 2 * x").unwrap();
+    /// let c = &c;
+    /// assert_eq!(Ok(6.0), c.evaluate("f(3)"));
+    /// assert_eq!(
+    ///     Err(Error::CalcError(CalcError::NotAnExpression("a := 1".to_string()))),
+    ///     c.evaluate("a := 1")
+    /// );
+    /// ```
+    pub fn evaluate(&self, line: &str) -> Result<Number, Error> {
+        let not_an_expression = || CalcError::NotAnExpression(line.trim().to_string()).into();
+        match self.parse_line(line)? {
+            Statement::Expression { op } => match self.with_rng(|| self.expression_value(&op))? {
+                Value::Number(num) => Ok(num),
+                _ => Err(not_an_expression()),
+            },
+            _ => Err(not_an_expression()),
+        }
+    }
+
+    /// Evaluates an expression like [`Calculator::evaluate`], but with the number type `N`.
+    /// Literals are parsed as `N` and variables converted to `N`, sequences and functions
+    /// of lists are computed with [`Number`]. A number too large for `N` is an error.
+    /// ```
+    /// use rust_expression::Calculator;
+    /// let mut c = Calculator::new();
+    /// c.execute("f(x) := x / 3").unwrap();
+    /// assert_eq!(Ok(0.5f32), c.evaluate_as::<f32>("f(1.5)"));
+    /// assert_eq!(Ok(1.0 / 3.0), c.evaluate_as::<f64>("f(1)"));
+    /// ```
+    pub fn evaluate_as<N: Num>(&self, line: &str) -> Result<N, Error> {
+        match self.parse_line(line)? {
+            Statement::Expression { op } => Ok(self.with_rng(|| calc_operand(&op, &self.env))?),
+            _ => Err(CalcError::NotAnExpression(line.trim().to_string()).into()),
+        }
+    }
+
+    fn parse_line(&self, line: &str) -> Result<Statement, Error> {
+        Ok(match line.trim_start().strip_prefix("rpn ") {
+            Some(rpn) => parse_rpn(rpn, &self.env)?,
+            None if self.rpn => parse_rpn(line, &self.env)?,
+            #[cfg(feature = "latex")]
+            None if self.math_input => parse_math(line, &self.env)?,
+            None => parse(line)?,
+        })
+    }
+
+    /// Parses a script of statements separated by newlines or `;`, empty lines are skipped.
+    /// The resulting [`Program`] can be run repeatedly with [`Calculator::run`].
+    /// ```
+    /// use rust_expression::{Calculator, Value};
+    /// let mut c = Calculator::new();
+    /// let program = c.compile_script("b := 2 * a\nb + 1").unwrap();
+    /// c.execute("a := 1").unwrap();
+    /// assert_eq!(Ok(vec![Value::Void, Value::Number(3.0)]), c.run(&program));
+    /// c.execute("a := 2").unwrap();
+    /// assert_eq!(Ok(vec![Value::Void, Value::Number(5.0)]), c.run(&program));
+    /// ```
+    pub fn compile_script(&self, script: &str) -> Result<Program, Error> {
+        let statements = script::statements(script)
+            .map(|(_, st)| self.parse_line(st))
+            .collect::<Result<_, _>>()?;
+        Ok(Program { statements })
+    }
+
+    /// All variables, functions, formulas and equations as script, one statement per line.
+    /// Running the script with [`Calculator::compile_script`] and [`Calculator::run`]
+    /// recreates them. Lists have no literal and are left out.
+    /// ```
+    /// use rust_expression::Calculator;
+    /// let mut c = Calculator::new();
+    /// c.execute("f(x) := x ^ 2 + a").unwrap();
+    /// c.execute("a := 6").unwrap();
+    /// let script = c.dump_script();
+    /// assert_eq!("a := 6\nf(x) := x ^ 2 + a\n", script);
+    ///
+    /// let mut copy = Calculator::new();
+    /// copy.run(&copy.compile_script(&script).unwrap()).unwrap();
+    /// assert_eq!(script, copy.dump_script());
+    /// ```
+    pub fn dump_script(&self) -> String {
+        dump::dump(&self.env)
+            .iter()
+            .map(|st| format!("{}\n", st))
+            .collect()
+    }
+
+    /// Sets the directory of the script files executed by `run "file"`.
+    /// ```
+    /// use rust_expression::{Calculator, Number, Value};
+    /// let dir = std::env::temp_dir().join("calc_run_doctest");
+    /// std::fs::create_dir_all(dir.join("lib")).unwrap();
+    /// std::fs::write(dir.join("lib/circle.calc"), "area(r) := pi * r ^ 2\n").unwrap();
+    ///
+    /// let mut c = Calculator::new();
+    /// c.set_base_path(&dir);
+    /// assert_eq!(Ok(Value::Void), c.execute("run \"lib/circle.calc\""));
+    /// let pi = std::f64::consts::PI as Number;
+    /// assert_eq!(Ok(Value::Number(pi)), c.execute("area(1)"));
+    /// ```
+    pub fn set_base_path(&mut self, base_path: impl Into<PathBuf>) {
+        self.base_path = base_path.into();
+    }
+
+    /// In sandboxed mode `run` and `import` fail with [`ScriptError::FileAccessDisabled`],
+    /// e.g. for statements of untrusted users.
+    /// ```
+    /// use rust_expression::{Calculator, Error, ScriptError};
+    /// let mut c = Calculator::new();
+    /// c.set_sandboxed(true);
+    /// assert_eq!(
+    ///     Err(Error::ScriptError(ScriptError::FileAccessDisabled("a.calc".to_string()))),
+    ///     c.execute("run \"a.calc\"")
+    /// );
+    /// ```
+    pub fn set_sandboxed(&mut self, sandboxed: bool) {
+        self.sandboxed = sandboxed;
+    }
+
+    fn check_file_access(&self, path: &str) -> Result<(), Error> {
+        match self.sandboxed {
+            true => Err(ScriptError::FileAccessDisabled(path.to_string()).into()),
+            false => Ok(()),
+        }
+    }
+
+    fn run_file(&mut self, path: &str) -> Result<(), Error> {
+        self.check_file_access(path)?;
+        let resolved = script::resolve(&self.base_path, path, &self.running)?;
+        let content = std::fs::read_to_string(&resolved).map_err(|err| ImportError::Io {
+            path: path.to_string(),
+            message: err.to_string(),
+        })?;
+        self.running.push(resolved);
+        let result = script::statements(&content).try_for_each(|(line, st)| {
+            // the statement is not quoted, the file may be no script at all
+            let st = self
+                .parse_line(st)
+                .map_err(|_| ScriptError::InvalidStatement {
+                    path: path.to_string(),
+                    line,
+                })?;
+            self.execute_statement(st)
+                .map(|_| ())
+                .map_err(|err| match err {
+                    // keep the location in the innermost script
+                    Error::ScriptError(ScriptError::Statement { .. })
+                    | Error::ScriptError(ScriptError::InvalidStatement { .. }) => err,
+                    err => ScriptError::Statement {
+                        path: path.to_string(),
+                        line,
+                        error: Box::new(err),
+                    }
+                    .into(),
+                })
+        });
+        self.running.pop();
+        result
+    }
+
+    /// Executes a script of statements separated by newlines or `;`, e.g. a prelude of
+    /// function definitions. All statements are executed, also after a failing one.
+    /// A failure is reported as [`ScriptError::Failed`] with its line and the number of
+    /// the statement, both counted from 1.
+    /// ```
+    /// use rust_expression::{Calculator, Error, ScriptError, Value};
+    /// let mut c = Calculator::new();
+    /// let results = c.execute_script("sq(x) := x ^ 2; a := sq(3)\nb := c\na + 1");
+    /// assert_eq!(Ok(Value::Void), results[1]);
+    /// assert!(matches!(
+    ///     results[2],
+    ///     Err(Error::ScriptError(ScriptError::Failed { line: 2, statement: 3, .. }))
+    /// ));
+    /// assert_eq!(Ok(Value::Number(10.0)), results[3]);
+    /// ```
+    pub fn execute_script(&mut self, script: &str) -> Vec<Result<Value, Error>> {
+        script::statements(script)
+            .enumerate()
+            .map(|(idx, (line, st))| {
+                self.execute(st).map_err(|err| {
+                    ScriptError::Failed {
+                        line,
+                        statement: idx + 1,
+                        error: Box::new(err),
+                    }
+                    .into()
+                })
+            })
+            .collect()
+    }
+
+    /// Executes the statements of `program` in order and returns their values.
+    /// Stops at the first failing statement.
+    pub fn run(&mut self, program: &Program) -> Result<Vec<Value>, Error> {
+        program
+            .statements
+            .iter()
+            .map(|st| self.execute_statement(st.clone()))
+            .collect()
+    }
+
+    /// Checks whether two expressions are equivalent.
+    /// Variables that are not defined are free variables and probed with random values.
+    /// ```
+    /// use rust_expression::{Calculator, Equivalence};
+    /// let c = Calculator::new();
+    /// assert_eq!(Ok(Equivalence::Proven), c.equivalent("a + b", "b + a"));
+    /// ```
+    pub fn equivalent(&self, lhs: &str, rhs: &str) -> Result<Equivalence, Error> {
+        let lhs = parse_expression(lhs)?;
+        let rhs = parse_expression(rhs)?;
+        Ok(equivalent(&lhs, &rhs, &self.env))
+    }
+
+    /// Definition of the function or value of the variable `name` with unicode symbols,
+    /// e.g. for display in a terminal. Returns `None` for unknown names and build-in functions.
+    /// ```
+    /// use rust_expression::Calculator;
+    /// let mut c = Calculator::new();
+    /// c.execute("f(x) := 3 * x^2 - 1/2").unwrap();
+    /// assert_eq!(Some("f(x) := 3·x² − ½".to_string()), c.show("f"));
+    /// ```
+    pub fn show(&self, name: &str) -> Option<String> {
+        match self.env.get(name) {
+            Some(num) => Some(format!(
+                "{} = {}",
+                name,
+                unicode::operand(&Operand::Number(*num))
+            )),
+            None => match self.env.get_text(name) {
+                Some(text) => Some(format!("{} = \"{}\"", name, text)),
+                None => match self.env.get_fun(name) {
+                    Some(fun) => unicode::function(name, fun),
+                    None => self
+                        .env
+                        .equation(name)
+                        .map(|equation| unicode::equation(name, equation)),
+                },
+            },
+        }
+    }
+
+    /// Stored equations ordered by name, as shown by [`Calculator::show`].
+    /// ```
+    /// use rust_expression::{Calculator, Value};
+    /// let mut c = Calculator::new();
+    /// c.execute("store ohm: v = i * r").unwrap();
+    /// assert_eq!(vec!["ohm: v = i·r".to_string()], c.equations());
+    /// assert_eq!(
+    ///     Ok(Value::Solved { variable: "i".to_string(), value: 3.0 }),
+    ///     c.execute("solve ohm for i given v := 12, r := 4")
+    /// );
+    /// ```
+    pub fn equations(&self) -> Vec<String> {
+        self.env
+            .equations()
+            .into_iter()
+            .map(|(name, equation)| unicode::equation(name, equation))
+            .collect()
+    }
+
+    /// The definitions of the user as source text ordered by name: variables, formulas,
+    /// functions and equations. Lists and sequence values are shown as values.
+    /// ```
+    /// use rust_expression::Calculator;
+    /// let mut c = Calculator::new();
+    /// c.execute("f(x) := (x + 1) * 2").unwrap();
+    /// c.execute("a := 1").unwrap();
+    /// c.execute("define b = a * f(a)").unwrap();
+    /// assert_eq!(
+    ///     vec!["a := 1", "define b = a * f(a)", "f(x) := (x + 1) * 2"],
+    ///     c.list()
+    /// );
+    /// ```
+    pub fn list(&self) -> Vec<String> {
+        let mut definitions = Vec::new();
+        for sym in self.env.formula_names() {
+            if let Some(op) = self.env.formula(sym) {
+                let statement = Statement::Define {
+                    sym: sym.to_string(),
+                    op: op.clone(),
+                };
+                definitions.push((sym.to_string(), statement.to_string()));
             }
-            Statement::SolveFor { lhs, rhs, sym } => Ok(Value::Solved {
-                variable: sym.to_string(),
-                value: solve_for(&lhs, &rhs, &sym, &self.env)?,
-            }),
-            Statement::Function { name, fun } => {
-                self.env.put_fun(name, fun);
-                Ok(Value::Void)
+        }
+        for sym in self.env.user_var_names() {
+            let definition = match (self.env.get(sym), self.env.get_list(sym)) {
+                _ if self.env.formula(sym).is_some() => continue,
+                (Some(num), _) => format!("{} := {}", sym, num),
+                (_, Some(list)) => format!("{} = {}", sym, Value::List(list.to_vec())),
+                _ => match (self.env.get_text(sym), self.env.get_matrix(sym)) {
+                    (Some(text), _) => format!("{} := \"{}\"", sym, text),
+                    (_, Some(matrix)) => format!("{} = {}", sym, matrix),
+                    _ => continue,
+                },
+            };
+            definitions.push((sym.to_string(), definition));
+        }
+        for name in self.env.fun_names() {
+            if let Some(
+                fun @ (Function::Custom(_) | Function::Sequence(_) | Function::Piecewise(_)),
+            ) = self.env.get_fun(name)
+            {
+                definitions.push((name.to_string(), fun.definition(name).to_string()));
             }
-            Statement::Plot { name } => Ok(Value::Graph(Graph::new(&name, &self.env)?)),
         }
+        for (name, equation) in self.env.equations() {
+            let statement = Statement::Store {
+                name: name.clone(),
+                lhs: equation.lhs.clone(),
+                rhs: equation.rhs.clone(),
+            };
+            definitions.push((name.clone(), statement.to_string()));
+        }
+        definitions.sort();
+        definitions
+            .into_iter()
+            .map(|(_, definition)| definition)
+            .collect()
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// The variables defined by the user ordered by name, like the statement `list`.
+    /// ```
+    /// use rust_expression::{Calculator, Value};
+    /// let mut c = Calculator::new();
+    /// c.execute("a := 2").unwrap();
+    /// c.execute("define b = a * 3").unwrap();
+    /// let variables = c.variables();
+    /// assert_eq!(Value::Number(6.0), variables[1].value);
+    /// assert_eq!(Some("a * 3".to_string()), variables[1].formula);
+    /// ```
+    pub fn variables(&self) -> Vec<VariableEntry> {
+        listing::variables(&self.env)
+    }
 
-    #[test]
-    fn simple_calc() {
-        let mut calc = Calculator::new();
-        assert_eq!(Ok(Value::Number(3.0)), calc.execute("1 + 2"));
+    /// The functions defined by the user ordered by name, like the statement `list`.
+    /// ```
+    /// use rust_expression::Calculator;
+    /// let mut c = Calculator::new();
+    /// c.execute("f(x, y) := x * y").unwrap();
+    /// let functions = c.functions();
+    /// assert_eq!(vec!["x", "y"], functions[0].args);
+    /// assert_eq!("f(x, y) := x * y", functions[0].definition);
+    /// ```
+    pub fn functions(&self) -> Vec<FunctionEntry> {
+        listing::functions(&self.env)
     }
 
-    #[test]
-    fn simple_assign() {
-        let mut calc = Calculator::new();
-        assert_eq!(Ok(Value::Void), calc.execute("a := 1"));
-        assert_eq!(Ok(Value::Number(1.0)), calc.execute("a"));
+    /// Variables, constants, functions and keywords completing the name before `cursor`,
+    /// a byte offset in `line`, e.g. for tab completion in a REPL.
+    /// ```
+    /// use rust_expression::{Calculator, CompletionKind};
+    /// let mut c = Calculator::new();
+    /// c.execute("speed := 3").unwrap();
+    /// let completions = c.complete("2 * spe", 7);
+    /// assert_eq!("speed", completions[0].text);
+    /// assert_eq!(CompletionKind::Variable, completions[0].kind);
+    /// assert_eq!(4..7, completions[0].span.start..completions[0].span.end);
+    /// ```
+    pub fn complete(&self, line: &str, cursor: usize) -> Vec<Completion> {
+        complete(line, cursor, &self.env)
     }
 
-    #[test]
-    fn simple_function() {
-        let mut calc = Calculator::new();
-        assert_eq!(Ok(Value::Void), calc.execute("fun(x, y) := y - x"));
-        assert_eq!(
-            Ok(Value::Number(20.0)),
-            calc.execute("fun(1 + 2, 3 * 9) - 4")
-        );
+    /// The signature of the build-in or user function `name`, `None` for other names.
+    /// ```
+    /// use rust_expression::Calculator;
+    /// let mut c = Calculator::new();
+    /// c.execute("f(x, y) := x * y").unwrap();
+    /// assert_eq!(Some("f(x, y)".to_string()), c.hint("f"));
+    /// assert_eq!(Some("atan2(y, x)".to_string()), c.hint("atan2"));
+    /// ```
+    pub fn hint(&self, name: &str) -> Option<String> {
+        hint(name, &self.env)
     }
 
-    #[test]
-    fn simple_solve_for() {
-        let mut calc = Calculator::new();
-        assert_eq!(
-            Ok(Value::Solved {
-                variable: "y".to_string(),
-                value: 4.0
+    /// Variables and functions the function `name` references, directly or through
+    /// the functions it calls. Returns `None` if there is no function `name`.
+    /// ```
+    /// use rust_expression::Calculator;
+    /// let mut c = Calculator::new();
+    /// c.execute("f(x) := g(x) * a").unwrap();
+    /// c.execute("g(x) := x + b").unwrap();
+    /// let deps = c.dependencies_of("f").unwrap();
+    /// assert!(deps.variables.contains("a") && deps.variables.contains("b"));
+    /// assert!(deps.functions.contains("g"));
+    /// ```
+    pub fn dependencies_of(&self, name: &str) -> Option<Dependencies> {
+        dependencies_of(name, &self.env)
+    }
+
+    /// Names of all functions that reference the variable or function `name`,
+    /// directly or transitively, i.e. what breaks if `name` is removed.
+    pub fn dependents_of(&self, name: &str) -> BTreeSet<String> {
+        dependents_of(name, &self.env)
+    }
+
+    /// Evaluates the function `name` of one argument for all `xs`.
+    /// The function is resolved once, functions of plain arithmetic are compiled.
+    /// ```
+    /// use rust_expression::Calculator;
+    /// let mut c = Calculator::new();
+    /// c.execute("f(x) := 2 * x + 1").unwrap();
+    /// assert_eq!(vec![Ok(1.0), Ok(3.0)], c.eval_fn_many("f", &[0.0, 1.0]));
+    /// ```
+    pub fn eval_fn_many(&self, name: &str, xs: &[Number]) -> Vec<Result<Number, CalcError>> {
+        self.with_rng(|| calc_function_many(name, xs, &self.env))
+    }
+
+    /// runs `f` with the random number generator of the calculator
+    /// Runs `f` with the generator of the calculator. Readers evaluating at the same
+    /// time start with the same generator and the last one keeps its advanced state.
+    fn with_rng<T>(&self, f: impl FnOnce() -> T) -> T {
+        let rng = *self.rng.lock().unwrap_or_else(PoisonError::into_inner);
+        let (result, rng) = with_generator(rng, f);
+        *self.rng.lock().unwrap_or_else(PoisonError::into_inner) = rng;
+        result
+    }
+
+    /// Compiles the expression `expr` for repeated evaluation, e.g. in hot loops.
+    /// Variables and functions defined in the calculator are resolved now,
+    /// the other symbols are the variables of the compiled expression.
+    /// ```
+    /// use rust_expression::Calculator;
+    /// let mut c = Calculator::new();
+    /// c.execute("a := 2").unwrap();
+    /// let f = c.compile("sin(x) * a").unwrap();
+    /// assert_eq!(Ok(0.0), f.eval(&[("x", 0.0)]));
+    /// assert_eq!(["x"], f.variables());
+    /// ```
+    pub fn compile(&self, expr: &str) -> Result<CompiledExpression, Error> {
+        let op = parse_expression(expr)?;
+        Ok(CompiledExpression::new(op, &self.env))
+    }
+
+    /// Registers the closure `body` as function `name` of `arity` numbers.
+    /// The calculator checks the number of parameters before calling `body`.
+    /// `body` must be `Send + Sync`, as the calculator can be shared by threads.
+    /// ```
+    /// use rust_expression::{CalcError, Calculator, Value};
+    /// let mut c = Calculator::new();
+    /// let table = [1.5, 2.5, 4.0];
+    /// c.register_function("lookup", 1, move |params| {
+    ///     table
+    ///         .get(params[0] as usize)
+    ///         .copied()
+    ///         .ok_or_else(|| CalcError::InvalidArguments {
+    ///             name: "lookup".to_string(),
+    ///             reason: format!("no entry {}", params[0]),
+    ///         })
+    /// });
+    /// assert_eq!(Ok(Value::Number(5.0)), c.execute("2 * lookup(1)"));
+    /// assert!(c.execute("lookup(3)").is_err());
+    /// ```
+    pub fn register_function(
+        &mut self,
+        name: &str,
+        arity: usize,
+        body: impl Fn(&[Number]) -> Result<Number, CalcError> + Send + Sync + 'static,
+    ) {
+        let args = match arity {
+            1 => vec!["x".to_string()],
+            _ => (1..=arity).map(|idx| format!("x{}", idx)).collect(),
+        };
+        let before = self.env.clone();
+        self.env.put_fun(
+            name.to_string(),
+            Function::BuildIn(BuildInFunction {
+                name: name.to_string(),
+                args,
+                variadic: false,
+                body: BuildInBody::Native(Arc::new(body)),
             }),
-            calc.execute("solve 3 * y - 2 = y + 6 for y")
         );
+        self.record(&before, &format!("register {}", name));
     }
 
-    #[test]
-    fn simple_plot() {
-        let mut calc = Calculator::new();
-        assert_eq!(Ok(Value::Void), calc.execute("f(x) := x ^ 2"));
-        let graph = calc.execute("plot f").unwrap();
-        assert!(matches!(&graph, Value::Graph(_)));
-        if let Value::Graph(graph) = graph {
-            let plot = graph
-                .plot(
-                    &Area::new(-100., -100., 100., 100.),
-                    &Area::new(0., 0., 80., 30.),
-                )
-                .unwrap();
-            assert!(!plot.points.is_empty());
+    /// Reads the numbers in `column` (counted from 1) of the CSV file `path`
+    /// into the list variable `name`. The same as the statement `import "path" column 1 as name`.
+    pub fn load_csv(&mut self, path: &str, column: usize, name: &str) -> Result<(), Error> {
+        let list = read_csv_file(path, column)?;
+        let statement = Statement::Import {
+            path: path.to_string(),
+            column,
+            sym: name.to_string(),
+        };
+        self.put_imported(name, list, &statement.to_string())
+    }
+
+    /// Like [`Calculator::load_csv`], but reads the CSV data from a string.
+    /// ```
+    /// use rust_expression::{Calculator, Value};
+    /// let mut c = Calculator::new();
+    /// c.load_csv_str("t,h\n0,1.5\n1,2.5", 2, "hs").unwrap();
+    /// assert_eq!(Ok(Value::List(vec![1.5, 2.5])), c.execute("hs"));
+    /// ```
+    pub fn load_csv_str(&mut self, content: &str, column: usize, name: &str) -> Result<(), Error> {
+        let list = read_csv_column(content, column)?;
+        let statement = format!("import <csv data> column {} as {}", column, name);
+        self.put_imported(name, list, &statement)
+    }
+
+    fn put_imported(
+        &mut self,
+        name: &str,
+        list: Vec<Number>,
+        statement: &str,
+    ) -> Result<(), Error> {
+        let before = self.env.clone();
+        self.env.put_list(name.to_string(), list)?;
+        if let Err(err) = self.limits.check_env(&self.env, 0) {
+            self.env = before;
+            return Err(err.into());
         }
+        self.record(&before, statement);
+        Ok(())
     }
-}
 
-pub const HELP_SUMMARY: &str = include_str!("../doc/summary.md");
+    /// The variables, functions, formulas and equations defined so far as JSON,
+    /// to be restored by [`Calculator::load_state`]. Build-ins and functions added by
+    /// [`Calculator::register_function`] are not saved.
+    /// ```
+    /// use rust_expression::{Calculator, Value};
+    /// let mut c = Calculator::new();
+    /// c.execute("f(x) := 2 * x + a").unwrap();
+    /// c.execute("a := 1").unwrap();
+    /// let state = c.save_state();
+    /// let mut restarted = Calculator::new();
+    /// restarted.load_state(&state).unwrap();
+    /// assert_eq!(Ok(Value::Number(7.0)), restarted.execute("f(3)"));
+    /// ```
+    #[cfg(feature = "persistence")]
+    pub fn save_state(&self) -> String {
+        state::save(&self.env)
+    }
+
+    /// Adds the definitions of a state saved by [`Calculator::save_state`], replacing
+    /// definitions of the same name. Nothing is changed if the state cannot be loaded.
+    #[cfg(feature = "persistence")]
+    pub fn load_state(&mut self, saved: &str) -> Result<(), Error> {
+        let before = self.env.clone();
+        let loaded = state::load(saved, &mut self.env)
+            .and_then(|_| Ok(self.limits.check_env(&self.env, 0)?));
+        if let Err(err) = loaded {
+            self.env = before;
+            return Err(err);
+        }
+        self.record(&before, "load state");
+        Ok(())
+    }
+
+    /// evaluates the texts of the labels of a plot
+    fn plot_labels(&self, labels: PlotLabels) -> Result<Labels, Error> {
+        let label = |text: Option<Text>| text.map(|text| calc_text(&text, &self.env)).transpose();
+        Ok(Labels {
+            title: label(labels.title)?,
+            xlabel: label(labels.xlabel)?,
+            ylabel: label(labels.ylabel)?,
+        })
+    }
+
+    /// adds the changes since `before` to the audit log, if auditing is enabled,
+    /// and passes them to the observers
+    fn record(&mut self, before: &TopLevelEnv, statement: &str) {
+        if let Some(audit) = &mut self.audit {
+            audit.extend(audit::mutations(
+                before,
+                &self.env,
+                statement,
+                SystemTime::now(),
+            ));
+        }
+        self.observers.notify(before, &self.env);
+    }
+
+    /// Registers `observer`, which is called for every variable assigned, function defined
+    /// or entry removed by a statement, after the statement succeeded.
+    /// Like registered functions, observers must be `Send + Sync`.
+    /// ```
+    /// use rust_expression::{Calculator, EnvEvent};
+    /// use std::sync::{Arc, Mutex};
+    /// let mut c = Calculator::new();
+    /// let events = Arc::new(Mutex::new(Vec::new()));
+    /// let log = events.clone();
+    /// c.on_change(move |event| log.lock().unwrap().push(event.clone()));
+    /// c.execute("a := 1").unwrap();
+    /// c.execute("f(x) := a * x").unwrap();
+    /// c.execute("unset a").unwrap();
+    /// assert_eq!(
+    ///     vec![
+    ///         EnvEvent::Assigned("a".to_string()),
+    ///         EnvEvent::Defined("f".to_string()),
+    ///         EnvEvent::Removed("a".to_string()),
+    ///     ],
+    ///     *events.lock().unwrap()
+    /// );
+    /// ```
+    pub fn on_change(&mut self, observer: impl Fn(&EnvEvent) + Send + Sync + 'static) {
+        self.observers.add(Box::new(observer));
+    }
+
+    /// Enables logging all changes of variables and functions, see [`Calculator::audit`].
+    /// Disabling auditing discards the log.
+    pub fn set_audit(&mut self, audit: bool) {
+        self.audit = audit.then(Vec::new);
+    }
+
+    /// Changes of variables and functions in order since auditing was enabled.
+    /// Every change names the statement causing it.
+    /// ```
+    /// use rust_expression::{Calculator, MutationKind};
+    /// let mut c = Calculator::new();
+    /// c.set_audit(true);
+    /// c.execute("a := 1").unwrap();
+    /// c.execute("a := a + 1").unwrap();
+    /// let log: Vec<_> = c
+    ///     .audit()
+    ///     .iter()
+    ///     .map(|mutation| (mutation.statement.as_str(), mutation.name.as_str(), mutation.kind))
+    ///     .collect();
+    /// assert_eq!(
+    ///     vec![
+    ///         ("a := 1", "a", MutationKind::Defined),
+    ///         ("a := a + 1", "a", MutationKind::Changed),
+    ///     ],
+    ///     log
+    /// );
+    /// ```
+    pub fn audit(&self) -> &[Mutation] {
+        self.audit.as_deref().unwrap_or_default()
+    }
+
+    /// Captures all variables and functions of the calculator.
+    /// Taking a snapshot is cheap, the snapshot shares its entries with the calculator.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            env: self.env.clone(),
+        }
+    }
+
+    /// Resets all variables and functions to the state of `snapshot`, which can be undone.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        let before = std::mem::replace(&mut self.env, snapshot.env.clone());
+        self.record(&before, "restore");
+        self.remember(before);
+    }
+
+    /// Reverts the last statement, which changed variables or functions, like the statement
+    /// `undo`. The last 32 changes are kept, a script executed by `run` is one change.
+    /// Returns `false` if there is nothing to undo.
+    /// ```
+    /// use rust_expression::{Calculator, Value};
+    /// let mut c = Calculator::new();
+    /// c.execute("a := 1").unwrap();
+    /// c.execute("a := 2").unwrap();
+    /// c.execute("2 * a").unwrap();
+    /// assert!(c.undo());
+    /// assert_eq!(Ok(Value::Number(1.0)), c.execute("a"));
+    /// assert!(c.undo());
+    /// assert!(!c.undo());
+    /// ```
+    pub fn undo(&mut self) -> bool {
+        let Some(env) = self.history.pop_back() else {
+            return false;
+        };
+        let before = std::mem::replace(&mut self.env, env);
+        self.changed = self.env.changed_vars(&before);
+        self.record(&before, "undo");
+        true
+    }
+
+    /// keeps `env` before a change for `undo`
+    fn remember(&mut self, env: TopLevelEnv) {
+        if self.history.len() == MAX_UNDO {
+            self.history.pop_front();
+        }
+        self.history.push_back(env);
+    }
+
+    /// Seeds the random number generator used by `randn()`, `randexp(lambda)` and `sample`.
+    /// Each calculator has its own generator.
+    pub fn seed_random(&mut self, seed: u64) {
+        *self.rng.get_mut().unwrap_or_else(PoisonError::into_inner) = Rng::new(seed);
+    }
+
+    /// Sets the precision of `solve ... for x in [a, b]`, which solves equations numerically,
+    /// if they are neither linear nor quadratic. The search stops when the interval containing
+    /// the solution is smaller than `tolerance`. The default `0` searches as precise as possible.
+    /// ```
+    /// use rust_expression::{Calculator, Number};
+    /// let mut c = Calculator::new();
+    /// c.set_solve_tolerance(0.01);
+    /// let x = c.execute("solve x ^ 3 = 2 for x in [0, 2]").unwrap().as_number().unwrap();
+    /// assert!((x - (2.0 as Number).cbrt()).abs() < 0.01);
+    /// ```
+    pub fn set_solve_tolerance(&mut self, tolerance: Number) {
+        self.solve_tolerance = tolerance;
+    }
+
+    /// Sets the precision of `integrate f from a to b` and `integrate expr for x in [a, b]`,
+    /// which integrate numerically. The integral is refined until its estimated error is below
+    /// `tolerance`. The default is `1e-8`.
+    /// ```
+    /// use rust_expression::Calculator;
+    /// let mut c = Calculator::new();
+    /// c.set_integration_tolerance(1e-3);
+    /// let area = c.execute("integrate sin(x) for x in [0, pi]").unwrap().as_number().unwrap();
+    /// assert!((area - 2.0).abs() < 1e-3);
+    /// ```
+    pub fn set_integration_tolerance(&mut self, tolerance: Number) {
+        self.integration_tolerance = Some(tolerance);
+    }
+
+    /// Switches the input syntax of [`Calculator::execute`] to postfix notation.
+    /// In this mode every line is an expression like `3 4 + 2 *`.
+    /// Names of known functions take their arguments from the stack, e.g. `2 sqrt`.
+    pub fn set_rpn(&mut self, rpn: bool) {
+        self.rpn = rpn;
+    }
+
+    /// Accepts LaTeX commands like `\frac{1}{2}`, `\sqrt{x}` or `2\cdot x` and Unicode
+    /// symbols like `π`, `×`, `÷`, `√` or `x²` in [`Calculator::execute`] (requires feature
+    /// `latex`). Lines, which are no plain statements, are parsed like
+    /// [`Calculator::execute_latex`].
+    /// ```
+    /// use rust_expression::{Calculator, Value};
+    /// let mut c = Calculator::new();
+    /// assert!(c.execute("\\frac{1}{2}").is_err());
+    /// c.set_math_input(true);
+    /// c.execute("f(x) := x² − 1").unwrap();
+    /// assert_eq!(Ok(Value::Number(3.5)), c.execute("f(2) × \\frac{1}{2} + √(4)"));
+    /// ```
+    #[cfg(feature = "latex")]
+    pub fn set_math_input(&mut self, math_input: bool) {
+        self.math_input = math_input;
+    }
+
+    /// In strict mode redefining a variable or function fails with
+    /// [`CalcError::AlreadyDefined`], unless the statement starts with `override`.
+    /// ```
+    /// use rust_expression::{Calculator, Value};
+    /// let mut c = Calculator::new();
+    /// c.set_strict(true);
+    /// c.execute("a := 1").unwrap();
+    /// assert!(c.execute("a := 2").is_err());
+    /// assert_eq!(Ok(Value::Void), c.execute("override a := 2"));
+    /// ```
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// By default a division by zero and undefined results like `sqrt(-1)` or `ln(0)` fail
+    /// with [`CalcError::DivisionByZero`] or [`CalcError::DomainError`].
+    /// With IEEE semantics they are `inf` or `NaN` as in floating point arithmetic.
+    /// ```
+    /// use rust_expression::{CalcError, Calculator, Number, Value};
+    /// let mut c = Calculator::new();
+    /// assert_eq!(Err(CalcError::DivisionByZero.into()), c.execute("1 / 0"));
+    /// c.set_ieee(true);
+    /// assert_eq!(Ok(Value::Number(Number::INFINITY)), c.execute("1 / 0"));
+    /// ```
+    pub fn set_ieee(&mut self, ieee: bool) {
+        self.env.set_ieee(ieee);
+    }
+
+    /// Numbers within `tolerance` are equal in comparisons like `if(a == b, ...)` and
+    /// coefficients within `tolerance` of `0` vanish in `solve ... for ...`. It is relative
+    /// to the larger magnitude, but absolute below `1`. The default `0` compares exactly.
+    /// ```
+    /// use rust_expression::{Calculator, Value};
+    /// let mut c = Calculator::new();
+    /// assert_eq!(Ok(Value::Number(0.0)), c.execute("if(1 - 0.9 == 0.1, 1, 0)"));
+    /// c.set_tolerance(1e-6);
+    /// assert_eq!(Ok(Value::Number(1.0)), c.execute("if(1 - 0.9 == 0.1, 1, 0)"));
+    /// ```
+    pub fn set_tolerance(&mut self, tolerance: Number) {
+        self.env.set_tolerance(tolerance);
+    }
+
+    /// Rounds numeric results to the fewest significant digits within the tolerance
+    /// of [`Calculator::set_tolerance`], so artifacts of binary floating point disappear.
+    /// ```
+    /// use rust_expression::{Calculator, Value};
+    /// let mut c = Calculator::new();
+    /// c.set_tolerance(1e-12);
+    /// c.set_round_results(true);
+    /// assert_eq!(Ok(Value::Number(0.3)), c.execute("0.1 + 0.2"));
+    /// ```
+    pub fn set_round_results(&mut self, round: bool) {
+        self.round_results = round;
+    }
+
+    /// Limits the depth of nested calls of custom functions, a deeper recursion fails with
+    /// [`CalcError::RecursionLimitExceeded`]. The default is 200.
+    /// ```
+    /// use rust_expression::{CalcError, Calculator, Value};
+    /// let mut c = Calculator::new();
+    /// c.execute("count(n) := if(n <= 0, 0, 1 + count(n - 1))").unwrap();
+    /// assert_eq!(Ok(Value::Number(100.0)), c.execute("count(100)"));
+    /// c.set_recursion_limit(50);
+    /// assert_eq!(
+    ///     Err(CalcError::RecursionLimitExceeded { name: "count".to_string(), limit: 50 }.into()),
+    ///     c.execute("count(100)")
+    /// );
+    /// ```
+    pub fn set_recursion_limit(&mut self, limit: usize) {
+        self.env.set_recursion_limit(limit);
+    }
+
+    /// With echo enabled an assignment returns the assigned value instead of [`Value::Void`].
+    /// ```
+    /// use rust_expression::{Calculator, Value};
+    /// let mut c = Calculator::new();
+    /// assert_eq!(Ok(Value::Void), c.execute("a := 6"));
+    /// c.set_echo(true);
+    /// assert_eq!(Ok(Value::Number(6.0)), c.execute("a := 2 * 3"));
+    /// ```
+    pub fn set_echo(&mut self, echo: bool) {
+        self.echo = echo;
+    }
+
+    /// In reactive mode an assignment like `y := x + 1` keeps its formula and `y` is
+    /// recomputed whenever `x` changes, like a cell of a spreadsheet.
+    /// An assignment outside of reactive mode replaces the formula by a fixed value.
+    /// ```
+    /// use rust_expression::{Calculator, Value};
+    /// let mut c = Calculator::new();
+    /// c.set_reactive(true);
+    /// c.execute("x := 1").unwrap();
+    /// c.execute("y := x + 1").unwrap();
+    /// c.execute("x := 5").unwrap();
+    /// assert!(c.changed_bindings().contains("y"));
+    /// assert_eq!(Ok(Value::Number(6.0)), c.execute("y"));
+    /// ```
+    pub fn set_reactive(&mut self, reactive: bool) {
+        self.reactive = reactive;
+    }
+
+    /// Names of the variables changed by the last executed statement,
+    /// including variables recomputed in reactive mode.
+    pub fn changed_bindings(&self) -> &BTreeSet<String> {
+        &self.changed
+    }
+
+    /// With declared-only mode assignments and plots referencing undefined variables or
+    /// functions fail with [`CalcError::UndefinedNames`] before evaluation starts.
+    /// ```
+    /// use rust_expression::{Calculator, CalcError, Error};
+    /// let mut c = Calculator::new();
+    /// c.set_declared_only(true);
+    /// assert_eq!(
+    ///     Err(Error::CalcError(CalcError::UndefinedNames(vec!["a".to_string(), "b".to_string()]))),
+    ///     c.execute("x := a * b + 1")
+    /// );
+    /// ```
+    pub fn set_declared_only(&mut self, declared_only: bool) {
+        self.declared_only = declared_only;
+    }
+
+    /// undefined names referenced by `st`, only checked for assignments and plots
+    fn undeclared_names(&self, st: &Statement) -> Vec<String> {
+        let names = match st {
+            Statement::Assignment { op, .. } => undefined_names(op, &self.env),
+            Statement::Plot { name, sweep, .. } => {
+                let mut names = undefined_names_of(name, &self.env);
+                if let Some(sweep) = sweep {
+                    let ops = [Some(&sweep.from), Some(&sweep.to), sweep.step.as_ref()];
+                    for op in ops.into_iter().flatten() {
+                        names.extend(undefined_names(op, &self.env));
+                    }
+                }
+                names
+            }
+            Statement::TextAssignment { text, .. } | Statement::TextExpression { text } => text
+                .parts
+                .iter()
+                .filter_map(|part| match part {
+                    TextPart::Symbol(sym) if !self.env.is_bound(sym) => Some(sym.clone()),
+                    _ => None,
+                })
+                .collect(),
+            _ => BTreeSet::new(),
+        };
+        names.into_iter().collect()
+    }
+
+    /// name defined by `st`, if it is already defined
+    fn redefined_name<'a>(&self, st: &'a Statement) -> Option<&'a str> {
+        let is_var = |sym: &str| {
+            self.env.get(sym).is_some()
+                || self.env.get_list(sym).is_some()
+                || self.env.get_text(sym).is_some()
+        };
+        match st {
+            Statement::Assignment { sym, .. }
+            | Statement::TextAssignment { sym, .. }
+            | Statement::Define { sym, .. }
+            | Statement::Import { sym, .. }
+                if is_var(sym) || self.env.formula(sym).is_some() =>
+            {
+                Some(sym)
+            }
+            Statement::Function { name, .. }
+            | Statement::FitLinear {
+                name: Some(name), ..
+            } => match self.env.get_fun(name) {
+                // the recurrence of a sequence with only initial values is no redefinition
+                Some(Function::Sequence(Sequence { body: None, .. })) | None => None,
+                // neither is a further clause of an incomplete definition
+                Some(Function::Piecewise(existing))
+                    if matches!(st, Statement::Function { fun: Function::Piecewise(fun), .. }
+                        if fun.args == existing.args && !existing.is_complete()) =>
+                {
+                    None
+                }
+                Some(_) => Some(name),
+            },
+            Statement::SequenceValue { name, index, .. } => match self.env.get_fun(name) {
+                Some(Function::Sequence(seq)) if seq.values.contains_key(index) => Some(name),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Executes a math expression or definition written in LaTeX (requires feature `latex`).
+    /// Known functions are called like `f(2)`, other names are multiplied like `x(x + 1)`.
+    /// ```
+    /// use rust_expression::{Calculator, Value};
+    /// let mut c = Calculator::new();
+    /// assert_eq!(Ok(Value::Number(3.5)), c.execute_latex("\\frac{1}{2} + \\sqrt{9}"));
+    /// ```
+    #[cfg(feature = "latex")]
+    pub fn execute_latex(&mut self, line: &str) -> Result<Value, Error> {
+        let st = crate::latex::parse_latex(line, &self.env)?;
+        self.execute_statement(st)
+    }
+
+    fn execute_statement(&mut self, st: Statement) -> Result<Value, Error> {
+        if let (Statement::Undo, true) = (&st, self.running.is_empty()) {
+            return match self.undo() {
+                true => Ok(Value::Void),
+                false => Err(CalcError::NothingToUndo.into()),
+            };
+        }
+        // the statements of a script are recorded one by one
+        let scripted = matches!(st, Statement::Run { .. });
+        let statement = (self.audit.is_some() && !scripted).then(|| st.to_string());
+        // an overriding clause starts a new definition instead of adding a clause
+        let mut restarted = None;
+        let st = match st {
+            Statement::Override { st } => {
+                if let Statement::Function {
+                    name,
+                    fun: Function::Piecewise(_),
+                } = st.as_ref()
+                {
+                    restarted = Some(name.clone());
+                }
+                *st
+            }
+            st => match self.redefined_name(&st) {
+                Some(name) if self.strict => {
+                    return Err(CalcError::AlreadyDefined(name.to_string()).into())
+                }
+                _ => st,
+            },
+        };
+        if self.declared_only {
+            let names = self.undeclared_names(&st);
+            if !names.is_empty() {
+                return Err(CalcError::UndefinedNames(names).into());
+            }
+        }
+        self.limits.check_statement(&st)?;
+        let before = self.env.clone();
+        if let Some(name) = restarted {
+            self.env.remove_fun(&name);
+        }
+        let limits = self.limits;
+        let rng = *self.rng.get_mut().unwrap_or_else(PoisonError::into_inner);
+        let (((result, recomputed), rng), exceeded) = budget::limited(&limits, || {
+            with_generator(rng, || {
+                let result = self.apply_statement(st);
+                let mut changed = self.env.changed_vars(&before);
+                changed.extend(self.env.changed_funs(&before));
+                (result, reactive::recompute(&mut self.env, &changed))
+            })
+        });
+        *self.rng.get_mut().unwrap_or_else(PoisonError::into_inner) = rng;
+        let returned = match &result {
+            Ok(Value::List(list)) | Ok(Value::Solutions(list)) => list.len(),
+            Ok(Value::Text(text)) => text.len(),
+            _ => 0,
+        };
+        let checked = match exceeded {
+            Some(exceeded) => Err(exceeded),
+            None => self.limits.check_env(&self.env, returned),
+        };
+        if let Err(err) = checked {
+            self.env = before;
+            self.changed.clear();
+            return Err(err.into());
+        }
+        self.changed = self.env.changed_vars(&before);
+        if !scripted {
+            self.record(&before, statement.as_deref().unwrap_or_default());
+        }
+        // the statements of a script are undone together with `run`
+        let changed = !self.changed.is_empty() || !self.env.changed_funs(&before).is_empty();
+        if changed && self.running.is_empty() {
+            self.remember(before);
+        }
+        let mut value = result?;
+        recomputed?;
+        if self.round_results {
+            value = rounded(value, self.env.tolerance());
+        }
+        if let Some(num) = value.as_number() {
+            self.env.put_answer(num);
+        }
+        Ok(value)
+    }
+
+    fn bounds(&self, bracket: &Bracket) -> Result<(Number, Number), CalcError> {
+        Ok((
+            calc_operand(&bracket.from, &self.env)?,
+            calc_operand(&bracket.to, &self.env)?,
+        ))
+    }
+
+    fn integration_tolerance(&self) -> Number {
+        self.integration_tolerance
+            .unwrap_or(DEFAULT_INTEGRATION_TOLERANCE)
+    }
+
+    /// value of an expression, which never changes the environment
+    fn expression_value(&self, op: &Operand) -> Result<Value, Error> {
+        match calc_array(op, &self.env) {
+            Some(array) => Ok(match array? {
+                Array::Number(num) => Value::Number(num),
+                Array::Vector(vector) => Value::List(vector),
+                Array::Matrix(matrix) => Value::Matrix(matrix),
+            }),
+            None => match as_text(op, &self.env) {
+                Some(text) => Ok(Value::Text(calc_text(&text, &self.env)?)),
+                None if has_units(op, &self.env) => {
+                    let quantity = calc_quantity(op, &self.env)?;
+                    Ok(if quantity.unit.is_none() {
+                        Value::Number(quantity.value)
+                    } else {
+                        Value::Quantity(quantity)
+                    })
+                }
+                None => Ok(Value::Number(calc_operand(op, &self.env)?)),
+            },
+        }
+    }
+
+    fn apply_statement(&mut self, st: Statement) -> Result<Value, Error> {
+        match st {
+            Statement::Expression { op } => self.expression_value(&op),
+            Statement::Assignment { sym, op } => {
+                if let Some(text) = as_text(&op, &self.env) {
+                    return self.apply_statement(Statement::TextAssignment { sym, text });
+                }
+                if self.reactive {
+                    reactive::check_cycle(&sym, &op, &self.env)?;
+                }
+                let value = match calc_array(&op, &self.env).transpose()? {
+                    Some(Array::Number(num)) => {
+                        self.env.put(sym.clone(), num)?;
+                        Value::Number(num)
+                    }
+                    Some(Array::Vector(list)) => {
+                        self.env.put_list(sym.clone(), list.clone())?;
+                        Value::List(list)
+                    }
+                    Some(Array::Matrix(matrix)) => {
+                        self.env.put_matrix(sym.clone(), matrix.clone())?;
+                        Value::Matrix(matrix)
+                    }
+                    None => {
+                        let num = if has_units(&op, &self.env) {
+                            // quantities without unit like `1 km / 1 m` are numbers
+                            let quantity = calc_quantity(&op, &self.env)?;
+                            if !quantity.unit.is_none() {
+                                return Err(UnitError::UnitInVariable {
+                                    sym,
+                                    unit: quantity.unit.to_string(),
+                                }
+                                .into());
+                            }
+                            quantity.value
+                        } else {
+                            calc_operand(&op, &self.env)?
+                        };
+                        self.env.put(sym.clone(), num)?;
+                        Value::Number(num)
+                    }
+                };
+                if self.reactive {
+                    self.env.put_formula(sym, op);
+                } else {
+                    self.env.remove_formula(&sym);
+                }
+                Ok(if self.echo { value } else { Value::Void })
+            }
+            Statement::Define { sym, op } => {
+                reactive::check_cycle(&sym, &op, &self.env)?;
+                self.env.put_formula(sym.clone(), op);
+                // the formula may refer to variables defined later
+                let _ = reactive::evaluate(&sym, &mut self.env);
+                Ok(Value::Void)
+            }
+            Statement::SolveFor {
+                lhs,
+                rhs,
+                sym,
+                bracket,
+            } => {
+                let solution = match bracket {
+                    Some(bracket) => {
+                        let (from, to) = self.bounds(&bracket)?;
+                        if !from.is_finite() || !to.is_finite() || from >= to {
+                            return Err(Error::SolverError(SolverError::InvalidInterval {
+                                variable: sym,
+                                from: from.to_string(),
+                                to: to.to_string(),
+                            }));
+                        }
+                        let tolerance = self.solve_tolerance;
+                        solve_in(&lhs, &rhs, &sym, (from, to), tolerance, &self.env)?
+                    }
+                    None => solve_for(&lhs, &rhs, &sym, &self.env)?,
+                };
+                Ok(solved(sym, solution))
+            }
+            Statement::SolveInequality { lhs, cmp, rhs, sym } => {
+                let intervals = solve_inequality(&lhs, cmp, &rhs, &sym, &self.env)?;
+                Ok(Value::Intervals {
+                    variable: sym,
+                    intervals,
+                })
+            }
+            Statement::Integrate { name, bracket } => {
+                let bounds = self.bounds(&bracket)?;
+                let tolerance = self.integration_tolerance();
+                Ok(Value::Number(integrate_function(
+                    &name, bounds, tolerance, &self.env,
+                )?))
+            }
+            Statement::IntegrateExpression { op, sym, bracket } => {
+                let bounds = self.bounds(&bracket)?;
+                let tolerance = self.integration_tolerance();
+                Ok(Value::Number(integrate_expression(
+                    &op, &sym, bounds, tolerance, &self.env,
+                )?))
+            }
+            Statement::Store { name, lhs, rhs } => {
+                self.env.put_equation(name, Equation { lhs, rhs });
+                Ok(Value::Void)
+            }
+            Statement::SolveStored { name, sym, given } => {
+                let equation = self
+                    .env
+                    .equation(&name)
+                    .ok_or(CalcError::UnknownEquation(name))?;
+                let given = given
+                    .iter()
+                    .map(|(sym, op)| Ok((sym.as_str(), calc_operand::<Number>(op, &self.env)?)))
+                    .collect::<Result<Vec<_>, CalcError>>()?;
+                let scope = ScopedEnv::new(&self.env, given);
+                let solution = solve_for(&equation.lhs, &equation.rhs, &sym, &scope)?;
+                Ok(solved(sym, solution))
+            }
+            Statement::Function { name, fun } => {
+                self.env.put_fun(name, fun);
+                Ok(Value::Void)
+            }
+            Statement::SequenceValue { name, index, op } => {
+                let value = calc_operand(&op, &self.env)?;
+                self.env.put_sequence_value(name, index, value)?;
+                Ok(Value::Void)
+            }
+            Statement::Plot {
+                name,
+                sweep,
+                labels,
+            } => {
+                let labels = self.plot_labels(labels)?;
+                match sweep {
+                    Some(sweep) => {
+                        let step = match &sweep.step {
+                            Some(step) => calc_operand(step, &self.env)?,
+                            None => 1.0,
+                        };
+                        let range = (
+                            calc_operand(&sweep.from, &self.env)?,
+                            calc_operand(&sweep.to, &self.env)?,
+                            step,
+                        );
+                        Ok(Value::PlotSequence(
+                            PlotSequence::new(
+                                &name,
+                                &sweep.args,
+                                &sweep.param,
+                                range,
+                                &labels,
+                                &self.env,
+                            )?
+                            .with_limits(self.limits),
+                        ))
+                    }
+                    None => {
+                        let graph = Graph::new(&name, &self.env)?
+                            .with_labels(labels)
+                            .with_limits(self.limits);
+                        Ok(match graph.arity() {
+                            2 => Value::Surface(Surface::new(graph)),
+                            _ => Value::Graph(graph),
+                        })
+                    }
+                }
+            }
+            Statement::PlotExpression { op, labels } => {
+                let labels = self.plot_labels(labels)?;
+                Ok(Value::Graph(
+                    Graph::of_expression(&op, &self.env)?
+                        .with_labels(labels)
+                        .with_limits(self.limits),
+                ))
+            }
+            Statement::PlotPolar {
+                op,
+                bracket,
+                labels,
+            } => {
+                let labels = self.plot_labels(labels)?;
+                let angles = match bracket {
+                    Some(bracket) => (
+                        calc_operand(&bracket.from, &self.env)?,
+                        calc_operand(&bracket.to, &self.env)?,
+                    ),
+                    None => (0.0, Number::from_f64(std::f64::consts::TAU)),
+                };
+                Ok(Value::Graph(
+                    Graph::polar(&op, angles, &self.env)?
+                        .with_labels(labels)
+                        .with_limits(self.limits),
+                ))
+            }
+            Statement::PlotImplicit { lhs, rhs, labels } => {
+                let labels = self.plot_labels(labels)?;
+                Ok(Value::Graph(
+                    Graph::implicit(&lhs, rhs.as_ref(), &self.env)?
+                        .with_labels(labels)
+                        .with_limits(self.limits),
+                ))
+            }
+            Statement::TextAssignment { sym, text } => {
+                let text = calc_text(&text, &self.env)?;
+                self.env.put_text(sym.clone(), text.clone())?;
+                // texts are not recomputed in reactive mode
+                self.env.remove_formula(&sym);
+                Ok(if self.echo {
+                    Value::Text(text)
+                } else {
+                    Value::Void
+                })
+            }
+            Statement::TextExpression { text } => Ok(Value::Text(calc_text(&text, &self.env)?)),
+            Statement::Equivalent { lhs, rhs } => {
+                Ok(Value::Equivalence(equivalent(&lhs, &rhs, &self.env)))
+            }
+            Statement::Simplify { op } => Ok(Value::Expression(Rewriter::default().rewrite(&op))),
+            Statement::Import { path, column, sym } => {
+                self.check_file_access(&path)?;
+                self.env.put_list(sym, read_csv_file(&path, column)?)?;
+                Ok(Value::Void)
+            }
+            Statement::Run { path } => {
+                self.run_file(&path)?;
+                Ok(Value::Void)
+            }
+            Statement::List => Ok(Value::Listing(listing::listing(&self.env))),
+            Statement::Empty => Ok(Value::Void),
+            Statement::Unset { name } => {
+                self.env.unset(&name)?;
+                Ok(Value::Void)
+            }
+            Statement::Override { .. } => Err(ParserError::InvalidStatement(
+                "`override` can only be used once".to_string(),
+            )
+            .into()),
+            // outside of scripts it is handled before
+            Statement::Undo => Err(ParserError::InvalidStatement(
+                "`undo` cannot be used in a script".to_string(),
+            )
+            .into()),
+            Statement::FitLinear { xs, ys, name } => {
+                let list = |sym: &str| {
+                    self.env
+                        .get_list(sym)
+                        .ok_or_else(|| FitError::NotAList(sym.to_string()))
+                };
+                let fit = fit_linear(list(&xs)?, list(&ys)?)?;
+                if let Some(name) = name {
+                    self.env.put_fun(name, fit.function("x"));
+                }
+                Ok(Value::Fit(fit))
+            }
+        }
+    }
+}
+
+/// Parses `line` in the plain syntax or else as LaTeX math, the syntax error which got
+/// further is reported, see [`Calculator::set_math_input`].
+#[cfg(feature = "latex")]
+fn parse_math(line: &str, env: &TopLevelEnv) -> Result<Statement, ParserError> {
+    parse(line).or_else(|plain| {
+        crate::latex::parse_latex(line, env).map_err(|latex| match (&plain, &latex) {
+            (ParserError::Syntax { span: lhs, .. }, ParserError::Syntax { span: rhs, .. })
+                if lhs.start > rhs.start =>
+            {
+                plain
+            }
+            _ => latex,
+        })
+    })
+}
+
+/// Renders a statement as presentation MathML, e.g. for pretty display in web frontends.
+/// ```
+/// use rust_expression::to_mathml;
+/// assert_eq!(
+///     Ok("<math xmlns=\"http://www.w3.org/1998/Math/MathML\"><mrow><mfrac><mrow><mn>1</mn></mrow><mrow><mi>x</mi></mrow></mfrac></mrow></math>".to_string()),
+///     to_mathml("1 / x")
+/// );
+/// ```
+pub fn to_mathml(line: &str) -> Result<String, Error> {
+    Ok(crate::mathml::statement(&parse(line)?))
+}
+
+/// Size metrics of a statement, e.g. to warn about pathological inputs before executing them.
+/// ```
+/// use rust_expression::expression_stats;
+/// let stats = expression_stats("f(x) := x ^ 2 + 1").unwrap();
+/// assert_eq!(5, stats.nodes);
+/// assert_eq!(3, stats.depth);
+/// ```
+pub fn expression_stats(line: &str) -> Result<ExpressionStats, Error> {
+    Ok(ExpressionStats::of_statement(&parse(line)?))
+}
+
+pub const HELP_SUMMARY: &str = include_str!("../doc/summary.md");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Operation;
+    use crate::number::TEST_TOLERANCE;
+    use assert_approx_eq::assert_approx_eq;
+    use std::time::Duration;
+
+    impl Calculator {
+        fn changed_bindings_after(&mut self, line: &str) -> Vec<String> {
+            self.execute(line).unwrap();
+            self.changed_bindings().iter().cloned().collect()
+        }
+    }
+
+    /// `line` evaluates to `expected` up to the rounding errors of a few operations
+    fn assert_number(calc: &mut Calculator, line: &str, expected: Number) {
+        let actual = calc.execute(line).unwrap().as_number().unwrap();
+        assert!(
+            nearly_equal(expected, actual, 16.0 * Number::EPSILON),
+            "{} = {}",
+            line,
+            actual
+        );
+    }
+
+    #[test]
+    fn simple_calc() {
+        let mut calc = Calculator::new();
+        assert_eq!(Ok(Value::Number(3.0)), calc.execute("1 + 2"));
+    }
+
+    #[test]
+    fn simple_assign() {
+        let mut calc = Calculator::new();
+        assert_eq!(Ok(Value::Void), calc.execute("a := 1"));
+        assert_eq!(Ok(Value::Number(1.0)), calc.execute("a"));
+    }
+
+    #[test]
+    fn simple_function() {
+        let mut calc = Calculator::new();
+        assert_eq!(Ok(Value::Void), calc.execute("fun(x, y) := y - x"));
+        assert_eq!(
+            Ok(Value::Number(20.0)),
+            calc.execute("fun(1 + 2, 3 * 9) - 4")
+        );
+    }
+
+    #[test]
+    fn simple_solve_for() {
+        let mut calc = Calculator::new();
+        assert_eq!(
+            Ok(Value::Solved {
+                variable: "y".to_string(),
+                value: 4.0
+            }),
+            calc.execute("solve 3 * y - 2 = y + 6 for y")
+        );
+    }
+
+    #[test]
+    fn numeric_solve_for() {
+        let mut calc = Calculator::new();
+        let value = calc.execute("solve cos(x) = x for x in [0, 1]").unwrap();
+        let x = value.as_number().unwrap();
+        assert!((x.cos() - x).abs() < 1e-12);
+        assert!(matches!(
+            calc.execute("solve cos(x) = x for x"),
+            Err(Error::SolverError(SolverError::VariableInFunctionCall(..)))
+        ));
+        assert!(matches!(
+            calc.execute("solve cos(x) = 2 for x in [0, pi]"),
+            Err(Error::SolverError(SolverError::NoSignChange { .. }))
+        ));
+        assert_eq!(
+            Err(Error::SolverError(SolverError::InvalidInterval {
+                variable: "x".to_string(),
+                from: "1".to_string(),
+                to: "0".to_string(),
+            })),
+            calc.execute("solve cos(x) = x for x in [1, 0]")
+        );
+    }
+
+    #[test]
+    fn integrate() {
+        let mut calc = Calculator::new();
+        calc.execute("f(x) := 3 * x ^ 2").unwrap();
+        assert_eq!(
+            Ok(Value::Number(8.0)),
+            calc.execute("integrate f from 0 to 2")
+        );
+        calc.execute("a := 2").unwrap();
+        assert_eq!(
+            Ok(Value::Number(-8.0)),
+            calc.execute("integrate 3 t ^ 2 for t in [a, 0]")
+        );
+        let area = calc.execute("integrate sin from 0 to pi").unwrap();
+        assert!((area.as_number().unwrap() - 2.0).abs() < 1e-8);
+        calc.set_integration_tolerance(0.1);
+        let area = calc.execute("integrate sin from 0 to pi").unwrap();
+        assert!((area.as_number().unwrap() - 2.0).abs() < 0.1);
+        assert_eq!(Ok(Value::Number(2.0)), calc.execute("a"));
+        assert!(matches!(
+            calc.execute("integrate 1 / x for x in [0, 1]"),
+            Err(Error::IntegrationError(IntegrationError::CalcError(
+                CalcError::DivisionByZero
+            )))
+        ));
+        assert_eq!(
+            "N0001",
+            calc.execute("integrate f from 0 to 10 ^ 400")
+                .unwrap_err()
+                .diagnostic()
+                .code
+        );
+    }
+
+    #[test]
+    fn quadratic_solve_for() {
+        let mut calc = Calculator::new();
+        calc.execute("c := 4").unwrap();
+        let value = calc.execute("solve x^2 - c = 0 for x").unwrap();
+        assert_eq!(Value::Solutions(vec![-2.0, 2.0]), value);
+        assert_eq!(ValueKind::Solutions, value.kind());
+        assert_eq!(Some(&[-2.0, 2.0][..]), value.as_list());
+        assert_eq!(
+            Ok(Value::Solutions(vec![])),
+            calc.execute("solve x^2 + 1 = 0 for x")
+        );
+        calc.execute("store area: a = s^2").unwrap();
+        assert_eq!(
+            Ok(Value::Solutions(vec![-3.0, 3.0])),
+            calc.execute("solve area for s given a := 9")
+        );
+    }
+
+    #[test]
+    fn negation() {
+        let mut calc = Calculator::new();
+        calc.execute("x := 2").unwrap();
+        assert_eq!(Ok(Value::Number(1.0)), calc.execute("-x + 3"));
+        assert_eq!(Ok(Value::Number(-6.0)), calc.execute("2 * -(x + 1)"));
+        assert_eq!(Ok(Value::Number(-2.0)), calc.execute("+(-x)"));
+        assert_eq!(Ok(Value::Number(-4.0)), calc.execute("-x ^ 2"));
+        assert_eq!(Ok(Value::Number(-4.0)), calc.execute("-2 ^ 2"));
+        assert_eq!(Ok(Value::Number(4.0)), calc.execute("(-2) ^ 2"));
+        assert_eq!(Ok(Value::Number(-6.0)), calc.execute("-3!"));
+        assert_eq!(
+            Some(-4.0),
+            calc.execute("solve -y = 4 for y").unwrap().as_number()
+        );
+    }
+
+    #[test]
+    fn implicit_multiplication() {
+        let mut calc = Calculator::new();
+        calc.execute("x := 3").unwrap();
+        assert_eq!(Ok(Value::Number(18.0)), calc.execute("2x^2"));
+        assert_eq!(Ok(Value::Number(12.0)), calc.execute("3(x + 1)"));
+        assert_eq!(Ok(Value::Number(0.0)), calc.execute("2 sin(0)"));
+        assert_eq!(
+            Some(2.0),
+            calc.execute("solve 2y = 4 for y").unwrap().as_number()
+        );
+    }
+
+    #[test]
+    fn rpn_mode() {
+        let mut calc = Calculator::new();
+        assert_eq!(Ok(Value::Void), calc.execute("a := 6"));
+        calc.set_rpn(true);
+        assert_eq!(Ok(Value::Number(3.0)), calc.execute("a 9 sqrt - 1 *"));
+        calc.set_rpn(false);
+        assert_eq!(Ok(Value::Number(7.0)), calc.execute("rpn a 1 +"));
+        assert_eq!(Ok(Value::Number(7.0)), calc.execute("a + 1"));
+    }
+
+    #[test]
+    #[cfg(feature = "latex")]
+    fn math_input() {
+        let mut calc = Calculator::new();
+        assert!(calc.execute("2 × 3").is_err());
+        assert!(calc.execute("\\frac{1}{2}").is_err());
+        calc.set_math_input(true);
+        assert_eq!(Ok(Value::Number(6.0)), calc.execute("2 × 3"));
+        let err = calc.execute("√x × × 2").unwrap_err();
+        assert_eq!(Some(Span { start: 8, end: 10 }), err.diagnostic().span);
+        assert_eq!(Ok(Value::Number(0.5)), calc.execute("\\frac{1}{2}"));
+        assert_eq!(Ok(Value::Number(2.0)), calc.execute("\\sqrt[3]{8}"));
+        assert_eq!(Ok(Value::Number(2.0)), calc.execute("cos(2π) ÷ 2 × 4"));
+        calc.execute("r := 2").unwrap();
+        assert_eq!(calc.execute("pi * r ^ 2"), calc.execute("\\pi \\cdot r²"));
+        assert_eq!(Ok(Value::Number(-0.001)), calc.execute("−10⁻³"));
+        assert_eq!(Ok(Value::Number(3.0)), calc.execute("|-3|"));
+        assert_eq!(calc.execute_latex("-2^2"), calc.execute("-2^2"));
+        calc.execute("g(x) := \\frac{x}{2}").unwrap();
+        assert_eq!(Ok(Value::Number(1.5)), calc.execute("g(3)"));
+        calc.execute("title := \"x² − 1\"").unwrap();
+        assert_eq!(Ok(Value::Text("x² − 1".to_string())), calc.execute("title"));
+        let err = calc.execute("π × × 2").unwrap_err();
+        assert_eq!(Some(Span { start: 6, end: 8 }), err.diagnostic().span);
+        assert!(err.to_string().contains("`×`"));
+    }
+
+    #[test]
+    fn import_csv() {
+        let path = std::env::temp_dir().join("rust_expression_import_csv.csv");
+        std::fs::write(&path, "x;y\n1;2\n3;4\n").unwrap();
+        let mut calc = Calculator::new();
+        assert_eq!(
+            Ok(Value::Void),
+            calc.execute(&format!("import \"{}\" column 2 as ys", path.display()))
+        );
+        assert_eq!(Ok(Value::List(vec![2.0, 4.0])), calc.execute("ys"));
+        assert_eq!(Ok(Value::Void), calc.execute("zs := ys"));
+        assert_eq!(Ok(Value::List(vec![2.0, 4.0])), calc.execute("zs"));
+        assert_eq!(Ok(Value::List(vec![3.0, 5.0])), calc.execute("ys + 1"));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    #[cfg(not(feature = "embedded"))]
+    fn interpolate_lists() {
+        let mut calc = Calculator::new();
+        calc.load_csv_str("0,10\n1,20\n3,40", 1, "xs").unwrap();
+        calc.load_csv_str("0,10\n1,20\n3,40", 2, "ys").unwrap();
+        assert_eq!(
+            Ok(Value::Number(31.0)),
+            calc.execute("1 + interp(xs, ys, 2)")
+        );
+        assert_eq!(
+            Err(Error::CalcError(CalcError::InvalidArguments {
+                name: "interp".to_string(),
+                reason: "xs must be a list".to_string()
+            })),
+            calc.execute("interp(1, ys, 2)")
+        );
+    }
+
+    #[cfg(feature = "fft")]
+    #[test]
+    fn fft_of_list() {
+        let mut calc = Calculator::new();
+        calc.load_csv_str("1\n0\n-1\n0", 1, "xs").unwrap();
+        assert_eq!(
+            Ok(Value::List(vec![0.0, 2.0, 0.0, 2.0])),
+            calc.execute("fft(xs)")
+        );
+        assert_eq!(Ok(Value::Void), calc.execute("m := fft(xs)"));
+        assert_eq!(Ok(Value::Void), calc.execute("p := fft_phase(xs)"));
+        assert_eq!(
+            Ok(Value::List(vec![1.0, 0.0, -1.0, 0.0])),
+            calc.execute("ifft(m, p)")
+        );
+    }
+
+    #[cfg(feature = "finance")]
+    #[test]
+    fn finance_functions() {
+        let mut calc = Calculator::new();
+        calc.load_csv_str("-100\n110", 1, "cf").unwrap();
+        assert_eq!(Ok(Value::Number(10.0)), calc.execute("npv(0, cf)"));
+        assert_eq!(Ok(Value::Number(-10.0)), calc.execute("pmt(0, 10, 100)"));
+        assert_eq!(Ok(Value::Number(-300.0)), calc.execute("fv(0, 3, 100, 0)"));
+    }
+
+    #[test]
+    fn sequence_recurrence() {
+        let mut calc = Calculator::new();
+        assert_eq!(Ok(Value::Void), calc.execute("a(0) := 1"));
+        assert_eq!(Ok(Value::Void), calc.execute("a(n) := 2 * a(n - 1)"));
+        assert_eq!(Ok(Value::Number(1024.0)), calc.execute("a(10)"));
+        // memoized, so deep recurrences are cheap and do not overflow the stack
+        assert_eq!(Ok(Value::Void), calc.execute("b(n) := b(n - 1) + 1"));
+        assert_eq!(Ok(Value::Void), calc.execute("b(0) := 0"));
+        assert_eq!(Ok(Value::Number(50000.0)), calc.execute("b(50000)"));
+    }
+
+    #[test]
+    fn sequence_errors() {
+        let mut calc = Calculator::new();
+        calc.execute("a(n) := a(n - 1) + a(n - 2)").unwrap();
+        calc.execute("a(1) := 1").unwrap();
+        assert_eq!(
+            Err(Error::CalcError(CalcError::InvalidSequenceIndex {
+                name: "a".to_string(),
+                index: "1.5".to_string()
+            })),
+            calc.execute("a(1.5)")
+        );
+        assert_eq!(
+            Err(Error::CalcError(CalcError::InvalidSequenceIndex {
+                name: "a".to_string(),
+                index: "-1".to_string()
+            })),
+            calc.execute("a(-1)")
+        );
+        assert_eq!(
+            Err(Error::CalcError(CalcError::MissingSequenceValue {
+                name: "a".to_string(),
+                index: 0
+            })),
+            calc.execute("a(2)")
+        );
+        assert_eq!(
+            Err(Error::CalcError(CalcError::MissingSequenceValue {
+                name: "c".to_string(),
+                index: 2
+            })),
+            calc.execute("c(1) := 1").and_then(|_| calc.execute("c(2)"))
+        );
+        assert_eq!(
+            Err(Error::CalcError(CalcError::NotASequence("sin".to_string()))),
+            calc.execute("sin(0) := 1")
+        );
+    }
+
+    #[test]
+    fn random_sample() {
+        let mut calc = Calculator::new();
+        calc.seed_random(1);
+        let first = calc.execute("sample(randexp(2) + 1, 5)");
+        match &first {
+            Ok(Value::List(list)) => assert!(list.len() == 5 && list.iter().all(|x| *x >= 1.0)),
+            _ => panic!("expected a list, but got {:?}", first),
+        }
+        calc.seed_random(1);
+        assert_eq!(first, calc.execute("sample(randexp(2) + 1, 5)"));
+        assert_eq!(
+            Err(Error::CalcError(CalcError::UnexpectedList(
+                "sample".to_string()
+            ))),
+            calc.execute("sin(sample(randn(), 2))")
+        );
+        assert!(calc.execute("sample(randn(), -1)").is_err());
+    }
+
+    #[test]
+    fn random_per_calculator() {
+        let mut calc = Calculator::new();
+        let mut other = Calculator::new();
+        calc.seed_random(7);
+        other.seed_random(7);
+        for _ in 0..3 {
+            let expected = calc.execute("randn()").unwrap();
+            assert_eq!(Ok(expected), other.execute("randn()"));
+        }
+        assert_eq!(calc.evaluate("randexp(1)"), other.evaluate("randexp(1)"));
+        assert_ne!(calc.evaluate("randn()"), calc.evaluate("randn()"));
+    }
+
+    #[test]
+    #[cfg(not(feature = "embedded"))]
+    fn linear_algebra_on_lists() {
+        let mut calc = Calculator::new();
+        calc.load_csv_str("2\n1\n1\n3", 1, "a").unwrap();
+        calc.load_csv_str("3\n5", 1, "b").unwrap();
+        assert_eq!(Ok(Value::Number(5.0)), calc.execute("det(a)"));
+        assert_eq!(
+            Ok(Value::List(vec![0.8, 1.4])),
+            calc.execute("linsolve(a, b)")
+        );
+        assert_eq!(
+            Ok(Value::List(vec![2.0, 1.0, 1.0, 3.0])),
+            calc.execute("transpose(a, 2)")
+        );
+        assert_number(&mut calc, "det([1, 2; 3, 4])", -2.0);
+    }
+
+    #[test]
+    fn evaluate_without_changes() {
+        let mut calc = Calculator::new();
+        calc.execute("a := 2").unwrap();
+        calc.execute("xs := [1, 2]").unwrap();
+        let calc = &calc;
+        assert_eq!(Ok(5.0), calc.evaluate("a ^ 2 + 1"));
+        assert_eq!(Ok(3.0), calc.evaluate("sum(xs)"));
+        assert_eq!(Ok(2.0), calc.evaluate("3 km / 1500 m"));
+        for line in [
+            "a := 3",
+            "f(x) := x",
+            "define b = a",
+            "xs",
+            "plot sin",
+            "2 km",
+        ] {
+            assert_eq!(
+                Err(Error::CalcError(CalcError::NotAnExpression(
+                    line.to_string()
+                ))),
+                calc.evaluate(line)
+            );
+        }
+        assert_eq!(Ok(2.0), calc.evaluate("a"));
+        assert!(matches!(calc.evaluate("b"), Err(Error::CalcError(_))));
+    }
+
+    #[test]
+    fn eval_ast_like_parsed_line() {
+        use crate::ast::{Operand, Statement};
+        let mut calc = Calculator::new();
+        let v = Operand::matrix(vec![vec![Operand::number(1.0), Operand::number(2.0)]]);
+        calc.eval_ast(&Statement::assignment("v", v)).unwrap();
+        let op = Operand::symbol("v") * Operand::symbol("v") % Operand::number(3.0);
+        assert_eq!(
+            Ok(Value::Number(2.0)),
+            calc.eval_ast(&Statement::expression(op))
+        );
+        assert_eq!(
+            calc.execute("2 * v"),
+            calc.eval_ast(&parse("2 * v").unwrap())
+        );
+        assert_eq!(
+            Err(Error::CalcError(CalcError::UnknownSymbol {
+                name: "w".to_string(),
+                candidates: vec![],
+            })),
+            calc.eval_ast(&Statement::expression(Operand::symbol("w")))
+        );
+    }
+
+    #[test]
+    fn simplify_expressions() {
+        let mut calc = Calculator::new();
+        let simplify = |calc: &mut Calculator, line| calc.execute(line).unwrap().to_string();
+        assert_eq!("5 * x", simplify(&mut calc, "simplify 2 * x + 3 * x"));
+        assert_eq!("6 + 2 * y", simplify(&mut calc, "simplify 2 * (y + 3) * 1"));
+        assert_eq!("x ^ 3", simplify(&mut calc, "simplify x * x * x"));
+        // variables stay symbolic
+        calc.execute("a := 2").unwrap();
+        assert_eq!("sin(x) + a", simplify(&mut calc, "simplify sin(x) + a + 0"));
+        assert_eq!(
+            ValueKind::Expression,
+            calc.execute("simplify x").unwrap().kind()
+        );
+    }
+
+    #[test]
+    fn solve_through_custom_functions() {
+        let mut calc = Calculator::new();
+        calc.execute("f(x) := 2 * x + 1").unwrap();
+        calc.execute("g(t) := f(t) ^ 2").unwrap();
+        let solve = |calc: &mut Calculator, line| calc.execute(line).unwrap().to_string();
+        assert_eq!("x = 1.5", solve(&mut calc, "solve f(x) = 4 for x"));
+        assert_eq!("y = 1", solve(&mut calc, "solve f(y) = f(2 - y) for y"));
+        assert_eq!("[-2, 1]", solve(&mut calc, "solve g(x) = 9 for x"));
+        assert_eq!("x < 1.5", solve(&mut calc, "solve f(x) < 4 for x"));
+    }
+
+    #[test]
+    fn solve_inequalities() {
+        let mut calc = Calculator::new();
+        let solve = |calc: &mut Calculator, line| calc.execute(line).unwrap().to_string();
+        assert_eq!("x < 3", solve(&mut calc, "solve 2 * x + 1 < 7 for x"));
+        assert_eq!("x >= -3", solve(&mut calc, "solve -2 * x <= 6 for x"));
+        calc.execute("a := 2").unwrap();
+        assert_eq!("-2 < x < 2", solve(&mut calc, "solve x ^ 2 < a ^ 2 for x"));
+        assert_eq!("x = 0", solve(&mut calc, "solve x ^ 2 <= 0 for x"));
+        assert_eq!("any x", solve(&mut calc, "solve x ^ 2 + 1 > 0 for x"));
+        assert_eq!("no solution", solve(&mut calc, "solve x ^ 2 + 1 < 0 for x"));
+        assert_eq!(
+            ValueKind::Intervals,
+            calc.execute("solve x > 1 for x").unwrap().kind()
+        );
+        assert_eq!(
+            Err(Error::SolverError(SolverError::UnsupportedPower)),
+            calc.execute("solve x ^ 3 > 1 for x")
+        );
+    }
+
+    #[test]
+    fn statistics() {
+        let mut calc = Calculator::new();
+        calc.execute("xs := [3, 1, 2, 6]").unwrap();
+        assert_eq!(Ok(Value::Number(3.0)), calc.execute("mean(xs)"));
+        assert_eq!(Ok(Value::Number(2.5)), calc.execute("median(xs)"));
+        assert_eq!(Ok(Value::Number(12.0)), calc.execute("sum(xs)"));
+        assert_eq!(Ok(Value::Number(6.0)), calc.execute("sum(i, 1, 3, i)"));
+        assert_eq!(Ok(Value::Number(1.0)), calc.execute("minv(xs)"));
+        assert_eq!(Ok(Value::Number(6.0)), calc.execute("maxv(xs * 2) / 2"));
+        assert_eq!(Ok(Value::Number(1.0)), calc.execute("variance([1, 2, 3])"));
+        assert_eq!(Ok(Value::Number(2.0)), calc.execute("stddev([1; 3; 5])"));
+        assert_eq!(
+            Err(Error::CalcError(CalcError::InvalidArguments {
+                name: "mean".to_string(),
+                reason: "xs must be a list".to_string(),
+            })),
+            calc.execute("mean(1)")
+        );
+    }
+
+    #[test]
+    fn vectors_and_matrices() {
+        let mut calc = Calculator::new();
+        let matrix = |rows, elements: &[Number]| {
+            Ok(Value::Matrix(Matrix::new(rows, elements.to_vec()).unwrap()))
+        };
+        assert_eq!(
+            Ok(Value::List(vec![4.0, 6.0])),
+            calc.execute("[1, 2] + [3, 4]")
+        );
+        assert_eq!(Ok(Value::Number(11.0)), calc.execute("[1, 2] * [3, 4]"));
+        assert_eq!(Ok(Value::List(vec![0.5, 1.0])), calc.execute("[1, 2] / 2"));
+        assert_eq!(Ok(Value::Void), calc.execute("m := [1, 2; 3, 4]"));
+        assert_eq!(matrix(2, &[1.0, 2.0, 3.0, 4.0]), calc.execute("m"));
+        assert_eq!(matrix(2, &[7.0, 10.0, 15.0, 22.0]), calc.execute("m * m"));
+        assert_eq!(Ok(Value::List(vec![3.0, 7.0])), calc.execute("m * [1, 1]"));
+        #[cfg(not(feature = "embedded"))] // linear algebra
+        {
+            assert_number(&mut calc, "det(m)", -2.0);
+            assert_number(&mut calc, "det(transpose(m))", -2.0);
+            assert_eq!(
+                matrix(2, &[1.0, 3.0, 2.0, 4.0]),
+                calc.execute("transpose([1, 2; 3, 4])")
+            );
+        }
+        assert_eq!(
+            Err(Error::CalcError(CalcError::UnexpectedList(
+                "[1]".to_string()
+            ))),
+            calc.execute("[[1], 2]")
+        );
+        assert_eq!(
+            Some(2),
+            calc.execute("m").unwrap().as_matrix().map(Matrix::rows)
+        );
+        assert_eq!(
+            Err(Error::CalcError(CalcError::IncompatibleShapes {
+                op: Operation::Add,
+                lhs: "a 2x2 matrix".to_string(),
+                rhs: "a vector of 3 elements".to_string(),
+            })),
+            calc.execute("m + [1, 2, 3]")
+        );
+        assert_eq!(
+            Err(Error::CalcError(CalcError::UnexpectedList("m".to_string()))),
+            calc.execute("sin(m)")
+        );
+    }
+
+    #[test]
+    fn eval_fn_many() {
+        let mut calc = Calculator::new();
+        calc.execute("a := 2").unwrap();
+        calc.execute("f(x) := a * x").unwrap();
+        calc.execute("g(x) := f(x) + 1").unwrap();
+        calc.execute("h(x, y) := x + y").unwrap();
+        assert_eq!(vec![Ok(2.0), Ok(4.0)], calc.eval_fn_many("f", &[1.0, 2.0]));
+        assert_eq!(vec![Ok(3.0), Ok(5.0)], calc.eval_fn_many("g", &[1.0, 2.0]));
+        assert_eq!(vec![Ok(1.0)], calc.eval_fn_many("sqrt", &[1.0]));
+        assert_eq!(
+            vec![Err(CalcError::UnknownFunction {
+                name: "k".to_string(),
+                candidates: vec![],
+            })],
+            calc.eval_fn_many("k", &[1.0])
+        );
+        assert_eq!(
+            vec![Err(CalcError::UnexpectedNumberOfParameters {
+                name: "h".to_string(),
+                act: 1,
+                exp: 2
+            })],
+            calc.eval_fn_many("h", &[1.0])
+        );
+    }
+
+    #[test]
+    fn snapshot_and_restore() {
+        let mut calc = Calculator::new();
+        calc.execute("a := 2").unwrap();
+        let snapshot = calc.snapshot();
+        calc.execute("a := 3").unwrap();
+        calc.execute("f(x) := a * x").unwrap();
+        assert_eq!(Ok(Value::Number(6.0)), calc.execute("f(2)"));
+        calc.restore(&snapshot);
+        assert_eq!(Ok(Value::Number(2.0)), calc.execute("a"));
+        assert!(calc.execute("f(2)").is_err());
+    }
+
+    #[test]
+    #[cfg(not(feature = "f32"))] // rounding errors of `f64`
+    fn tolerance_and_rounding() {
+        let mut calc = Calculator::new();
+        let cancelled = "solve 0.1 * x^2 + 0.2 * x^2 = 0.3 * x^2 + x - 2 for x";
+        let double_root = "solve x^2 - 0.2 * x + 0.01 = 0 for x";
+        assert_eq!(
+            ValueKind::Solutions,
+            calc.execute(cancelled).unwrap().kind()
+        );
+        assert_eq!(
+            2,
+            calc.execute(double_root).unwrap().as_list().unwrap().len()
+        );
+        calc.set_tolerance(1e-12);
+        assert_eq!(
+            Ok(Value::Solved {
+                variable: "x".to_string(),
+                value: 2.0
+            }),
+            calc.execute(cancelled)
+        );
+        assert_eq!(Ok(Value::Solutions(vec![0.1])), calc.execute(double_root));
+        assert_eq!(
+            "any x",
+            calc.execute("solve x^2 - 0.2 * x + 0.01 >= 0 for x")
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            Ok(Value::Number(0.30000000000000004)),
+            calc.execute("0.1 + 0.2")
+        );
+
+        calc.set_round_results(true);
+        assert_eq!(Ok(Value::Number(0.3)), calc.execute("0.1 + 0.2"));
+        assert_eq!(Ok(Value::Number(0.3)), calc.execute("ans"));
+        // digits beyond the tolerance are dropped
+        assert_eq!(Ok(Value::Number(0.333333333333)), calc.execute("1 / 3"));
+        calc.set_tolerance(0.0);
+        assert_eq!(Ok(Value::Number(1.0 / 3.0)), calc.execute("1 / 3"));
+    }
+
+    #[test]
+    fn undo_changes() {
+        let mut calc = Calculator::new();
+        calc.execute("a := 1").unwrap();
+        calc.execute("f(x) := a * x").unwrap();
+        calc.execute("a := 2").unwrap();
+        calc.execute("f(3)").unwrap();
+        assert_eq!(Ok(Value::Void), calc.execute("undo"));
+        assert_eq!(Ok(Value::Number(3.0)), calc.execute("f(3)"));
+        assert_eq!(Ok(Value::Void), calc.execute("undo"));
+        assert!(calc.execute("f(3)").is_err());
+        calc.execute("undo").unwrap();
+        assert_eq!(
+            Err(Error::CalcError(CalcError::NothingToUndo)),
+            calc.execute("undo")
+        );
+
+        // only the latest changes are kept
+        for n in 0..40 {
+            calc.execute(&format!("a := {}", n)).unwrap();
+        }
+        assert_eq!(MAX_UNDO, (0..).take_while(|_| calc.undo()).count());
+        assert_eq!(Ok(Value::Number(7.0)), calc.execute("a"));
+
+        let snapshot = calc.snapshot();
+        calc.execute("a := 100").unwrap();
+        calc.restore(&snapshot);
+        assert!(calc.undo());
+        assert_eq!(Ok(Value::Number(100.0)), calc.execute("a"));
+
+        // a script is undone as a whole
+        let dir = std::env::temp_dir().join("calc_undo_run");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("b.calc"), "b := 1\nc := 2\n").unwrap();
+        calc.set_base_path(&dir);
+        calc.execute("run \"b.calc\"").unwrap();
+        calc.execute("undo").unwrap();
+        assert!(calc.execute("b").is_err() && calc.execute("c").is_err());
+        assert_eq!(Ok(Value::Number(100.0)), calc.execute("a"));
+    }
+
+    #[test]
+    fn compile_and_run_script() {
+        let mut calc = Calculator::new();
+        let program = calc
+            .compile_script("total := price * count\n\ntotal * 1.5\n")
+            .unwrap();
+        calc.execute("price := 2").unwrap();
+        calc.execute("count := 3").unwrap();
+        assert_eq!(
+            Ok(vec![Value::Void, Value::Number(9.0)]),
+            calc.run(&program)
+        );
+        calc.execute("count := 4").unwrap();
+        assert_eq!(
+            Ok(vec![Value::Void, Value::Number(12.0)]),
+            calc.run(&program)
+        );
+        assert!(calc.compile_script("a := 1\n1 +").is_err());
+    }
+
+    #[test]
+    fn execute_script() {
+        let mut calc = Calculator::new();
+        let results = calc.execute_script(
+            "f(x) := let t := x ^ 2; t + 1\nunit := \"m;s\"; g(x) := f(x) * 2\n\ny := 1 +; g(1)",
+        );
+        assert_eq!(5, results.len());
+        assert_eq!(Ok(Value::Text("m;s".to_string())), calc.execute("unit"));
+        match &results[3] {
+            Err(err @ Error::ScriptError(ScriptError::Failed { .. })) => {
+                assert!(err.to_string().starts_with("line 4, statement 4: "))
+            }
+            result => panic!("expected failed statement, got {:?}", result),
+        }
+        assert_eq!(Ok(Value::Number(4.0)), results[4]);
+    }
+
+    #[test]
+    fn execute_with_warnings() {
+        let mut calc = Calculator::new();
+        let output = calc.execute_with_warnings("1 + 2").unwrap();
+        assert_eq!(Value::Number(3.0), output.value);
+        assert!(output.warnings.is_empty());
+
+        let output = calc.execute_with_warnings("sin(x) := 2 * x").unwrap();
+        assert_eq!(Value::Void, output.value);
+        assert_eq!(
+            vec!["warning[W0002]: Function `sin` shadows a build-in function".to_string()],
+            output
+                .warnings
+                .iter()
+                .map(|warning| warning.to_string())
+                .collect::<Vec<_>>()
+        );
+
+        let output = calc.execute_with_warnings("a := 10 ^ 400").unwrap();
+        assert_eq!(Severity::Warning, output.warnings[0].severity);
+        assert_eq!("Value of `a` is not finite", output.warnings[0].message);
+        assert!(calc.execute_with_warnings("b + 1").is_err());
+    }
+
+    #[test]
+    fn strict_mode() {
+        let mut calc = Calculator::new();
+        calc.execute("a := 1").unwrap();
+        calc.execute("a := 2").unwrap();
+        calc.execute("override f(x) := x").unwrap();
+        calc.set_strict(true);
+        let already_defined = |name: &str| {
+            Err(Error::CalcError(CalcError::AlreadyDefined(
+                name.to_string(),
+            )))
+        };
+        assert_eq!(already_defined("a"), calc.execute("a := 3"));
+        assert_eq!(already_defined("f"), calc.execute("f(y) := 2 * y"));
+        assert_eq!(already_defined("sin"), calc.execute("sin(x) := x"));
+        assert_eq!(Ok(Value::Void), calc.execute("override a := 3"));
+        assert_eq!(Ok(Value::Void), calc.execute("override f(y) := 2 * y"));
+        assert_eq!(Ok(Value::Void), calc.execute("b := 1"));
+        assert_eq!(Ok(Value::Number(9.0)), calc.execute("f(a) + 3 * b"));
+
+        calc.execute("s(0) := 1").unwrap();
+        calc.execute("s(n) := s(n - 1) + 1").unwrap();
+        assert_eq!(already_defined("s"), calc.execute("s(n) := s(n - 1) + 2"));
+        assert_eq!(already_defined("s"), calc.execute("s(0) := 2"));
+        assert_eq!(Ok(Value::Void), calc.execute("override s(0) := 2"));
+        assert_eq!(Ok(Value::Number(4.0)), calc.execute("s(2)"));
+
+        assert!(calc.execute("override override a := 1").is_err());
+        assert!(calc.execute("override 1 + 2").is_err());
+        assert_eq!(Ok(Value::Void), calc.execute("overrides := 1"));
+    }
+
+    #[test]
+    fn declared_only_mode() {
+        let mut calc = Calculator::new();
+        calc.execute("f(x) := a * x + g(x)").unwrap();
+        calc.set_declared_only(true);
+        let undefined = |names: &[&str]| {
+            Err(Error::CalcError(CalcError::UndefinedNames(
+                names.iter().map(|name| name.to_string()).collect(),
+            )))
+        };
+        assert_eq!(undefined(&["a", "b", "g"]), calc.execute("c := f(b)"));
+        assert_eq!(undefined(&["a", "g"]), calc.execute("plot f"));
+        calc.execute("g(x) := x").unwrap();
+        calc.execute("a := 2").unwrap();
+        assert!(calc.execute("plot f").is_ok());
+        assert_eq!(Ok(Value::Void), calc.execute("c := f(a)"));
+        assert_eq!(
+            "Undefined names `d`, `e2`",
+            calc.execute("e3 := d + e2").unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn echo_assignments() {
+        let mut calc = Calculator::new();
+        calc.set_echo(true);
+        assert_eq!(Ok(Value::Number(6.0)), calc.execute("a := 6"));
+        calc.load_csv_str("1\n2", 1, "xs").unwrap();
+        assert_eq!(Ok(Value::List(vec![1.0, 2.0])), calc.execute("ys := xs"));
+        assert_eq!(Ok(Value::Void), calc.execute("f(x) := x"));
+        calc.set_echo(false);
+        assert_eq!(Ok(Value::Void), calc.execute("a := 7"));
+        assert_eq!(Ok(Value::Number(7.0)), calc.execute("a"));
+    }
+
+    #[test]
+    fn value_kind_and_accessors() {
+        let mut calc = Calculator::new();
+        let value = calc.execute("1 + 2").unwrap();
+        assert_eq!(ValueKind::Number, value.kind());
+        assert_eq!(None, value.as_graph());
+        assert_eq!(ValueKind::Void, calc.execute("f(x) := x").unwrap().kind());
+        let value = calc.execute("plot f").unwrap();
+        assert_eq!(ValueKind::Graph, value.kind());
+        assert!(value.as_graph().is_some());
+        assert_eq!(None, value.as_number());
+        calc.load_csv_str("1\n2", 1, "xs").unwrap();
+        assert_eq!(Some(&[1.0, 2.0][..]), calc.execute("xs").unwrap().as_list());
+    }
+
+    #[test]
+    fn show_definitions() {
+        let mut calc = Calculator::new();
+        calc.execute("a := -2").unwrap();
+        calc.execute("s(n) := s(n - 1) * a").unwrap();
+        assert_eq!(Some("a = −2".to_string()), calc.show("a"));
+        assert_eq!(Some("s(n) := s(n − 1)·a".to_string()), calc.show("s"));
+        assert_eq!(None, calc.show("sin"));
+        assert_eq!(None, calc.show("b"));
+    }
+
+    #[test]
+    fn reactive_mode() {
+        let mut calc = Calculator::new();
+        calc.set_reactive(true);
+        calc.execute("x := 1").unwrap();
+        calc.execute("f(v) := 2 * v").unwrap();
+        calc.execute("y := f(x) + 1").unwrap();
+        calc.execute("z := y * y").unwrap();
+        calc.execute("w := 7").unwrap();
+        assert_eq!(
+            ["x", "y", "z"],
+            calc.changed_bindings_after("x := 2").as_slice()
+        );
+        assert_eq!(Ok(Value::Number(25.0)), calc.execute("z"));
+        assert_eq!(
+            ["y", "z"],
+            calc.changed_bindings_after("f(v) := 3 * v").as_slice()
+        );
+        assert_eq!(Ok(Value::Number(49.0)), calc.execute("z"));
+        assert!(calc.changed_bindings_after("1 + 2").is_empty());
+
+        assert_eq!(
+            Err(Error::CalcError(CalcError::CyclicDefinition(vec![
+                "x".to_string(),
+                "z".to_string(),
+                "y".to_string(),
+                "x".to_string()
+            ]))),
+            calc.execute("x := z")
+        );
+
+        calc.set_reactive(false);
+        calc.execute("y := 1").unwrap();
+        calc.execute("x := 3").unwrap();
+        assert_eq!(Ok(Value::Number(1.0)), calc.execute("y"));
+        assert_eq!(Ok(Value::Number(1.0)), calc.execute("z"));
+    }
+
+    #[test]
+    fn define_lazy_variable() {
+        let mut calc = Calculator::new();
+        assert_eq!(Ok(Value::Void), calc.execute("define y = x^2 + 1"));
+        assert_eq!(
+            Err(Error::CalcError(CalcError::UnknownSymbol {
+                name: "y".to_string(),
+                candidates: vec![],
+            })),
+            calc.execute("y")
+        );
+        calc.execute("x := 2").unwrap();
+        assert_eq!(Ok(Value::Number(5.0)), calc.execute("y"));
+        calc.execute("x := 3").unwrap();
+        assert_eq!(Ok(Value::Number(10.0)), calc.execute("y"));
+        assert!(calc.execute("define x = y").is_err());
+        calc.execute("y := 1").unwrap();
+        calc.execute("x := 4").unwrap();
+        assert_eq!(Ok(Value::Number(1.0)), calc.execute("y"));
+    }
+
+    #[test]
+    fn text_values() {
+        let mut calc = Calculator::new();
+        calc.execute("f(t) := t ^ 2").unwrap();
+        calc.execute("title := \"position vs time\"").unwrap();
+        calc.execute("unit := \"m\"").unwrap();
+        calc.execute("label := title + \" in \" + unit").unwrap();
+        assert_eq!(
+            Ok(Value::Text("position vs time in m".to_string())),
+            calc.execute("label")
+        );
+        calc.execute("both := title + unit").unwrap();
+        assert_eq!(
+            Some("position vs timem"),
+            calc.execute("both").unwrap().as_text()
+        );
+        assert_eq!(
+            Ok(Value::Text("f at 2".to_string())),
+            calc.execute("x := 2")
+                .and_then(|_| calc.execute("\"f at \" + x"))
+        );
+        assert!(matches!(
+            calc.execute("\"f at \" + 2"),
+            Err(Error::ParserError(ParserError::Syntax { .. }))
+        ));
+        assert_eq!(
+            "C0015",
+            calc.execute("title * 2").unwrap_err().diagnostic().code
+        );
+        assert_eq!(
+            Some("title = \"position vs time\"".to_string()),
+            calc.show("title")
+        );
+
+        let graph = calc
+            .execute("plot f title title + \" (\" + unit + \")\"")
+            .unwrap();
+        assert_eq!(
+            Some("position vs time (m)"),
+            graph.as_graph().unwrap().title()
+        );
+        assert!(calc
+            .dump_script()
+            .contains("title := \"position vs time\"\n"));
+    }
+
+    #[test]
+    fn plot_names() {
+        let mut calc = Calculator::new();
+        calc.execute("f(x) := x ^ 2").unwrap();
+        calc.execute("g(x, a) := a * x").unwrap();
+        let area = Area::new(-2., -4., 2., 4.);
+        let screen = Area::new(0., 0., 4., 8.);
+        let plot = |calc: &mut Calculator, line| {
+            let value = calc.execute(line).unwrap();
+            value.as_graph().unwrap().plot(&area, &screen).unwrap()
+        };
+        let labels = |plot: &Plot| {
+            let label = |axis: &Option<Axis>| axis.as_ref().unwrap().label.clone();
+            (plot.name.clone(), label(&plot.x_axis), label(&plot.y_axis))
+        };
+        let named = |name: &str, x: &str, y: &str| (name.to_string(), x.to_string(), y.to_string());
+        assert_eq!(
+            named("f(x)", "x", "f(x)"),
+            labels(&plot(&mut calc, "plot f"))
+        );
+        assert_eq!(
+            named("t ^ 2", "t", "t ^ 2"),
+            labels(&plot(&mut calc, "plot t ^ 2"))
+        );
+        assert_eq!(
+            named("f(x)", "x", "y"),
+            labels(&plot(&mut calc, "plot polar f"))
+        );
+        let sequence = calc.execute("plot g(x, a) for a in 0..1 step 0.5").unwrap();
+        let names: Vec<_> = sequence
+            .as_plot_sequence()
+            .unwrap()
+            .frames()
+            .iter()
+            .map(|(_, graph)| graph.name().to_string())
+            .collect();
+        assert_eq!(vec!["g(x, a = 0)", "g(x, a = 0.5)", "g(x, a = 1)"], names);
+    }
+
+    #[test]
+    fn plot_sweep() {
+        let mut calc = Calculator::new();
+        calc.execute("f(x, a) := a * x").unwrap();
+        calc.execute("n := 2").unwrap();
+        let sequence = calc.execute("plot f(x, a) for a in 0..n step 0.5").unwrap();
+        let sequence = sequence.as_plot_sequence().unwrap();
+        assert_eq!("a", sequence.param());
+        let values: Vec<_> = sequence.frames().iter().map(|(a, _)| *a).collect();
+        assert_eq!(vec![0.0, 0.5, 1.0, 1.5, 2.0], values);
+
+        let area = Area::new(-2., -4., 2., 4.);
+        let screen = Area::new(0., 0., 4., 8.);
+        let plots = sequence.plot(&area, &screen).unwrap();
+        assert_eq!(5, plots.len());
+        // at x = -2 the lines start at y = -a * 2, projected onto the screen
+        let starts: Vec<_> = plots.iter().map(|plot| plot.points[0]).collect();
+        assert_eq!(
+            vec![
+                Some((0.0, 4.0)),
+                Some((0.0, 3.0)),
+                Some((0.0, 2.0)),
+                Some((0.0, 1.0)),
+                Some((0.0, 0.0))
+            ],
+            starts
+        );
+
+        let frames = calc.execute("plot f(a, x) for a in 1..3").unwrap();
+        assert_eq!(3, frames.as_plot_sequence().unwrap().frames().len());
+        assert_eq!(
+            "G0002",
+            calc.execute("plot f(x, a) for a in 0..1 step -1")
+                .unwrap_err()
+                .diagnostic()
+                .code
+        );
+        assert_eq!(
+            "G0002",
+            calc.execute("plot f(x, a) for a in 0..1e6 step 1")
+                .unwrap_err()
+                .diagnostic()
+                .code
+        );
+    }
+
+    #[test]
+    fn stored_equations() {
+        let mut calc = Calculator::new();
+        calc.execute("store ohm: v = i * r").unwrap();
+        calc.execute("store power: p = v * i").unwrap();
+        calc.execute("r := 4").unwrap();
+        assert_eq!(
+            Ok(Value::Solved {
+                variable: "v".to_string(),
+                value: 8.0,
+            }),
+            calc.execute("solve ohm for v given i := 1 + 1")
+        );
+        assert_eq!(Some(&4.0), calc.env.get("r"));
+        assert_eq!(None, calc.env.get("i"));
+        assert_eq!(
+            Some(6.0),
+            calc.execute("solve power for i given p := 72, v := 12")
+                .unwrap()
+                .as_number()
+        );
+        assert!(calc.execute("solve ohm for i").is_err());
+        assert_eq!(
+            "C0016",
+            calc.execute("solve watt for i")
+                .unwrap_err()
+                .diagnostic()
+                .code
+        );
+        assert_eq!(Some("ohm: v = i·r".to_string()), calc.show("ohm"));
+        assert!(calc
+            .dump_script()
+            .ends_with("store ohm: v = i * r\nstore power: p = v * i\n"));
+    }
+
+    #[test]
+    fn analyze_graph() {
+        let mut calc = Calculator::new();
+        calc.execute("h(t) := 20 - 5 * t ^ 2").unwrap();
+        let graph = calc.execute("plot h").unwrap();
+        let graph = graph.as_graph().unwrap();
+        let points = graph.analyze(&Range::new(-3., 3.));
+        let kinds: Vec<_> = points.iter().map(|point| point.kind).collect();
+        assert_eq!(
+            vec![PointKind::Zero, PointKind::Maximum, PointKind::Zero],
+            kinds
+        );
+        assert_approx_eq!(-2.0, points[0].x);
+        assert_approx_eq!(20.0, points[1].y, TEST_TOLERANCE);
+
+        let area = Area::new(-4., -10., 4., 30.);
+        let screen = Area::new(0., 0., 80., 40.);
+        let plot = graph.plot_analyzed(&area, &screen).unwrap();
+        assert_eq!(3, plot.markers.len());
+        // the screen is 10 times the area
+        assert_approx_eq!(40.0, plot.markers[1].at.0, 10.0 * TEST_TOLERANCE);
+        assert_approx_eq!(30.0, plot.markers[1].at.1);
+        assert!(graph.plot(&area, &screen).unwrap().markers.is_empty());
+    }
+
+    #[test]
+    fn plot_labels() {
+        let mut calc = Calculator::new();
+        calc.execute("h(t) := 20 - 5 * t ^ 2").unwrap();
+        calc.execute("unit := \"m\"").unwrap();
+        let graph = calc
+            .execute("plot h ylabel \"h in \" + unit title \"height\" xlabel \"t\"")
+            .unwrap();
+        let labels = Labels {
+            title: Some("height".to_string()),
+            xlabel: Some("t".to_string()),
+            ylabel: Some("h in m".to_string()),
+        };
+        let graph = graph.as_graph().unwrap();
+        assert_eq!(&labels, graph.labels());
+        let area = Area::new(-1., -1., 1., 1.);
+        let plot = graph.plot(&area, &area).unwrap();
+        assert_eq!(labels, plot.labels);
+        assert_eq!(
+            "P0029",
+            calc.execute("plot h xlabel \"t\" xlabel \"s\"")
+                .unwrap_err()
+                .diagnostic()
+                .code
+        );
+    }
+
+    #[test]
+    fn plot_expression() {
+        let mut calc = Calculator::new();
+        calc.execute("a := 2").unwrap();
+        let graph = calc.execute("plot a * t ^ 2 + 1 title \"t\"").unwrap();
+        let graph = graph.as_graph().unwrap();
+        assert_eq!(Some("t"), graph.title());
+        let points = graph.analyze(&Range::new(-1.05, 0.95));
+        assert_eq!(1, points.len());
+        assert_eq!(PointKind::Minimum, points[0].kind);
+        assert_approx_eq!(1.0, points[0].y);
+        assert!(calc.execute("plot sin(3)").is_ok());
+        assert_eq!(
+            Err(Error::GraphError(GraphError::AmbiguousVariable(vec![
+                "x".to_string(),
+                "y".to_string()
+            ]))),
+            calc.execute("plot x * y")
+        );
+    }
+
+    #[test]
+    fn polar_plot() {
+        let mut calc = Calculator::new();
+        calc.execute("r(t) := 2").unwrap();
+        let graph = calc.execute("plot polar r").unwrap();
+        let graph = graph.as_graph().unwrap();
+        assert_eq!(
+            PlotKind::Polar {
+                from: 0.0,
+                to: std::f64::consts::TAU as Number
+            },
+            graph.kind()
+        );
+        let screen = Area::new(0., 0., 100., 100.);
+        let plot = graph.plot(&Area::new(-4., -4., 4., 4.), &screen).unwrap();
+        // a circle of radius 2 around the center of the screen
+        assert!(plot.points.len() > 100);
+        for (x, y) in plot.points.iter().flatten() {
+            assert_approx_eq!(25.0, ((x - 50.0).powi(2) + (y - 50.0).powi(2)).sqrt(), 1e-3);
+        }
+        let graph = calc.execute("plot polar t in [0, pi / 2]").unwrap();
+        let plot = graph
+            .as_graph()
+            .unwrap()
+            .plot(&Area::new(-4., -4., 4., 4.), &screen);
+        let last = plot.unwrap().points.last().copied().flatten().unwrap();
+        assert_approx_eq!(50.0, last.0, 1e-3);
+        assert_eq!(
+            "G0004",
+            calc.execute("plot polar t in [1, 0]")
+                .unwrap_err()
+                .diagnostic()
+                .code
+        );
+    }
+
+    #[test]
+    fn surface_plot() {
+        let mut calc = Calculator::new();
+        calc.execute("f(x, y) := x * y").unwrap();
+        let surface = calc.execute("plot f title \"saddle\"").unwrap();
+        assert_eq!("<surface f(x, y)>", surface.to_string());
+        let surface = surface.as_surface().unwrap();
+        assert_eq!(Some("saddle".to_string()), surface.labels().title);
+        let plot = surface.sample(&Area::new(-2., -1., 2., 1.), 5, 3).unwrap();
+        assert_eq!(("x", "y"), (plot.x_label.as_str(), plot.y_label.as_str()));
+        assert_eq!(vec![-2., -1., 0., 1., 2.], plot.xs);
+        assert_eq!(vec![-1., 0., 1.], plot.ys);
+        assert_eq!(Some(-2.0), plot.z[2][0]);
+        assert_eq!(Some(Range::new(-2., 2.)), plot.z_range);
+        assert!(plot.z_tics.iter().any(|tic| tic.label == 0.0));
+        calc.execute("g(x, y) := sqrt(x) + 1").unwrap();
+        let plot = calc
+            .execute("plot g")
+            .unwrap()
+            .as_surface()
+            .unwrap()
+            .sample(&Area::new(-1., 0., 1., 1.), 3, 2)
+            .unwrap();
+        assert_eq!(vec![None, Some(1.0), Some(2.0)], plot.z[0]);
+        assert_eq!(Some(Range { min: 1., max: 2. }), plot.z_range);
+        assert_eq!(ValueKind::Graph, calc.execute("plot sin").unwrap().kind());
+    }
+
+    #[test]
+    fn implicit_plot() {
+        let mut calc = Calculator::new();
+        let graph = calc.execute("plot x^2 + y^2 = 4").unwrap();
+        let graph = graph.as_graph().unwrap();
+        assert_eq!(PlotKind::Implicit, graph.kind());
+        assert_eq!("x ^ 2 + y ^ 2 = 4", graph.name());
+        let screen = Area::new(0., 0., 100., 100.);
+        let plot = graph.plot(&Area::new(-4., -4., 4., 4.), &screen).unwrap();
+        // segments of a circle of radius 2 around the center of the screen
+        assert!(plot.points.len() > 100);
+        assert!(plot.samples.is_empty());
+        assert_eq!("y", plot.y_axis.unwrap().label);
+        for (x, y) in plot.points.iter().flatten() {
+            assert_approx_eq!(25.0, ((x - 50.0).powi(2) + (y - 50.0).powi(2)).sqrt(), 0.5);
+        }
+        calc.execute("f(a, b) := a * b - 1").unwrap();
+        let graph = calc.execute("plot implicit f").unwrap();
+        let graph = graph.as_graph().unwrap();
+        assert_eq!("f(a, b) = 0", graph.name());
+        let plot = graph.plot(&Area::new(-4., -4., 4., 4.), &screen).unwrap();
+        assert_eq!("a", plot.x_axis.unwrap().label);
+        for pair in plot.points.chunks(3) {
+            assert_eq!(None, pair[2]);
+        }
+        assert_eq!(
+            "G0006",
+            calc.execute("plot x + y = z")
+                .unwrap_err()
+                .diagnostic()
+                .code
+        );
+        assert_eq!(
+            "G0006",
+            calc.execute("plot implicit sin")
+                .unwrap_err()
+                .diagnostic()
+                .code
+        );
+    }
+
+    #[test]
+    fn guarded_clauses() {
+        let mut calc = Calculator::new();
+        calc.set_strict(true);
+        calc.execute("f(x) := x when x >= 0").unwrap();
+        assert_eq!(Ok(Value::Number(2.0)), calc.execute("f(2)"));
+        assert_eq!(
+            "C0014",
+            calc.execute("f(-2)").unwrap_err().diagnostic().code
+        );
+        calc.execute("f(x) := 0 - x otherwise").unwrap();
+        assert_eq!(Ok(Value::Number(2.0)), calc.execute("f(-2)"));
+        assert_eq!(
+            Some("f(x) := x when x ≥ 0\nf(x) := 0 − x otherwise".to_string()),
+            calc.show("f")
+        );
+        assert_eq!(
+            Err(Error::CalcError(CalcError::AlreadyDefined("f".to_string()))),
+            calc.execute("f(x) := 1 when x > 9")
+        );
+        calc.execute("override f(x) := 1 when x > 9").unwrap();
+        assert_eq!("C0014", calc.execute("f(2)").unwrap_err().diagnostic().code);
+
+        calc.execute("override f(x) := x when x >= 0").unwrap();
+        calc.execute("f(x) := 0 - x otherwise").unwrap();
+        let graph = match calc.execute("plot f") {
+            Ok(Value::Graph(graph)) => graph,
+            value => panic!("expected a graph, got {:?}", value),
+        };
+        assert_eq!(
+            vec![Some(2.0), Some(0.0), Some(3.0)],
+            graph.eval_many(&[-2.0, 0.0, 3.0])
+        );
+        assert_eq!(
+            "f(x) := x when x >= 0\nf(x) := 0 - x otherwise\n",
+            calc.dump_script()
+        );
+    }
+
+    #[test]
+    fn run_scripts() {
+        let dir = std::env::temp_dir().join("calc_run_scripts");
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        std::fs::write(dir.join("lib/a.calc"), "a := 1\n\nrun \"lib/b.calc\"\n").unwrap();
+        std::fs::write(dir.join("lib/b.calc"), "b := a + 1\nc := d\n").unwrap();
+        std::fs::write(dir.join("loop.calc"), "run \"./loop.calc\"\n").unwrap();
+
+        let mut calc = Calculator::new();
+        calc.set_base_path(&dir);
+        assert_eq!(
+            Err(Error::ScriptError(ScriptError::Statement {
+                path: "lib/b.calc".to_string(),
+                line: 2,
+                error: Box::new(
+                    CalcError::UnknownSymbol {
+                        name: "d".to_string(),
+                        candidates: vec![],
+                    }
+                    .into()
+                ),
+            })),
+            calc.execute("run \"lib/a.calc\"")
+        );
+        assert_eq!(Ok(Value::Number(2.0)), calc.execute("b"));
+        assert!(calc.running.is_empty());
+
+        let err = calc.execute("run \"loop.calc\"").unwrap_err();
+        assert_eq!("R0002", err.diagnostic().code);
+        match err {
+            Error::ScriptError(ScriptError::Statement { error, .. }) => {
+                assert_eq!("R0001", error.diagnostic().code)
+            }
+            err => panic!("expected a script error, got {:?}", err),
+        }
+        assert_eq!(
+            "I0001",
+            calc.execute("run \"missing.calc\"")
+                .unwrap_err()
+                .diagnostic()
+                .code
+        );
+    }
+
+    #[test]
+    fn run_scripts_within_base_path() {
+        let dir = std::env::temp_dir().join("calc_run_base");
+        std::fs::create_dir_all(dir.join("lib")).unwrap();
+        std::fs::write(dir.join("secret.txt"), "root:x:0:0\n").unwrap();
+        std::fs::write(dir.join("lib/a.calc"), "a := 1\n").unwrap();
+
+        let mut calc = Calculator::new();
+        calc.set_base_path(dir.join("lib"));
+        assert_eq!(Ok(Value::Void), calc.execute("run \"a.calc\""));
+        for path in ["../secret.txt", "/etc/passwd"] {
+            assert!(matches!(
+                calc.execute(&format!("run \"{}\"", path)),
+                Err(Error::ScriptError(ScriptError::OutsideBase { .. }))
+            ));
+        }
+
+        calc.set_base_path(&dir);
+        let err = calc.execute("run \"secret.txt\"").unwrap_err();
+        assert_eq!(
+            Error::ScriptError(ScriptError::InvalidStatement {
+                path: "secret.txt".to_string(),
+                line: 1,
+            }),
+            err
+        );
+        assert!(!err.to_string().contains("root"));
+
+        calc.set_sandboxed(true);
+        assert_eq!(
+            Err(Error::ScriptError(ScriptError::FileAccessDisabled(
+                "lib/a.calc".to_string()
+            ))),
+            calc.execute("run \"lib/a.calc\"")
+        );
+        assert!(matches!(
+            calc.execute("import \"secret.txt\" column 1 as s"),
+            Err(Error::ScriptError(ScriptError::FileAccessDisabled(_)))
+        ));
+    }
+
+    #[test]
+    fn audit_log() {
+        let mut calc = Calculator::new();
+        calc.execute("a := 1").unwrap();
+        assert!(calc.audit().is_empty());
+        calc.set_audit(true);
+        let snapshot = calc.snapshot();
+        calc.set_reactive(true);
+        calc.execute("b := a * 2").unwrap();
+        calc.execute("override a := 2").unwrap();
+        calc.execute("f(x) := x").unwrap();
+        calc.load_csv_str("1\n2", 1, "xs").unwrap();
+        calc.execute("b + 1").unwrap();
+        calc.restore(&snapshot);
+        let log: Vec<(&str, &str, MutationKind)> = calc
+            .audit()
+            .iter()
+            .map(|m| (m.statement.as_str(), m.name.as_str(), m.kind))
+            .collect();
+        assert_eq!(
+            vec![
+                ("b := a * 2", "b", MutationKind::Defined),
+                ("override a := 2", "a", MutationKind::Changed),
+                ("override a := 2", "b", MutationKind::Changed),
+                ("f(x) := x", "f", MutationKind::Defined),
+                (
+                    "import <csv data> column 1 as xs",
+                    "xs",
+                    MutationKind::Defined
+                ),
+                ("restore", "a", MutationKind::Changed),
+                ("restore", "b", MutationKind::Removed),
+                ("restore", "f", MutationKind::Removed),
+                ("restore", "xs", MutationKind::Removed),
+            ],
+            log
+        );
+        assert!(calc.audit().windows(2).all(|w| w[0].time <= w[1].time));
+        calc.set_audit(false);
+        assert!(calc.audit().is_empty());
+    }
+
+    #[test]
+    fn change_observers() {
+        let mut calc = Calculator::new();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let log = events.clone();
+        calc.on_change(move |event| log.lock().unwrap().push(event.clone()));
+        calc.execute("a := 1").unwrap();
+        calc.execute("b := a * 2").unwrap();
+        calc.execute("override a := 2").unwrap();
+        assert!(calc.execute("c := 1 / 0").is_err());
+        calc.execute("2 * a").unwrap();
+        assert!(calc.undo());
+        let assigned = |name: &str| EnvEvent::Assigned(name.to_string());
+        // failed statements and statements without changes do not notify
+        assert_eq!(
+            vec![assigned("a"), assigned("b"), assigned("a"), assigned("a")],
+            *events.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn calculator_is_shared_by_threads() {
+        fn assert_sync<T: Send + Sync>() {}
+        assert_sync::<Calculator>();
+
+        let mut calc = Calculator::new();
+        calc.execute("f(x) := 2 * x").unwrap();
+        let calc = &calc;
+        std::thread::scope(|scope| {
+            let readers: Vec<_> = (0..4)
+                .map(|i| scope.spawn(move || calc.evaluate(&format!("f({})", i))))
+                .collect();
+            for (i, reader) in readers.into_iter().enumerate() {
+                assert_eq!(Ok(2.0 * i as Number), reader.join().unwrap());
+            }
+        });
+    }
+
+    #[test]
+    fn resource_limits() {
+        let mut calc = Calculator::new();
+        calc.set_limits(Limits {
+            max_nodes: Some(3),
+            max_numbers: Some(4),
+            ..Limits::default()
+        });
+        assert!(calc.execute("1 + 2").is_ok());
+        assert_eq!(
+            Err(Error::LimitError(LimitError::TooManyNodes {
+                nodes: 5,
+                max: 3
+            })),
+            calc.execute("1 + 2 * 3")
+        );
+        calc.load_csv_str("1\n2\n3", 1, "xs").unwrap();
+        assert_eq!(
+            Err(Error::LimitError(LimitError::TooManyNumbers {
+                count: 5,
+                max: 4
+            })),
+            calc.execute("sample(1, 2)")
+        );
+        assert_eq!(
+            "L0003",
+            calc.load_csv_str("1\n2", 1, "ys")
+                .unwrap_err()
+                .diagnostic()
+                .code
+        );
+        assert_eq!(None, calc.env.get_list("ys"));
+    }
+
+    #[test]
+    fn evaluation_budget() {
+        let mut calc = Calculator::new();
+        calc.set_limits(Limits {
+            max_operations: Some(10_000),
+            ..Limits::default()
+        });
+        let too_many = LimitError::TooManyOperations { max: 10_000 };
+        calc.execute("a := sum(i, 1, 100, i)").unwrap();
+        assert_eq!(
+            Err(Error::LimitError(too_many)),
+            calc.execute("a := sum(i, 1, 100000, i)")
+        );
+        assert_eq!(Ok(Value::Number(5050.0)), calc.execute("a"));
+        calc.execute("f(x) := sum(i, 1, 1000, i * x)").unwrap();
+        let graph = calc.execute("plot f").unwrap();
+        let screen = Area::new(0.0, 0.0, 100.0, 100.0);
+        assert_eq!(
+            Err(GraphError::LimitExceeded(too_many)),
+            graph.as_graph().unwrap().plot(&Area::default(), &screen)
+        );
+
+        calc.set_limits(Limits {
+            timeout: Some(Duration::from_millis(10)),
+            ..Limits::default()
+        });
+        assert_eq!(
+            Err(Error::LimitError(LimitError::Timeout { millis: 10 })),
+            calc.execute("sum(i, 1, 1000000, sin(i))")
+        );
+    }
+
+    #[test]
+    fn timing_of_statements() {
+        let mut calc = Calculator::new();
+        calc.execute("f(x) := sqrt(x) * 2").unwrap();
+        assert_eq!(None, calc.last_timing());
+        calc.set_timing(true);
+        calc.execute("solve f(4) * x = 8 for x").unwrap();
+        let timing = *calc.last_timing().unwrap();
+        assert_eq!((1, 1), (timing.function_calls, timing.builtin_calls));
+        assert_eq!(Duration::ZERO, timing.plot);
+
+        let graph = match calc.execute("plot f") {
+            Ok(Value::Graph(graph)) => graph,
+            value => panic!("expected a graph, got {:?}", value),
+        };
+        let screen = Area::new(0.0, 0.0, 10.0, 10.0);
+        let (plot, timing) = graph.plot_with_timing(&Area::new(0.0, 0.0, 10.0, 10.0), &screen);
+        assert!(plot.is_ok());
+        // one call per column and one between the columns looking for spikes
+        assert_eq!((19, 19), (timing.function_calls, timing.builtin_calls));
+    }
+
+    #[cfg(feature = "persistence")]
+    #[test]
+    fn load_state() {
+        let mut calc = Calculator::new();
+        calc.execute("a := 1").unwrap();
+        calc.execute("f(x) := a * x").unwrap();
+        let saved = calc.save_state();
+
+        let mut restarted = Calculator::new();
+        restarted.set_limits(Limits {
+            max_bindings: Some(1),
+            ..Limits::default()
+        });
+        assert_eq!(
+            Err(Error::LimitError(LimitError::TooManyBindings { max: 1 })),
+            restarted.load_state(&saved)
+        );
+        assert!(restarted.execute("a").is_err());
+        restarted.set_limits(Limits::default());
+        restarted.load_state(&saved).unwrap();
+        assert_eq!(Ok(Value::Number(3.0)), restarted.execute("f(3)"));
+    }
+
+    #[test]
+    fn list_statement() {
+        let mut calc = Calculator::new();
+        calc.execute("f(x) := x when x > 0").unwrap();
+        calc.execute("f(x) := 0 otherwise").unwrap();
+        calc.execute("a := 2").unwrap();
+        calc.execute("define b = a + 1").unwrap();
+        calc.load_csv_str("1\n2", 1, "xs").unwrap();
+        let value = calc.execute("list").unwrap();
+        assert_eq!(
+            "a = 2\nb = a + 1 = 3\nxs = [1, 2]\nf(x) := x when x > 0\nf(x) := 0 otherwise",
+            value.to_string()
+        );
+        let listing = value.as_listing().unwrap();
+        assert_eq!(calc.functions(), listing.functions);
+        calc.execute("list := 4").unwrap();
+        assert_eq!(Ok(Value::Number(8.0)), calc.execute("list * 2"));
+    }
+
+    #[test]
+    fn unset_statement() {
+        let mut calc = Calculator::new();
+        calc.execute("define b = a * 2").unwrap();
+        calc.execute("a := 3").unwrap();
+        assert_eq!(Ok(Value::Void), calc.execute("unset b"));
+        assert!(calc.execute("b").is_err());
+        calc.execute("unset a").unwrap();
+        assert!(calc.variables().is_empty());
+        assert_eq!(
+            Err(Error::CalcError(CalcError::CannotRemoveBuildIn(
+                "max".to_string()
+            ))),
+            calc.execute("unset max")
+        );
+    }
+
+    #[test]
+    fn conditional_expressions() {
+        let mut calc = Calculator::new();
+        calc.execute("fact(n) := if(n <= 1, 1, n * fact(n - 1))")
+            .unwrap();
+        assert_eq!(Ok(Value::Number(120.0)), calc.execute("fact(5)"));
+        calc.execute("ramp(x) := if(x < 0, 0, x)").unwrap();
+        assert_eq!(
+            vec![Ok(0.0), Ok(0.0), Ok(2.0)],
+            calc.eval_fn_many("ramp", &[-1.0, 0.0, 2.0])
+        );
+        calc.execute("a := 2").unwrap();
+        assert_eq!(
+            Ok(Value::Solved {
+                variable: "x".to_string(),
+                value: 3.0
+            }),
+            calc.execute("solve if(a > 1, 2, 1) * x = 6 for x")
+        );
+        assert!(matches!(
+            calc.execute("solve if(x > 1, x, 1) = 6 for x"),
+            Err(Error::SolverError(SolverError::VariableInFunctionCall(..)))
+        ));
+    }
+
+    #[test]
+    fn recursion_limit() {
+        let mut calc = Calculator::new();
+        let exceeded = |name: &str, limit| {
+            Err(Error::CalcError(CalcError::RecursionLimitExceeded {
+                name: name.to_string(),
+                limit,
+            }))
+        };
+        calc.execute("loop(n) := loop(n) + 1").unwrap();
+        assert_eq!(exceeded("loop", 200), calc.execute("loop(1)"));
+        calc.execute("depth(n) := 0 when n <= 0").unwrap();
+        calc.execute("depth(n) := 1 + depth(n - 1) otherwise")
+            .unwrap();
+        assert_eq!(Ok(Value::Number(199.0)), calc.execute("depth(199)"));
+        assert_eq!(exceeded("depth", 200), calc.execute("depth(200)"));
+        calc.set_recursion_limit(10);
+        assert_eq!(exceeded("depth", 10), calc.execute("depth(10)"));
+        assert_eq!(Ok(Value::Number(9.0)), calc.execute("depth(9)"));
+    }
+
+    #[test]
+    fn factorials_and_gamma() {
+        let mut calc = Calculator::new();
+        assert_eq!(Ok(Value::Number(120.0)), calc.execute("10! / (3! * 7!)"));
+        assert_eq!(Ok(Value::Number(1.0)), calc.execute("0!"));
+        assert_eq!(Ok(Value::Number(720.0)), calc.execute("3!!"));
+        assert_eq!(Ok(Value::Number(24.0)), calc.execute("gamma(5)"));
+        assert!(matches!(
+            calc.execute("lgamma(1000)"),
+            Ok(Value::Number(num)) if (num - 5905.22).abs() < 0.01
+        ));
+        assert_eq!(
+            Err(Error::CalcError(CalcError::DomainError {
+                function: "!".to_string(),
+                argument: "2.5".to_string(),
+            })),
+            calc.execute("2.5!")
+        );
+        assert!(matches!(
+            calc.execute("gamma(-1)"),
+            Err(Error::CalcError(CalcError::DomainError { .. }))
+        ));
+        calc.set_ieee(true);
+        assert!(matches!(calc.execute("(-1)!"), Ok(Value::Number(num)) if num.is_nan()));
+    }
+
+    #[test]
+    fn sums_and_products() {
+        let mut calc = Calculator::new();
+        assert_eq!(
+            Ok(Value::Number(338350.0)),
+            calc.execute("sum(i, 1, 100, i^2)")
+        );
+        calc.execute("fact(n) := prod(i, 1, n, i)").unwrap();
+        assert_eq!(Ok(Value::Number(120.0)), calc.execute("fact(5)"));
+        assert_eq!(Ok(Value::Number(1.0)), calc.execute("fact(0)"));
+        calc.execute("i := 10").unwrap();
+        assert_eq!(Ok(Value::Number(16.0)), calc.execute("i + sum(i, 1, 3, i)"));
+        assert_eq!(
+            Ok(Value::Number(4.0)),
+            calc.execute("sum(k, 1, 2, sum(j, 1, k, j))")
+        );
+        calc.execute("geom(x) := sum(k, 0, 3, x^k)").unwrap();
+        assert_eq!(
+            vec![Ok(1.0), Ok(15.0)],
+            calc.eval_fn_many("geom", &[0.0, 2.0])
+        );
+        assert_eq!(
+            Err(Error::CalcError(CalcError::InvalidSeriesBound {
+                name: "sum".to_string(),
+                bound: "0.5".to_string()
+            })),
+            calc.execute("sum(k, 0.5, 3, k)")
+        );
+        assert!(matches!(
+            calc.execute("prod(k, 1, 1e9, 1)"),
+            Err(Error::CalcError(CalcError::SeriesTooLong { .. }))
+        ));
+    }
+
+    #[test]
+    fn let_bindings() {
+        let mut calc = Calculator::new();
+        calc.execute("f(x) := let t := x ^ 2; t + 1 / t").unwrap();
+        assert_eq!(Ok(Value::Number(4.25)), calc.execute("f(2)"));
+        assert_eq!(vec![Ok(2.0), Ok(4.25)], calc.eval_fn_many("f", &[1.0, 2.0]));
+        calc.execute("t := 10").unwrap();
+        assert_eq!(
+            Ok(Value::Number(13.0)),
+            calc.execute("let a := 1; let b := a + 2; t + b")
+        );
+        assert_eq!(Ok(Value::Number(11.0)), calc.execute("(let t := 1; t) + t"));
+        assert_eq!(
+            Ok(Value::Solved {
+                variable: "x".to_string(),
+                value: 3.0
+            }),
+            calc.execute("solve let a := 2; a * x = 6 for x")
+        );
+        assert!(calc.execute("let u := 1; u").is_ok());
+        assert_eq!(
+            Err(Error::CalcError(CalcError::UnknownSymbol {
+                name: "u".to_string(),
+                candidates: vec![],
+            })),
+            calc.execute("u")
+        );
+    }
+
+    #[test]
+    fn functions_as_arguments() {
+        let mut calc = Calculator::new();
+        calc.execute("apply_twice(g, x) := g(g(x))").unwrap();
+        assert_eq!(
+            Ok(Value::Number((1.0 as Number).sin().sin())),
+            calc.execute("apply_twice(sin, 1)")
+        );
+        calc.execute("sq(x) := x ^ 2").unwrap();
+        assert_eq!(Ok(Value::Number(81.0)), calc.execute("apply_twice(sq, 3)"));
+        calc.execute("g := 5").unwrap();
+        assert_eq!(Ok(Value::Number(625.0)), calc.execute("apply_twice(sq, g)"));
+
+        calc.execute(
+            "newton(f, df, x, n) := if(n <= 0, x, newton(f, df, x - f(x) / df(x), n - 1))",
+        )
+        .unwrap();
+        // `h` is a unit as well
+        calc.execute("h(x) := x ^ 2 - 2").unwrap();
+        calc.execute("dh(x) := 2 * x").unwrap();
+        let root = calc.execute("newton(h, dh, 1, 6)").unwrap();
+        assert!(matches!(root, Value::Number(x) if (x - (2.0 as Number).sqrt()).abs() < 1e-6));
+
+        calc.load_csv_str("1\n2\n3", 1, "xs").unwrap();
+        calc.execute("add(a, b) := a + b").unwrap();
+        calc.execute("total(g) := reduce(add, 0, map(g, xs))")
+            .unwrap();
+        assert_eq!(Ok(Value::Number(14.0)), calc.execute("total(sq)"));
+
+        calc.execute("pos(f, x) := f(x) when x > 0").unwrap();
+        calc.execute("pos(f, x) := 0 otherwise").unwrap();
+        assert_eq!(Ok(Value::Number(4.0)), calc.execute("pos(sq, 2)"));
+        assert_eq!(
+            Err(Error::CalcError(CalcError::UnknownSymbol {
+                name: "nope".to_string(),
+                candidates: vec![],
+            })),
+            calc.execute("apply_twice(nope, 1)")
+        );
+    }
+
+    #[test]
+    fn quantities_with_units() {
+        let mut calc = Calculator::new();
+        let value = |calc: &mut Calculator, line| calc.execute(line).unwrap().to_string();
+        assert_eq!("3.2 m", value(&mut calc, "3 m + 20 cm"));
+        assert_eq!("2.5 km/h", value(&mut calc, "5 km / 2 h"));
+        assert_eq!("1.5 h", value(&mut calc, "1 h + 30 min"));
+        assert_eq!("12 m^2", value(&mut calc, "(2 m + 1 m) * 4 m"));
+        calc.execute("ratio := 1 km / 1 m").unwrap();
+        assert_number(&mut calc, "ratio", 1000.0);
+        assert!(matches!(
+            calc.execute("3 m + 2 s"),
+            Err(Error::UnitError(UnitError::IncompatibleUnits { .. }))
+        ));
+        assert_eq!(
+            Err(Error::UnitError(UnitError::UnitInVariable {
+                sym: "d".to_string(),
+                unit: "m".to_string()
+            })),
+            calc.execute("d := 3 m")
+        );
+        calc.execute("m := 2").unwrap();
+        assert_eq!(Ok(Value::Number(6.0)), calc.execute("3 m"));
+    }
+
+    #[test]
+    fn answers() {
+        let mut calc = Calculator::new();
+        assert_eq!(Ok(Value::Number(0.0)), calc.execute("ans"));
+        calc.execute("1 + 2").unwrap();
+        calc.execute("a := 4").unwrap();
+        calc.execute("solve 2 * x = a for x").unwrap();
+        assert_eq!(Ok(Value::Number(4.0)), calc.execute("ans * 2"));
+        assert_eq!(Ok(Value::Number(4.0)), calc.execute("ans"));
+        assert_eq!(Ok(Value::Number(2.0)), calc.execute("ans3"));
+        assert_eq!(
+            Err(Error::CalcError(CalcError::CannotChangeConstant(
+                "ans".to_string()
+            ))),
+            calc.execute("ans := 1")
+        );
+        assert!(calc.changed_bindings().is_empty());
+    }
+
+    #[test]
+    fn registered_functions() {
+        let mut calc = Calculator::new();
+        calc.register_function("clamp", 3, |params| {
+            Ok(params[0].max(params[1]).min(params[2]))
+        });
+        calc.register_function("inv", 1, |params| {
+            if params[0] == 0.0 {
+                Err(CalcError::InvalidArguments {
+                    name: "inv".to_string(),
+                    reason: "division by zero".to_string(),
+                })
+            } else {
+                Ok(1.0 / params[0])
+            }
+        });
+        assert_eq!(Ok(Value::Number(1.0)), calc.execute("clamp(5, 0, 1)"));
+        assert_eq!(Ok(Value::Number(0.5)), calc.execute("inv(2)"));
+        assert_eq!(
+            Err(Error::CalcError(CalcError::UnexpectedNumberOfParameters {
+                name: "clamp".to_string(),
+                act: 1,
+                exp: 3,
+            })),
+            calc.execute("clamp(5)")
+        );
+        assert!(matches!(
+            calc.execute("inv(0)"),
+            Err(Error::CalcError(CalcError::InvalidArguments { .. }))
+        ));
+        assert_eq!(
+            vec![Ok(0.5), Ok(0.25)],
+            calc.eval_fn_many("inv", &[2.0, 4.0])
+        );
+        assert_eq!(Ok(Value::Void), calc.execute("g(x) := inv(x) + 1"));
+        assert_eq!(Ok(Value::Number(1.5)), calc.execute("g(2)"));
+    }
+
+    #[test]
+    fn simple_plot() {
+        let mut calc = Calculator::new();
+        assert_eq!(Ok(Value::Void), calc.execute("f(x) := x ^ 2"));
+        let graph = calc.execute("plot f").unwrap();
+        assert!(matches!(&graph, Value::Graph(_)));
+        if let Value::Graph(graph) = graph {
+            let plot = graph
+                .plot(
+                    &Area::new(-100., -100., 100., 100.),
+                    &Area::new(0., 0., 80., 30.),
+                )
+                .unwrap();
+            assert!(!plot.points.is_empty());
+        }
+    }
+}
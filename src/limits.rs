@@ -0,0 +1,111 @@
+//! Caps on the resources used by untrusted input.
+
+use crate::ast::Statement;
+use crate::calc::TopLevelEnv;
+use crate::stats::ExpressionStats;
+
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum LimitError {
+    #[error("Too many definitions - at most {max} variables and functions are allowed")]
+    TooManyBindings { max: usize },
+    #[error("Statement is too large - it has {nodes} nodes, but at most {max} are allowed")]
+    TooManyNodes { nodes: usize, max: usize },
+    #[error("Too much data - {count} numbers are stored, but at most {max} are allowed")]
+    TooManyNumbers { count: usize, max: usize },
+    #[error("Evaluation takes too long - it needs more than {max} operations")]
+    TooManyOperations { max: u64 },
+    #[error("Evaluation takes too long - it needs more than {millis} ms")]
+    Timeout { millis: u128 },
+}
+
+/// Resource limits of a [`Calculator`](crate::Calculator), `None` means unlimited.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// variables, functions, formulas and equations defined by the user
+    pub max_bindings: Option<usize>,
+    /// nodes of the expressions in a single statement
+    pub max_nodes: Option<usize>,
+    /// numbers in lists and sequence values plus bytes of texts, stored or returned by a statement
+    pub max_numbers: Option<usize>,
+    /// operations like arithmetic, function calls and plotted points of a single statement
+    /// or plot, exceeding them aborts the evaluation
+    pub max_operations: Option<u64>,
+    /// wall-clock time of a single statement or plot, exceeding it aborts the evaluation
+    pub timeout: Option<Duration>,
+}
+
+impl Limits {
+    /// checks the size of a statement before it is executed
+    pub(crate) fn check_statement(&self, st: &Statement) -> Result<(), LimitError> {
+        if let Some(max) = self.max_nodes {
+            let nodes = ExpressionStats::of_statement(st).nodes;
+            if nodes > max {
+                return Err(LimitError::TooManyNodes { nodes, max });
+            }
+        }
+        Ok(())
+    }
+
+    /// checks the environment and the number of returned numbers after a statement
+    pub(crate) fn check_env(&self, env: &TopLevelEnv, returned: usize) -> Result<(), LimitError> {
+        if let Some(max) = self.max_bindings {
+            if env.binding_count() > max {
+                return Err(LimitError::TooManyBindings { max });
+            }
+        }
+        if let Some(max) = self.max_numbers {
+            let count = env.stored_numbers() + returned;
+            if count > max {
+                return Err(LimitError::TooManyNumbers { count, max });
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::Env;
+    use crate::parser::parse;
+
+    #[test]
+    fn unlimited_by_default() {
+        let limits = Limits::default();
+        let st = parse("f(x) := x ^ 2 + 1").unwrap();
+        assert_eq!(Ok(()), limits.check_statement(&st));
+        assert_eq!(Ok(()), limits.check_env(&TopLevelEnv::default(), 1_000_000));
+    }
+
+    #[test]
+    fn exceeded_limits() {
+        let limits = Limits {
+            max_bindings: Some(1),
+            max_nodes: Some(4),
+            max_numbers: Some(3),
+            ..Limits::default()
+        };
+        let st = parse("f(x) := x ^ 2 + 1").unwrap();
+        assert_eq!(
+            Err(LimitError::TooManyNodes { nodes: 5, max: 4 }),
+            limits.check_statement(&st)
+        );
+
+        let mut env = TopLevelEnv::default();
+        env.put_list("xs".to_string(), vec![1.0, 2.0]).unwrap();
+        assert_eq!(Ok(()), limits.check_env(&env, 1));
+        assert_eq!(
+            Err(LimitError::TooManyNumbers { count: 4, max: 3 }),
+            limits.check_env(&env, 2)
+        );
+        env.put("a".to_string(), 1.0).unwrap();
+        assert!(env.get("pi").is_some());
+        assert_eq!(
+            Err(LimitError::TooManyBindings { max: 1 }),
+            limits.check_env(&env, 0)
+        );
+    }
+}
@@ -0,0 +1,231 @@
+//! Linear algebra on matrices stored as lists in row-major order.
+//!
+//! `det`, `inv` and `linsolve` take square matrices, so the dimension follows from the
+//! length of the list, e.g. `[1, 2, 3, 4]` is the matrix with rows `1 2` and `3 4`.
+//! `transpose(A, rows)` needs the number of rows, `transpose(A)` of a matrix value is
+//! evaluated in `matrix`.
+
+use crate::ast::{Data, DataFunction, Number};
+
+/// pivots with a smaller absolute value are treated as zero
+const EPSILON: Number = 1e-12;
+
+fn list<'a>(params: &'a [Data], idx: usize, name: &str) -> Result<&'a [Number], String> {
+    params[idx]
+        .list()
+        .ok_or_else(|| format!("{} must be a list", name))
+}
+
+/// dimension of the square matrix `a`
+fn dimension(a: &[Number]) -> Result<usize, String> {
+    let n = (a.len() as Number).sqrt().round() as usize;
+    if n == 0 || n * n != a.len() {
+        Err(format!(
+            "a square matrix is needed, but got a list of {} elements",
+            a.len()
+        ))
+    } else {
+        Ok(n)
+    }
+}
+
+/// Gauss-Jordan elimination of the `n`x`n` matrix `a` augmented by the `n`x`m` matrix `b`.
+/// Returns the determinant of `a` and, if `a` is regular, the solution `x` of `a * x = b`.
+fn eliminate(a: &[Number], n: usize, b: &[Number], m: usize) -> (Number, Option<Vec<Number>>) {
+    let width = n + m;
+    let mut rows: Vec<Number> = (0..n)
+        .flat_map(|row| {
+            a[row * n..(row + 1) * n]
+                .iter()
+                .chain(b[row * m..(row + 1) * m].iter())
+                .copied()
+        })
+        .collect();
+
+    let mut det = 1.0;
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|i, j| {
+                rows[i * width + col]
+                    .abs()
+                    .total_cmp(&rows[j * width + col].abs())
+            })
+            .unwrap();
+        if rows[pivot * width + col].abs() < EPSILON {
+            return (0.0, None);
+        }
+        if pivot != col {
+            for k in 0..width {
+                rows.swap(pivot * width + k, col * width + k);
+            }
+            det = -det;
+        }
+        let value = rows[col * width + col];
+        det *= value;
+        for k in 0..width {
+            rows[col * width + k] /= value;
+        }
+        for row in (0..n).filter(|row| *row != col) {
+            let factor = rows[row * width + col];
+            for k in 0..width {
+                rows[row * width + k] -= factor * rows[col * width + k];
+            }
+        }
+    }
+
+    let x = (0..n)
+        .flat_map(|row| rows[row * width + n..(row + 1) * width].to_vec())
+        .collect();
+    (det, Some(x))
+}
+
+fn identity(n: usize) -> Vec<Number> {
+    (0..n * n)
+        .map(|idx| if idx / n == idx % n { 1.0 } else { 0.0 })
+        .collect()
+}
+
+pub fn det(a: &[Number]) -> Result<Number, String> {
+    let n = dimension(a)?;
+    Ok(eliminate(a, n, &[], 0).0)
+}
+
+pub fn inv(a: &[Number]) -> Result<Vec<Number>, String> {
+    let n = dimension(a)?;
+    eliminate(a, n, &identity(n), n)
+        .1
+        .ok_or_else(|| "matrix is singular".to_string())
+}
+
+pub fn linsolve(a: &[Number], b: &[Number]) -> Result<Vec<Number>, String> {
+    let n = dimension(a)?;
+    if b.len() != n {
+        return Err(format!(
+            "b must have {} elements, but has {} elements",
+            n,
+            b.len()
+        ));
+    }
+    eliminate(a, n, b, 1)
+        .1
+        .ok_or_else(|| "matrix is singular".to_string())
+}
+
+pub fn transpose(a: &[Number], rows: usize) -> Result<Vec<Number>, String> {
+    if rows == 0 || !a.len().is_multiple_of(rows) {
+        return Err(format!(
+            "a list of {} elements has no {} rows",
+            a.len(),
+            rows
+        ));
+    }
+    let cols = a.len() / rows;
+    Ok((0..a.len())
+        .map(|idx| a[(idx % rows) * cols + idx / rows])
+        .collect())
+}
+
+fn det_fn(params: &[Data]) -> Result<Data, String> {
+    det(list(params, 0, "A")?).map(Data::Number)
+}
+
+fn inv_fn(params: &[Data]) -> Result<Data, String> {
+    inv(list(params, 0, "A")?).map(Data::List)
+}
+
+fn linsolve_fn(params: &[Data]) -> Result<Data, String> {
+    linsolve(list(params, 0, "A")?, list(params, 1, "b")?).map(Data::List)
+}
+
+fn transpose_fn(params: &[Data]) -> Result<Data, String> {
+    let rows = match params[1].number() {
+        Some(rows) if rows >= 1.0 && rows.fract() == 0.0 => rows as usize,
+        _ => return Err("rows must be a positive integer".to_string()),
+    };
+    transpose(list(params, 0, "A")?, rows).map(Data::List)
+}
+
+pub(crate) fn functions() -> Vec<DataFunction> {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect();
+    vec![
+        DataFunction {
+            name: "det".to_string(),
+            args: args(&["A"]),
+            returns_list: false,
+            body: &det_fn,
+        },
+        DataFunction {
+            name: "inv".to_string(),
+            args: args(&["A"]),
+            returns_list: true,
+            body: &inv_fn,
+        },
+        DataFunction {
+            name: "linsolve".to_string(),
+            args: args(&["A", "b"]),
+            returns_list: true,
+            body: &linsolve_fn,
+        },
+        DataFunction {
+            name: "transpose".to_string(),
+            args: args(&["A", "rows"]),
+            returns_list: true,
+            body: &transpose_fn,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    fn assert_all_approx_eq(exp: &[Number], act: &[Number]) {
+        assert_eq!(exp.len(), act.len());
+        for (exp, act) in exp.iter().zip(act.iter()) {
+            assert_approx_eq!(*exp, *act);
+        }
+    }
+
+    #[test]
+    fn linalg_det() {
+        assert_approx_eq!(-2.0, det(&[1.0, 2.0, 3.0, 4.0]).unwrap());
+        assert_approx_eq!(0.0, det(&[1.0, 2.0, 2.0, 4.0]).unwrap());
+        assert_approx_eq!(
+            5.0,
+            det(&[2.0, 0.0, 1.0, 1.0, 1.0, 0.0, 0.0, 3.0, 1.0]).unwrap()
+        );
+        assert!(det(&[1.0, 2.0]).is_err());
+    }
+
+    #[test]
+    fn linalg_inv() {
+        assert_all_approx_eq(
+            &[-2.0, 1.0, 1.5, -0.5],
+            &inv(&[1.0, 2.0, 3.0, 4.0]).unwrap(),
+        );
+        assert_eq!(
+            Err("matrix is singular".to_string()),
+            inv(&[1.0, 2.0, 2.0, 4.0])
+        );
+    }
+
+    #[test]
+    fn linalg_linsolve() {
+        // x + y = 3, x - y = 1
+        assert_all_approx_eq(
+            &[2.0, 1.0],
+            &linsolve(&[1.0, 1.0, 1.0, -1.0], &[3.0, 1.0]).unwrap(),
+        );
+        assert!(linsolve(&[1.0, 1.0, 1.0, -1.0], &[3.0]).is_err());
+    }
+
+    #[test]
+    fn linalg_transpose() {
+        assert_eq!(
+            Ok(vec![1.0, 4.0, 2.0, 5.0, 3.0, 6.0]),
+            transpose(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 2)
+        );
+        assert!(transpose(&[1.0, 2.0, 3.0], 2).is_err());
+    }
+}
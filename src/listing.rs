@@ -0,0 +1,111 @@
+//! Overview of the variables and functions defined in a session, e.g. for a sidebar.
+
+use crate::ast::Function;
+use crate::calc::{Env, TopLevelEnv};
+use crate::Value;
+
+/// A variable defined by the user.
+#[derive(Debug, PartialEq)]
+pub struct VariableEntry {
+    pub name: String,
+    /// a number, list or text
+    pub value: Value,
+    /// the formula of a variable defined by `define`
+    pub formula: Option<String>,
+}
+
+/// A function defined by the user.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionEntry {
+    pub name: String,
+    pub args: Vec<String>,
+    /// the definition as source text, one line per clause
+    pub definition: String,
+}
+
+/// Result of the `list` statement.
+#[derive(Debug, PartialEq, Default)]
+pub struct Listing {
+    pub variables: Vec<VariableEntry>,
+    pub functions: Vec<FunctionEntry>,
+}
+
+/// variables of the user ordered by name, variables of failed formulas have no value
+pub fn variables(env: &TopLevelEnv) -> Vec<VariableEntry> {
+    let mut names: Vec<&str> = env.user_var_names().chain(env.formula_names()).collect();
+    names.sort();
+    names.dedup();
+    names
+        .into_iter()
+        .map(|name| {
+            let value = match (env.get(name), env.get_list(name), env.get_text(name)) {
+                (Some(num), _, _) => Value::Number(*num),
+                (_, Some(list), _) => Value::List(list.to_vec()),
+                (_, _, Some(text)) => Value::Text(text.to_string()),
+                _ => match env.get_matrix(name) {
+                    Some(matrix) => Value::Matrix(matrix.clone()),
+                    None => Value::Void,
+                },
+            };
+            VariableEntry {
+                name: name.to_string(),
+                value,
+                formula: env.formula(name).map(|op| op.to_string()),
+            }
+        })
+        .collect()
+}
+
+/// functions of the user ordered by name, without build-in and registered functions
+pub fn functions(env: &TopLevelEnv) -> Vec<FunctionEntry> {
+    let mut functions: Vec<FunctionEntry> = env
+        .fun_names()
+        .filter_map(|name| {
+            let args = match env.get_fun(name)? {
+                Function::Custom(fun) => fun.args.clone(),
+                Function::Piecewise(fun) => fun.args.clone(),
+                Function::Sequence(seq) => vec![seq.arg.clone()],
+                Function::BuildIn(_) | Function::Data(_) => return None,
+            };
+            Some(FunctionEntry {
+                name: name.to_string(),
+                args,
+                definition: env.get_fun(name)?.definition(name).to_string(),
+            })
+        })
+        .collect();
+    functions.sort_by(|lhs, rhs| lhs.name.cmp(&rhs.name));
+    functions
+}
+
+pub fn listing(env: &TopLevelEnv) -> Listing {
+    Listing {
+        variables: variables(env),
+        functions: functions(env),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_expression;
+
+    #[test]
+    fn user_definitions_only() {
+        let mut env = TopLevelEnv::default();
+        env.put_text("t".to_string(), "s".to_string()).unwrap();
+        env.put("a".to_string(), 1.0).unwrap();
+        env.put_formula("b".to_string(), parse_expression("a + c").unwrap());
+        let variables = variables(&env);
+        assert_eq!(
+            vec!["a", "b", "t"],
+            variables
+                .iter()
+                .map(|var| var.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Value::Void, variables[1].value);
+        assert_eq!(Some("a + c".to_string()), variables[1].formula);
+        assert!(functions(&env).is_empty());
+    }
+}
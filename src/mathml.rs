@@ -0,0 +1,515 @@
+//! Export of expressions and statements to presentation MathML.
+
+use crate::ast::*;
+
+fn mo(op: &str) -> String {
+    format!("<mo>{}</mo>", op)
+}
+
+fn mi(name: &str) -> String {
+    let name = match name {
+        "pi" => "&#x3C0;",
+        "tau" => "&#x3C4;",
+        name => name,
+    };
+    format!("<mi>{}</mi>", name)
+}
+
+fn mrow(content: &str) -> String {
+    format!("<mrow>{}</mrow>", content)
+}
+
+fn fenced(content: &str) -> String {
+    mrow(&format!("{}{}{}", mo("("), content, mo(")")))
+}
+
+fn number(num: Number) -> String {
+    if num < 0.0 {
+        mrow(&format!("{}<mn>{}</mn>", mo("-"), -num))
+    } else {
+        format!("<mn>{}</mn>", num)
+    }
+}
+
+fn operation(op: Operation) -> String {
+    mo(match op {
+        Operation::Add => "+",
+        Operation::Sub => "-",
+        Operation::Mul => "&#x22C5;",
+        Operation::Div => "/",
+        Operation::Rem => "mod",
+        Operation::Pow => "^",
+    })
+}
+
+/// renders `op` as child of a term with operation `parent`,
+/// adds parenthesis if the precedence requires them
+fn child(op: &Operand, parent: Operation, is_rhs: bool) -> String {
+    let needs_parens = match op {
+        Operand::Number(num) => *num < 0.0 && (is_rhs || parent == Operation::Pow),
+        op => parent.needs_parens(op, is_rhs),
+    };
+    let rendered = operand(op);
+    if needs_parens {
+        fenced(&rendered)
+    } else {
+        rendered
+    }
+}
+
+fn term(term: &Term) -> String {
+    match term.op {
+        Operation::Div => format!(
+            "<mfrac>{}{}</mfrac>",
+            mrow(&operand(&term.lhs)),
+            mrow(&operand(&term.rhs))
+        ),
+        Operation::Pow => format!(
+            "<msup>{}{}</msup>",
+            child(&term.lhs, term.op, false),
+            mrow(&operand(&term.rhs))
+        ),
+        op => mrow(&format!(
+            "{}{}{}",
+            child(&term.lhs, op, false),
+            operation(op),
+            child(&term.rhs, op, true)
+        )),
+    }
+}
+
+fn fun_call(fun_call: &FunCall) -> String {
+    match (fun_call.name.as_str(), fun_call.params.as_slice()) {
+        ("sqrt", [param]) => format!("<msqrt>{}</msqrt>", operand(param)),
+        ("abs", [param]) => mrow(&format!("{}{}{}", mo("|"), operand(param), mo("|"))),
+        (name, params) => signature(name, &params.iter().map(operand).collect::<Vec<String>>()),
+    }
+}
+
+fn text(text: &Text) -> String {
+    text.parts
+        .iter()
+        .map(|part| match part {
+            TextPart::Literal(literal) => format!("<ms>{}</ms>", literal),
+            TextPart::Symbol(sym) => mi(sym),
+        })
+        .collect::<Vec<_>>()
+        .join(&mo("+"))
+}
+
+/// e.g. `in [0, 1]` of a numeric solve
+fn interval(bracket: &Bracket) -> String {
+    format!(
+        "<mtext>in</mtext>{}{}{}{}{}",
+        mo("["),
+        operand(&bracket.from),
+        mo(","),
+        operand(&bracket.to),
+        mo("]")
+    )
+}
+
+fn plot_labels(labels: &PlotLabels) -> String {
+    let labels = [
+        ("title", &labels.title),
+        ("xlabel", &labels.xlabel),
+        ("ylabel", &labels.ylabel),
+    ];
+    labels
+        .iter()
+        .filter_map(|(keyword, label)| {
+            label
+                .as_ref()
+                .map(|label| format!("<mtext>{}</mtext>{}", keyword, text(label)))
+        })
+        .collect()
+}
+
+fn signature(name: &str, params: &[String]) -> String {
+    mrow(&format!(
+        "{}{}{}",
+        mi(name),
+        mo("&#x2061;"),
+        fenced(&params.join(&mo(",")))
+    ))
+}
+
+fn guard(guard: &Guard) -> String {
+    comparison(&guard.lhs, guard.cmp, &guard.rhs)
+}
+
+fn comparison(lhs: &Operand, cmp: Comparison, rhs: &Operand) -> String {
+    format!(
+        "{}{}{}",
+        operand(lhs),
+        mo(match cmp {
+            Comparison::Less => "&lt;",
+            Comparison::LessEqual => "&#x2264;",
+            Comparison::Greater => "&gt;",
+            Comparison::GreaterEqual => "&#x2265;",
+            Comparison::Equal => "=",
+            Comparison::NotEqual => "&#x2260;",
+        }),
+        operand(rhs)
+    )
+}
+
+/// renders an operand as MathML fragment (without the surrounding `<math>` element)
+pub fn operand(op: &Operand) -> String {
+    match op {
+        Operand::Number(num) => number(*num),
+        Operand::Literal(lit) => number(lit.value),
+        Operand::Symbol(sym) => mi(sym),
+        Operand::Term(t) => term(t),
+        Operand::FunCall(call) => fun_call(call),
+        Operand::Conditional(cond) => format!(
+            "{}{}{}",
+            mi("if"),
+            mo("&#x2061;"),
+            fenced(
+                &[
+                    guard(&cond.guard),
+                    operand(&cond.then),
+                    operand(&cond.otherwise)
+                ]
+                .join(&mo(","))
+            )
+        ),
+        Operand::Series(series) => mrow(&format!(
+            "<munderover>{}{}{}</munderover>{}",
+            mo(match series.kind {
+                SeriesKind::Sum => "&#x2211;",
+                SeriesKind::Product => "&#x220F;",
+            }),
+            mrow(&format!(
+                "{}{}{}",
+                mi(&series.var),
+                mo("="),
+                operand(&series.from)
+            )),
+            mrow(&operand(&series.to)),
+            match &series.body {
+                Operand::Term(term) if term.op.precedence() == 1 => fenced(&operand(&series.body)),
+                body => operand(body),
+            }
+        )),
+        Operand::Let(binding) => mrow(&format!(
+            "{}{}{}{}{}{}",
+            mi("let"),
+            mi(&binding.var),
+            mo(":="),
+            operand(&binding.value),
+            mo(";"),
+            operand(&binding.body)
+        )),
+        Operand::Factorial(op) if op.needs_parens_in_factorial() => {
+            mrow(&format!("{}{}", fenced(&operand(op)), mo("!")))
+        }
+        Operand::Factorial(op) => mrow(&format!("{}{}", operand(op), mo("!"))),
+        Operand::Matrix(rows) => mrow(&format!(
+            "{}<mtable>{}</mtable>{}",
+            mo("["),
+            rows.iter()
+                .map(|row| {
+                    let cells = row.iter().map(|op| format!("<mtd>{}</mtd>", operand(op)));
+                    format!("<mtr>{}</mtr>", cells.collect::<String>())
+                })
+                .collect::<String>(),
+            mo("]")
+        )),
+    }
+}
+
+/// renders a statement as a complete `<math>` element
+pub fn statement(st: &Statement) -> String {
+    format!(
+        "<math xmlns=\"http://www.w3.org/1998/Math/MathML\">{}</math>",
+        mrow(&content(st))
+    )
+}
+
+fn content(st: &Statement) -> String {
+    match st {
+        Statement::Expression { op } => operand(op),
+        Statement::Assignment { sym, op } => format!("{}{}{}", mi(sym), mo(":="), operand(op)),
+        Statement::SolveFor {
+            lhs,
+            rhs,
+            sym,
+            bracket,
+        } => format!(
+            "<mtext>solve</mtext>{}{}{}<mtext>for</mtext>{}{}",
+            operand(lhs),
+            mo("="),
+            operand(rhs),
+            mi(sym),
+            bracket.as_ref().map(interval).unwrap_or_default()
+        ),
+        Statement::SolveInequality { lhs, cmp, rhs, sym } => format!(
+            "<mtext>solve</mtext>{}<mtext>for</mtext>{}",
+            comparison(lhs, *cmp, rhs),
+            mi(sym)
+        ),
+        Statement::Integrate { name, bracket } => format!(
+            "<mtext>integrate</mtext>{}<mtext>from</mtext>{}<mtext>to</mtext>{}",
+            mi(name),
+            operand(&bracket.from),
+            operand(&bracket.to)
+        ),
+        Statement::IntegrateExpression { op, sym, bracket } => format!(
+            "<mtext>integrate</mtext>{}<mtext>for</mtext>{}{}",
+            operand(op),
+            mi(sym),
+            interval(bracket)
+        ),
+        Statement::Store { name, lhs, rhs } => format!(
+            "<mtext>store</mtext>{}{}{}{}{}",
+            mi(name),
+            mo(":"),
+            operand(lhs),
+            mo("="),
+            operand(rhs)
+        ),
+        Statement::SolveStored { name, sym, given } => {
+            let given = given
+                .iter()
+                .map(|(sym, op)| format!("{}{}{}", mi(sym), mo(":="), operand(op)))
+                .collect::<Vec<_>>();
+            let given = match given.is_empty() {
+                true => String::new(),
+                false => format!("<mtext>given</mtext>{}", given.join(&mo(","))),
+            };
+            format!(
+                "<mtext>solve</mtext>{}<mtext>for</mtext>{}{}",
+                mi(name),
+                mi(sym),
+                given
+            )
+        }
+        Statement::Function { name, fun } => match fun {
+            Function::Custom(fun) => format!(
+                "{}{}{}",
+                signature(
+                    name,
+                    &fun.args.iter().map(|arg| mi(arg)).collect::<Vec<String>>()
+                ),
+                mo(":="),
+                operand(&fun.body)
+            ),
+            Function::BuildIn(fun) => {
+                let mut args = fun.args.iter().map(|arg| mi(arg)).collect::<Vec<String>>();
+                if fun.variadic {
+                    args.push(mo("&#x2026;"));
+                }
+                signature(name, &args)
+            }
+            Function::Data(fun) => signature(
+                name,
+                &fun.args.iter().map(|arg| mi(arg)).collect::<Vec<String>>(),
+            ),
+            Function::Sequence(seq) => match &seq.body {
+                Some(body) => format!(
+                    "{}{}{}",
+                    signature(name, &[mi(&seq.arg)]),
+                    mo(":="),
+                    operand(body)
+                ),
+                None => signature(name, &[mi(&seq.arg)]),
+            },
+            Function::Piecewise(fun) => {
+                let args = fun.args.iter().map(|arg| mi(arg)).collect::<Vec<String>>();
+                let clauses = fun.clauses.iter().map(|clause| {
+                    let condition = match &clause.guard {
+                        Some(guard) => format!("<mtext>when</mtext>{}", self::guard(guard)),
+                        None => "<mtext>otherwise</mtext>".to_string(),
+                    };
+                    format!(
+                        "{}{}{}{}",
+                        signature(name, &args),
+                        mo(":="),
+                        operand(&clause.body),
+                        condition
+                    )
+                });
+                clauses.collect::<Vec<_>>().join(&mo(";"))
+            }
+        },
+        Statement::Plot {
+            name,
+            sweep,
+            labels,
+        } => {
+            let (function, sweep) = match sweep {
+                Some(sweep) => {
+                    let args = sweep
+                        .args
+                        .iter()
+                        .map(|arg| mi(arg))
+                        .collect::<Vec<String>>();
+                    let step = match &sweep.step {
+                        Some(step) => format!("<mtext>step</mtext>{}", operand(step)),
+                        None => String::new(),
+                    };
+                    let sweep = format!(
+                        "<mtext>for</mtext>{}{}{}{}{}{}",
+                        mi(&sweep.param),
+                        mo("&#x2208;"),
+                        operand(&sweep.from),
+                        mo(".."),
+                        operand(&sweep.to),
+                        step
+                    );
+                    (signature(name, &args), sweep)
+                }
+                None => (mi(name), String::new()),
+            };
+            format!(
+                "<mtext>plot</mtext>{}{}{}",
+                function,
+                sweep,
+                plot_labels(labels)
+            )
+        }
+        Statement::PlotExpression { op, labels } => {
+            format!("<mtext>plot</mtext>{}{}", operand(op), plot_labels(labels))
+        }
+        Statement::PlotPolar {
+            op,
+            bracket,
+            labels,
+        } => format!(
+            "<mtext>plot</mtext><mtext>polar</mtext>{}{}{}",
+            operand(op),
+            bracket.as_ref().map(interval).unwrap_or_default(),
+            plot_labels(labels)
+        ),
+        Statement::PlotImplicit {
+            lhs,
+            rhs: Some(rhs),
+            labels,
+        } => format!(
+            "<mtext>plot</mtext>{}{}{}{}",
+            operand(lhs),
+            mo("="),
+            operand(rhs),
+            plot_labels(labels)
+        ),
+        Statement::PlotImplicit {
+            lhs,
+            rhs: None,
+            labels,
+        } => format!(
+            "<mtext>plot</mtext><mtext>implicit</mtext>{}{}",
+            operand(lhs),
+            plot_labels(labels)
+        ),
+        Statement::TextAssignment { sym, text: t } => {
+            format!("{}{}{}", mi(sym), mo(":="), text(t))
+        }
+        Statement::TextExpression { text: t } => text(t),
+        Statement::Equivalent { lhs, rhs } => format!(
+            "<mtext>is</mtext>{}{}{}",
+            operand(lhs),
+            mo("&#x2261;"),
+            operand(rhs)
+        ),
+        Statement::Simplify { op } => format!("<mtext>simplify</mtext>{}", operand(op)),
+        Statement::Import { path, column, sym } => format!(
+            "<mtext>import</mtext><ms>{}</ms><mtext>column</mtext><mn>{}</mn><mtext>as</mtext>{}",
+            path,
+            column,
+            mi(sym)
+        ),
+        Statement::Run { path } => format!("<mtext>run</mtext><ms>{}</ms>", path),
+        Statement::SequenceValue { name, index, op } => format!(
+            "{}{}{}",
+            signature(name, &[number(*index as Number)]),
+            mo(":="),
+            operand(op)
+        ),
+        Statement::FitLinear { xs, ys, name } => format!(
+            "<mtext>fit linear</mtext>{}{}{}{}",
+            mi(xs),
+            mo(","),
+            mi(ys),
+            name.as_ref()
+                .map(|name| format!("<mtext>as</mtext>{}", mi(name)))
+                .unwrap_or_default()
+        ),
+        Statement::Define { sym, op } => {
+            format!("<mtext>define</mtext>{}{}{}", mi(sym), mo("="), operand(op))
+        }
+        Statement::Override { st } => format!("<mtext>override</mtext>{}", content(st)),
+        Statement::List => "<mtext>list</mtext>".to_string(),
+        Statement::Undo => "<mtext>undo</mtext>".to_string(),
+        Statement::Empty => String::new(),
+        Statement::Unset { name } => format!("<mtext>unset</mtext>{}", mi(name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn expr(s: &str) -> String {
+        match parse(s).unwrap() {
+            Statement::Expression { op } => operand(&op),
+            _ => panic!("not an expression"),
+        }
+    }
+
+    #[test]
+    fn mathml_simple_sum() {
+        assert_eq!("<mrow><mi>x</mi><mo>+</mo><mn>1</mn></mrow>", expr("x + 1"));
+    }
+
+    #[test]
+    fn mathml_fraction_and_power() {
+        assert_eq!(
+            "<mfrac><mrow><mn>1</mn></mrow><mrow><msup><mi>x</mi><mrow><mn>2</mn></mrow></msup></mrow></mfrac>",
+            expr("1 / x ^ 2")
+        );
+    }
+
+    #[test]
+    fn mathml_parenthesis_by_precedence() {
+        assert_eq!(
+            "<mrow><mrow><mo>(</mo><mrow><mi>a</mi><mo>+</mo><mi>b</mi></mrow><mo>)</mo></mrow><mo>&#x22C5;</mo><mi>c</mi></mrow>",
+            expr("(a + b) * c")
+        );
+        assert_eq!(
+            "<mrow><mi>a</mi><mo>-</mo><mrow><mo>(</mo><mrow><mi>b</mi><mo>-</mo><mi>c</mi></mrow><mo>)</mo></mrow></mrow>",
+            expr("a - (b - c)")
+        );
+        assert_eq!(
+            "<mrow><mrow><mi>a</mi><mo>-</mo><mi>b</mi></mrow><mo>-</mo><mi>c</mi></mrow>",
+            expr("a - b - c")
+        );
+    }
+
+    #[test]
+    fn mathml_function_calls() {
+        assert_eq!("<msqrt><mi>x</mi></msqrt>", expr("sqrt(x)"));
+        assert_eq!(
+            "<mrow><mi>f</mi><mo>&#x2061;</mo><mrow><mo>(</mo><mi>x</mi><mo>,</mo><mi>&#x3C0;</mi><mo>)</mo></mrow></mrow>",
+            expr("f(x, pi)")
+        );
+    }
+
+    #[test]
+    fn mathml_matrix() {
+        assert_eq!(
+            "<mrow><mo>[</mo><mtable><mtr><mtd><mn>1</mn></mtd><mtd><mi>x</mi></mtd></mtr></mtable><mo>]</mo></mrow>",
+            expr("[1, x]")
+        );
+    }
+
+    #[test]
+    fn mathml_statement() {
+        assert_eq!(
+            "<math xmlns=\"http://www.w3.org/1998/Math/MathML\"><mrow><mi>a</mi><mo>:=</mo><mrow><mo>-</mo><mn>2</mn></mrow></mrow></math>",
+            statement(&parse("a := -2").unwrap())
+        );
+    }
+}
@@ -0,0 +1,339 @@
+//! Vectors and matrices with element-wise and linear algebra operations.
+//!
+//! A vector is a list, e.g. `[1, 2, 3]`, the rows of a matrix are separated by `;`,
+//! e.g. `[1, 2; 3, 4]`. Like in `linalg`, matrices are stored in row-major order.
+
+use crate::ast::{FunCall, Function, Number, Operand, Operation};
+use crate::budget;
+use crate::calc::{calc_list, calc_operand, calc_operation, CalcError, Env};
+
+/// A matrix of at least one row and one column.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Matrix {
+    rows: usize,
+    cols: usize,
+    elements: Vec<Number>,
+}
+
+impl Matrix {
+    /// the matrix of `rows` rows with `elements` in row-major order,
+    /// if the elements fill all rows equally
+    pub fn new(rows: usize, elements: Vec<Number>) -> Option<Matrix> {
+        if rows == 0 || elements.is_empty() || !elements.len().is_multiple_of(rows) {
+            return None;
+        }
+        Some(Matrix {
+            rows,
+            cols: elements.len() / rows,
+            elements,
+        })
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    /// the elements in row-major order
+    pub fn elements(&self) -> &[Number] {
+        &self.elements
+    }
+
+    /// the elements of row `row` counted from 0
+    pub fn row(&self, row: usize) -> &[Number] {
+        &self.elements[row * self.cols..(row + 1) * self.cols]
+    }
+
+    fn get(&self, row: usize, col: usize) -> Number {
+        self.elements[row * self.cols + col]
+    }
+
+    /// `self * rhs` with `rhs` of `self.cols` rows and `cols` columns in row-major order
+    fn mul(&self, rhs: &[Number], cols: usize) -> Result<Vec<Number>, CalcError> {
+        budget::spend((self.rows * self.cols * cols) as u64)?;
+        Ok((0..self.rows)
+            .flat_map(|row| {
+                (0..cols).map(move |col| {
+                    (0..self.cols)
+                        .map(|k| self.get(row, k) * rhs[k * cols + col])
+                        .sum()
+                })
+            })
+            .collect())
+    }
+
+    fn transposed(&self) -> Matrix {
+        Matrix {
+            rows: self.cols,
+            cols: self.rows,
+            elements: (0..self.cols)
+                .flat_map(|col| (0..self.rows).map(move |row| self.get(row, col)))
+                .collect(),
+        }
+    }
+}
+
+/// Value of an operand with vectors or matrices.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Array {
+    Number(Number),
+    Vector(Vec<Number>),
+    Matrix(Matrix),
+}
+
+impl Array {
+    /// e.g. `a vector of 3 elements` in errors
+    fn shape(&self) -> String {
+        match self {
+            Array::Number(_) => "a number".to_string(),
+            Array::Vector(vector) => format!("a vector of {} elements", vector.len()),
+            Array::Matrix(matrix) => format!("a {}x{} matrix", matrix.rows, matrix.cols),
+        }
+    }
+
+    /// the elements in row-major order, a number is a list of one element
+    pub fn into_elements(self) -> Vec<Number> {
+        match self {
+            Array::Number(num) => vec![num],
+            Array::Vector(vector) => vector,
+            Array::Matrix(matrix) => matrix.elements,
+        }
+    }
+
+    fn map(self, f: impl Fn(Number) -> Result<Number, CalcError>) -> Result<Array, CalcError> {
+        let map = |elements: Vec<Number>| elements.into_iter().map(&f).collect::<Result<_, _>>();
+        Ok(match self {
+            Array::Number(num) => Array::Number(f(num)?),
+            Array::Vector(vector) => Array::Vector(map(vector)?),
+            Array::Matrix(matrix) => Array::Matrix(Matrix {
+                elements: map(matrix.elements)?,
+                ..matrix
+            }),
+        })
+    }
+}
+
+/// Evaluates a vector or matrix literal, a single row is a vector.
+fn calc_literal(rows: &[Vec<Operand>], env: &dyn Env) -> Result<Array, CalcError> {
+    let elements = rows
+        .iter()
+        .flatten()
+        .map(|op| calc_operand(op, env))
+        .collect::<Result<Vec<_>, _>>()?;
+    match Matrix::new(rows.len(), elements) {
+        Some(matrix) if matrix.rows > 1 => Ok(Array::Matrix(matrix)),
+        Some(matrix) => Ok(Array::Vector(matrix.elements)),
+        None => Err(CalcError::UnexpectedList(
+            Operand::Matrix(rows.to_vec()).to_string(),
+        )),
+    }
+}
+
+/// Evaluates an operand with vectors or matrices, `None` if it has neither.
+pub fn calc_array(op: &Operand, env: &dyn Env) -> Option<Result<Array, CalcError>> {
+    match op {
+        Operand::Matrix(rows) => Some(calc_literal(rows, env)),
+        Operand::Symbol(sym) if env.get_matrix(sym).is_some() => env
+            .get_matrix(sym)
+            .map(|matrix| Ok(Array::Matrix(matrix.clone()))),
+        Operand::FunCall(fun_call) if is_transpose_call(fun_call, env) => {
+            let param = &fun_call.params[0];
+            let array = calc_array(param, env)
+                .unwrap_or_else(|| calc_operand(param, env).map(Array::Number));
+            Some(array.map(|array| match array {
+                Array::Matrix(matrix) => Array::Matrix(matrix.transposed()),
+                array => array,
+            }))
+        }
+        Operand::Symbol(_) | Operand::FunCall(_) => {
+            calc_list(op, env).map(|list| list.map(Array::Vector))
+        }
+        Operand::Term(term) => {
+            let (lhs, rhs) = match (calc_array(&term.lhs, env), calc_array(&term.rhs, env)) {
+                (None, None) => return None,
+                arrays => arrays,
+            };
+            let operand = |array: Option<Result<Array, CalcError>>, op| {
+                array.unwrap_or_else(|| calc_operand(op, env).map(Array::Number))
+            };
+            Some(
+                operand(lhs, &term.lhs)
+                    .and_then(|lhs| Ok((lhs, operand(rhs, &term.rhs)?)))
+                    .and_then(|(lhs, rhs)| apply(term.op, lhs, rhs, env)),
+            )
+        }
+        _ => None,
+    }
+}
+
+/// `transpose(A)` of a matrix value, the build-in `transpose(A, rows)` of `linalg`
+/// needs the rows of a list
+pub(crate) fn is_transpose_call(fun_call: &FunCall, env: &dyn Env) -> bool {
+    fun_call.name == "transpose"
+        && fun_call.params.len() == 1
+        && matches!(env.get_fun(&fun_call.name), Some(Function::Data(_)))
+}
+
+/// Applies `op` to numbers, vectors or matrices:
+/// - with a number, `op` applies to each element, e.g. `[1, 2] ^ 2` is `[1, 4]`
+/// - `+` and `-` of vectors or matrices of the same shape apply to each element
+/// - `*` of two vectors is the dot product, otherwise the matrix product,
+///   where a vector is a column on the right and a row on the left
+fn apply(op: Operation, lhs: Array, rhs: Array, env: &dyn Env) -> Result<Array, CalcError> {
+    let elementwise = |lhs: &[Number], rhs: &[Number]| {
+        lhs.iter()
+            .zip(rhs)
+            .map(|(lhs, rhs)| calc_operation(op, *lhs, *rhs, env))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let is_additive = matches!(op, Operation::Add | Operation::Sub);
+    match (&lhs, &rhs) {
+        (Array::Number(lhs), Array::Number(rhs)) => {
+            return calc_operation(op, *lhs, *rhs, env).map(Array::Number)
+        }
+        (Array::Number(num), _) => return rhs.map(|rhs| calc_operation(op, *num, rhs, env)),
+        (_, Array::Number(num)) => return lhs.map(|lhs| calc_operation(op, lhs, *num, env)),
+        (Array::Vector(lhs), Array::Vector(rhs)) if lhs.len() == rhs.len() => {
+            if is_additive {
+                return elementwise(lhs, rhs).map(Array::Vector);
+            }
+            if op == Operation::Mul {
+                budget::spend(lhs.len() as u64)?;
+                return Ok(Array::Number(lhs.iter().zip(rhs).map(|(l, r)| l * r).sum()));
+            }
+        }
+        (Array::Matrix(lhs), Array::Matrix(rhs)) => {
+            if is_additive && (lhs.rows, lhs.cols) == (rhs.rows, rhs.cols) {
+                return Ok(Array::Matrix(Matrix {
+                    elements: elementwise(&lhs.elements, &rhs.elements)?,
+                    ..lhs.clone()
+                }));
+            }
+            if op == Operation::Mul && lhs.cols == rhs.rows {
+                return Ok(Array::Matrix(Matrix {
+                    rows: lhs.rows,
+                    cols: rhs.cols,
+                    elements: lhs.mul(&rhs.elements, rhs.cols)?,
+                }));
+            }
+        }
+        (Array::Matrix(lhs), Array::Vector(rhs))
+            if op == Operation::Mul && lhs.cols == rhs.len() =>
+        {
+            return lhs.mul(rhs, 1).map(Array::Vector);
+        }
+        (Array::Vector(lhs), Array::Matrix(rhs))
+            if op == Operation::Mul && lhs.len() == rhs.rows =>
+        {
+            return rhs.transposed().mul(lhs, 1).map(Array::Vector);
+        }
+        _ => {}
+    }
+    Err(CalcError::IncompatibleShapes {
+        op,
+        lhs: lhs.shape(),
+        rhs: rhs.shape(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::TopLevelEnv;
+    use crate::parser::parse_expression;
+
+    fn calc(expr: &str) -> Result<Array, CalcError> {
+        let mut env = TopLevelEnv::default();
+        env.put_list("v".to_string(), vec![1.0, 2.0]).unwrap();
+        let m = Matrix::new(2, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+        env.put_matrix("m".to_string(), m).unwrap();
+        calc_array(&parse_expression(expr).unwrap(), &env).unwrap()
+    }
+
+    fn matrix(rows: usize, elements: &[Number]) -> Result<Array, CalcError> {
+        Ok(Array::Matrix(Matrix::new(rows, elements.to_vec()).unwrap()))
+    }
+
+    #[test]
+    fn shapes() {
+        let m = Matrix::new(2, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]).unwrap();
+        assert_eq!((2, 3), (m.rows(), m.cols()));
+        assert_eq!(&[4.0, 5.0, 6.0], m.row(1));
+        assert_eq!(None, Matrix::new(4, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]));
+        assert_eq!(None, Matrix::new(0, vec![]));
+    }
+
+    #[test]
+    fn elementwise_and_broadcast() {
+        assert_eq!(Ok(Array::Vector(vec![4.0, 6.0])), calc("[1, 2] + [3, 4]"));
+        assert_eq!(Ok(Array::Vector(vec![2.0, 4.0])), calc("2 * v"));
+        assert_eq!(Ok(Array::Vector(vec![1.0, 4.0])), calc("v ^ 2"));
+        assert_eq!(matrix(2, &[0.0, 1.0, 2.0, 3.0]), calc("m - 1"));
+        assert_eq!(matrix(2, &[2.0, 4.0, 6.0, 8.0]), calc("m + m"));
+        assert_eq!(Err(CalcError::DivisionByZero), calc("v / 0"));
+    }
+
+    #[test]
+    fn products() {
+        assert_eq!(Ok(Array::Number(11.0)), calc("[1, 2] * [3, 4]"));
+        assert_eq!(matrix(2, &[7.0, 10.0, 15.0, 22.0]), calc("m * m"));
+        assert_eq!(Ok(Array::Vector(vec![5.0, 11.0])), calc("m * v"));
+        assert_eq!(Ok(Array::Vector(vec![7.0, 10.0])), calc("v * m"));
+        assert_eq!(
+            matrix(3, &[2.0, 1.0, 4.0, 3.0, 6.0, 5.0]),
+            calc("[1, 2; 3, 4; 5, 6] * [0, 1; 1, 0]")
+        );
+    }
+
+    #[test]
+    fn incompatible_shapes() {
+        assert_eq!(
+            Err(CalcError::IncompatibleShapes {
+                op: Operation::Add,
+                lhs: "a vector of 2 elements".to_string(),
+                rhs: "a vector of 3 elements".to_string(),
+            }),
+            calc("v + [1, 2, 3]")
+        );
+        assert_eq!(
+            Err(CalcError::IncompatibleShapes {
+                op: Operation::Div,
+                lhs: "a 2x2 matrix".to_string(),
+                rhs: "a 2x2 matrix".to_string(),
+            }),
+            calc("m / m")
+        );
+        assert!(calc("[1, 2, 3] * m").is_err());
+    }
+
+    #[test]
+    fn nested_elements() {
+        assert_eq!(
+            Err(CalcError::UnexpectedList("[1]".to_string())),
+            calc("[[1], 2]")
+        );
+        let sum = parse_expression("sum(i, 1, 3, [i, i])").unwrap();
+        assert_eq!(
+            Err(CalcError::UnexpectedList("[i, i]".to_string())),
+            calc_operand::<Number>(&sum, &TopLevelEnv::default())
+        );
+    }
+
+    #[cfg(not(feature = "embedded"))]
+    #[test]
+    fn transpose_matrix() {
+        assert_eq!(matrix(2, &[1.0, 3.0, 2.0, 4.0]), calc("transpose(m)"));
+        assert_eq!(
+            matrix(3, &[1.0, 4.0, 2.0, 5.0, 3.0, 6.0]),
+            calc("transpose([1, 2, 3; 4, 5, 6])")
+        );
+        assert_eq!(Ok(Array::Vector(vec![1.0, 2.0])), calc("transpose(v)"));
+        assert_eq!(
+            Ok(Array::Vector(vec![1.0, 3.0, 2.0, 4.0])),
+            calc("transpose([1, 2, 3, 4], 2)")
+        );
+    }
+}
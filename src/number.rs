@@ -0,0 +1,155 @@
+//! Numeric backend of the calculator.
+//!
+//! The evaluator only relies on the operations of the [`Num`] trait. The [`Number`] type
+//! of the calculator is selected at compile time: `f64` by default, `f32` with the feature
+//! `f32` (part of `embedded`). Expressions are evaluated with any other type implementing
+//! [`Num`] by [`crate::Calculator::evaluate_as`].
+//! Results which are no number, e.g. of `sqrt(-1)`, must be `NaN` (see [`Num::is_nan`]),
+//! the decimal type `crate::Decimal` (feature `decimal`) also represents overflows as `NaN`,
+//! which the evaluator reports as [`crate::CalcError::Overflow`].
+//!
+//! [`Number`]: crate::ast::Number
+
+#[cfg(test)]
+use crate::ast::Number;
+use std::fmt::{Debug, Display};
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+use std::str::FromStr;
+
+macro_rules! num_trait {
+    ($($fun:ident) +) => {
+        /// Operations a numeric backend must provide.
+        pub trait Num:
+            Copy
+            + PartialEq
+            + PartialOrd
+            + Debug
+            + Display
+            + FromStr
+            + Add<Output = Self>
+            + Sub<Output = Self>
+            + Mul<Output = Self>
+            + Div<Output = Self>
+            + Rem<Output = Self>
+            + Neg<Output = Self>
+            + Send
+            + Sync
+            + 'static
+        {
+            /// nearest value to `value`, used for constants like `pi`
+            fn from_f64(value: f64) -> Self;
+
+            fn to_f64(self) -> f64;
+
+            fn pow(self, exp: Self) -> Self;
+
+            /// angle of the point `(x, self)`
+            fn atan2(self, x: Self) -> Self;
+
+            fn hypot(self, other: Self) -> Self;
+
+            fn is_finite(self) -> bool;
+
+            fn is_nan(self) -> bool;
+
+            $(fn $fun(self) -> Self;)+
+
+            /// the build-in function `name` of one number, if it is an operation of `Num`
+            fn unary(name: &str, x: Self) -> Option<Self> {
+                match name {
+                    $(stringify!($fun) => Some(x.$fun()),)+
+                    _ => None,
+                }
+            }
+        }
+
+        macro_rules! impl_num {
+            ($t:ty) => {
+                impl Num for $t {
+                    fn from_f64(value: f64) -> Self {
+                        value as $t
+                    }
+
+                    fn to_f64(self) -> f64 {
+                        self as f64
+                    }
+
+                    fn pow(self, exp: Self) -> Self {
+                        self.powf(exp)
+                    }
+
+                    fn atan2(self, x: Self) -> Self {
+                        <$t>::atan2(self, x)
+                    }
+
+                    fn hypot(self, other: Self) -> Self {
+                        <$t>::hypot(self, other)
+                    }
+
+                    fn is_finite(self) -> bool {
+                        <$t>::is_finite(self)
+                    }
+
+                    fn is_nan(self) -> bool {
+                        <$t>::is_nan(self)
+                    }
+
+                    $(fn $fun(self) -> Self {
+                        <$t>::$fun(self)
+                    })+
+                }
+            };
+        }
+    };
+}
+
+num_trait!(abs sqrt sin sinh cos cosh tan tanh exp ln log2 log10 atan atanh asin asinh acos acosh);
+
+impl_num!(f32);
+impl_num!(f64);
+
+/// `x ^ exp` with the real root of a negative `x` if `exp` is `1 / n` of an odd `n`,
+/// e.g. `(-8) ^ (1 / 3)` is `-2` instead of `NaN`
+pub fn real_pow<N: Num>(x: N, exp: N) -> N {
+    if x < N::from_f64(0.0) {
+        let degree = (1.0 / exp.to_f64()).round();
+        if degree % 2.0 != 0.0 && N::from_f64(1.0 / degree) == exp {
+            return -(-x).pow(exp);
+        }
+    }
+    x.pow(exp)
+}
+
+/// absolute error tests allow for approximated results, e.g. of a numeric search
+#[cfg(test)]
+pub(crate) const TEST_TOLERANCE: Number = if cfg!(feature = "f32") { 1e-3 } else { 1e-6 };
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hypot<N: Num>(a: N, b: N) -> N {
+        (a * a + b * b).sqrt()
+    }
+
+    #[test]
+    fn num_backends() {
+        assert_eq!(5.0f32, hypot(3.0f32, 4.0f32));
+        assert_eq!(5.0f64, hypot(3.0f64, 4.0f64));
+        assert_eq!(8.0f32, Num::pow(2.0f32, 3.0));
+        assert_eq!(std::f32::consts::PI, f32::from_f64(std::f64::consts::PI));
+        assert!(!Num::is_finite(1.0f32 / 0.0));
+        assert_eq!(Some(2.0), f64::unary("sqrt", 4.0));
+        assert_eq!(None, f32::unary("gamma", 4.0));
+    }
+
+    #[test]
+    fn odd_roots_of_negative_numbers() {
+        assert_eq!(-2.0, real_pow(-8.0f64, 1.0 / 3.0));
+        assert_eq!(-2.0, real_pow(-8.0f32, 1.0 / 3.0));
+        assert_eq!(-0.5, real_pow(-8.0f64, -1.0 / 3.0));
+        assert_eq!(-32.0, real_pow(-2.0f64, 5.0));
+        assert!(real_pow(-4.0f64, 0.5).is_nan());
+        assert!(real_pow(-8.0f64, 2.0 / 3.0).is_nan());
+    }
+}
@@ -1,12 +1,14 @@
 #![allow(clippy::upper_case_acronyms)]
 
 use crate::ast::*;
+use crate::diagnostic::Span;
 
 use lazy_static::lazy_static;
-use pest::prec_climber::{Assoc, Operator, PrecClimber};
+use pest::error::{ErrorVariant, InputLocation};
+use pest::pratt_parser::{Assoc, Op, PrattParser};
 use pest::{
     iterators::{Pair, Pairs},
-    Parser,
+    Parser, RuleType,
 };
 use pest_derive::Parser;
 use thiserror::Error;
@@ -51,6 +53,100 @@ pub enum ParserError {
     PlotMissingFunction,
     #[error("Expected function name, but got {0}")]
     PlotUnexpectedSymbol(String),
+    #[error("Expected expression in `is` left from the `==`, but got `{0}`")]
+    MissingEquivalentLeftExpression(String),
+    #[error("Expected expression in `is` right from the `==`, but got `{0}`")]
+    MissingEquivalentRightExpression(String),
+    #[error("Expected a file name in quotes after `import`, but got `{0}`")]
+    MissingImportPath(String),
+    #[error("Expected a file name in quotes after `run`, but got `{0}`")]
+    MissingRunPath(String),
+    #[error("Expected a comparison like `x >= 0` after `when`, but got `{0}`")]
+    MissingGuard(String),
+    #[error("Plot has more than one `{0}`")]
+    DuplicatePlotLabel(String),
+    #[error("Expected the swept parameter and one plotted variable as arguments, but got `{0}`")]
+    InvalidSweep(String),
+    #[error("Expected a name and an equation like `store ohm: v = i * r`, but got `{0}`")]
+    MissingStoredEquation(String),
+    #[error("Expected a column number after `column`, but got `{0}`")]
+    InvalidColumn(String),
+    #[error("Expected a non-negative integer as index, but got `{0}`")]
+    InvalidSequenceIndex(String),
+    #[error("Expected two list variables after `fit linear`, but got `{0}`")]
+    MissingFitData(String),
+    #[error("Not enough operands on the stack for `{0}`")]
+    RpnStackUnderflow(String),
+    #[error("Expected a single result, but {0} operands are left on the stack")]
+    RpnUnconsumedOperands(usize),
+    #[error("Expected rows with the same number of elements, but got `{0}`")]
+    RaggedMatrix(String),
+    #[error("Syntax error at `{found}` - {expected}")]
+    Syntax {
+        found: String,
+        expected: String,
+        /// the offending token in the input
+        span: Span,
+    },
+}
+
+/// The syntax error of the grammar located at the offending token of `input`,
+/// i.e. up to the next whitespace.
+pub(crate) fn syntax_error<R: RuleType>(
+    input: &str,
+    mut err: pest::error::Error<R>,
+) -> ParserError {
+    let name = |rule: &R| format!("{:?}", rule);
+    if let ErrorVariant::ParsingError { positives, .. } = &mut err.variant {
+        // the signs are listed with the operands of a term
+        if positives.iter().any(|rule| name(rule) == "term") {
+            positives.retain(|rule| !matches!(name(rule).as_str(), "neg" | "pos"));
+        }
+    }
+    let span = match err.location {
+        InputLocation::Pos(start) => Span {
+            start,
+            end: input[start..]
+                .find(char::is_whitespace)
+                .map_or(input.len(), |len| start + len),
+        },
+        InputLocation::Span((start, end)) => Span { start, end },
+    };
+    let found = match &input[span.start..span.end] {
+        "" => "end of input",
+        found => found,
+    };
+    let expected = err
+        .renamed_rules(|rule| {
+            let name = match format!("{:?}", rule).as_str() {
+                "EOI" => "end of input",
+                "num" => "number",
+                "expr" | "row" => "expression",
+                "add" | "pos" => "`+`",
+                "subtract" | "neg" => "`-`",
+                "multiply" => "`*`",
+                "divide" => "`/`",
+                "rem" => "`%`",
+                "power" => "`^`",
+                "factorial" => "`!`",
+                // the operands of an expression, which may be followed by `!`
+                "term" | "implicit_term" => {
+                    "number, symbol, matrix, `-`, `+`, `if`, or `sum`/`prod`"
+                }
+                "conditional" => "`if`",
+                "series" | "series_kind" => "`sum`/`prod`",
+                rule => return rule.replace('_', " "),
+            };
+            name.to_string()
+        })
+        .variant
+        .message()
+        .to_string();
+    ParserError::Syntax {
+        found: found.to_string(),
+        expected,
+        span,
+    }
 }
 
 #[derive(Parser)]
@@ -58,23 +154,60 @@ pub enum ParserError {
 pub struct EquationParser;
 
 lazy_static! {
-    static ref PREC_CLIMBER: PrecClimber<Rule> = {
+    static ref PRATT_PARSER: PrattParser<Rule> = {
         use Assoc::*;
         use Rule::*;
 
-        PrecClimber::new(vec![
-            Operator::new(add, Left) | Operator::new(subtract, Left),
-            Operator::new(multiply, Left) | Operator::new(divide, Left) | Operator::new(rem, Left),
-            Operator::new(power, Right),
-        ])
+        PrattParser::new()
+            .op(Op::infix(add, Left) | Op::infix(subtract, Left))
+            .op(Op::infix(multiply, Left) | Op::infix(divide, Left) | Op::infix(rem, Left))
+            .op(Op::prefix(neg) | Op::prefix(pos))
+            .op(Op::infix(power, Right))
     };
 }
 
+/// decimal numbers like `1.5e-3` and integers with radix prefix like `0xFF`, `0o17` or `0b1010`
 fn parse_num(pair: Pair<Rule>) -> Result<Operand, ParserError> {
-    match pair.as_str().parse::<f64>() {
-        Ok(num) => Ok(Operand::Number(num)),
-        Err(_) => Err(ParserError::InvalidNumber(pair.as_str().to_string())),
+    let input = pair.as_str();
+    let radix = match input.get(..2).map(str::to_ascii_lowercase).as_deref() {
+        Some("0x") => Some(16),
+        Some("0o") => Some(8),
+        Some("0b") => Some(2),
+        _ => None,
+    };
+    let num = match radix {
+        Some(radix) => u64::from_str_radix(&input[2..], radix)
+            .ok()
+            .map(|num| (num as Number, num.to_string())),
+        None => input
+            .parse::<Number>()
+            .ok()
+            .map(|num| (num, input.to_string())),
+    };
+    let (num, text) = num.ok_or_else(|| ParserError::InvalidNumber(input.to_string()))?;
+    Ok(
+        match num.is_finite() && digits(&text) == digits(&num.to_string()) {
+            true => Operand::Number(num),
+            false => Operand::Literal(Box::new(Literal { value: num, text })),
+        },
+    )
+}
+
+/// significant digits and exponent of a decimal literal, e.g. `("125", -4)` of `0.01250`
+fn digits(text: &str) -> (String, i64) {
+    let (mantissa, exp) = match text.split_once(['e', 'E']) {
+        Some((mantissa, exp)) => (mantissa, exp.parse().unwrap_or(i64::MAX)),
+        None => (text, 0),
+    };
+    let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", int.trim_start_matches(['+', '-']), frac);
+    let significant = digits.trim_start_matches('0').trim_end_matches('0');
+    if significant.is_empty() {
+        return (String::new(), 0);
     }
+    let trailing_zeros = digits.len() - digits.trim_end_matches('0').len();
+    let exp = exp.saturating_sub(frac.len() as i64) + trailing_zeros as i64;
+    (significant.to_string(), exp)
 }
 
 fn new_operand_term(lhs: Operand, op: Operation, rhs: Operand) -> Operand {
@@ -119,18 +252,87 @@ fn parse_fun_call(fun_call: Pairs<Rule>) -> Result<Operand, ParserError> {
     Ok(Operand::FunCall(FunCall { name, params }))
 }
 
+/// `-x` is parsed as `-1 * x`, a negated number is folded into the number,
+/// also the leading number of a product like `-2x y`
+fn parse_sign(sign: Pair<Rule>, op: Result<Operand, ParserError>) -> Result<Operand, ParserError> {
+    let op = op?;
+    Ok(match sign.as_rule() {
+        Rule::neg => negate(op),
+        _ => op,
+    })
+}
+
+fn has_leading_number(op: &Operand) -> bool {
+    match op {
+        Operand::Number(_) | Operand::Literal(_) => true,
+        Operand::Term(term) => term.op == Operation::Mul && has_leading_number(&term.lhs),
+        _ => false,
+    }
+}
+
+fn negate(op: Operand) -> Operand {
+    match op {
+        Operand::Number(num) => Operand::Number(-num),
+        Operand::Literal(lit) => Operand::Literal(Box::new(Literal {
+            value: -lit.value,
+            text: match lit.text.strip_prefix('-') {
+                Some(text) => text.to_string(),
+                None => format!("-{}", lit.text),
+            },
+        })),
+        // `--x` is `x`
+        Operand::Term(term) if term.op == Operation::Mul && term.lhs == Operand::Number(-1.0) => {
+            term.rhs
+        }
+        Operand::Term(term) if term.op == Operation::Mul && has_leading_number(&term.lhs) => {
+            let Term { op, lhs, rhs } = *term;
+            new_operand_term(negate(lhs), op, rhs)
+        }
+        op => new_operand_term(Operand::Number(-1.0), Operation::Mul, op),
+    }
+}
+
+/// `2x ^ 2` is parsed as `2 * x ^ 2`, a number without factors is the number
+fn parse_implicit_product(product: Pairs<Rule>) -> Result<Operand, ParserError> {
+    let mut it = product;
+    let input = it.as_str().to_string();
+    let num = parse_num(it.next().ok_or(ParserError::InvalidOperand(input))?)?;
+    it.try_fold(num, |lhs, factor| {
+        let rhs = parse_operand(factor.into_inner())?;
+        Ok(new_operand_term(lhs, Operation::Mul, rhs))
+    })
+}
+
+/// a factor followed by `!`, e.g. `3!` or `(n - 1)!`
+fn parse_factorial(term: Pairs<Rule>) -> Result<Operand, ParserError> {
+    let mut it = term;
+    let input = it.as_str().to_string();
+    let factor = parse_primary(it.next().ok_or(ParserError::InvalidOperand(input))?)?;
+    Ok(it.fold(factor, |op, _| op.factorial()))
+}
+
+fn parse_primary(pair: Pair<Rule>) -> Result<Operand, ParserError> {
+    match pair.as_rule() {
+        Rule::term | Rule::implicit_term => parse_factorial(pair.into_inner()),
+        Rule::implicit_product => parse_implicit_product(pair.into_inner()),
+        Rule::num => parse_num(pair),
+        Rule::expr => parse_operand(pair.into_inner()),
+        Rule::symbol => Ok(Operand::Symbol(pair.as_str().to_string())),
+        Rule::fun_call => parse_fun_call(pair.into_inner()),
+        Rule::conditional => parse_conditional(pair.into_inner()),
+        Rule::series => parse_series(pair.into_inner()),
+        Rule::let_binding => parse_let(pair.into_inner()),
+        Rule::matrix => parse_matrix(pair),
+        _ => Err(ParserError::InvalidOperand(pair.as_str().to_string())),
+    }
+}
+
 fn parse_operand(expression: Pairs<Rule>) -> Result<Operand, ParserError> {
-    PREC_CLIMBER.climb(
-        expression,
-        |pair: Pair<Rule>| match pair.as_rule() {
-            Rule::num => parse_num(pair),
-            Rule::expr => parse_operand(pair.into_inner()),
-            Rule::symbol => Ok(Operand::Symbol(pair.as_str().to_string())),
-            Rule::fun_call => parse_fun_call(pair.into_inner()),
-            _ => Err(ParserError::InvalidOperand(pair.as_str().to_string())),
-        },
-        parse_term,
-    )
+    PRATT_PARSER
+        .map_primary(parse_primary)
+        .map_infix(parse_term)
+        .map_prefix(parse_sign)
+        .parse(expression)
 }
 
 fn parse_assignment(assignment: Pairs<Rule>) -> Result<Statement, ParserError> {
@@ -177,8 +379,110 @@ fn parse_solve_for(solve_for: Pairs<Rule>) -> Result<Statement, ParserError> {
         Err(ParserError::InvalidSymbol(sym.as_str().to_string()))
     }?;
     let sym = sym.to_string();
+    let bracket = it.next().map(parse_bracket).transpose()?;
+
+    Ok(Statement::SolveFor {
+        lhs,
+        rhs,
+        sym,
+        bracket,
+    })
+}
+
+fn parse_solve_inequality(solve_inequality: Pairs<Rule>) -> Result<Statement, ParserError> {
+    let mut it = solve_inequality;
+    let Guard { lhs, cmp, rhs } = parse_comparison(&mut it)?;
+    let sym = it
+        .next()
+        .ok_or_else(|| ParserError::MissingSolveForSymbol(it.as_str().to_string()))?;
+    Ok(Statement::SolveInequality {
+        lhs,
+        cmp,
+        rhs,
+        sym: sym.as_str().to_string(),
+    })
+}
+
+fn parse_bracket(bracket: Pair<Rule>) -> Result<Bracket, ParserError> {
+    let input = bracket.as_str().to_string();
+    let mut ops = bracket
+        .into_inner()
+        .map(|expr| parse_operand(expr.into_inner()));
+    match (ops.next(), ops.next()) {
+        (Some(from), Some(to)) => Ok(Bracket {
+            from: from?,
+            to: to?,
+        }),
+        _ => Err(ParserError::InvalidExpression(input)),
+    }
+}
 
-    Ok(Statement::SolveFor { lhs, rhs, sym })
+fn parse_integrate(integrate: Pairs<Rule>) -> Result<Statement, ParserError> {
+    let input = integrate.as_str().to_string();
+    let mut it = integrate.skip(1);
+    match (it.next(), it.next(), it.next()) {
+        (Some(name), Some(from), Some(to)) if name.as_rule() == Rule::symbol => {
+            Ok(Statement::Integrate {
+                name: name.as_str().to_string(),
+                bracket: Bracket {
+                    from: parse_operand(from.into_inner())?,
+                    to: parse_operand(to.into_inner())?,
+                },
+            })
+        }
+        (Some(op), Some(sym), Some(bracket)) => Ok(Statement::IntegrateExpression {
+            op: parse_operand(op.into_inner())?,
+            sym: sym.as_str().to_string(),
+            bracket: parse_bracket(bracket)?,
+        }),
+        _ => Err(ParserError::InvalidExpression(input)),
+    }
+}
+
+fn parse_store(store: Pairs<Rule>) -> Result<Statement, ParserError> {
+    let input = store.as_str().to_string();
+    let mut it = store.skip(1);
+    let name = it
+        .next()
+        .ok_or_else(|| ParserError::MissingStoredEquation(input.clone()))?
+        .as_str()
+        .to_string();
+    let mut ops = it.map(|expr| parse_operand(expr.into_inner()));
+    match (ops.next(), ops.next()) {
+        (Some(lhs), Some(rhs)) => Ok(Statement::Store {
+            name,
+            lhs: lhs?,
+            rhs: rhs?,
+        }),
+        _ => Err(ParserError::MissingStoredEquation(input)),
+    }
+}
+
+fn parse_solve_stored(solve_stored: Pairs<Rule>) -> Result<Statement, ParserError> {
+    let mut it = solve_stored;
+    let name = it
+        .next()
+        .ok_or_else(|| ParserError::MissingStoredEquation(it.as_str().to_string()))?
+        .as_str()
+        .to_string();
+    let sym = it
+        .next()
+        .ok_or_else(|| ParserError::MissingSolveForSymbol(it.as_str().to_string()))?
+        .as_str()
+        .to_string();
+    let given = it
+        .map(|given| {
+            let input = given.as_str().to_string();
+            let mut given = given.into_inner();
+            match (given.next(), given.next()) {
+                (Some(sym), Some(expr)) => {
+                    Ok((sym.as_str().to_string(), parse_operand(expr.into_inner())?))
+                }
+                _ => Err(ParserError::MissingAssignmentExpression(input)),
+            }
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Statement::SolveStored { name, sym, given })
 }
 
 fn parse_function(function: Pairs<Rule>) -> Result<Statement, ParserError> {
@@ -191,43 +495,463 @@ fn parse_function(function: Pairs<Rule>) -> Result<Statement, ParserError> {
         .to_string();
 
     let mut args = Vec::new();
-    for p in it {
+    while let Some(p) = it.next() {
         if p.as_rule() == Rule::symbol {
             args.push(p.as_str().to_string());
         } else {
             let body = parse_operand(p.into_inner())?;
-            return Ok(Statement::Function {
-                name,
-                fun: Function::Custom(CustomFunction { args, body }),
-            });
+            let fun = match it.next() {
+                Some(guard) => Function::Piecewise(Piecewise {
+                    args,
+                    clauses: vec![Clause {
+                        body,
+                        guard: parse_guard(guard.into_inner())?,
+                    }],
+                }),
+                None => Function::Custom(CustomFunction { args, body }),
+            };
+            return Ok(Statement::Function { name, fun });
         }
     }
 
     Err(ParserError::MissingFunctionBody)
 }
 
+/// parses the comparison `lhs cmp rhs` of a guard or conditional
+fn parse_comparison(it: &mut Pairs<Rule>) -> Result<Guard, ParserError> {
+    let lhs = it
+        .next()
+        .ok_or_else(|| ParserError::MissingGuard(it.as_str().to_string()))?;
+    let cmp = match it.next().map(|cmp| cmp.as_str()) {
+        Some("<") => Comparison::Less,
+        Some("<=") => Comparison::LessEqual,
+        Some(">") => Comparison::Greater,
+        Some(">=") => Comparison::GreaterEqual,
+        Some("==") => Comparison::Equal,
+        Some("!=") => Comparison::NotEqual,
+        _ => return Err(ParserError::MissingGuard(it.as_str().to_string())),
+    };
+    let rhs = it
+        .next()
+        .ok_or_else(|| ParserError::MissingGuard(it.as_str().to_string()))?;
+    Ok(Guard {
+        lhs: parse_operand(lhs.into_inner())?,
+        cmp,
+        rhs: parse_operand(rhs.into_inner())?,
+    })
+}
+
+fn parse_guard(guard: Pairs<Rule>) -> Result<Option<Guard>, ParserError> {
+    let mut it = guard;
+    match it.next() {
+        Some(keyword) if keyword.as_rule() == Rule::when_keyword => {
+            Ok(Some(parse_comparison(&mut it)?))
+        }
+        _ => Ok(None),
+    }
+}
+
+fn parse_conditional(conditional: Pairs<Rule>) -> Result<Operand, ParserError> {
+    let mut it = conditional;
+    let guard = parse_comparison(&mut it)?;
+    let mut branch = || {
+        it.next()
+            .ok_or_else(|| ParserError::ExpectedParamExpression(it.as_str().to_string()))
+            .and_then(|expr| parse_operand(expr.into_inner()))
+    };
+    let then = branch()?;
+    let otherwise = branch()?;
+    Ok(Operand::Conditional(Box::new(Conditional {
+        guard,
+        then,
+        otherwise,
+    })))
+}
+
+fn parse_series(series: Pairs<Rule>) -> Result<Operand, ParserError> {
+    let mut it = series;
+    let kind = match it.next().map(|kind| kind.as_str()) {
+        Some("prod") => SeriesKind::Product,
+        _ => SeriesKind::Sum,
+    };
+    let var = it
+        .next()
+        .ok_or_else(|| ParserError::ExpectedParamExpression(it.as_str().to_string()))?
+        .as_str()
+        .to_string();
+    let mut operand = || {
+        it.next()
+            .ok_or_else(|| ParserError::ExpectedParamExpression(it.as_str().to_string()))
+            .and_then(|expr| parse_operand(expr.into_inner()))
+    };
+    let from = operand()?;
+    let to = operand()?;
+    let body = operand()?;
+    Ok(Operand::Series(Box::new(Series {
+        kind,
+        var,
+        from,
+        to,
+        body,
+    })))
+}
+
+fn parse_let(binding: Pairs<Rule>) -> Result<Operand, ParserError> {
+    let mut it = binding;
+    it.next(); // let keyword
+    let var = it
+        .next()
+        .ok_or_else(|| ParserError::ExpectedParamExpression(it.as_str().to_string()))?
+        .as_str()
+        .to_string();
+    let mut operand = || {
+        it.next()
+            .ok_or_else(|| ParserError::ExpectedParamExpression(it.as_str().to_string()))
+            .and_then(|expr| parse_operand(expr.into_inner()))
+    };
+    let value = operand()?;
+    let body = operand()?;
+    Ok(Operand::Let(Box::new(Let { var, value, body })))
+}
+
+fn parse_matrix(matrix: Pair<Rule>) -> Result<Operand, ParserError> {
+    let input = matrix.as_str().to_string();
+    let rows = matrix
+        .into_inner()
+        .map(|row| {
+            row.into_inner()
+                .map(|expr| parse_operand(expr.into_inner()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if rows.iter().any(|row| row.len() != rows[0].len()) {
+        return Err(ParserError::RaggedMatrix(input));
+    }
+    Ok(Operand::Matrix(rows))
+}
+
+fn parse_sequence_value(sequence_value: Pairs<Rule>) -> Result<Statement, ParserError> {
+    let mut it = sequence_value;
+
+    let name = it
+        .next()
+        .ok_or(ParserError::MissingFunctionName)?
+        .as_str()
+        .to_string();
+    let index = it
+        .next()
+        .ok_or_else(|| ParserError::InvalidSequenceIndex(it.as_str().to_string()))?;
+    let index = index
+        .as_str()
+        .parse::<u64>()
+        .map_err(|_| ParserError::InvalidSequenceIndex(index.as_str().to_string()))?;
+    let op = parse_operand(
+        it.next()
+            .ok_or(ParserError::MissingFunctionBody)?
+            .into_inner(),
+    )?;
+
+    Ok(Statement::SequenceValue { name, index, op })
+}
+
 fn parse_plot(plot: Pairs<Rule>) -> Result<Statement, ParserError> {
     let mut it = plot;
     let fun = it.next().ok_or(ParserError::PlotMissingFunction)?;
     match fun.as_rule() {
-        Rule::symbol => Ok(Statement::Plot {
-            name: fun.as_str().to_string(),
-        }),
+        Rule::polar_keyword => {
+            let op = it
+                .next()
+                .ok_or(ParserError::PlotMissingFunction)
+                .and_then(|expr| parse_operand(expr.into_inner()))?;
+            let mut bracket = None;
+            let mut labels = PlotLabels::default();
+            for pair in it {
+                match pair.as_rule() {
+                    Rule::bracket => bracket = Some(parse_bracket(pair)?),
+                    _ => parse_plot_label(pair, &mut labels)?,
+                }
+            }
+            Ok(Statement::PlotPolar {
+                op,
+                bracket,
+                labels,
+            })
+        }
+        Rule::symbol => {
+            let mut args = Vec::new();
+            let mut sweep = None;
+            let mut labels = PlotLabels::default();
+            for pair in it {
+                match pair.as_rule() {
+                    Rule::symbol => args.push(pair.as_str().to_string()),
+                    Rule::sweep => sweep = Some(parse_sweep(pair, std::mem::take(&mut args))?),
+                    _ => parse_plot_label(pair, &mut labels)?,
+                }
+            }
+            Ok(Statement::Plot {
+                name: fun.as_str().to_string(),
+                sweep,
+                labels,
+            })
+        }
+        Rule::implicit_keyword => {
+            let lhs = it
+                .next()
+                .ok_or(ParserError::PlotMissingFunction)
+                .and_then(|expr| parse_operand(expr.into_inner()))?;
+            let mut labels = PlotLabels::default();
+            for pair in it {
+                parse_plot_label(pair, &mut labels)?;
+            }
+            Ok(Statement::PlotImplicit {
+                lhs,
+                rhs: None,
+                labels,
+            })
+        }
+        Rule::expr => {
+            let op = parse_operand(fun.into_inner())?;
+            let mut rhs = None;
+            let mut labels = PlotLabels::default();
+            for pair in it {
+                match pair.as_rule() {
+                    Rule::expr => rhs = Some(parse_operand(pair.into_inner())?),
+                    _ => parse_plot_label(pair, &mut labels)?,
+                }
+            }
+            match rhs {
+                Some(rhs) => Ok(Statement::PlotImplicit {
+                    lhs: op,
+                    rhs: Some(rhs),
+                    labels,
+                }),
+                None => Ok(Statement::PlotExpression { op, labels }),
+            }
+        }
         _ => Err(ParserError::PlotUnexpectedSymbol(fun.as_str().to_string())),
     }
 }
 
+fn parse_sweep(sweep: Pair<Rule>, args: Vec<String>) -> Result<Sweep, ParserError> {
+    let input = sweep.as_str().to_string();
+    let mut it = sweep.into_inner();
+    let param = it
+        .next()
+        .ok_or_else(|| ParserError::InvalidSweep(input.clone()))?
+        .as_str()
+        .to_string();
+    if args.len() != 2 || args.iter().filter(|arg| **arg == param).count() != 1 {
+        return Err(ParserError::InvalidSweep(input));
+    }
+    let mut ops = it.map(|expr| parse_operand(expr.into_inner()));
+    let (from, to) = match (ops.next(), ops.next()) {
+        (Some(from), Some(to)) => (from?, to?),
+        _ => return Err(ParserError::InvalidSweep(input)),
+    };
+    Ok(Sweep {
+        args,
+        param,
+        from,
+        to,
+        step: ops.next().transpose()?,
+    })
+}
+
+fn parse_plot_label(label: Pair<Rule>, labels: &mut PlotLabels) -> Result<(), ParserError> {
+    let mut label = label.into_inner();
+    if let (Some(keyword), Some(text)) = (label.next(), label.next()) {
+        let slot = match keyword.as_str() {
+            "title" => &mut labels.title,
+            "xlabel" => &mut labels.xlabel,
+            _ => &mut labels.ylabel,
+        };
+        if slot.is_some() {
+            return Err(ParserError::DuplicatePlotLabel(
+                keyword.as_str().to_string(),
+            ));
+        }
+        *slot = Some(parse_text(text.into_inner()));
+    }
+    Ok(())
+}
+
+fn parse_text(text: Pairs<Rule>) -> Text {
+    let parts = text
+        .map(|part| match part.as_rule() {
+            Rule::string => TextPart::Literal(part.as_str().trim_matches('"').to_string()),
+            _ => TextPart::Symbol(part.as_str().to_string()),
+        })
+        .collect();
+    Text { parts }
+}
+
+fn parse_text_assignment(text_assignment: Pairs<Rule>) -> Result<Statement, ParserError> {
+    let mut it = text_assignment;
+    let sym = it
+        .next()
+        .ok_or_else(|| ParserError::MissingAssignmentTarget(it.as_str().to_string()))?
+        .as_str()
+        .to_string();
+    let text = it
+        .next()
+        .ok_or_else(|| ParserError::MissingAssignmentExpression(it.as_str().to_string()))?;
+    Ok(Statement::TextAssignment {
+        sym,
+        text: parse_text(text.into_inner()),
+    })
+}
+
+fn parse_equivalent(equivalent: Pairs<Rule>) -> Result<Statement, ParserError> {
+    let mut it = equivalent;
+
+    let lhs = parse_operand(
+        it.next()
+            .ok_or_else(|| ParserError::MissingEquivalentLeftExpression(it.as_str().to_string()))?
+            .into_inner(),
+    )?;
+    let rhs = parse_operand(
+        it.next()
+            .ok_or_else(|| ParserError::MissingEquivalentRightExpression(it.as_str().to_string()))?
+            .into_inner(),
+    )?;
+
+    Ok(Statement::Equivalent { lhs, rhs })
+}
+
+fn parse_simplify(simplify: Pairs<Rule>) -> Result<Statement, ParserError> {
+    let mut it = simplify;
+    it.next(); // simplify keyword
+    let op = parse_operand(
+        it.next()
+            .ok_or_else(|| ParserError::InvalidExpression(it.as_str().to_string()))?
+            .into_inner(),
+    )?;
+    Ok(Statement::Simplify { op })
+}
+
+fn parse_import(import: Pairs<Rule>) -> Result<Statement, ParserError> {
+    let mut it = import;
+
+    let path = it
+        .next()
+        .ok_or_else(|| ParserError::MissingImportPath(it.as_str().to_string()))?
+        .as_str()
+        .trim_matches('"')
+        .to_string();
+    let column = it
+        .next()
+        .ok_or_else(|| ParserError::InvalidColumn(it.as_str().to_string()))?;
+    let column = column
+        .as_str()
+        .parse::<usize>()
+        .map_err(|_| ParserError::InvalidColumn(column.as_str().to_string()))?;
+    let sym = it
+        .next()
+        .ok_or_else(|| ParserError::MissingAssignmentTarget(it.as_str().to_string()))?
+        .as_str()
+        .to_string();
+
+    Ok(Statement::Import { path, column, sym })
+}
+
+fn parse_run(run: Pairs<Rule>) -> Result<Statement, ParserError> {
+    let mut it = run;
+
+    let path = it
+        .next()
+        .ok_or_else(|| ParserError::MissingRunPath(it.as_str().to_string()))?
+        .as_str()
+        .trim_matches('"')
+        .to_string();
+
+    Ok(Statement::Run { path })
+}
+
+fn parse_fit(fit: Pairs<Rule>) -> Result<Statement, ParserError> {
+    let mut it = fit;
+
+    let xs = it
+        .next()
+        .ok_or_else(|| ParserError::MissingFitData(it.as_str().to_string()))?
+        .as_str()
+        .to_string();
+    let ys = it
+        .next()
+        .ok_or_else(|| ParserError::MissingFitData(it.as_str().to_string()))?
+        .as_str()
+        .to_string();
+    let name = it.next().map(|name| name.as_str().to_string());
+
+    Ok(Statement::FitLinear { xs, ys, name })
+}
+
+fn parse_definition(definition: Pairs<Rule>) -> Result<Statement, ParserError> {
+    let mut it = definition;
+    // skip the `define` keyword
+    it.next();
+    let sym = it
+        .next()
+        .ok_or_else(|| ParserError::MissingAssignmentTarget(it.as_str().to_string()))?
+        .as_str()
+        .to_string();
+    let op = parse_operand(
+        it.next()
+            .ok_or_else(|| ParserError::MissingAssignmentExpression(it.as_str().to_string()))?
+            .into_inner(),
+    )?;
+    Ok(Statement::Define { sym, op })
+}
+
+fn parse_redefinition(redefinition: Pairs<Rule>) -> Result<Statement, ParserError> {
+    let mut it = redefinition;
+    // skip the `override` keyword
+    it.next();
+    let st = parse_statement(it)?;
+    Ok(Statement::Override { st: Box::new(st) })
+}
+
+fn parse_unset(unset: Pairs<Rule>) -> Result<Statement, ParserError> {
+    let mut it = unset;
+    it.next(); // unset keyword
+    let name = it
+        .next()
+        .ok_or_else(|| ParserError::InvalidSymbol(it.as_str().to_string()))?
+        .as_str()
+        .to_string();
+    Ok(Statement::Unset { name })
+}
+
 fn parse_statement(statements: Pairs<Rule>) -> Result<Statement, ParserError> {
     let mut it = statements;
     let statement = it.next().ok_or(ParserError::EmptyStatement)?;
     match statement.as_rule() {
         Rule::assignment => parse_assignment(statement.into_inner()),
+        Rule::text_assignment => parse_text_assignment(statement.into_inner()),
+        Rule::text => Ok(Statement::TextExpression {
+            text: parse_text(statement.into_inner()),
+        }),
         Rule::expr => Ok(Statement::Expression {
             op: parse_operand(Pairs::single(statement))?,
         }),
         Rule::solvefor => parse_solve_for(statement.into_inner()),
+        Rule::solve_inequality => parse_solve_inequality(statement.into_inner()),
+        Rule::store => parse_store(statement.into_inner()),
+        Rule::integrate => parse_integrate(statement.into_inner()),
+        Rule::solve_stored => parse_solve_stored(statement.into_inner()),
         Rule::function => parse_function(statement.into_inner()),
+        Rule::sequence_value => parse_sequence_value(statement.into_inner()),
         Rule::plot => parse_plot(statement.into_inner()),
+        Rule::equivalent => parse_equivalent(statement.into_inner()),
+        Rule::simplify => parse_simplify(statement.into_inner()),
+        Rule::import => parse_import(statement.into_inner()),
+        Rule::run => parse_run(statement.into_inner()),
+        Rule::fit => parse_fit(statement.into_inner()),
+        Rule::redefinition => parse_redefinition(statement.into_inner()),
+        Rule::definition => parse_definition(statement.into_inner()),
+        Rule::listing => Ok(Statement::List),
+        Rule::undo => Ok(Statement::Undo),
+        Rule::empty => Ok(Statement::Empty),
+        Rule::unset => parse_unset(statement.into_inner()),
         r => Err(ParserError::InvalidStatement(format!(
             "Unexpected rule: {:?}",
             r
@@ -238,7 +962,15 @@ fn parse_statement(statements: Pairs<Rule>) -> Result<Statement, ParserError> {
 pub fn parse(cmd: &str) -> Result<Statement, ParserError> {
     match EquationParser::parse(Rule::statement, cmd) {
         Ok(rules) => parse_statement(rules),
-        Err(e) => Err(ParserError::InvalidExpression(e.to_string())),
+        Err(e) => Err(syntax_error(cmd, e)),
+    }
+}
+
+/// parses a line, which must be an expression
+pub fn parse_expression(cmd: &str) -> Result<Operand, ParserError> {
+    match parse(cmd)? {
+        Statement::Expression { op } => Ok(op),
+        _ => Err(ParserError::InvalidExpression(cmd.to_string())),
     }
 }
 
@@ -252,6 +984,60 @@ mod tests {
         assert_eq!(Ok(Statement::Expression { op }), parse("12.2"));
     }
 
+    #[test]
+    fn parse_number_literals() {
+        for (exp, input) in [
+            (1e-3, "1e-3"),
+            (2500.0, "2.5E3"),
+            (255.0, "0xFF"),
+            (255.0, "0Xff"),
+            (10.0, "0b1010"),
+            (15.0, "0o17"),
+        ] {
+            assert_eq!(Ok(Operand::Number(exp)), parse_expression(input));
+        }
+        assert_eq!(
+            Err(ParserError::InvalidNumber(
+                "0x10000000000000000".to_string()
+            )),
+            parse_expression("0x10000000000000000")
+        );
+        // without digits `0x` is still the implicit product `0 * x`
+        assert!(matches!(parse_expression("0x"), Ok(Operand::Term(_))));
+        assert!(parse("0b102").is_err());
+    }
+
+    #[test]
+    fn parse_literals_with_more_digits() {
+        for input in ["0.1", "0.0", "1000", "1.50e2", "0.001250"] {
+            assert!(
+                matches!(parse_expression(input), Ok(Operand::Number(_))),
+                "{}",
+                input
+            );
+        }
+        let literal = |value, text: &str| {
+            Ok(Operand::Literal(Box::new(Literal {
+                value,
+                text: text.to_string(),
+            })))
+        };
+        let digits = "123456789.123456789123";
+        assert_eq!(
+            literal(digits.parse().unwrap(), digits),
+            parse_expression(digits)
+        );
+        assert_eq!(
+            literal(-digits.parse::<Number>().unwrap(), &format!("-{}", digits)),
+            parse_expression(&format!("-{}", digits))
+        );
+        assert_eq!(
+            literal(Number::INFINITY, "1e999"),
+            parse_expression("1e999")
+        );
+        assert_eq!(digits, parse_expression(digits).unwrap().to_string());
+    }
+
     #[test]
     fn parse_symbol() {
         let op = Operand::Symbol("x".to_string());
@@ -302,6 +1088,93 @@ mod tests {
         assert_eq!(Ok(Statement::Expression { op }), parse("1 + 2 * val"));
     }
 
+    #[test]
+    fn parse_signs() {
+        let expr = |s: &str| parse_expression(s).unwrap();
+        let neg = |op| new_operand_term(Operand::Number(-1.0), Operation::Mul, op);
+        let sym = |s: &str| Operand::Symbol(s.to_string());
+        assert_eq!(
+            new_operand_term(neg(sym("x")), Operation::Add, Operand::Number(3.0)),
+            expr("-x + 3")
+        );
+        assert_eq!(
+            new_operand_term(Operand::Number(2.0), Operation::Mul, neg(expr("a + 1"))),
+            expr("2 * -(a + 1)")
+        );
+        assert_eq!(neg(expr("sin(x)")), expr("-sin(x)"));
+        assert_eq!(
+            neg(new_operand_term(
+                sym("x"),
+                Operation::Pow,
+                Operand::Number(2.0)
+            )),
+            expr("-x ^ 2")
+        );
+        assert_eq!(
+            neg(new_operand_term(
+                Operand::Number(2.0),
+                Operation::Pow,
+                Operand::Number(2.0)
+            )),
+            expr("-2 ^ 2")
+        );
+        assert_eq!(neg(expr("3!")), expr("-3!"));
+        assert_eq!(
+            new_operand_term(Operand::Number(2.0), Operation::Pow, Operand::Number(-1.0)),
+            expr("2 ^ -1")
+        );
+        assert_eq!(sym("x"), expr("--x"));
+        assert_eq!(sym("x"), expr("+x"));
+        assert_eq!(Operand::Number(-2.0), expr("-(2)"));
+        assert_eq!(
+            new_operand_term(sym("a"), Operation::Sub, neg(sym("b"))),
+            expr("a - -b")
+        );
+    }
+
+    #[test]
+    fn parse_factorials() {
+        let expr = |s: &str| parse_expression(s).unwrap();
+        let sym = |s: &str| Operand::Symbol(s.to_string());
+        assert_eq!(Operand::Number(3.0).factorial(), expr("3!"));
+        assert_eq!(sym("n").factorial().factorial(), expr("n!!"));
+        assert_eq!(expr("2 ^ (3!)"), expr("2 ^ 3!"));
+        assert_eq!(expr("2 * (x!)"), expr("2x!"));
+        assert_eq!(
+            new_operand_term(Operand::Number(-1.0), Operation::Mul, sym("x").factorial()),
+            expr("-x!")
+        );
+        assert_eq!(expr("(a - 1)!"), expr("(a-1)!"));
+        assert!(matches!(
+            parse("if(n! != 2, 1, 0)"),
+            Ok(Statement::Expression { .. })
+        ));
+        assert!(matches!(
+            parse("if(n != 2, 1, 0)"),
+            Ok(Statement::Expression { op: Operand::Conditional(cond) }) if cond.guard.lhs == sym("n")
+        ));
+    }
+
+    #[test]
+    fn parse_implicit_multiplication() {
+        let expr = |s: &str| parse_expression(s).unwrap();
+        assert_eq!(expr("2 * x"), expr("2x"));
+        assert_eq!(expr("3 * (x + 1)"), expr("3(x + 1)"));
+        assert_eq!(expr("2 * sin(x)"), expr("2 sin(x)"));
+        assert_eq!(expr("2 * x ^ 2 + 1"), expr("2x^2 + 1"));
+        assert_eq!(expr("-2 * x * y"), expr("-2x y"));
+        assert_eq!(expr("1.5e3 * x"), expr("1.5e3x"));
+        assert!(matches!(
+            parse("solve 2x = 4 for x"),
+            Ok(Statement::SolveFor { .. })
+        ));
+        assert!(matches!(
+            parse("f(x) := 2x when x > 0"),
+            Ok(Statement::Function { .. })
+        ));
+        assert!(parse("x y").is_err());
+    }
+
     #[test]
     fn parse_term_precedence_sub_div_pow() {
         let lhs = Operand::Number(1.0);
@@ -321,6 +1194,36 @@ mod tests {
         assert_eq!(Ok(Statement::Expression { op }), parse("1 + 2 ^ exp * val"));
     }
 
+    #[test]
+    fn parse_associativity() {
+        let term =
+            |op, lhs: f64, rhs: Operand| Operand::term(op, Operand::Number(lhs as Number), rhs);
+        assert_eq!(
+            Ok(Operand::term(
+                Operation::Sub,
+                term(Operation::Sub, 8.0, Operand::Number(4.0)),
+                Operand::Number(2.0)
+            )),
+            parse_expression("8 - 4 - 2")
+        );
+        assert_eq!(
+            Ok(term(
+                Operation::Pow,
+                2.0,
+                term(Operation::Pow, 3.0, Operand::Number(2.0))
+            )),
+            parse_expression("2 ^ 3 ^ 2")
+        );
+        let long = (1..=1000)
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>()
+            .join(" + ");
+        assert!(matches!(
+            parse_expression(&long),
+            Ok(Operand::Term(term)) if term.rhs == Operand::Number(1000.0)
+        ));
+    }
+
     #[test]
     fn parse_a_is_1() {
         let statement = Statement::Assignment {
@@ -336,10 +1239,67 @@ mod tests {
             lhs: Operand::Number(13.0),
             rhs: Operand::Symbol("x".to_string()),
             sym: "x".to_string(),
+            bracket: None,
         };
         assert_eq!(Ok(statement), parse("solve 13 = x for x"));
     }
 
+    #[test]
+    fn parse_integrate() {
+        let bracket = Bracket {
+            from: Operand::Number(0.0),
+            to: Operand::Symbol("a".to_string()),
+        };
+        assert_eq!(
+            Ok(Statement::Integrate {
+                name: "f".to_string(),
+                bracket: bracket.clone(),
+            }),
+            parse("integrate f from 0 to a")
+        );
+        assert_eq!(
+            Ok(Statement::IntegrateExpression {
+                op: parse_expression("x ^ 2").unwrap(),
+                sym: "x".to_string(),
+                bracket,
+            }),
+            parse("integrate x ^ 2 for x in [0, a]")
+        );
+        assert!(matches!(
+            parse("integrated := 1"),
+            Ok(Statement::Assignment { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_solve_for_bracket() {
+        let statement = Statement::SolveFor {
+            lhs: Operand::FunCall(FunCall {
+                name: "sin".to_string(),
+                params: vec![Operand::Symbol("x".to_string())],
+            }),
+            rhs: Operand::Number(0.5),
+            sym: "x".to_string(),
+            bracket: Some(Bracket {
+                from: Operand::Number(0.0),
+                to: Operand::Symbol("a".to_string()),
+            }),
+        };
+        assert_eq!(Ok(statement), parse("solve sin(x) = 0.5 for x in [0, a]"));
+    }
+
+    #[test]
+    fn parse_solve_inequality() {
+        let statement = Statement::SolveInequality {
+            lhs: Operand::Symbol("x".to_string()),
+            cmp: Comparison::GreaterEqual,
+            rhs: Operand::Number(1.0),
+            sym: "x".to_string(),
+        };
+        assert_eq!(Ok(statement), parse("solve x >= 1 for x"));
+        assert!(parse("solve x != 1 for x").is_err());
+    }
+
     #[test]
     fn parse_fun_no_args() {
         let fun = Function::Custom(CustomFunction {
@@ -371,6 +1331,105 @@ mod tests {
         assert_eq!(Ok(statement), parse("f(x) := 1 + x"));
     }
 
+    #[test]
+    fn parse_fun_clauses() {
+        let x = || Operand::Symbol("x".to_string());
+        let fun = Function::Piecewise(Piecewise {
+            args: vec!["x".to_string()],
+            clauses: vec![Clause {
+                body: x(),
+                guard: Some(Guard {
+                    lhs: x(),
+                    cmp: Comparison::GreaterEqual,
+                    rhs: Operand::Number(0.0),
+                }),
+            }],
+        });
+        let statement = Statement::Function {
+            name: "f".to_string(),
+            fun,
+        };
+        assert_eq!(Ok(statement), parse("f(x) := x when x >= 0"));
+        assert!(matches!(
+            parse("f(x) := 0 - x otherwise"),
+            Ok(Statement::Function {
+                fun: Function::Piecewise(Piecewise { clauses, .. }),
+                ..
+            }) if clauses[0].guard.is_none()
+        ));
+        assert!(matches!(
+            parse("f(x) := whenever + 1"),
+            Ok(Statement::Function {
+                fun: Function::Custom(_),
+                ..
+            })
+        ));
+        assert!(parse("f(x) := x when x").is_err());
+    }
+
+    #[test]
+    fn parse_conditional() {
+        let x = || Operand::Symbol("x".to_string());
+        let op = Operand::Conditional(Box::new(Conditional {
+            guard: Guard {
+                lhs: x(),
+                cmp: Comparison::Less,
+                rhs: Operand::Number(0.0),
+            },
+            then: Operand::Number(0.0),
+            otherwise: x(),
+        }));
+        assert_eq!(Ok(op), parse_expression("if(x < 0, 0, x)"));
+        assert!(matches!(
+            parse_expression("iff(x)"),
+            Ok(Operand::FunCall(_))
+        ));
+        assert!(matches!(
+            parse_expression("if(x, 0, x)"),
+            Ok(Operand::FunCall(_))
+        ));
+    }
+
+    #[test]
+    fn parse_series() {
+        let op = Operand::Series(Box::new(Series {
+            kind: SeriesKind::Product,
+            var: "i".to_string(),
+            from: Operand::Number(1.0),
+            to: Operand::Symbol("n".to_string()),
+            body: Operand::Symbol("i".to_string()),
+        }));
+        assert_eq!(Ok(op), parse_expression("prod(i, 1, n, i)"));
+        assert!(matches!(
+            parse_expression("sum(xs)"),
+            Ok(Operand::FunCall(_))
+        ));
+        assert!(matches!(
+            parse_expression("sum(2, 1, 3, x)"),
+            Ok(Operand::FunCall(_))
+        ));
+    }
+
+    #[test]
+    fn parse_let() {
+        let op = Operand::Let(Box::new(Let {
+            var: "t".to_string(),
+            value: parse_expression("x ^ 2").unwrap(),
+            body: parse_expression("t + 1 / t").unwrap(),
+        }));
+        assert_eq!(Ok(op), parse_expression("let t := x ^ 2; t + 1 / t"));
+        assert!(matches!(
+            parse("f(x) := let a := x; let b := a * 2; a + b"),
+            Ok(Statement::Function { .. })
+        ));
+        assert!(matches!(
+            parse_expression("letter + 1"),
+            Ok(Operand::Term(_))
+        ));
+        assert!(parse_expression("1 + let t := 2; t").is_err());
+        assert!(parse_expression("let t := 2").is_err());
+    }
+
     #[test]
     fn parse_fun_call_without_params() {
         let fun_call = FunCall {
@@ -404,11 +1463,393 @@ mod tests {
         assert_eq!(Ok(stat), parse("fun(42)"));
     }
 
+    #[test]
+    fn parse_equivalent() {
+        let stat = Statement::Equivalent {
+            lhs: Operand::Symbol("x".to_string()),
+            rhs: Operand::Number(1.0),
+        };
+        assert_eq!(Ok(stat), parse("is x == 1"));
+    }
+
+    #[test]
+    fn parse_simplify() {
+        let stat = Statement::Simplify {
+            op: Operand::Term(Box::new(Term {
+                op: Operation::Add,
+                lhs: Operand::Symbol("x".to_string()),
+                rhs: Operand::Symbol("x".to_string()),
+            })),
+        };
+        assert_eq!(Ok(stat), parse("simplify x + x"));
+        assert_eq!(
+            Ok(Statement::Expression {
+                op: Operand::Symbol("simplifyx".to_string())
+            }),
+            parse("simplifyx")
+        );
+    }
+
+    #[test]
+    fn parse_run() {
+        let stat = Statement::Run {
+            path: "lib/trig_helpers.calc".to_string(),
+        };
+        assert_eq!(Ok(stat), parse("run \"lib/trig_helpers.calc\""));
+        assert!(matches!(
+            parse("runs := 1"),
+            Ok(Statement::Assignment { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_list() {
+        assert_eq!(Ok(Statement::List), parse("list"));
+        assert_eq!(Ok(Statement::List), parse(" env "));
+        assert_eq!(Ok(Statement::Undo), parse("undo"));
+        assert!(matches!(
+            parse("list * 2"),
+            Ok(Statement::Expression { .. })
+        ));
+        assert!(matches!(
+            parse("envelope := 1"),
+            Ok(Statement::Assignment { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_comments() {
+        assert_eq!(parse("a := 1"), parse("a := 1 # one"));
+        assert_eq!(parse("f(x) := x / 2"), parse("f(x) := x / 2 // half"));
+        assert_eq!(parse("t := \"#1\""), parse("t := \"#1\" # text"));
+        assert_eq!(Ok(Statement::Empty), parse("# only a comment"));
+        assert_eq!(Ok(Statement::Empty), parse("  "));
+        assert!(parse("a := # 1").is_err());
+    }
+
+    #[test]
+    fn parse_matrix() {
+        let num = Operand::Number;
+        assert_eq!(
+            Ok(Operand::Matrix(vec![
+                vec![num(1.0), num(2.0)],
+                vec![num(3.0), num(4.0)]
+            ])),
+            parse_expression("[1, 2; 3, 4]")
+        );
+        assert_eq!(
+            Ok(Operand::Matrix(vec![vec![
+                num(1.0),
+                Operand::Symbol("x".to_string())
+            ]])),
+            parse_expression("[1, x]")
+        );
+        assert_eq!(
+            Err(ParserError::RaggedMatrix("[1, 2; 3]".to_string())),
+            parse_expression("[1, 2; 3]")
+        );
+        assert!(parse_expression("[]").is_err());
+    }
+
+    #[test]
+    fn parse_unset() {
+        assert_eq!(
+            Ok(Statement::Unset {
+                name: "a".to_string()
+            }),
+            parse("unset a")
+        );
+        assert!(parse("unset 1").is_err());
+        assert!(matches!(
+            parse("unsetting := 1"),
+            Ok(Statement::Assignment { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_import() {
+        let stat = Statement::Import {
+            path: "data/measurements.csv".to_string(),
+            column: 2,
+            sym: "ys".to_string(),
+        };
+        assert_eq!(
+            Ok(stat),
+            parse("import \"data/measurements.csv\" column 2 as ys")
+        );
+    }
+
+    #[test]
+    fn parse_fit_linear() {
+        let stat = Statement::FitLinear {
+            xs: "xs".to_string(),
+            ys: "ys".to_string(),
+            name: None,
+        };
+        assert_eq!(Ok(stat), parse("fit linear xs, ys"));
+        let stat = Statement::FitLinear {
+            xs: "xs".to_string(),
+            ys: "ys".to_string(),
+            name: Some("fit1".to_string()),
+        };
+        assert_eq!(Ok(stat), parse("fit linear xs, ys as fit1"));
+    }
+
+    #[test]
+    fn parse_sequence_value() {
+        let stat = Statement::SequenceValue {
+            name: "a".to_string(),
+            index: 1,
+            op: Operand::Number(1.0),
+        };
+        assert_eq!(Ok(stat), parse("a(1) := 1"));
+        assert!(parse("a(-1) := 1").is_err());
+        assert!(parse("a(1.5) := 1").is_err());
+    }
+
+    #[test]
+    fn parse_define() {
+        let stat = Statement::Define {
+            sym: "y".to_string(),
+            op: Operand::Symbol("x".to_string()),
+        };
+        assert_eq!(Ok(stat), parse("define y = x"));
+        assert!(matches!(
+            parse("override define y = x"),
+            Ok(Statement::Override { .. })
+        ));
+        assert!(parse("definey = x").is_err());
+    }
+
+    #[test]
+    fn parse_override() {
+        let stat = Statement::Override {
+            st: Box::new(Statement::Assignment {
+                sym: "a".to_string(),
+                op: Operand::Number(1.0),
+            }),
+        };
+        assert_eq!(Ok(stat), parse("override a := 1"));
+        assert!(matches!(
+            parse("overridea := 1"),
+            Ok(Statement::Assignment { sym, .. }) if sym == "overridea"
+        ));
+        assert!(parse("override plot f").is_err());
+    }
+
+    #[test]
+    fn syntax_error_spans() {
+        assert_eq!(
+            Err(ParserError::Syntax {
+                found: "*".to_string(),
+                expected: "expected number, symbol, matrix, `-`, `+`, `if`, or `sum`/`prod`"
+                    .to_string(),
+                span: Span { start: 4, end: 5 },
+            }),
+            parse("1 + * 2")
+        );
+        assert!(matches!(
+            parse("f(x := 2"),
+            Err(ParserError::Syntax {
+                span: Span { start: 4, end: 6 },
+                ..
+            })
+        ));
+        assert_eq!(
+            Err(ParserError::Syntax {
+                found: "end of input".to_string(),
+                expected: "expected expression".to_string(),
+                span: Span { start: 1, end: 1 },
+            }),
+            parse("(")
+        );
+        assert!(matches!(
+            parse("a := "),
+            Err(ParserError::Syntax { span: Span { start: 5, end: 5 }, found, .. }) if found == "end of input"
+        ));
+    }
+
+    #[test]
+    fn parse_expression_only() {
+        assert_eq!(Ok(Operand::Number(1.0)), parse_expression("1"));
+        assert_eq!(
+            Err(ParserError::InvalidExpression("a := 1".to_string())),
+            parse_expression("a := 1")
+        );
+    }
+
     #[test]
     fn parse_plot() {
         let stat = Statement::Plot {
             name: "fun".to_string(),
+            sweep: None,
+            labels: PlotLabels::default(),
         };
         assert_eq!(Ok(stat), parse("plot fun"));
     }
+
+    #[test]
+    fn parse_plot_expression() {
+        let stat = Statement::PlotExpression {
+            op: parse_expression("x ^ 2 + 1").unwrap(),
+            labels: PlotLabels::default(),
+        };
+        assert_eq!(Ok(stat), parse("plot x ^ 2 + 1"));
+        assert!(matches!(
+            parse("plot f(2 * t) title \"f\""),
+            Ok(Statement::PlotExpression {
+                op: Operand::FunCall(_),
+                labels: PlotLabels { title: Some(_), .. },
+            })
+        ));
+        assert!(matches!(
+            parse("plot f title \"f\""),
+            Ok(Statement::Plot { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_plot_polar() {
+        assert_eq!(
+            Ok(Statement::PlotPolar {
+                op: parse_expression("2 * t").unwrap(),
+                bracket: Some(Bracket {
+                    from: Operand::Number(0.0),
+                    to: Operand::Number(4.0),
+                }),
+                labels: PlotLabels::default(),
+            }),
+            parse("plot polar 2 * t in [0, 4]")
+        );
+        assert!(matches!(
+            parse("plot polar r title \"r\""),
+            Ok(Statement::PlotPolar { bracket: None, .. })
+        ));
+        // without a radius `polar` is the plotted function
+        assert!(matches!(
+            parse("plot polar"),
+            Ok(Statement::Plot { name, .. }) if name == "polar"
+        ));
+    }
+
+    #[test]
+    fn parse_plot_implicit() {
+        assert_eq!(
+            Ok(Statement::PlotImplicit {
+                lhs: parse_expression("x ^ 2 + y ^ 2").unwrap(),
+                rhs: Some(Operand::Number(100.0)),
+                labels: PlotLabels::default(),
+            }),
+            parse("plot x ^ 2 + y ^ 2 = 100")
+        );
+        assert!(matches!(
+            parse("plot implicit f title \"f\""),
+            Ok(Statement::PlotImplicit {
+                lhs: Operand::Symbol(_),
+                rhs: None,
+                labels: PlotLabels { title: Some(_), .. },
+            })
+        ));
+        assert!(parse("plot x = 1 = 2").is_err());
+    }
+
+    #[test]
+    fn parse_stored_equations() {
+        assert_eq!(
+            Ok(Statement::Store {
+                name: "ohm".to_string(),
+                lhs: Operand::Symbol("v".to_string()),
+                rhs: parse_expression("i * r").unwrap(),
+            }),
+            parse("store ohm: v = i * r")
+        );
+        assert_eq!(
+            Ok(Statement::SolveStored {
+                name: "ohm".to_string(),
+                sym: "i".to_string(),
+                given: vec![
+                    ("v".to_string(), Operand::Number(12.0)),
+                    ("r".to_string(), parse_expression("2 * a").unwrap()),
+                ],
+            }),
+            parse("solve ohm for i given v := 12, r := 2 * a")
+        );
+        assert!(matches!(
+            parse("solve ohm for i"),
+            Ok(Statement::SolveStored { given, .. }) if given.is_empty()
+        ));
+        assert!(matches!(
+            parse("store := 1"),
+            Ok(Statement::Assignment { .. })
+        ));
+    }
+
+    #[test]
+    fn parse_plot_sweep() {
+        let stat = Statement::Plot {
+            name: "f".to_string(),
+            sweep: Some(Sweep {
+                args: vec!["x".to_string(), "a".to_string()],
+                param: "a".to_string(),
+                from: Operand::Number(0.0),
+                to: Operand::Number(5.0),
+                step: Some(Operand::Number(0.5)),
+            }),
+            labels: PlotLabels::default(),
+        };
+        assert_eq!(Ok(stat), parse("plot f(x, a) for a in 0..5 step 0.5"));
+        assert!(matches!(
+            parse("plot f(x, a) for a in -1..n title \"f\""),
+            Ok(Statement::Plot {
+                sweep: Some(Sweep { step: None, .. }),
+                labels: PlotLabels { title: Some(_), .. },
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("plot f(x, y) for a in 0..5"),
+            Err(ParserError::InvalidSweep(_))
+        ));
+    }
+
+    #[test]
+    fn parse_texts() {
+        let literal = |s: &str| TextPart::Literal(s.to_string());
+        let symbol = |s: &str| TextPart::Symbol(s.to_string());
+        assert_eq!(
+            Ok(Statement::TextAssignment {
+                sym: "title".to_string(),
+                text: Text {
+                    parts: vec![literal("position vs time")],
+                },
+            }),
+            parse("title := \"position vs time\"")
+        );
+        assert_eq!(
+            Ok(Statement::TextExpression {
+                text: Text {
+                    parts: vec![symbol("a"), literal(" = "), symbol("b")],
+                },
+            }),
+            parse("a + \" = \" + b")
+        );
+        assert_eq!(
+            Ok(Statement::Plot {
+                name: "f".to_string(),
+                sweep: None,
+                labels: PlotLabels {
+                    title: Some(Text {
+                        parts: vec![symbol("title"), literal(" (m)")],
+                    }),
+                    xlabel: None,
+                    ylabel: None,
+                },
+            }),
+            parse("plot f title title + \" (m)\"")
+        );
+        assert!(matches!(
+            parse("a := b + c"),
+            Ok(Statement::Assignment { .. })
+        ));
+    }
 }
@@ -0,0 +1,195 @@
+//! Small seedable pseudo random number generator (xorshift64*).
+//!
+//! Not suitable for cryptography, but deterministic for a given seed,
+//! which keeps sampling based features reproducible.
+//!
+//! Each calculator has its own generator for the random functions (`randn()`,
+//! `randexp(lambda)`), which is the generator of the thread while it evaluates,
+//! see [`with_generator`].
+
+use crate::ast::{Data, DataFunction, Number};
+
+use std::cell::Cell;
+
+/// name of the build-in function evaluating an expression repeatedly, see [`functions`]
+pub const SAMPLE: &str = "sample";
+
+/// maximal number of elements `sample` creates
+pub const MAX_SAMPLES: usize = 10_000_000;
+
+thread_local! {
+    /// generator of the evaluating calculator, evaluations without one use the thread's own
+    static RNG: Cell<Rng> = Cell::new(Rng::default());
+    static INSTALLED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `f` with `rng` as generator of this thread and returns `rng` advanced by `f`.
+/// Within an evaluation with a generator, `f` continues the outer generator.
+pub(crate) fn with_generator<T>(rng: Rng, f: impl FnOnce() -> T) -> (T, Rng) {
+    if INSTALLED.with(Cell::get) {
+        return (f(), rng);
+    }
+    let outer = RNG.with(|current| current.replace(rng));
+    INSTALLED.with(|installed| installed.set(true));
+    let result = f();
+    INSTALLED.with(|installed| installed.set(false));
+    (result, RNG.with(|current| current.replace(outer)))
+}
+
+fn with_rng<T>(f: impl FnOnce(&mut Rng) -> T) -> T {
+    RNG.with(|current| {
+        let mut rng = current.get();
+        let result = f(&mut rng);
+        current.set(rng);
+        result
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Default for Rng {
+    fn default() -> Self {
+        Rng::new(0x853c_49e6_748f_ea9b)
+    }
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // the state of xorshift must never be zero
+        Rng {
+            state: if seed == 0 {
+                0x9e37_79b9_7f4a_7c15
+            } else {
+                seed
+            },
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// uniformly distributed number in `[0, 1)`
+    pub fn next_number(&mut self) -> Number {
+        (self.next_u64() >> 11) as Number / (1u64 << 53) as Number
+    }
+
+    /// uniformly distributed number in `[min, max)`
+    pub fn uniform(&mut self, min: Number, max: Number) -> Number {
+        min + (max - min) * self.next_number()
+    }
+
+    /// standard normal distributed number (Box-Muller transform)
+    pub fn normal(&mut self) -> Number {
+        // 1 - u is in (0, 1], so the logarithm is finite
+        let radius = (-2.0 * (1.0 - self.next_number()).ln()).sqrt();
+        radius * (std::f64::consts::TAU as Number * self.next_number()).cos()
+    }
+
+    /// exponentially distributed number with rate `lambda`
+    pub fn exponential(&mut self, lambda: Number) -> Number {
+        -(1.0 - self.next_number()).ln() / lambda
+    }
+}
+
+fn randn_fn(_: &[Data]) -> Result<Data, String> {
+    Ok(Data::Number(with_rng(Rng::normal)))
+}
+
+fn randexp_fn(params: &[Data]) -> Result<Data, String> {
+    match params[0].number() {
+        Some(lambda) if lambda > 0.0 => Ok(Data::Number(with_rng(|rng| rng.exponential(lambda)))),
+        _ => Err("lambda must be a positive number".to_string()),
+    }
+}
+
+fn sample_fn(_: &[Data]) -> Result<Data, String> {
+    // the calculator evaluates `sample` itself, because it needs the unevaluated expression
+    Err("sample must be evaluated lazily".to_string())
+}
+
+/// `randn()`, `randexp(lambda)` and `sample(expr, n)`, which evaluates `expr` `n` times
+pub(crate) fn functions() -> Vec<DataFunction> {
+    vec![
+        DataFunction {
+            name: "randn".to_string(),
+            args: vec![],
+            returns_list: false,
+            body: &randn_fn,
+        },
+        DataFunction {
+            name: "randexp".to_string(),
+            args: vec!["lambda".to_string()],
+            returns_list: false,
+            body: &randexp_fn,
+        },
+        DataFunction {
+            name: SAMPLE.to_string(),
+            args: vec!["expr".to_string(), "n".to_string()],
+            returns_list: true,
+            body: &sample_fn,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rng_is_deterministic() {
+        let mut a = Rng::new(42);
+        let mut b = Rng::new(42);
+        assert!((0..10).all(|_| a.next_u64() == b.next_u64()));
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+
+    #[test]
+    fn rng_distributions() {
+        let mut rng = Rng::default();
+        let n = 10_000;
+        let normal: Vec<Number> = (0..n).map(|_| rng.normal()).collect();
+        let mean = normal.iter().sum::<Number>() / n as Number;
+        let var = normal.iter().map(|x| (x - mean).powi(2)).sum::<Number>() / n as Number;
+        assert!(mean.abs() < 0.05);
+        assert!((var - 1.0).abs() < 0.05);
+
+        let exp: Vec<Number> = (0..n).map(|_| rng.exponential(2.0)).collect();
+        assert!(exp.iter().all(|x| *x >= 0.0));
+        assert!((exp.iter().sum::<Number>() / n as Number - 0.5).abs() < 0.02);
+    }
+
+    #[test]
+    fn rng_seeded_functions() {
+        let (a, _) = with_generator(Rng::new(7), || randn_fn(&[]));
+        let (b, advanced) = with_generator(Rng::new(7), || randn_fn(&[]));
+        assert_eq!(a, b);
+        assert_ne!(Rng::new(7), advanced);
+        // nested evaluations continue the outer generator
+        let ((a, b), _) = with_generator(Rng::new(7), || {
+            (
+                randn_fn(&[]),
+                with_generator(Rng::new(7), || randn_fn(&[])).0,
+            )
+        });
+        assert_ne!(a, b);
+        assert_eq!(
+            Err("lambda must be a positive number".to_string()),
+            randexp_fn(&[Data::Number(0.0)])
+        );
+    }
+
+    #[test]
+    fn rng_uniform_in_range() {
+        let mut rng = Rng::default();
+        assert!((0..1000)
+            .map(|_| rng.uniform(-2.0, 3.0))
+            .all(|x| (-2.0..3.0).contains(&x)));
+    }
+}
@@ -0,0 +1,182 @@
+//! Variables defined by formulas, which are recomputed when their dependencies change.
+
+use crate::ast::Operand;
+use crate::calc::{calc_list, calc_operand, CalcError, TopLevelEnv};
+use crate::dependencies::dependencies_of_operand;
+
+use std::collections::BTreeSet;
+
+/// variables and functions the formula `op` depends on
+fn dependencies(op: &Operand, env: &TopLevelEnv) -> BTreeSet<String> {
+    let deps = dependencies_of_operand(op, env);
+    deps.variables.into_iter().chain(deps.functions).collect()
+}
+
+/// variables with a formula, which the formula of `sym` depends on directly
+fn formula_dependencies(sym: &str, env: &TopLevelEnv) -> BTreeSet<String> {
+    match env.formula(sym) {
+        Some(op) => dependencies(op, env)
+            .into_iter()
+            .filter(|dep| env.formula(dep).is_some())
+            .collect(),
+        None => BTreeSet::new(),
+    }
+}
+
+/// path from `from` to `to` through the formulas
+fn path(
+    from: &str,
+    to: &str,
+    env: &TopLevelEnv,
+    visited: &mut BTreeSet<String>,
+) -> Option<Vec<String>> {
+    if from == to {
+        return Some(vec![to.to_string()]);
+    }
+    if !visited.insert(from.to_string()) {
+        return None;
+    }
+    let deps = match env.formula(from) {
+        Some(op) => dependencies(op, env),
+        None => return None,
+    };
+    deps.iter().find_map(|dep| {
+        path(dep, to, env, visited).map(|mut path| {
+            path.insert(0, from.to_string());
+            path
+        })
+    })
+}
+
+/// Fails with [`CalcError::CyclicDefinition`], if the formula `op` for `sym` depends on `sym`.
+pub fn check_cycle(sym: &str, op: &Operand, env: &TopLevelEnv) -> Result<(), CalcError> {
+    let mut visited = BTreeSet::new();
+    for dep in dependencies(op, env) {
+        if let Some(path) = path(&dep, sym, env, &mut visited) {
+            let cycle = std::iter::once(sym.to_string()).chain(path).collect();
+            return Err(CalcError::CyclicDefinition(cycle));
+        }
+    }
+    Ok(())
+}
+
+/// formulas ordered such that every formula comes after the formulas it depends on
+pub fn topological_order(env: &TopLevelEnv) -> Vec<String> {
+    fn visit(
+        sym: &str,
+        env: &TopLevelEnv,
+        visited: &mut BTreeSet<String>,
+        order: &mut Vec<String>,
+    ) {
+        if visited.insert(sym.to_string()) {
+            for dep in formula_dependencies(sym, env) {
+                visit(&dep, env, visited, order);
+            }
+            order.push(sym.to_string());
+        }
+    }
+    let mut names: Vec<&str> = env.formula_names().collect();
+    names.sort();
+    let mut visited = BTreeSet::new();
+    let mut order = Vec::new();
+    for name in names {
+        visit(name, env, &mut visited, &mut order);
+    }
+    order
+}
+
+/// Evaluates the formula of `sym` and stores its value.
+/// If the evaluation fails, `sym` has no value until the formula can be evaluated again.
+pub fn evaluate(sym: &str, env: &mut TopLevelEnv) -> Result<(), CalcError> {
+    let op = match env.formula(sym) {
+        Some(op) => op.clone(),
+        None => return Ok(()),
+    };
+    let result = match calc_list(&op, env) {
+        Some(list) => list.and_then(|list| env.put_list(sym.to_string(), list)),
+        None => calc_operand(&op, env).and_then(|num| env.put(sym.to_string(), num)),
+    };
+    if result.is_err() {
+        env.remove(sym)?;
+    }
+    result
+}
+
+/// Recomputes all formulas depending on the variables or functions in `changed`.
+/// Returns the first error, but recomputes all other formulas anyway.
+pub fn recompute(env: &mut TopLevelEnv, changed: &BTreeSet<String>) -> Result<(), CalcError> {
+    let mut dirty = changed.clone();
+    let mut first_error = Ok(());
+    for sym in topological_order(env) {
+        let op = env.formula(&sym).unwrap();
+        if dirty.contains(&sym) || dependencies(op, env).is_disjoint(&dirty) {
+            continue;
+        }
+        let before = env.clone();
+        let result = evaluate(&sym, env);
+        if first_error.is_ok() {
+            first_error = result;
+        }
+        if !env.changed_vars(&before).is_empty() {
+            dirty.insert(sym);
+        }
+    }
+    first_error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::Env;
+    use crate::parser::parse_expression;
+
+    fn define(env: &mut TopLevelEnv, sym: &str, formula: &str) -> Result<(), CalcError> {
+        let op = parse_expression(formula).unwrap();
+        check_cycle(sym, &op, env)?;
+        env.put_formula(sym.to_string(), op);
+        evaluate(sym, env)
+    }
+
+    fn changed(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn recompute_in_dependency_order() {
+        let mut env = TopLevelEnv::default();
+        env.put("x".to_string(), 1.0).unwrap();
+        define(&mut env, "z", "y * 2").unwrap_err();
+        define(&mut env, "y", "x + 1").unwrap();
+        define(&mut env, "z", "y * 2").unwrap();
+        assert_eq!(Some(&4.0), env.get("z"));
+
+        env.put("x".to_string(), 2.0).unwrap();
+        recompute(&mut env, &changed(&["x"])).unwrap();
+        assert_eq!(Some(&3.0), env.get("y"));
+        assert_eq!(Some(&6.0), env.get("z"));
+    }
+
+    #[test]
+    fn cycles() {
+        let mut env = TopLevelEnv::default();
+        env.put("c".to_string(), 1.0).unwrap();
+        define(&mut env, "a", "b + 1").unwrap_err();
+        define(&mut env, "b", "c + 1").unwrap();
+        assert_eq!(
+            Err(CalcError::CyclicDefinition(vec![
+                "c".to_string(),
+                "a".to_string(),
+                "b".to_string(),
+                "c".to_string()
+            ])),
+            define(&mut env, "c", "a")
+        );
+        assert_eq!(
+            Err(CalcError::CyclicDefinition(vec![
+                "d".to_string(),
+                "d".to_string()
+            ])),
+            define(&mut env, "d", "d + 1")
+        );
+    }
+}
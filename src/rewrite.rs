@@ -0,0 +1,378 @@
+//! Term rewriting of operands.
+//!
+//! A [`Rewriter`] holds an ordered list of [`RewriteRule`]s. Every rule is tried on every node
+//! (children before parents) and the whole pass is repeated until no rule applies anymore.
+
+use crate::ast::*;
+
+use std::collections::HashMap;
+use std::ops;
+
+/// maximal number of rewrite passes before [`Rewriter::rewrite`] gives up
+const MAX_PASSES: usize = 100;
+
+/// Left or right hand side of a [`RewriteRule`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    /// pattern variable matching any operand
+    Any(String),
+    /// pattern variable matching only numbers
+    Constant(String),
+    /// exactly this number
+    Number(Number),
+    /// exactly this symbol (variable name)
+    Symbol(String),
+    Term(Operation, Box<Pattern>, Box<Pattern>),
+    FunCall(String, Vec<Pattern>),
+}
+
+impl Pattern {
+    pub fn any(name: &str) -> Pattern {
+        Pattern::Any(name.to_string())
+    }
+
+    pub fn constant(name: &str) -> Pattern {
+        Pattern::Constant(name.to_string())
+    }
+
+    pub fn number(num: Number) -> Pattern {
+        Pattern::Number(num)
+    }
+
+    pub fn symbol(name: &str) -> Pattern {
+        Pattern::Symbol(name.to_string())
+    }
+
+    pub fn call(name: &str, params: Vec<Pattern>) -> Pattern {
+        Pattern::FunCall(name.to_string(), params)
+    }
+
+    pub fn pow(self, rhs: Pattern) -> Pattern {
+        Pattern::Term(Operation::Pow, Box::new(self), Box::new(rhs))
+    }
+
+    fn matches(&self, op: &Operand, bindings: &mut HashMap<String, Operand>) -> bool {
+        fn bind(name: &str, op: &Operand, bindings: &mut HashMap<String, Operand>) -> bool {
+            match bindings.get(name) {
+                Some(bound) => bound == op,
+                None => {
+                    bindings.insert(name.to_string(), op.clone());
+                    true
+                }
+            }
+        }
+
+        match (self, op) {
+            (Pattern::Any(name), op) => bind(name, op, bindings),
+            (Pattern::Constant(name), Operand::Number(_)) => bind(name, op, bindings),
+            (Pattern::Number(exp), Operand::Number(act)) => exp == act,
+            (Pattern::Symbol(exp), Operand::Symbol(act)) => exp == act,
+            (Pattern::Term(op, lhs, rhs), Operand::Term(term)) => {
+                *op == term.op
+                    && lhs.matches(&term.lhs, bindings)
+                    && rhs.matches(&term.rhs, bindings)
+            }
+            (Pattern::FunCall(name, params), Operand::FunCall(fun_call)) => {
+                *name == fun_call.name
+                    && params.len() == fun_call.params.len()
+                    && params
+                        .iter()
+                        .zip(fun_call.params.iter())
+                        .all(|(pattern, op)| pattern.matches(op, bindings))
+            }
+            _ => false,
+        }
+    }
+
+    fn instantiate(&self, bindings: &HashMap<String, Operand>) -> Option<Operand> {
+        Some(match self {
+            Pattern::Any(name) | Pattern::Constant(name) => bindings.get(name)?.clone(),
+            Pattern::Number(num) => Operand::Number(*num),
+            Pattern::Symbol(sym) => Operand::Symbol(sym.clone()),
+            Pattern::Term(op, lhs, rhs) => Operand::Term(Box::new(Term {
+                op: *op,
+                lhs: lhs.instantiate(bindings)?,
+                rhs: rhs.instantiate(bindings)?,
+            })),
+            Pattern::FunCall(name, params) => Operand::FunCall(FunCall {
+                name: name.clone(),
+                params: params
+                    .iter()
+                    .map(|p| p.instantiate(bindings))
+                    .collect::<Option<Vec<Operand>>>()?,
+            }),
+        })
+    }
+}
+
+macro_rules! pattern_operation {
+    ($($trait:ident $method:ident $op:ident),+) => {
+        $(
+            impl ops::$trait for Pattern {
+                type Output = Pattern;
+
+                fn $method(self, rhs: Pattern) -> Pattern {
+                    Pattern::Term(Operation::$op, Box::new(self), Box::new(rhs))
+                }
+            }
+        )+
+    };
+}
+
+pattern_operation!(Add add Add, Sub sub Sub, Mul mul Mul, Div div Div, Rem rem Rem);
+
+#[derive(Clone)]
+enum RuleKind {
+    Pattern { lhs: Pattern, rhs: Pattern },
+    Native(fn(&Operand) -> Option<Operand>),
+}
+
+/// A single named rewrite rule, e.g. `x * 1 -> x`.
+#[derive(Clone)]
+pub struct RewriteRule {
+    name: String,
+    kind: RuleKind,
+}
+
+impl std::fmt::Debug for RewriteRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RewriteRule")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+impl RewriteRule {
+    /// Creates a rule replacing operands matching `lhs` by `rhs`.
+    /// Pattern variables in `rhs` are replaced by the operands bound in `lhs`.
+    pub fn new(name: &str, lhs: Pattern, rhs: Pattern) -> RewriteRule {
+        RewriteRule {
+            name: name.to_string(),
+            kind: RuleKind::Pattern { lhs, rhs },
+        }
+    }
+
+    pub(crate) fn native(name: &str, rule: fn(&Operand) -> Option<Operand>) -> RewriteRule {
+        RewriteRule {
+            name: name.to_string(),
+            kind: RuleKind::Native(rule),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn apply(&self, op: &Operand) -> Option<Operand> {
+        match &self.kind {
+            RuleKind::Pattern { lhs, rhs } => {
+                let mut bindings = HashMap::new();
+                if lhs.matches(op, &mut bindings) {
+                    rhs.instantiate(&bindings)
+                } else {
+                    None
+                }
+            }
+            RuleKind::Native(rule) => rule(op),
+        }
+    }
+}
+
+/// evaluates terms of two numbers
+fn fold_constants(op: &Operand) -> Option<Operand> {
+    match op {
+        Operand::Term(term) => match (&term.lhs, &term.rhs) {
+            (Operand::Number(lhs), Operand::Number(rhs)) => {
+                let num = term.op.apply(*lhs, *rhs);
+                // keep terms like `1 / 0` as they are
+                num.is_finite().then_some(Operand::Number(num))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// An ordered set of rewrite rules applied until a fixpoint is reached.
+#[derive(Debug, Clone)]
+pub struct Rewriter {
+    rules: Vec<RewriteRule>,
+}
+
+impl Default for Rewriter {
+    /// rewriter with the standard simplification rules
+    fn default() -> Self {
+        use Pattern as P;
+        let x = || P::any("x");
+        let y = || P::any("y");
+        let a = || P::constant("a");
+        let b = || P::constant("b");
+        let num = P::number;
+
+        Rewriter {
+            rules: vec![
+                RewriteRule::native("fold constants", fold_constants),
+                RewriteRule::new("x + 0", x() + num(0.0), x()),
+                RewriteRule::new("0 + x", num(0.0) + x(), x()),
+                RewriteRule::new("x - 0", x() - num(0.0), x()),
+                RewriteRule::new("x - x", x() - x(), num(0.0)),
+                RewriteRule::new("x * 1", x() * num(1.0), x()),
+                RewriteRule::new("1 * x", num(1.0) * x(), x()),
+                RewriteRule::new("x * 0", x() * num(0.0), num(0.0)),
+                RewriteRule::new("0 * x", num(0.0) * x(), num(0.0)),
+                RewriteRule::new("x / 1", x() / num(1.0), x()),
+                RewriteRule::new("x ^ 1", x().pow(num(1.0)), x()),
+                RewriteRule::new("x ^ 0", x().pow(num(0.0)), num(1.0)),
+                RewriteRule::new("x + x", x() + x(), num(2.0) * x()),
+                RewriteRule::new("x * x", x() * x(), x().pow(num(2.0))),
+                // collecting like terms
+                RewriteRule::new("a * x + b * x", a() * x() + b() * x(), (a() + b()) * x()),
+                RewriteRule::new("a * x - b * x", a() * x() - b() * x(), (a() - b()) * x()),
+                RewriteRule::new("a * x + x", a() * x() + x(), (a() + num(1.0)) * x()),
+                RewriteRule::new("x + a * x", x() + a() * x(), (a() + num(1.0)) * x()),
+                RewriteRule::new("a * x - x", a() * x() - x(), (a() - num(1.0)) * x()),
+                RewriteRule::new("x - a * x", x() - a() * x(), (num(1.0) - a()) * x()),
+                RewriteRule::new(
+                    "(y + a * x) + b * x",
+                    (y() + a() * x()) + b() * x(),
+                    y() + (a() + b()) * x(),
+                ),
+                RewriteRule::new(
+                    "(y + x) + a * x",
+                    (y() + x()) + a() * x(),
+                    y() + (a() + num(1.0)) * x(),
+                ),
+                RewriteRule::new(
+                    "(y + a * x) + x",
+                    (y() + a() * x()) + x(),
+                    y() + (a() + num(1.0)) * x(),
+                ),
+                RewriteRule::new("(y + x) + x", (y() + x()) + x(), y() + num(2.0) * x()),
+                // products of powers
+                RewriteRule::new("x ^ a * x", x().pow(a()) * x(), x().pow(a() + num(1.0))),
+                RewriteRule::new(
+                    "x ^ a * x ^ b",
+                    x().pow(a()) * x().pow(b()),
+                    x().pow(a() + b()),
+                ),
+                RewriteRule::new("(x ^ a) ^ b", x().pow(a()).pow(b()), x().pow(a() * b())),
+                // commutativity normalization: constants to the left
+                RewriteRule::new("x * a", x() * a(), a() * x()),
+                RewriteRule::new("x + a", x() + a(), a() + x()),
+                // associativity of constants
+                RewriteRule::new("a * (b * x)", a() * (b() * x()), (a() * b()) * x()),
+                RewriteRule::new("a + (b + x)", a() + (b() + x()), (a() + b()) + x()),
+                // distributing constants
+                RewriteRule::new("a * (x + y)", a() * (x() + y()), a() * x() + a() * y()),
+                RewriteRule::new("a * (x - y)", a() * (x() - y()), a() * x() - a() * y()),
+            ],
+        }
+    }
+}
+
+impl Rewriter {
+    /// rewriter without any rule
+    pub fn empty() -> Rewriter {
+        Rewriter { rules: Vec::new() }
+    }
+
+    /// appends a rule, rules are tried in the order they were added
+    pub fn add_rule(&mut self, rule: RewriteRule) {
+        self.rules.push(rule);
+    }
+
+    pub fn rules(&self) -> &[RewriteRule] {
+        &self.rules
+    }
+
+    fn rewrite_node(&self, op: Operand) -> Operand {
+        self.rules
+            .iter()
+            .find_map(|rule| rule.apply(&op))
+            .unwrap_or(op)
+    }
+
+    /// applies the rules until the operand does not change anymore
+    pub fn rewrite(&self, op: &Operand) -> Operand {
+        let mut op = op.clone();
+        for _ in 0..MAX_PASSES {
+            let next = op.clone().map(&mut |op| self.rewrite_node(op));
+            if next == op {
+                break;
+            }
+            op = next;
+        }
+        op
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse;
+
+    fn expr(s: &str) -> Operand {
+        match parse(s).unwrap() {
+            Statement::Expression { op } => op,
+            _ => panic!("not an expression"),
+        }
+    }
+
+    fn assert_rewrite(exp: &str, act: &str) {
+        assert_eq!(expr(exp), Rewriter::default().rewrite(&expr(act)));
+    }
+
+    #[test]
+    fn rewrite_neutral_elements() {
+        assert_rewrite("x", "x * 1 + 0");
+        assert_rewrite("y", "1 * (y - 0) ^ 1");
+        assert_rewrite("0", "x * 0");
+    }
+
+    #[test]
+    fn rewrite_fold_constants() {
+        assert_rewrite("6 + x", "x + 2 * 3");
+        assert_rewrite("1 / 0", "1 / 0");
+    }
+
+    #[test]
+    fn rewrite_commutativity_and_distribution() {
+        assert_rewrite("18 + 6 * x", "(x + 3) * 2 * 3");
+    }
+
+    #[test]
+    fn rewrite_like_terms() {
+        assert_rewrite("5 * x", "2 * x + 3 * x");
+        assert_rewrite("0", "x + x - 2 * x");
+        assert_rewrite("1 + 4 * y", "(y + 1) + 3 * y");
+        assert_rewrite("x ^ 5", "x ^ 2 * x * x ^ 2");
+        assert_rewrite("a ^ 6", "(a ^ 2) ^ 3");
+    }
+
+    #[test]
+    fn rewrite_non_linear_pattern() {
+        assert_rewrite("0", "sin(x) - sin(x)");
+        assert_rewrite("sin(x) - sin(y)", "sin(x) - sin(y)");
+    }
+
+    #[test]
+    fn rewrite_custom_rule() {
+        let mut rewriter = Rewriter::empty();
+        rewriter.add_rule(RewriteRule::new(
+            "sin^2 + cos^2",
+            Pattern::call("sin", vec![Pattern::any("x")]).pow(Pattern::number(2.0))
+                + Pattern::call("cos", vec![Pattern::any("x")]).pow(Pattern::number(2.0)),
+            Pattern::number(1.0),
+        ));
+        assert_eq!(1, rewriter.rules().len());
+        assert_eq!("sin^2 + cos^2", rewriter.rules()[0].name());
+        assert_eq!(
+            expr("1"),
+            rewriter.rewrite(&expr("sin(a + 1) ^ 2 + cos(a + 1) ^ 2"))
+        );
+        assert_eq!(
+            expr("sin(a) ^ 2 + cos(b) ^ 2"),
+            rewriter.rewrite(&expr("sin(a) ^ 2 + cos(b) ^ 2"))
+        );
+    }
+}
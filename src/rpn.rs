@@ -0,0 +1,127 @@
+//! Postfix (reverse polish notation) input like `3 4 + 2 *`.
+//!
+//! Names of known functions pop as many operands as the function has arguments,
+//! e.g. `2 sqrt` or `1 2 f` for a custom `f(x, y)`. All other names are variables.
+
+use crate::ast::*;
+use crate::calc::Env;
+use crate::parser::ParserError;
+
+fn operation(token: &str) -> Option<Operation> {
+    match token {
+        "+" => Some(Operation::Add),
+        "-" => Some(Operation::Sub),
+        "*" => Some(Operation::Mul),
+        "/" => Some(Operation::Div),
+        "%" => Some(Operation::Rem),
+        "^" => Some(Operation::Pow),
+        _ => None,
+    }
+}
+
+fn is_symbol(token: &str) -> bool {
+    let mut chars = token.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn pop(stack: &mut Vec<Operand>, token: &str) -> Result<Operand, ParserError> {
+    stack
+        .pop()
+        .ok_or_else(|| ParserError::RpnStackUnderflow(token.to_string()))
+}
+
+/// parses a postfix expression, `env` is used to tell functions from variables
+pub fn parse_rpn(cmd: &str, env: &dyn Env) -> Result<Statement, ParserError> {
+    let mut stack = Vec::new();
+    for token in cmd.split_whitespace() {
+        if let Some(op) = operation(token) {
+            let rhs = pop(&mut stack, token)?;
+            let lhs = pop(&mut stack, token)?;
+            stack.push(Operand::Term(Box::new(Term { op, lhs, rhs })));
+        } else if is_symbol(token) {
+            match env.get_fun(token) {
+                Some(fun) => {
+                    let arity = fun.arity();
+                    if stack.len() < arity {
+                        return Err(ParserError::RpnStackUnderflow(token.to_string()));
+                    }
+                    let params = stack.split_off(stack.len() - arity);
+                    stack.push(Operand::FunCall(FunCall {
+                        name: token.to_string(),
+                        params,
+                    }));
+                }
+                None => stack.push(Operand::Symbol(token.to_string())),
+            }
+        } else if let Ok(num) = token.parse::<Number>() {
+            stack.push(Operand::Number(num));
+        } else {
+            return Err(ParserError::InvalidOperand(token.to_string()));
+        }
+    }
+    match stack.len() {
+        0 => Err(ParserError::EmptyStatement),
+        1 => Ok(Statement::Expression {
+            op: stack.pop().unwrap(),
+        }),
+        n => Err(ParserError::RpnUnconsumedOperands(n)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::TopLevelEnv;
+    use crate::parser::parse;
+
+    fn assert_same(infix: &str, rpn: &str) {
+        assert_eq!(parse(infix), parse_rpn(rpn, &TopLevelEnv::default()));
+    }
+
+    #[test]
+    fn rpn_simple_term() {
+        assert_same("(3 + 4) * 2", "3 4 + 2 *");
+    }
+
+    #[test]
+    fn rpn_right_operand_first() {
+        assert_same("x - 2 ^ y", "x 2 y ^ -");
+    }
+
+    #[test]
+    fn rpn_function_call() {
+        assert_same("sin(x) / 2", "x sin 2 /");
+    }
+
+    #[test]
+    fn rpn_custom_function_arity() {
+        let mut env = TopLevelEnv::default();
+        env.put_fun(
+            "f".to_string(),
+            Function::Custom(CustomFunction {
+                args: vec!["a".to_string(), "b".to_string()],
+                body: Operand::Number(1.0),
+            }),
+        );
+        assert_eq!(parse("1 + f(2, 3)"), parse_rpn("1 2 3 f +", &env));
+    }
+
+    #[test]
+    fn rpn_errors() {
+        let env = TopLevelEnv::default();
+        assert_eq!(
+            Err(ParserError::RpnStackUnderflow("+".to_string())),
+            parse_rpn("1 +", &env)
+        );
+        assert_eq!(
+            Err(ParserError::RpnUnconsumedOperands(2)),
+            parse_rpn("1 2", &env)
+        );
+        assert_eq!(Err(ParserError::EmptyStatement), parse_rpn("  ", &env));
+        assert_eq!(
+            Err(ParserError::InvalidOperand("1a".to_string())),
+            parse_rpn("1a", &env)
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! Scripts of statements, e.g. files executed by `run "file"`.
+//!
+//! Statements are separated by newlines and `;`.
+
+use crate::import::ImportError;
+
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum ScriptError {
+    #[error("Script `{}` runs itself: {}", .0.last().map(String::as_str).unwrap_or_default(), .0.join(" -> "))]
+    RecursiveRun(Vec<String>),
+    #[error("{path}:{line}: {error}")]
+    Statement {
+        path: String,
+        line: usize,
+        error: Box<crate::Error>,
+    },
+    #[error("line {line}, statement {statement}: {error}")]
+    Failed {
+        line: usize,
+        statement: usize,
+        error: Box<crate::Error>,
+    },
+    #[error("Script `{path}` is outside of the directory `{base}`")]
+    OutsideBase { path: String, base: String },
+    #[error("{path}:{line}: Invalid statement")]
+    InvalidStatement { path: String, line: usize },
+    #[error("File access is disabled, `{0}` is not read")]
+    FileAccessDisabled(String),
+}
+
+fn io_error(path: &Path, err: std::io::Error) -> ImportError {
+    ImportError::Io {
+        path: path.display().to_string(),
+        message: err.to_string(),
+    }
+}
+
+/// Resolves `path` relative to `base` and fails if it is outside of `base`
+/// or one of the `running` scripts.
+pub fn resolve(base: &Path, path: &str, running: &[PathBuf]) -> Result<PathBuf, crate::Error> {
+    let base = match base.as_os_str().is_empty() {
+        true => Path::new("."),
+        false => base,
+    };
+    let base = base.canonicalize().map_err(|err| io_error(base, err))?;
+    let joined = base.join(path);
+    let resolved = joined
+        .canonicalize()
+        .map_err(|err| io_error(&joined, err))?;
+    if !resolved.starts_with(&base) {
+        return Err(ScriptError::OutsideBase {
+            path: path.to_string(),
+            base: base.display().to_string(),
+        }
+        .into());
+    }
+    if running.contains(&resolved) {
+        let chain = running
+            .iter()
+            .skip_while(|script| **script != resolved)
+            .chain(std::iter::once(&resolved))
+            .map(|script| script.display().to_string())
+            .collect();
+        return Err(ScriptError::RecursiveRun(chain).into());
+    }
+    Ok(resolved)
+}
+
+/// is `let` a keyword at byte `idx` of `line`
+fn is_let(line: &str, idx: usize) -> bool {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    line[idx..].starts_with("let")
+        && !line[..idx].ends_with(is_ident)
+        && !line[idx + 3..].starts_with(is_ident)
+}
+
+/// a comment starts with `#` or `//` and ends with the line
+fn is_comment(rest: &str) -> bool {
+    rest.starts_with('#') || rest.starts_with("//")
+}
+
+/// Splits a line at the `;` between statements, the comment at its end is left out.
+/// A `;` within a string or a matrix or ending the value of a `let` belongs to the statement.
+fn split(line: &str) -> Vec<&str> {
+    let mut statements = Vec::new();
+    let (mut start, mut end, mut in_string, mut lets) = (0, line.len(), false, 0);
+    let mut brackets = 0usize;
+    for (idx, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            _ if in_string => {}
+            '#' | '/' if is_comment(&line[idx..]) => {
+                end = idx;
+                break;
+            }
+            '[' => brackets += 1,
+            ']' => brackets = brackets.saturating_sub(1),
+            ';' if brackets > 0 => {}
+            ';' if lets > 0 => lets -= 1,
+            ';' => {
+                statements.push(&line[start..idx]);
+                start = idx + 1;
+            }
+            'l' if is_let(line, idx) => lets += 1,
+            _ => {}
+        }
+    }
+    statements.push(&line[start..end]);
+    statements
+}
+
+/// non-empty statements of a script with their line number counted from 1
+pub fn statements(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    content
+        .lines()
+        .enumerate()
+        .flat_map(|(idx, line)| split(line).into_iter().map(move |st| (idx + 1, st)))
+        .filter(|(_, st)| !st.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Error;
+
+    #[test]
+    fn resolve_detects_recursion() {
+        let base = std::env::current_dir().unwrap();
+        let manifest = resolve(&base, "Cargo.toml", &[]).unwrap();
+        assert!(manifest.is_absolute());
+        match resolve(&base, "./Cargo.toml", std::slice::from_ref(&manifest)) {
+            Err(Error::ScriptError(ScriptError::RecursiveRun(chain))) => assert_eq!(2, chain.len()),
+            result => panic!("expected recursion, got {:?}", result),
+        }
+        assert!(matches!(
+            resolve(&base, "missing.calc", &[]),
+            Err(Error::ImportError(ImportError::Io { .. }))
+        ));
+    }
+
+    #[test]
+    fn resolve_stays_in_base() {
+        let base = std::env::current_dir().unwrap().join("src");
+        assert!(resolve(&base, "lib.rs", &[]).is_ok());
+        assert!(resolve(&base, "../src/lib.rs", &[]).is_ok());
+        for path in ["../Cargo.toml", "/etc/passwd"] {
+            assert!(
+                matches!(
+                    resolve(&base, path, &[]),
+                    Err(Error::ScriptError(ScriptError::OutsideBase { .. }))
+                ),
+                "{}",
+                path
+            );
+        }
+    }
+
+    #[test]
+    fn numbered_statements() {
+        let lines: Vec<_> = statements("a := 1\n\n  \nb := 2").collect();
+        assert_eq!(vec![(1, "a := 1"), (4, "b := 2")], lines);
+        let lines: Vec<_> =
+            statements("a := 1; b := 2;\nc := \"x;y\"; f(x) := let t := x; t").collect();
+        assert_eq!(
+            vec![
+                (1, "a := 1"),
+                (1, " b := 2"),
+                (2, "c := \"x;y\""),
+                (2, " f(x) := let t := x; t")
+            ],
+            lines
+        );
+        assert_eq!(vec!["outlet := 1", " 2"], split("outlet := 1; 2"));
+        assert_eq!(
+            vec!["m := [1, 2; 3, 4]", " m"],
+            split("m := [1, 2; 3, 4]; m")
+        );
+        let lines: Vec<_> = statements("# prelude\na := 1 // let; \"\nb := \"#1\"; # c").collect();
+        assert_eq!(vec![(2, "a := 1 "), (3, "b := \"#1\"")], lines);
+    }
+}
@@ -1,22 +1,125 @@
 use crate::ast::*;
-use crate::calc::{calc_function_call, CalcError, Env};
+use crate::calc::{calc_function_call, calc_operand, calc_series, CalcError, Env, ScopedEnv};
+use crate::number::Num;
+use crate::suggest::{did_you_mean, hint_suffix, is_similar, similar_names};
 
 use thiserror::Error;
 
+/// bisection steps of a numeric solve, enough to reach the precision of `Number`
+const MAX_BISECTION_STEPS: usize = 200;
+
+/// Hint for an unknown variable in `solve ... for ...`.
+pub fn unknown_variable_hint(
+    name: &str,
+    candidates: &[String],
+    misspelled_for: Option<&str>,
+) -> Option<String> {
+    match (misspelled_for, candidates) {
+        (Some(sym), _) => Some(format!(
+            "did you mean `for {}` instead of `for {}`?",
+            name, sym
+        )),
+        (None, candidates) => did_you_mean(candidates),
+    }
+}
+
 /// Normalized form of a any operand
-/// `factor * x + summand`#
+/// `a2 * x^2 + a1 * x + a0`
+#[derive(Debug, PartialEq)]
+struct NormForm<N> {
+    a2: N,
+    a1: N,
+    a0: N,
+}
+
+fn num<N: Num>(value: f64) -> N {
+    N::from_f64(value)
+}
+
+impl<N: Num> NormForm<N> {
+    fn constant(a0: N) -> Self {
+        NormForm {
+            a2: num(0.0),
+            a1: num(0.0),
+            a0,
+        }
+    }
+
+    fn has_variable(&self) -> bool {
+        self.a2 != num(0.0) || self.a1 != num(0.0)
+    }
+
+    fn mul(&self, other: &NormForm<N>) -> Result<NormForm<N>, SolverError> {
+        let a4 = self.a2 * other.a2;
+        let a3 = self.a2 * other.a1 + self.a1 * other.a2;
+        if a4 != num(0.0) || a3 != num(0.0) {
+            return Err(SolverError::UnsupportedDegree);
+        }
+        Ok(NormForm {
+            a2: self.a2 * other.a0 + self.a1 * other.a1 + self.a0 * other.a2,
+            a1: self.a1 * other.a0 + self.a0 * other.a1,
+            a0: self.a0 * other.a0,
+        })
+    }
+}
+
+/// Solution of `solve ... for ...`
 #[derive(Debug, PartialEq)]
-struct NormForm {
-    a1: Number,
-    a0: Number,
+pub enum Solution<N = Number> {
+    /// the solution of a linear equation or the root found numerically
+    Single(N),
+    /// the real solutions of a quadratic equation in ascending order
+    Quadratic(Vec<N>),
+}
+
+/// Interval of the solution set of an inequality, unbounded ends are infinite and open.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Interval<N = Number> {
+    pub from: N,
+    pub to: N,
+    /// does the interval contain `from`
+    pub from_closed: bool,
+    /// does the interval contain `to`
+    pub to_closed: bool,
+}
+
+impl<N: Num> Interval<N> {
+    fn open(from: N, to: N) -> Interval<N> {
+        Interval {
+            from,
+            to,
+            from_closed: false,
+            to_closed: false,
+        }
+    }
+
+    fn point(at: N) -> Interval<N> {
+        Interval {
+            from: at,
+            to: at,
+            from_closed: true,
+            to_closed: true,
+        }
+    }
+
+    pub fn contains(&self, x: N) -> bool {
+        (self.from < x || (self.from_closed && self.from == x))
+            && (x < self.to || (self.to_closed && self.to == x))
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Error)]
 pub enum SolverError {
-    #[error("Unknown variable `{0}` in `solve ... for ...`")]
-    UnknownVariable(String),
-    #[error("Unsupported `^2` of variable to solve for in `solve ... for ...`")]
-    UnsupportedXSquare,
+    #[error("Unknown variable `{name}` in `solve ... for ...`{}", hint_suffix(unknown_variable_hint(name, candidates, misspelled_for.as_deref())))]
+    UnknownVariable {
+        name: String,
+        /// known variables with a similar name
+        candidates: Vec<String>,
+        /// the variable after `for`, if it looks like a misspelling of `name`
+        misspelled_for: Option<String>,
+    },
+    #[error("Unsupported power above `^2` of variable to solve for in `solve ... for ...`")]
+    UnsupportedDegree,
     #[error("Unsupported variable in denominator in `solve ... for ...`")]
     UnsupportedXDenominator,
     #[error("Unsupported % with solve for variable in `solve ... for ...`")]
@@ -25,108 +128,328 @@ pub enum SolverError {
     UnsupportedPower,
     #[error("`solve ... for ...` contains no variable (after simplification)")]
     NoVariable,
+    #[error(
+        "Unsupported variable `{0}` in the arguments of function `{1}` in `solve ... for ...`"
+    )]
+    VariableInFunctionCall(String, String),
+    #[error("Both sides of `solve ... for {variable} in [{from}, {to}]` differ with the same sign at both ends of the interval")]
+    NoSignChange {
+        variable: String,
+        from: String,
+        to: String,
+    },
+    #[error("Expected a finite interval `[{from}, {to}]` with a lower bound smaller than the upper bound in `solve ... for {variable} in [...]`")]
+    InvalidInterval {
+        variable: String,
+        from: String,
+        to: String,
+    },
     #[error(transparent)]
     FunctionCallError(#[from] CalcError),
 }
 
-fn normalize_term(term: &Term, sym: &str, env: &dyn Env) -> Result<NormForm, SolverError> {
+impl SolverError {
+    /// is the equation not linear or quadratic, so it may be solved numerically
+    fn is_nonlinear(&self) -> bool {
+        matches!(
+            self,
+            SolverError::UnsupportedDegree
+                | SolverError::UnsupportedXDenominator
+                | SolverError::UnsupportedRemainder
+                | SolverError::UnsupportedPower
+                | SolverError::VariableInFunctionCall(..)
+        )
+    }
+}
+
+fn normalize_term<N: Num>(
+    term: &Term,
+    sym: &str,
+    env: &dyn Env,
+) -> Result<NormForm<N>, SolverError> {
     let lhs = normalize(&term.lhs, sym, env)?;
     let rhs = normalize(&term.rhs, sym, env)?;
     match term.op {
-        Operation::Add => Ok({
-            let factor = lhs.a1 + rhs.a1;
-            let summand = lhs.a0 + rhs.a0;
-            NormForm {
-                a1: factor,
-                a0: summand,
-            }
+        Operation::Add => Ok(NormForm {
+            a2: lhs.a2 + rhs.a2,
+            a1: lhs.a1 + rhs.a1,
+            a0: lhs.a0 + rhs.a0,
         }),
-        Operation::Sub => Ok({
-            let factor = lhs.a1 - rhs.a1;
-            let summand = lhs.a0 - rhs.a0;
-            NormForm {
-                a1: factor,
-                a0: summand,
-            }
+        Operation::Sub => Ok(NormForm {
+            a2: lhs.a2 - rhs.a2,
+            a1: lhs.a1 - rhs.a1,
+            a0: lhs.a0 - rhs.a0,
         }),
-        Operation::Mul => {
-            let a2 = lhs.a1 * rhs.a1;
-            let a1 = lhs.a1 * rhs.a0 + rhs.a1 * lhs.a0;
-            let a0 = lhs.a0 * rhs.a0;
-            if a2 != 0.0 {
-                Err(SolverError::UnsupportedXSquare)
-            } else {
-                Ok(NormForm { a1, a0 })
-            }
-        }
+        Operation::Mul => lhs.mul(&rhs),
         Operation::Div => {
-            if rhs.a1 != 0.0 {
+            if rhs.has_variable() {
                 Err(SolverError::UnsupportedXDenominator)
-            } else {
-                let a1 = lhs.a1 / rhs.a0;
-                let a0 = lhs.a0 / rhs.a0;
-                Ok(NormForm { a1, a0 })
-            }
-        }
-        Operation::Rem => {
-            if (lhs.a1 != 0.0) || (rhs.a1 != 0.0) {
-                Err(SolverError::UnsupportedRemainder)
             } else {
                 Ok(NormForm {
-                    a1: 0.0,
-                    a0: (lhs.a0 % rhs.a0),
+                    a2: lhs.a2 / rhs.a0,
+                    a1: lhs.a1 / rhs.a0,
+                    a0: lhs.a0 / rhs.a0,
                 })
             }
         }
-        Operation::Pow => {
-            if (lhs.a1 != 0.0) || (rhs.a1 != 0.0) {
-                Err(SolverError::UnsupportedPower)
+        Operation::Rem => {
+            if lhs.has_variable() || rhs.has_variable() {
+                Err(SolverError::UnsupportedRemainder)
             } else {
-                Ok(NormForm {
-                    a1: 0.0,
-                    a0: (lhs.a0.powf(rhs.a0)),
-                })
+                Ok(NormForm::constant(Operation::Rem.apply(lhs.a0, rhs.a0)))
             }
         }
+        Operation::Pow => match (lhs.has_variable(), rhs.has_variable(), rhs.a0) {
+            (false, false, exp) => Ok(NormForm::constant(Operation::Pow.apply(lhs.a0, exp))),
+            // (a1 * x + a0)^2 is expanded
+            (true, false, exp) if exp == num(2.0) => lhs.mul(&lhs),
+            (true, false, exp) if exp == num(1.0) => Ok(lhs),
+            _ => Err(SolverError::UnsupportedPower),
+        },
     }
 }
 
-fn normalize(op: &Operand, sym: &str, env: &dyn Env) -> Result<NormForm, SolverError> {
+fn normalize<N: Num>(op: &Operand, sym: &str, env: &dyn Env) -> Result<NormForm<N>, SolverError> {
     match op {
-        Operand::Number(num) => Ok(NormForm { a1: 0.0, a0: *num }),
+        Operand::Number(_) | Operand::Literal(_) => Ok(NormForm::constant(calc_operand(op, env)?)),
         Operand::Symbol(s) => {
             if op.is_symbol(sym) {
-                Ok(NormForm { a1: 1.0, a0: 0.0 })
+                Ok(NormForm {
+                    a2: num(0.0),
+                    a1: num(1.0),
+                    a0: num(0.0),
+                })
             } else {
-                let num = env
-                    .get(s)
-                    .ok_or_else(|| SolverError::UnknownVariable(s.clone()))?;
-                Ok(NormForm { a1: 0.0, a0: *num })
+                let value = env.get(s).ok_or_else(|| SolverError::UnknownVariable {
+                    name: s.clone(),
+                    candidates: similar_names(s, env.var_names()),
+                    misspelled_for: is_similar(s, sym).then(|| sym.to_string()),
+                })?;
+                Ok(NormForm::constant(num(value.to_f64())))
             }
         }
-        Operand::Term(term) => normalize_term(&*term, sym, env),
+        Operand::Term(term) => normalize_term(term, sym, env),
         Operand::FunCall(fun_call) => {
-            let num = calc_function_call(fun_call, env)?;
-            Ok(NormForm { a1: 0.0, a0: num })
+            if let Some(Function::Custom(fun)) = env.get_fun(&fun_call.name) {
+                if fun.args.len() == fun_call.params.len() {
+                    let body = inline(fun, &fun_call.params);
+                    if body.free_symbols().contains(sym) {
+                        // one level deeper, so recursive functions hit the recursion limit
+                        let scope = ScopedEnv::<N>::nested(&fun_call.name, env)?;
+                        return normalize(&body, sym, &scope);
+                    }
+                }
+            }
+            constant_of(op, sym, &fun_call.name)?;
+            Ok(NormForm::constant(calc_function_call(fun_call, env)?))
+        }
+        Operand::Series(series) => {
+            constant_of(op, sym, series.kind.name())?;
+            Ok(NormForm::constant(calc_series(series, env)?))
         }
+        Operand::Let(binding) => normalize(&binding.inlined(), sym, env),
+        Operand::Conditional(_) => {
+            constant_of(op, sym, "if")?;
+            Ok(NormForm::constant(calc_operand(op, env)?))
+        }
+        Operand::Factorial(_) => {
+            constant_of(op, sym, "!")?;
+            Ok(NormForm::constant(calc_operand(op, env)?))
+        }
+        // fails, equations of vectors are not supported
+        Operand::Matrix(_) => Ok(NormForm::constant(calc_operand(op, env)?)),
     }
 }
 
-pub fn solve_for(
+/// fails if the variable `sym` is free in `op`, which is a call of `function`
+fn constant_of(op: &Operand, sym: &str, function: &str) -> Result<(), SolverError> {
+    match op.free_symbols().contains(sym) {
+        true => Err(SolverError::VariableInFunctionCall(
+            sym.to_string(),
+            function.to_string(),
+        )),
+        false => Ok(()),
+    }
+}
+
+/// body of the custom function `fun` with its arguments replaced by `params`
+fn inline(fun: &CustomFunction, params: &[Operand]) -> Operand {
+    fun.body.clone().map(&mut |op| match op {
+        Operand::Symbol(name) => match fun.args.iter().position(|arg| *arg == name) {
+            Some(index) => params[index].clone(),
+            None => Operand::Symbol(name),
+        },
+        op => op,
+    })
+}
+
+/// real roots of `a2 * x^2 + a1 * x + a0` with `a2 != 0` in ascending order,
+/// a single root if the discriminant vanishes within `tolerance`
+fn quadratic_roots<N: Num>(a2: N, a1: N, a0: N, tolerance: N) -> Vec<N> {
+    let (zero, two, four): (N, N, N) = (num(0.0), num(2.0), num(4.0));
+    let discriminant = a1 * a1 - four * a2 * a0;
+    if nearly_equal(a1 * a1, four * a2 * a0, tolerance) {
+        // adding `0` turns `-0` into `0`
+        return vec![-a1 / (two * a2) + zero];
+    }
+    if discriminant < zero {
+        return Vec::new();
+    }
+    // avoids the cancellation of `-a1 + sqrt(discriminant)`
+    let sign: N = if a1 < zero { num(-1.0) } else { num(1.0) };
+    let q = -(a1 + sign * discriminant.sqrt()) / two;
+    let (x1, x2) = (q / a2, a0 / q);
+    if x1 < x2 {
+        vec![x1, x2]
+    } else {
+        vec![x2, x1]
+    }
+}
+
+/// Root of `lhs - rhs` between `from` and `to` found by bisection,
+/// which stops when the interval is smaller than `tolerance`.
+fn solve_numeric<N: Num>(
     lhs: &Operand,
     rhs: &Operand,
     sym: &str,
+    (from, to): (N, N),
+    tolerance: N,
     env: &dyn Env,
-) -> Result<Number, SolverError> {
-    let norm_form_lhs = normalize(lhs, sym, env)?;
-    let norm_form_rhs = normalize(rhs, sym, env)?;
-    let denominator = norm_form_lhs.a1 - norm_form_rhs.a1;
-    if 0.0 == denominator {
+) -> Result<N, SolverError> {
+    let diff = |x: N| -> Result<N, SolverError> {
+        let scope = ScopedEnv::new(env, [(sym, x)]);
+        Ok(calc_operand::<N>(lhs, &scope)? - calc_operand::<N>(rhs, &scope)?)
+    };
+    let (zero, two) = (num(0.0), num(2.0));
+    let (mut lo, mut hi) = if from < to { (from, to) } else { (to, from) };
+    let (f_lo, f_hi) = (diff(lo)?, diff(hi)?);
+    if f_lo == zero {
+        return Ok(lo);
+    }
+    if f_hi == zero {
+        return Ok(hi);
+    }
+    if (f_lo < zero) == (f_hi < zero) {
+        return Err(SolverError::NoSignChange {
+            variable: sym.to_string(),
+            from: from.to_string(),
+            to: to.to_string(),
+        });
+    }
+    for _ in 0..MAX_BISECTION_STEPS {
+        let mid = (lo + hi) / two;
+        if hi - lo <= tolerance || mid == lo || mid == hi {
+            break;
+        }
+        let f_mid = diff(mid)?;
+        if f_mid == zero {
+            return Ok(mid);
+        }
+        if (f_mid < zero) == (f_lo < zero) {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    Ok((lo + hi) / two)
+}
+
+/// Like [`solve_for`], but an equation, which is neither linear nor quadratic,
+/// is solved numerically in the interval `bracket`.
+pub fn solve_in<N: Num>(
+    lhs: &Operand,
+    rhs: &Operand,
+    sym: &str,
+    bracket: (N, N),
+    tolerance: N,
+    env: &dyn Env,
+) -> Result<Solution<N>, SolverError> {
+    match solve_for(lhs, rhs, sym, env) {
+        Err(err) if err.is_nonlinear() => {
+            solve_numeric(lhs, rhs, sym, bracket, tolerance, env).map(Solution::Single)
+        }
+        solution => solution,
+    }
+}
+
+/// Solves `lhs = rhs` for `sym` with the number type `N`, usually [`Number`], if both
+/// sides differ by a linear or quadratic polynomial in `sym`.
+pub fn solve_for<N: Num>(
+    lhs: &Operand,
+    rhs: &Operand,
+    sym: &str,
+    env: &dyn Env,
+) -> Result<Solution<N>, SolverError> {
+    let norm_form_lhs = normalize::<N>(lhs, sym, env)?;
+    let norm_form_rhs = normalize::<N>(rhs, sym, env)?;
+    let a2 = norm_form_lhs.a2 - norm_form_rhs.a2;
+    let a1 = norm_form_lhs.a1 - norm_form_rhs.a1;
+    let a0 = norm_form_lhs.a0 - norm_form_rhs.a0;
+    let tolerance = num(env.tolerance().to_f64());
+    if !nearly_equal(a2, num(0.0), tolerance) {
+        Ok(Solution::Quadratic(quadratic_roots(a2, a1, a0, tolerance)))
+    } else if nearly_equal(a1, num(0.0), tolerance) {
         Err(SolverError::NoVariable)
     } else {
-        let nominator = norm_form_rhs.a0 - norm_form_lhs.a0;
-        Ok(nominator / denominator)
+        Ok(Solution::Single(-a0 / a1))
+    }
+}
+
+/// Solution set of `lhs cmp rhs` for `sym` as ascending disjoint intervals,
+/// if both sides differ by a linear or quadratic polynomial in `sym`.
+pub fn solve_inequality<N: Num>(
+    lhs: &Operand,
+    cmp: Comparison,
+    rhs: &Operand,
+    sym: &str,
+    env: &dyn Env,
+) -> Result<Vec<Interval<N>>, SolverError> {
+    let norm_form_lhs = normalize::<N>(lhs, sym, env)?;
+    let norm_form_rhs = normalize::<N>(rhs, sym, env)?;
+    let a2 = norm_form_lhs.a2 - norm_form_rhs.a2;
+    let a1 = norm_form_lhs.a1 - norm_form_rhs.a1;
+    let a0 = norm_form_lhs.a0 - norm_form_rhs.a0;
+    let tolerance = num(env.tolerance().to_f64());
+    let (zero, one, two) = (num(0.0), num(1.0), num(2.0));
+    let roots = if !nearly_equal(a2, zero, tolerance) {
+        quadratic_roots(a2, a1, a0, tolerance)
+    } else if !nearly_equal(a1, zero, tolerance) {
+        vec![-a0 / a1]
+    } else {
+        return Err(SolverError::NoVariable);
+    };
+    // the sign of `lhs - rhs` only changes at the roots, so the open intervals between
+    // the roots are tested at one point each
+    let satisfies = |x: N| cmp.compare((a2 * x + a1) * x + a0, zero);
+    let mut pieces = Vec::new();
+    let mut from: N = num(f64::NEG_INFINITY);
+    for root in roots.iter().copied() {
+        let probe = if from.is_finite() {
+            (from + root) / two
+        } else {
+            root - one
+        };
+        pieces.push((Interval::open(from, root), satisfies(probe)));
+        pieces.push((Interval::point(root), cmp.compare(zero, zero)));
+        from = root;
+    }
+    let probe = if from.is_finite() { from + one } else { zero };
+    pieces.push((Interval::open(from, num(f64::INFINITY)), satisfies(probe)));
+
+    let mut intervals: Vec<Interval<N>> = Vec::new();
+    let mut adjacent = false;
+    for (piece, included) in pieces {
+        match intervals.last_mut() {
+            Some(last) if included && adjacent => {
+                last.to = piece.to;
+                last.to_closed = piece.to_closed;
+            }
+            _ if included => intervals.push(piece),
+            _ => {}
+        }
+        adjacent = included;
     }
+    Ok(intervals)
 }
 
 #[cfg(test)]
@@ -156,7 +479,7 @@ mod tests {
         }
 
         #[test]
-        #[should_panic(expected = "InvalidExpression")]
+        #[should_panic(expected = "Syntax")]
         fn parse_expression_failed_equation() {
             parse_expression("1 @");
         }
@@ -169,117 +492,179 @@ mod tests {
 
     #[test]
     fn normalize_operand_number() {
-        let exp = NormForm { a1: 0f64, a0: 1.2 };
+        let exp = NormForm {
+            a2: 0.0,
+            a1: 0.0,
+            a0: 1.2,
+        };
         assert_eq!(
             exp,
-            normalize(&parse_expression("1.2"), "x", &TopLevelEnv::default()).unwrap()
+            normalize::<Number>(&parse_expression("1.2"), "x", &TopLevelEnv::default()).unwrap()
         );
     }
 
     #[test]
     fn normalize_operand_symbol_x() {
-        let exp = NormForm { a1: 1f64, a0: 0f64 };
+        let exp = NormForm {
+            a2: 0.0,
+            a1: 1.0,
+            a0: 0.0,
+        };
         assert_eq!(
             exp,
-            normalize(&parse_expression("x"), "x", &TopLevelEnv::default()).unwrap()
+            normalize::<Number>(&parse_expression("x"), "x", &TopLevelEnv::default()).unwrap()
         );
     }
 
     #[test]
     fn normalize_operand_symbol_y_unknown() {
-        let act = normalize(&parse_expression("y"), "x", &TopLevelEnv::default());
-        assert!(matches!(act, Err(SolverError::UnknownVariable(s)) if s == "y"));
+        let act = normalize::<Number>(&parse_expression("y"), "x", &TopLevelEnv::default());
+        assert!(matches!(act, Err(SolverError::UnknownVariable { name, .. }) if name == "y"));
+    }
+
+    #[test]
+    fn unknown_variable_suggestions() {
+        let mut env = TopLevelEnv::default();
+        env.put("speed".to_string(), 2.0).unwrap();
+        let act = normalize::<Number>(&parse_expression("sped"), "x", &env);
+        assert_eq!(
+            Err(SolverError::UnknownVariable {
+                name: "sped".to_string(),
+                candidates: vec!["speed".to_string()],
+                misspelled_for: None,
+            }),
+            act
+        );
+        assert_eq!(
+            "Unknown variable `sped` in `solve ... for ...` - did you mean `speed`?",
+            act.unwrap_err().to_string()
+        );
+
+        let act = normalize::<Number>(&parse_expression("time"), "tme", &env).unwrap_err();
+        assert_eq!(
+            "Unknown variable `time` in `solve ... for ...` - did you mean `for time` instead of `for tme`?",
+            act.to_string()
+        );
     }
 
     #[test]
     fn normalize_operand_symbol_y() {
         let mut env = TopLevelEnv::default();
         env.put("y".to_string(), 12.0).unwrap();
-        let act = normalize(&parse_expression("y"), "x", &env);
-        assert_eq!(Ok(NormForm { a1: 0.0, a0: 12.0 }), act);
+        let act = normalize::<Number>(&parse_expression("y"), "x", &env);
+        assert_eq!(
+            Ok(NormForm {
+                a2: 0.0,
+                a1: 0.0,
+                a0: 12.0
+            }),
+            act
+        );
     }
 
     #[test]
     fn normalize_operand_simple_add() {
-        let exp = NormForm { a1: 1f64, a0: 1f64 };
+        let exp = NormForm {
+            a2: 0.0,
+            a1: 1.0,
+            a0: 1.0,
+        };
         assert_eq!(
             exp,
-            normalize(&parse_expression("x + 1"), "x", &TopLevelEnv::default()).unwrap()
+            normalize::<Number>(&parse_expression("x + 1"), "x", &TopLevelEnv::default()).unwrap()
         );
     }
 
     #[test]
     fn normalize_operand_simple_sub() {
         let exp = NormForm {
-            a1: 1f64,
-            a0: -12f64,
+            a2: 0.0,
+            a1: 1.0,
+            a0: -12.0,
         };
         assert_eq!(
             exp,
-            normalize(&parse_expression("x - 12"), "x", &TopLevelEnv::default()).unwrap()
+            normalize::<Number>(&parse_expression("x - 12"), "x", &TopLevelEnv::default()).unwrap()
         );
     }
 
     #[test]
     fn normalize_operand_simple_mul() {
-        let exp = NormForm { a1: 2f64, a0: 0f64 };
+        let exp = NormForm {
+            a2: 0.0,
+            a1: 2.0,
+            a0: 0.0,
+        };
         assert_eq!(
             exp,
-            normalize(&parse_expression("x * 2"), "x", &TopLevelEnv::default()).unwrap()
+            normalize::<Number>(&parse_expression("x * 2"), "x", &TopLevelEnv::default()).unwrap()
         );
     }
 
     #[test]
     fn normalize_operand_simple_rem() {
-        let exp = NormForm { a1: 0f64, a0: 1f64 };
+        let exp = NormForm {
+            a2: 0.0,
+            a1: 0.0,
+            a0: 1.0,
+        };
         assert_eq!(
             exp,
-            normalize(&parse_expression("7 % 3"), "x", &TopLevelEnv::default()).unwrap()
+            normalize::<Number>(&parse_expression("7 % 3"), "x", &TopLevelEnv::default()).unwrap()
         );
     }
 
     #[test]
     fn normalize_operand_simple_pow() {
         let exp = NormForm {
-            a1: 0f64,
-            a0: 27f64,
+            a2: 0.0,
+            a1: 0.0,
+            a0: 27.0,
         };
         assert_eq!(
             exp,
-            normalize(&parse_expression("3 ^ 3"), "x", &TopLevelEnv::default()).unwrap()
+            normalize::<Number>(&parse_expression("3 ^ 3"), "x", &TopLevelEnv::default()).unwrap()
         );
     }
 
     #[test]
     fn normalize_operand_simple_norm_form() {
-        let exp = NormForm { a1: 3f64, a0: 2f64 };
+        let exp = NormForm {
+            a2: 0.0,
+            a1: 3.0,
+            a0: 2.0,
+        };
         assert_eq!(
             exp,
-            normalize(&parse_expression("3 * x + 2"), "x", &TopLevelEnv::default()).unwrap()
+            normalize::<Number>(&parse_expression("3 * x + 2"), "x", &TopLevelEnv::default())
+                .unwrap()
         );
     }
 
     #[test]
     fn normalize_operand_simple_norm_sub() {
         let exp = NormForm {
-            a1: 3f64,
-            a0: -2f64,
+            a2: 0.0,
+            a1: 3.0,
+            a0: -2.0,
         };
         assert_eq!(
             exp,
-            normalize(&parse_expression("3 * x - 2"), "x", &TopLevelEnv::default()).unwrap()
+            normalize::<Number>(&parse_expression("3 * x - 2"), "x", &TopLevelEnv::default())
+                .unwrap()
         );
     }
 
     #[test]
     fn normalize_operand_div() {
         let exp = NormForm {
-            a1: 4f64,
-            a0: -5f64,
+            a2: 0.0,
+            a1: 4.0,
+            a0: -5.0,
         };
         assert_eq!(
             exp,
-            normalize(
+            normalize::<Number>(
                 &parse_expression("(12 * x - 15) / 3"),
                 "x",
                 &TopLevelEnv::default()
@@ -289,27 +674,58 @@ mod tests {
     }
 
     #[test]
-    fn solve_for_simple() {
-        assert!(
-            if let Statement::SolveFor { lhs, rhs, sym } = parse("solve x = 10 for x").unwrap() {
-                assert_eq!(
-                    Ok(10.0),
-                    solve_for(&lhs, &rhs, &sym, &TopLevelEnv::default())
-                );
-                true
-            } else {
-                false
-            }
+    fn normalize_variable_in_function_call() {
+        let act = normalize::<Number>(
+            &parse_expression("sin(2 * x)"),
+            "x",
+            &TopLevelEnv::default(),
+        );
+        assert_eq!(
+            Err(SolverError::VariableInFunctionCall(
+                "x".to_string(),
+                "sin".to_string()
+            )),
+            act
+        );
+    }
+
+    #[test]
+    fn normalize_bound_variable_in_sum() {
+        let act = normalize::<Number>(
+            &parse_expression("x + sum(x, 1, 3, x)"),
+            "x",
+            &TopLevelEnv::default(),
         );
+        let exp = NormForm {
+            a2: 0.0,
+            a1: 1.0,
+            a0: 6.0,
+        };
+        assert_eq!(Ok(exp), act);
+    }
+
+    #[test]
+    fn solve_for_simple() {
+        assert!(if let Statement::SolveFor { lhs, rhs, sym, .. } =
+            parse("solve x = 10 for x").unwrap()
+        {
+            assert_eq!(
+                Ok(Solution::Single(10.0)),
+                solve_for(&lhs, &rhs, &sym, &TopLevelEnv::default())
+            );
+            true
+        } else {
+            false
+        });
     }
 
     #[test]
     fn solve_for_complex() {
-        assert!(if let Statement::SolveFor { lhs, rhs, sym } =
+        assert!(if let Statement::SolveFor { lhs, rhs, sym, .. } =
             parse("solve 5 + 2 * x + 12 = 22 - 6 * x + 7 for x").unwrap()
         {
             assert_eq!(
-                Ok(1.5),
+                Ok(Solution::Single(1.5)),
                 solve_for(&lhs, &rhs, &sym, &TopLevelEnv::default())
             );
             true
@@ -332,13 +748,225 @@ mod tests {
                 })),
             }),
         );
-        assert!(if let Statement::SolveFor { lhs, rhs, sym } =
+        assert!(if let Statement::SolveFor { lhs, rhs, sym, .. } =
             parse("solve 2 * x + add(5, 12) = 22 - 6 * x + 7 for x").unwrap()
         {
-            assert_eq!(Ok(1.5), solve_for(&lhs, &rhs, &sym, &env));
+            assert_eq!(Ok(Solution::Single(1.5)), solve_for(&lhs, &rhs, &sym, &env));
             true
         } else {
             false
         });
     }
+
+    #[test]
+    fn solve_for_inlines_custom_functions() {
+        let mut env = TopLevelEnv::default();
+        env.put_fun(
+            "sub".to_string(),
+            Function::Custom(CustomFunction {
+                args: vec!["x".to_string(), "y".to_string()],
+                body: parse_expression("x - 2 * y"),
+            }),
+        );
+        env.put_fun(
+            "loop".to_string(),
+            Function::Custom(CustomFunction {
+                args: vec!["x".to_string()],
+                body: parse_expression("loop(x)"),
+            }),
+        );
+        env.put("y".to_string(), 11.0).unwrap();
+        let solve_in = |s: &str| match parse(s).unwrap() {
+            Statement::SolveFor { lhs, rhs, sym, .. } => solve_for(&lhs, &rhs, &sym, &env),
+            _ => panic!("not a solve statement: {}", s),
+        };
+        // arguments are replaced at once, `y` is not replaced by `x` again
+        assert_eq!(
+            Ok(Solution::Single(-3.0)),
+            solve_in("solve sub(4, x) = 10 for x")
+        );
+        assert_eq!(
+            Ok(Solution::Single(5.0)),
+            solve_in("solve sub(y, x) = 1 for x")
+        );
+        assert!(matches!(
+            solve_in("solve loop(x) = 1 for x"),
+            Err(SolverError::FunctionCallError(
+                CalcError::RecursionLimitExceeded { .. }
+            ))
+        ));
+    }
+
+    fn solve(s: &str) -> Result<Solution, SolverError> {
+        if let Statement::SolveFor { lhs, rhs, sym, .. } = parse(s).unwrap() {
+            solve_for(&lhs, &rhs, &sym, &TopLevelEnv::default())
+        } else {
+            panic!("not a solve statement: {}", s)
+        }
+    }
+
+    #[test]
+    fn normalize_square() {
+        let exp = NormForm {
+            a2: 4.0,
+            a1: -4.0,
+            a0: 1.0,
+        };
+        assert_eq!(
+            exp,
+            normalize::<Number>(
+                &parse_expression("(2 * x - 1) ^ 2"),
+                "x",
+                &TopLevelEnv::default()
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn solve_for_quadratic() {
+        assert_eq!(
+            Ok(Solution::Quadratic(vec![-2.0, 2.0])),
+            solve("solve x ^ 2 - 4 = 0 for x")
+        );
+        assert_eq!(
+            Ok(Solution::Quadratic(vec![1.0, 3.0])),
+            solve("solve x * (x - 4) = -3 for x")
+        );
+        assert_eq!(
+            Ok(Solution::Quadratic(vec![0.5])),
+            solve("solve (2 * x - 1) ^ 2 = 0 for x")
+        );
+        assert_eq!(
+            Ok(Solution::Quadratic(vec![])),
+            solve("solve x ^ 2 = -1 for x")
+        );
+        assert_eq!(
+            Ok(Solution::Single(2.0)),
+            solve("solve x ^ 2 = x ^ 2 + x - 2 for x")
+        );
+    }
+
+    #[test]
+    fn solve_for_unsupported_degree() {
+        assert_eq!(
+            Err(SolverError::UnsupportedDegree),
+            solve("solve x * x * x = 8 for x")
+        );
+        assert_eq!(
+            Err(SolverError::UnsupportedDegree),
+            solve("solve (x ^ 2) ^ 2 = 16 for x")
+        );
+        assert_eq!(
+            Err(SolverError::UnsupportedPower),
+            solve("solve x ^ 3 = 8 for x")
+        );
+    }
+
+    #[test]
+    fn solve_inequalities() {
+        let env = TopLevelEnv::default();
+        let solve = |s: &str| {
+            if let Statement::SolveInequality { lhs, cmp, rhs, sym } = parse(s).unwrap() {
+                solve_inequality(&lhs, cmp, &rhs, &sym, &env)
+            } else {
+                panic!("not an inequality: {}", s)
+            }
+        };
+        let (inf, open, point) = (Number::INFINITY, Interval::open, Interval::point);
+        let closed_from = |from| Interval {
+            from_closed: true,
+            ..open(from, inf)
+        };
+        assert_eq!(
+            Ok(vec![open(-inf, 3.0)]),
+            solve("solve 2 * x + 1 < 7 for x")
+        );
+        assert_eq!(
+            Ok(vec![closed_from(-3.0)]),
+            solve("solve -2 * x <= 6 for x")
+        );
+        assert_eq!(
+            Ok(vec![open(-inf, -2.0), open(2.0, inf)]),
+            solve("solve x ^ 2 > 4 for x")
+        );
+        assert_eq!(
+            Ok(vec![Interval {
+                from_closed: true,
+                to_closed: true,
+                ..open(1.0, 3.0)
+            }]),
+            solve("solve x * (x - 4) <= -3 for x")
+        );
+        assert_eq!(Ok(vec![point(0.0)]), solve("solve x ^ 2 <= 0 for x"));
+        assert_eq!(Ok(vec![open(-inf, inf)]), solve("solve x ^ 2 >= 0 for x"));
+        assert_eq!(Ok(vec![]), solve("solve x ^ 2 + 1 < 0 for x"));
+        assert_eq!(Err(SolverError::NoVariable), solve("solve x < x + 1 for x"));
+    }
+
+    #[test]
+    fn interval_contains() {
+        let interval = Interval {
+            from_closed: true,
+            ..Interval::open(1.0, 3.0)
+        };
+        assert!(interval.contains(1.0) && interval.contains(2.0));
+        assert!(!interval.contains(3.0) && !interval.contains(0.0));
+    }
+
+    #[test]
+    fn solve_in_bracket() {
+        let env = TopLevelEnv::default();
+        let solve = |s: &str, bracket, tolerance| {
+            if let Statement::SolveFor { lhs, rhs, sym, .. } = parse(s).unwrap() {
+                solve_in(&lhs, &rhs, &sym, bracket, tolerance, &env)
+            } else {
+                panic!("not a solve statement: {}", s)
+            }
+        };
+        let x = match solve("solve sin(x) = 0.5 for x", (0.0, 1.0), 0.0) {
+            Ok(Solution::Single(x)) => x,
+            solution => panic!("expected a single solution, got {:?}", solution),
+        };
+        assert!((x - (0.5 as Number).asin()).abs() < 1e3 * Number::EPSILON);
+        let x = match solve("solve x ^ 3 = 8 for x", (3.0, 0.0), 1e-3) {
+            Ok(Solution::Single(x)) => x,
+            solution => panic!("expected a single solution, got {:?}", solution),
+        };
+        assert!((x - 2.0).abs() < 1e-3);
+        assert_eq!(
+            Ok(Solution::Single(4.0)),
+            solve("solve 2 * x = 8 for x", (0.0, 1.0), 0.0)
+        );
+        assert_eq!(
+            Err(SolverError::NoSignChange {
+                variable: "x".to_string(),
+                from: "0".to_string(),
+                to: "1".to_string(),
+            }),
+            solve("solve exp(x) = 0 for x", (0.0, 1.0), 0.0)
+        );
+    }
+
+    #[test]
+    fn solve_with_other_number_type() {
+        let env = TopLevelEnv::default();
+        let solve_as = |s: &str| match parse(s).unwrap() {
+            Statement::SolveFor { lhs, rhs, sym, .. } => solve_for::<f32>(&lhs, &rhs, &sym, &env),
+            _ => panic!("not a solve statement: {}", s),
+        };
+        assert_eq!(
+            Ok(Solution::Single(2.5f32)),
+            solve_as("solve 2 * x + 1 = 6 for x")
+        );
+        assert_eq!(
+            Ok(Solution::Quadratic(vec![-2.0f32, 2.0])),
+            solve_as("solve x ^ 2 = 4 for x")
+        );
+        let (lhs, rhs) = (parse_expression("cos(x)"), parse_expression("x"));
+        match solve_in::<f32>(&lhs, &rhs, "x", (0.0, 1.0), 1e-5, &env) {
+            Ok(Solution::Single(x)) => assert!((x - 0.739_085).abs() < 1e-4),
+            result => panic!("expected a root, got {:?}", result),
+        }
+    }
 }
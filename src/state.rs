@@ -0,0 +1,160 @@
+//! Saving the definitions of the user as JSON, see [`Calculator::save_state`](crate::Calculator::save_state).
+//!
+//! Build-in constants and functions are not saved, but come with the environment
+//! the state is loaded into.
+
+use crate::ast::{Equation, Function, Number, Operand};
+use crate::calc::{Env, TopLevelEnv};
+use crate::matrix::Matrix;
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum StateError {
+    #[error("Invalid saved state: {0}")]
+    InvalidState(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Variable {
+    Number(Number),
+    /// `NaN` and infinite numbers, which JSON cannot represent
+    NotFinite(String),
+    List(Vec<Number>),
+    /// elements in row-major order
+    Matrix {
+        rows: usize,
+        elements: Vec<Number>,
+    },
+    Text(String),
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct State {
+    variables: BTreeMap<String, Variable>,
+    functions: BTreeMap<String, Function>,
+    formulas: BTreeMap<String, Operand>,
+    equations: BTreeMap<String, Equation>,
+}
+
+/// the variables, functions, formulas and equations defined by the user as JSON
+pub fn save(env: &TopLevelEnv) -> String {
+    let mut state = State::default();
+    for sym in env.user_var_names() {
+        let variable = match (env.get(sym), env.get_list(sym), env.get_text(sym)) {
+            (Some(num), _, _) if num.is_finite() => Variable::Number(*num),
+            (Some(num), _, _) => Variable::NotFinite(num.to_string()),
+            (_, Some(list), _) => Variable::List(list.to_vec()),
+            (_, _, Some(text)) => Variable::Text(text.to_string()),
+            _ => match env.get_matrix(sym) {
+                Some(matrix) => Variable::Matrix {
+                    rows: matrix.rows(),
+                    elements: matrix.elements().to_vec(),
+                },
+                None => continue,
+            },
+        };
+        state.variables.insert(sym.to_string(), variable);
+    }
+    for name in env.fun_names() {
+        if let Some(fun @ (Function::Custom(_) | Function::Sequence(_) | Function::Piecewise(_))) =
+            env.get_fun(name)
+        {
+            state.functions.insert(name.to_string(), fun.clone());
+        }
+    }
+    for sym in env.formula_names() {
+        if let Some(op) = env.formula(sym) {
+            state.formulas.insert(sym.to_string(), op.clone());
+        }
+    }
+    for (name, equation) in env.equations() {
+        state.equations.insert(name.clone(), equation.clone());
+    }
+    serde_json::to_string(&state).expect("a state is always serializable")
+}
+
+/// Adds the definitions saved by [`save`] to `env`.
+pub fn load(state: &str, env: &mut TopLevelEnv) -> Result<(), crate::Error> {
+    let state: State =
+        serde_json::from_str(state).map_err(|err| StateError::InvalidState(err.to_string()))?;
+    for (sym, variable) in state.variables {
+        match variable {
+            Variable::Number(num) => env.put(sym, num)?,
+            Variable::NotFinite(num) => {
+                let num = num
+                    .parse()
+                    .map_err(|_| StateError::InvalidState(format!("`{}` is no number", num)))?;
+                env.put(sym, num)?
+            }
+            Variable::List(list) => env.put_list(sym, list)?,
+            Variable::Matrix { rows, elements } => {
+                let matrix = Matrix::new(rows, elements)
+                    .ok_or_else(|| StateError::InvalidState(format!("`{}` is no matrix", sym)))?;
+                env.put_matrix(sym, matrix)?
+            }
+            Variable::Text(text) => env.put_text(sym, text)?,
+        }
+    }
+    for (name, fun) in state.functions {
+        env.put_fun(name, fun);
+    }
+    for (sym, op) in state.formulas {
+        env.put_formula(sym, op);
+    }
+    for (name, equation) in state.equations {
+        env.put_equation(name, equation);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::CalcError;
+    use crate::parser::parse_expression;
+    use crate::Error;
+
+    #[test]
+    fn round_trip() {
+        let mut env = TopLevelEnv::default();
+        env.put("a".to_string(), 1.5).unwrap();
+        env.put("b".to_string(), Number::NAN).unwrap();
+        env.put_list("xs".to_string(), vec![1.0, 2.0]).unwrap();
+        env.put_text("t".to_string(), "m/s".to_string()).unwrap();
+        let m = Matrix::new(2, vec![1.0, 2.0, 3.0, 4.0]).unwrap();
+        env.put_matrix("m".to_string(), m.clone()).unwrap();
+        env.put_formula("c".to_string(), parse_expression("a * 2").unwrap());
+        let saved = save(&env);
+        assert!(!saved.contains("\"sin\""));
+
+        let mut loaded = TopLevelEnv::default();
+        load(&saved, &mut loaded).unwrap();
+        assert_eq!(Some(&1.5), loaded.get("a"));
+        assert!(loaded.get("b").unwrap().is_nan());
+        assert_eq!(Some(&[1.0, 2.0][..]), loaded.get_list("xs"));
+        assert_eq!(Some("m/s"), loaded.get_text("t"));
+        assert_eq!(Some(&m), loaded.get_matrix("m"));
+        assert_eq!(env.formula("c"), loaded.formula("c"));
+        assert!(loaded.get_fun("sin").is_some());
+    }
+
+    #[test]
+    fn invalid_states() {
+        let mut env = TopLevelEnv::default();
+        assert!(matches!(
+            load("{", &mut env),
+            Err(Error::StateError(StateError::InvalidState(_)))
+        ));
+        let constant =
+            r#"{"variables":{"pi":{"Number":3.0}},"functions":{},"formulas":{},"equations":{}}"#;
+        assert_eq!(
+            Err(Error::CalcError(CalcError::CannotChangeConstant(
+                "pi".to_string()
+            ))),
+            load(constant, &mut env)
+        );
+    }
+}
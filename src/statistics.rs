@@ -0,0 +1,139 @@
+//! Aggregates of lists, e.g. `mean(xs)` or `stddev([1, 2, 4])`.
+//!
+//! Matrices are aggregated over all elements. `variance` and `stddev` are the sample
+//! statistics, they divide by `n - 1`.
+
+use crate::ast::{Data, DataFunction, Number};
+
+fn list(params: &[Data]) -> Result<&[Number], String> {
+    params[0]
+        .list()
+        .ok_or_else(|| "xs must be a list".to_string())
+}
+
+/// the list of at least `min` elements
+fn at_least(params: &[Data], min: usize) -> Result<&[Number], String> {
+    let xs = list(params)?;
+    if xs.len() < min {
+        return Err(format!(
+            "xs must have at least {} element{}, but has {}",
+            min,
+            if min == 1 { "" } else { "s" },
+            xs.len()
+        ));
+    }
+    Ok(xs)
+}
+
+fn mean(xs: &[Number]) -> Number {
+    xs.iter().sum::<Number>() / xs.len() as Number
+}
+
+fn variance(xs: &[Number]) -> Number {
+    let mean = mean(xs);
+    xs.iter().map(|x| (x - mean) * (x - mean)).sum::<Number>() / (xs.len() - 1) as Number
+}
+
+fn median(xs: &[Number]) -> Number {
+    let mut sorted = xs.to_vec();
+    sorted.sort_by(Number::total_cmp);
+    let mid = sorted.len() / 2;
+    if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    }
+}
+
+fn sum_fn(params: &[Data]) -> Result<Data, String> {
+    Ok(Data::Number(list(params)?.iter().sum()))
+}
+
+fn mean_fn(params: &[Data]) -> Result<Data, String> {
+    at_least(params, 1).map(|xs| Data::Number(mean(xs)))
+}
+
+fn median_fn(params: &[Data]) -> Result<Data, String> {
+    at_least(params, 1).map(|xs| Data::Number(median(xs)))
+}
+
+fn variance_fn(params: &[Data]) -> Result<Data, String> {
+    at_least(params, 2).map(|xs| Data::Number(variance(xs)))
+}
+
+fn stddev_fn(params: &[Data]) -> Result<Data, String> {
+    at_least(params, 2).map(|xs| Data::Number(variance(xs).sqrt()))
+}
+
+fn minv_fn(params: &[Data]) -> Result<Data, String> {
+    let xs = at_least(params, 1)?;
+    Ok(Data::Number(
+        xs.iter().copied().fold(Number::INFINITY, Number::min),
+    ))
+}
+
+fn maxv_fn(params: &[Data]) -> Result<Data, String> {
+    let xs = at_least(params, 1)?;
+    Ok(Data::Number(
+        xs.iter().copied().fold(Number::NEG_INFINITY, Number::max),
+    ))
+}
+
+/// `sum(xs)`, `mean(xs)`, `median(xs)`, `variance(xs)`, `stddev(xs)`, `minv(xs)` and `maxv(xs)`
+pub(crate) fn functions() -> Vec<DataFunction> {
+    let function = |name: &str, body| DataFunction {
+        name: name.to_string(),
+        args: vec!["xs".to_string()],
+        returns_list: false,
+        body,
+    };
+    vec![
+        function("sum", &sum_fn),
+        function("mean", &mean_fn),
+        function("median", &median_fn),
+        function("variance", &variance_fn),
+        function("stddev", &stddev_fn),
+        function("minv", &minv_fn),
+        function("maxv", &maxv_fn),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_approx_eq::assert_approx_eq;
+
+    fn call(body: fn(&[Data]) -> Result<Data, String>, xs: &[Number]) -> Result<Number, String> {
+        body(&[Data::List(xs.to_vec())]).map(|data| data.number().unwrap())
+    }
+
+    #[test]
+    fn aggregates() {
+        let xs = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        assert_eq!(Ok(40.0), call(sum_fn, &xs));
+        assert_eq!(Ok(5.0), call(mean_fn, &xs));
+        assert_eq!(Ok(4.5), call(median_fn, &xs));
+        assert_eq!(Ok(4.0), call(median_fn, &[9.0, 1.0, 4.0]));
+        assert_approx_eq!(32.0 / 7.0, call(variance_fn, &xs).unwrap());
+        assert_approx_eq!((32.0 / 7.0 as Number).sqrt(), call(stddev_fn, &xs).unwrap());
+        assert_eq!(Ok(2.0), call(minv_fn, &xs));
+        assert_eq!(Ok(9.0), call(maxv_fn, &xs));
+    }
+
+    #[test]
+    fn too_few_elements() {
+        assert_eq!(Ok(0.0), call(sum_fn, &[]));
+        assert_eq!(
+            Err("xs must have at least 1 element, but has 0".to_string()),
+            call(mean_fn, &[])
+        );
+        assert_eq!(
+            Err("xs must have at least 2 elements, but has 1".to_string()),
+            call(stddev_fn, &[1.0])
+        );
+        assert_eq!(
+            Err("xs must be a list".to_string()),
+            maxv_fn(&[Data::Number(1.0)])
+        );
+    }
+}
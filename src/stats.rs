@@ -0,0 +1,195 @@
+//! Size metrics of expressions, e.g. to reject pathological inputs.
+
+use crate::ast::*;
+
+use std::collections::BTreeMap;
+
+/// Node counts, depth and operation histogram of an operand.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct ExpressionStats {
+    /// total number of nodes
+    pub nodes: usize,
+    /// length of the longest path from the root to a leaf, a single number has depth 1
+    pub depth: usize,
+    pub numbers: usize,
+    pub symbols: usize,
+    pub function_calls: usize,
+    /// number of terms per operation
+    pub operations: BTreeMap<Operation, usize>,
+}
+
+impl ExpressionStats {
+    pub fn of(op: &Operand) -> ExpressionStats {
+        let mut stats = ExpressionStats::default();
+        stats.depth = stats.count(op);
+        stats
+    }
+
+    /// combined metrics of all operands of a statement
+    pub fn of_statement(st: &Statement) -> ExpressionStats {
+        let mut stats = ExpressionStats::default();
+        let ops: Vec<&Operand> = match st {
+            Statement::Expression { op }
+            | Statement::Simplify { op }
+            | Statement::Assignment { op, .. }
+            | Statement::SequenceValue { op, .. }
+            | Statement::Define { op, .. } => vec![op],
+            Statement::SolveFor {
+                lhs,
+                rhs,
+                bracket: Some(bracket),
+                ..
+            } => vec![lhs, rhs, &bracket.from, &bracket.to],
+            Statement::Integrate { bracket, .. } => vec![&bracket.from, &bracket.to],
+            Statement::IntegrateExpression { op, bracket, .. } => {
+                vec![op, &bracket.from, &bracket.to]
+            }
+            Statement::SolveFor { lhs, rhs, .. }
+            | Statement::SolveInequality { lhs, rhs, .. }
+            | Statement::Store { lhs, rhs, .. }
+            | Statement::Equivalent { lhs, rhs } => {
+                vec![lhs, rhs]
+            }
+            Statement::SolveStored { given, .. } => given.iter().map(|(_, op)| op).collect(),
+            Statement::Function {
+                fun: Function::Custom(fun),
+                ..
+            } => vec![&fun.body],
+            Statement::Function {
+                fun:
+                    Function::Sequence(Sequence {
+                        body: Some(body), ..
+                    }),
+                ..
+            } => vec![body],
+            Statement::Function {
+                fun: Function::Piecewise(fun),
+                ..
+            } => fun.operands(),
+            Statement::Plot {
+                sweep: Some(sweep), ..
+            } => [Some(&sweep.from), Some(&sweep.to), sweep.step.as_ref()]
+                .into_iter()
+                .flatten()
+                .collect(),
+            Statement::PlotExpression { op, .. } => vec![op],
+            Statement::PlotImplicit { lhs, rhs, .. } => {
+                std::iter::once(lhs).chain(rhs.as_ref()).collect()
+            }
+            Statement::PlotPolar { op, bracket, .. } => {
+                let mut ops = vec![op];
+                ops.extend(
+                    bracket
+                        .iter()
+                        .flat_map(|bracket| [&bracket.from, &bracket.to]),
+                );
+                ops
+            }
+            Statement::Override { st } => return Self::of_statement(st),
+            Statement::Function { .. }
+            | Statement::Plot { .. }
+            | Statement::Import { .. }
+            | Statement::Run { .. }
+            | Statement::List
+            | Statement::Undo
+            | Statement::Empty
+            | Statement::Unset { .. }
+            | Statement::TextAssignment { .. }
+            | Statement::TextExpression { .. }
+            | Statement::FitLinear { .. } => {
+                vec![]
+            }
+        };
+        for op in ops {
+            let depth = stats.count(op);
+            stats.depth = stats.depth.max(depth);
+        }
+        stats
+    }
+
+    /// counts the nodes of `op` and returns its depth
+    fn count(&mut self, op: &Operand) -> usize {
+        self.nodes += 1;
+        1 + match op {
+            Operand::Number(_) | Operand::Literal(_) => {
+                self.numbers += 1;
+                0
+            }
+            Operand::Symbol(_) => {
+                self.symbols += 1;
+                0
+            }
+            Operand::Term(term) => {
+                *self.operations.entry(term.op).or_insert(0) += 1;
+                let lhs = self.count(&term.lhs);
+                let rhs = self.count(&term.rhs);
+                lhs.max(rhs)
+            }
+            Operand::FunCall(fun_call) => {
+                self.function_calls += 1;
+                fun_call
+                    .params
+                    .iter()
+                    .map(|param| self.count(param))
+                    .max()
+                    .unwrap_or(0)
+            }
+            Operand::Conditional(cond) => cond
+                .operands()
+                .into_iter()
+                .map(|op| self.count(op))
+                .max()
+                .unwrap_or(0),
+            Operand::Series(series) => [&series.from, &series.to, &series.body]
+                .into_iter()
+                .map(|op| self.count(op))
+                .max()
+                .unwrap_or(0),
+            Operand::Let(binding) => self.count(&binding.value).max(self.count(&binding.body)),
+            Operand::Factorial(op) => self.count(op),
+            Operand::Matrix(rows) => rows
+                .iter()
+                .flatten()
+                .map(|op| self.count(op))
+                .max()
+                .unwrap_or(0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse, parse_expression};
+
+    #[test]
+    fn stats_of_number() {
+        let stats = ExpressionStats::of(&Operand::Number(1.0));
+        assert_eq!(1, stats.nodes);
+        assert_eq!(1, stats.depth);
+        assert_eq!(1, stats.numbers);
+    }
+
+    #[test]
+    fn stats_of_expression() {
+        let stats = ExpressionStats::of(&parse_expression("1 + 2 * sin(x - y) + x").unwrap());
+        assert_eq!(10, stats.nodes);
+        assert_eq!(6, stats.depth);
+        assert_eq!(2, stats.numbers);
+        assert_eq!(3, stats.symbols);
+        assert_eq!(1, stats.function_calls);
+        assert_eq!(Some(&2), stats.operations.get(&Operation::Add));
+        assert_eq!(Some(&1), stats.operations.get(&Operation::Mul));
+        assert_eq!(Some(&1), stats.operations.get(&Operation::Sub));
+        assert_eq!(None, stats.operations.get(&Operation::Pow));
+    }
+
+    #[test]
+    fn stats_of_statement() {
+        let stats =
+            ExpressionStats::of_statement(&parse("solve 2 * x = (1 + x) * 3 for x").unwrap());
+        assert_eq!(8, stats.nodes);
+        assert_eq!(3, stats.depth);
+        assert_eq!(Some(&2), stats.operations.get(&Operation::Mul));
+    }
+}
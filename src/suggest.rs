@@ -0,0 +1,103 @@
+//! Suggestions of similar names for misspelled symbols.
+
+/// Edit distance of two names, swapping two adjacent letters like `sni` for `sin` is
+/// a single edit (optimal string alignment distance)
+fn distance(lhs: &str, rhs: &str) -> usize {
+    let lhs: Vec<char> = lhs.chars().collect();
+    let rhs: Vec<char> = rhs.chars().collect();
+    let mut before: Vec<usize> = Vec::new();
+    let mut above: Vec<usize> = (0..=rhs.len()).collect();
+    for (i, l) in lhs.iter().enumerate() {
+        let mut row = vec![i + 1; rhs.len() + 1];
+        for (j, r) in rhs.iter().enumerate() {
+            row[j + 1] = if l == r {
+                above[j]
+            } else {
+                1 + above[j].min(above[j + 1]).min(row[j])
+            };
+            if i > 0 && j > 0 && *l == rhs[j - 1] && lhs[i - 1] == *r {
+                row[j + 1] = row[j + 1].min(before[j - 1] + 1);
+            }
+        }
+        before = std::mem::replace(&mut above, row);
+    }
+    above[rhs.len()]
+}
+
+/// Whether `candidate` is close enough to `name` to be a misspelling,
+/// single letter names are never similar.
+pub fn is_similar(name: &str, candidate: &str) -> bool {
+    let len = name.chars().count();
+    let dist = distance(name, candidate);
+    name != candidate && dist <= (len / 3).max(1) && dist < len
+}
+
+/// Names of `candidates` similar to `name`, the closest first.
+pub fn similar_names<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut similar: Vec<(usize, &str)> = candidates
+        .into_iter()
+        .filter(|candidate| is_similar(name, candidate))
+        .map(|candidate| (distance(name, candidate), candidate))
+        .collect();
+    similar.sort_unstable();
+    similar.dedup();
+    similar
+        .into_iter()
+        .map(|(_, candidate)| candidate.to_string())
+        .collect()
+}
+
+/// `did you mean ...?` for the `candidates`, if there are any
+pub fn did_you_mean(candidates: &[String]) -> Option<String> {
+    match candidates {
+        [] => None,
+        candidates => Some(format!(
+            "did you mean {}?",
+            candidates
+                .iter()
+                .map(|candidate| format!("`{}`", candidate))
+                .collect::<Vec<_>>()
+                .join(" or ")
+        )),
+    }
+}
+
+/// the hint appended to an error message, e.g. ` - did you mean `sin`?`
+pub fn hint_suffix(hint: Option<String>) -> String {
+    hint.map(|hint| format!(" - {}", hint)).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levenshtein() {
+        assert_eq!(0, distance("abc", "abc"));
+        assert_eq!(1, distance("abc", "abd"));
+        assert_eq!(1, distance("ab", "abc"));
+        assert_eq!(3, distance("", "abc"));
+        assert_eq!(2, distance("speed", "sped_"));
+        assert_eq!(1, distance("sni", "sin"));
+        assert_eq!(2, distance("abc", "cab"));
+    }
+
+    #[test]
+    fn suggestions_ordered_by_distance() {
+        assert_eq!(
+            vec!["growths".to_string(), "grwoth".to_string()],
+            similar_names("growth", ["grwoth", "pi", "growths", "growths"])
+        );
+        assert!(similar_names("x", ["x", "y"]).is_empty());
+    }
+
+    #[test]
+    fn hints() {
+        let candidates = ["sin".to_string(), "sinh".to_string()];
+        assert_eq!(
+            " - did you mean `sin` or `sinh`?",
+            hint_suffix(did_you_mean(&candidates))
+        );
+        assert_eq!("", hint_suffix(did_you_mean(&[])));
+    }
+}
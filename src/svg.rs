@@ -0,0 +1,228 @@
+//! SVG output of a [`Plot`], e.g. for web pages or files.
+
+use crate::analysis::PointKind;
+use crate::ast::Number;
+use crate::graph::{Area, Direction, Labels, Marker, Plot, PlotRenderer, Range, Tic};
+
+use std::fmt::Write;
+
+/// half length of a tic and radius of a marker in SVG units
+const TIC_SIZE: Number = 3.0;
+const FONT_SIZE: Number = 10.0;
+
+/// Collects the SVG elements of a plot, scaled from the screen area to `width` x `height`.
+struct SvgRenderer {
+    screen: Area,
+    width: Number,
+    height: Number,
+    /// the path of the function, continued while segments connect
+    path: String,
+    last: Option<(Number, Number)>,
+    elements: String,
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl SvgRenderer {
+    fn new(screen: &Area, width: Number, height: Number) -> Self {
+        SvgRenderer {
+            screen: *screen,
+            width,
+            height,
+            path: String::new(),
+            last: None,
+            elements: String::new(),
+        }
+    }
+
+    fn x(&self, x: Number) -> Number {
+        self.screen.x.project(x, &Range::new(0.0, self.width))
+    }
+
+    /// SVG counts `y` from the top, the screen from the bottom
+    fn y(&self, y: Number) -> Number {
+        self.height - self.screen.y.project(y, &Range::new(0.0, self.height))
+    }
+
+    fn point(&self, (x, y): (Number, Number)) -> (Number, Number) {
+        (self.x(x), self.y(y))
+    }
+
+    fn text(&mut self, (x, y): (Number, Number), anchor: &str, text: &str) {
+        let _ = write!(
+            self.elements,
+            r#"<text x="{}" y="{}" font-size="{}" text-anchor="{}">{}</text>"#,
+            x,
+            y,
+            FONT_SIZE,
+            anchor,
+            escape(text)
+        );
+    }
+
+    fn line(&mut self, from: (Number, Number), to: (Number, Number), class: &str) {
+        let _ = write!(
+            self.elements,
+            r#"<line class="{}" x1="{}" y1="{}" x2="{}" y2="{}" stroke="black"/>"#,
+            class, from.0, from.1, to.0, to.1
+        );
+    }
+
+    fn finish(self) -> String {
+        let mut svg = format!(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = self.width,
+            h = self.height
+        );
+        svg.push_str(&self.elements);
+        if !self.path.is_empty() {
+            let _ = write!(
+                svg,
+                r#"<path class="function" d="{}" fill="none" stroke="blue"/>"#,
+                self.path.trim_end()
+            );
+        }
+        svg.push_str("</svg>");
+        svg
+    }
+}
+
+impl PlotRenderer for SvgRenderer {
+    fn draw_axis(&mut self, direction: Direction, pos: Number, range: &Range) {
+        let (from, to) = match direction {
+            Direction::Horizontal => ((range.min, pos), (range.max, pos)),
+            Direction::Vertical => ((pos, range.min), (pos, range.max)),
+        };
+        self.line(self.point(from), self.point(to), "axis");
+    }
+
+    /// minor tics are half as long and without label
+    fn draw_tic(&mut self, direction: Direction, pos: Number, tic: &Tic) {
+        let (size, class) = match tic.minor {
+            true => (TIC_SIZE / 2.0, "minor-tic"),
+            false => (TIC_SIZE, "tic"),
+        };
+        match direction {
+            Direction::Horizontal => {
+                let (x, y) = self.point((tic.pos, pos));
+                self.line((x, y - size), (x, y + size), class);
+                if !tic.minor {
+                    self.text((x, y + TIC_SIZE + FONT_SIZE), "middle", &tic.text);
+                }
+            }
+            Direction::Vertical => {
+                let (x, y) = self.point((pos, tic.pos));
+                self.line((x - size, y), (x + size, y), class);
+                if !tic.minor {
+                    self.text((x - 2.0 * TIC_SIZE, y + FONT_SIZE / 3.0), "end", &tic.text);
+                }
+            }
+        }
+    }
+
+    fn draw_segment(&mut self, from: (Number, Number), to: (Number, Number)) {
+        let (from, to) = (self.point(from), self.point(to));
+        if self.last != Some(from) {
+            let _ = write!(self.path, "M{} {} ", from.0, from.1);
+        }
+        let _ = write!(self.path, "L{} {} ", to.0, to.1);
+        self.last = Some(to);
+    }
+
+    fn draw_point(&mut self, at: (Number, Number)) {
+        let (x, y) = self.point(at);
+        let _ = write!(
+            self.elements,
+            r#"<circle class="point" cx="{}" cy="{}" r="1" fill="blue"/>"#,
+            x, y
+        );
+    }
+
+    fn draw_labels(&mut self, labels: &Labels) {
+        if let Some(title) = &labels.title {
+            self.text((self.width / 2.0, FONT_SIZE), "middle", title);
+        }
+        if let Some(xlabel) = &labels.xlabel {
+            self.text((self.width, self.height - TIC_SIZE), "end", xlabel);
+        }
+        if let Some(ylabel) = &labels.ylabel {
+            self.text((TIC_SIZE, FONT_SIZE), "start", ylabel);
+        }
+    }
+
+    fn draw_marker(&mut self, marker: &Marker) {
+        let (x, y) = self.point(marker.at);
+        let class = match marker.kind {
+            PointKind::Zero => "zero",
+            PointKind::Minimum => "minimum",
+            PointKind::Maximum => "maximum",
+            PointKind::Inflection => "inflection",
+        };
+        let _ = write!(
+            self.elements,
+            r#"<circle class="{}" cx="{}" cy="{}" r="{}" fill="none" stroke="red"/>"#,
+            class, x, y, TIC_SIZE
+        );
+    }
+}
+
+impl Plot {
+    /// Renders the plot as SVG document of `width` x `height`, the screen area of the plot
+    /// is scaled to fit. Classes like `axis`, `function` or `minimum` allow styling by CSS.
+    /// ```
+    /// use rust_expression::{Area, Calculator};
+    /// let mut c = Calculator::new();
+    /// let graph = c.execute("plot x ^ 2 title \"square\"").unwrap();
+    /// let area = Area::new(-2.0, -1.0, 2.0, 4.0);
+    /// let plot = graph.as_graph().unwrap().plot(&area, &Area::new(0.0, 0.0, 100.0, 50.0)).unwrap();
+    /// let svg = plot.to_svg(400.0, 200.0);
+    /// assert!(svg.starts_with("<svg") && svg.contains("square") && svg.contains("<path"));
+    /// ```
+    pub fn to_svg(&self, width: Number, height: Number) -> String {
+        let mut renderer = SvgRenderer::new(&self.screen, width, height);
+        self.render(&mut renderer);
+        renderer.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scaled_elements() {
+        let plot = Plot {
+            name: "f(x)".to_string(),
+            points: vec![Some((0.0, 0.0)), Some((1.0, 5.0)), None, Some((3.0, 10.0))],
+            samples: Vec::new(),
+            area: Area::default(),
+            screen: Area::new(0.0, 0.0, 4.0, 10.0),
+            x_axis: None,
+            y_axis: None,
+            labels: Labels {
+                title: Some("a < b".to_string()),
+                ..Labels::default()
+            },
+            markers: vec![Marker {
+                kind: PointKind::Maximum,
+                at: (1.0, 5.0),
+            }],
+        };
+        assert_eq!(
+            concat!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="40" height="20" viewBox="0 0 40 20">"#,
+                r#"<text x="20" y="10" font-size="10" text-anchor="middle">a &lt; b</text>"#,
+                r#"<circle class="point" cx="30" cy="0" r="1" fill="blue"/>"#,
+                r#"<circle class="maximum" cx="10" cy="10" r="3" fill="none" stroke="red"/>"#,
+                r#"<path class="function" d="M0 20 L10 10" fill="none" stroke="blue"/>"#,
+                "</svg>"
+            ),
+            plot.to_svg(40.0, 20.0)
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! Texts for titles and labels, which can only be concatenated.
+
+use crate::ast::{Operand, Operation, Text, TextPart};
+use crate::calc::{CalcError, Env};
+
+/// Concatenates the parts of `text`. Numbers are written like results.
+pub fn calc_text(text: &Text, env: &dyn Env) -> Result<String, CalcError> {
+    text.parts.iter().try_fold(String::new(), |mut acc, part| {
+        match part {
+            TextPart::Literal(literal) => acc.push_str(literal),
+            TextPart::Symbol(sym) => match (env.get_text(sym), env.get(sym)) {
+                (Some(text), _) => acc.push_str(text),
+                (None, Some(num)) => acc.push_str(&num.to_string()),
+                (None, None) if env.get_list(sym).is_some() => {
+                    return Err(CalcError::UnexpectedList(sym.clone()))
+                }
+                (None, None) => return Err(CalcError::unknown_symbol(sym, env)),
+            },
+        }
+        Ok(acc)
+    })
+}
+
+fn symbols<'a>(op: &'a Operand, parts: &mut Vec<&'a str>) -> bool {
+    match op {
+        Operand::Symbol(sym) => {
+            parts.push(sym);
+            true
+        }
+        Operand::Term(term) if term.op == Operation::Add => {
+            symbols(&term.lhs, parts) && symbols(&term.rhs, parts)
+        }
+        _ => false,
+    }
+}
+
+/// The expression `op` as text, if it is a sum of variables with at least one text, e.g. `a + b`.
+pub fn as_text(op: &Operand, env: &dyn Env) -> Option<Text> {
+    let mut parts = Vec::new();
+    if !symbols(op, &mut parts) || parts.iter().all(|sym| env.get_text(sym).is_none()) {
+        return None;
+    }
+    let parts = parts
+        .into_iter()
+        .map(|sym| TextPart::Symbol(sym.to_string()))
+        .collect();
+    Some(Text { parts })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calc::TopLevelEnv;
+    use crate::parser::parse_expression;
+
+    #[test]
+    fn concatenate_texts() {
+        let mut env = TopLevelEnv::default();
+        env.put_text("unit".to_string(), "m".to_string()).unwrap();
+        env.put("t".to_string(), 1.5).unwrap();
+        let text = Text {
+            parts: vec![
+                TextPart::Literal("t = ".to_string()),
+                TextPart::Symbol("t".to_string()),
+                TextPart::Symbol("unit".to_string()),
+            ],
+        };
+        assert_eq!(Ok("t = 1.5m".to_string()), calc_text(&text, &env));
+
+        let op = parse_expression("unit + t").unwrap();
+        assert_eq!(
+            Ok("m1.5".to_string()),
+            calc_text(&as_text(&op, &env).unwrap(), &env)
+        );
+        assert_eq!(None, as_text(&parse_expression("t + t").unwrap(), &env));
+        assert_eq!(None, as_text(&parse_expression("unit * t").unwrap(), &env));
+        assert_eq!(
+            Err(CalcError::UnexpectedText("unit".to_string())),
+            crate::calc::calc_operand::<crate::ast::Number>(&op, &env)
+        );
+    }
+}
@@ -0,0 +1,96 @@
+//! Opt-in profiling of statement execution.
+//!
+//! Function calls are counted per thread while counting is active,
+//! otherwise counting costs a single check per call.
+
+use std::cell::Cell;
+use std::time::Duration;
+
+/// Durations of the phases of a statement and the number of evaluated function calls
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Timing {
+    pub parse: Duration,
+    /// evaluation of expressions and definitions
+    pub eval: Duration,
+    pub solve: Duration,
+    pub plot: Duration,
+    /// calls of custom functions and sequences
+    pub function_calls: u64,
+    /// calls of build-in functions
+    pub builtin_calls: u64,
+}
+
+thread_local! {
+    static COUNTS: Cell<Option<(u64, u64)>> = const { Cell::new(None) };
+}
+
+/// counts `n` calls of custom functions, if counting is active
+pub(crate) fn count_function_calls(n: u64) {
+    COUNTS.with(|counts| {
+        if let Some((functions, builtins)) = counts.get() {
+            counts.set(Some((functions + n, builtins)));
+        }
+    });
+}
+
+/// counts `n` calls of build-in functions, if counting is active
+pub(crate) fn count_builtin_calls(n: u64) {
+    COUNTS.with(|counts| {
+        if let Some((functions, builtins)) = counts.get() {
+            counts.set(Some((functions, builtins + n)));
+        }
+    });
+}
+
+/// Runs `f` with active counting and adds the counted calls to `timing`.
+pub(crate) fn counted<T>(timing: &mut Timing, f: impl FnOnce() -> T) -> T {
+    let outer = COUNTS.with(|counts| counts.replace(Some((0, 0))));
+    let result = f();
+    let (functions, builtins) = COUNTS
+        .with(|counts| counts.replace(outer))
+        .unwrap_or_default();
+    timing.function_calls += functions;
+    timing.builtin_calls += builtins;
+    if outer.is_some() {
+        count_function_calls(functions);
+        count_builtin_calls(builtins);
+    }
+    result
+}
+
+/// Runs `f` with active counting and returns the counted calls without adding them
+/// to an outer counting, e.g. on a worker thread.
+#[cfg(feature = "parallel")]
+pub(crate) fn isolated<T>(f: impl FnOnce() -> T) -> (T, Timing) {
+    let outer = COUNTS.with(|counts| counts.replace(Some((0, 0))));
+    let result = f();
+    let (function_calls, builtin_calls) = COUNTS
+        .with(|counts| counts.replace(outer))
+        .unwrap_or_default();
+    let timing = Timing {
+        function_calls,
+        builtin_calls,
+        ..Timing::default()
+    };
+    (result, timing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_only_while_counting() {
+        count_function_calls(1);
+        let mut timing = Timing::default();
+        counted(&mut timing, || {
+            count_function_calls(2);
+            count_builtin_calls(3);
+            let mut inner = Timing::default();
+            counted(&mut inner, || count_builtin_calls(1));
+            assert_eq!(1, inner.builtin_calls);
+        });
+        assert_eq!(2, timing.function_calls);
+        assert_eq!(4, timing.builtin_calls);
+    }
+}
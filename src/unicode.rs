@@ -0,0 +1,266 @@
+//! Formatting of expressions for terminals with unicode symbols, e.g. `3·x² + ½`.
+//!
+//! Unlike [`std::fmt::Display`] of the AST, the output is for reading only and cannot be parsed again.
+
+use crate::ast::*;
+
+const MINUS: char = '\u{2212}';
+
+fn superscript(c: char) -> Option<char> {
+    Some(match c {
+        '0' => '⁰',
+        '1' => '¹',
+        '2' => '²',
+        '3' => '³',
+        '4' => '⁴',
+        '5' => '⁵',
+        '6' => '⁶',
+        '7' => '⁷',
+        '8' => '⁸',
+        '9' => '⁹',
+        '-' => '⁻',
+        'n' => 'ⁿ',
+        'i' => 'ⁱ',
+        _ => return None,
+    })
+}
+
+/// the exponent in superscript, if all its characters have a superscript form
+fn exponent(op: &Operand) -> Option<String> {
+    let text = match op {
+        Operand::Number(num) if num.fract() == 0.0 && num.abs() < 1e6 => format!("{}", num),
+        Operand::Symbol(sym) => sym.clone(),
+        _ => return None,
+    };
+    text.chars().map(superscript).collect()
+}
+
+fn fraction(lhs: &Operand, rhs: &Operand) -> Option<char> {
+    let (Operand::Number(n), Operand::Number(d)) = (lhs, rhs) else {
+        return None;
+    };
+    Some(match (*n as i64, *d as i64) {
+        _ if n.fract() != 0.0 || d.fract() != 0.0 => return None,
+        (1, 2) => '½',
+        (1, 3) => '⅓',
+        (2, 3) => '⅔',
+        (1, 4) => '¼',
+        (3, 4) => '¾',
+        (1, 5) => '⅕',
+        (1, 6) => '⅙',
+        (1, 8) => '⅛',
+        _ => return None,
+    })
+}
+
+fn symbol(sym: &str) -> &str {
+    match sym {
+        "pi" => "π",
+        "tau" => "τ",
+        sym => sym,
+    }
+}
+
+fn number(num: Number) -> String {
+    if num.is_infinite() {
+        format!("{}∞", if num < 0.0 { "−" } else { "" })
+    } else if num < 0.0 {
+        format!("{}{}", MINUS, -num)
+    } else {
+        format!("{}", num)
+    }
+}
+
+fn child(op: &Operand, parent: Operation, is_rhs: bool) -> String {
+    if parent.needs_parens(op, is_rhs) {
+        format!("({})", operand(op))
+    } else {
+        operand(op)
+    }
+}
+
+fn term(term: &Term) -> String {
+    if let Some(fraction) = fraction(&term.lhs, &term.rhs).filter(|_| term.op == Operation::Div) {
+        return fraction.to_string();
+    }
+    let lhs = child(&term.lhs, term.op, false);
+    match term.op {
+        Operation::Pow => match exponent(&term.rhs) {
+            Some(exponent) => format!("{}{}", lhs, exponent),
+            None => format!("{}^{}", lhs, child(&term.rhs, term.op, true)),
+        },
+        Operation::Mul => format!("{}·{}", lhs, child(&term.rhs, term.op, true)),
+        op => {
+            let op = match op {
+                Operation::Add => "+".to_string(),
+                Operation::Sub => MINUS.to_string(),
+                Operation::Div => "/".to_string(),
+                op => op.to_string(),
+            };
+            format!("{} {} {}", lhs, op, child(&term.rhs, term.op, true))
+        }
+    }
+}
+
+fn guard(guard: &Guard) -> String {
+    format!(
+        "{} {} {}",
+        operand(&guard.lhs),
+        match guard.cmp {
+            Comparison::Less => "<",
+            Comparison::LessEqual => "≤",
+            Comparison::Greater => ">",
+            Comparison::GreaterEqual => "≥",
+            Comparison::Equal => "=",
+            Comparison::NotEqual => "≠",
+        },
+        operand(&guard.rhs)
+    )
+}
+
+pub fn operand(op: &Operand) -> String {
+    match op {
+        Operand::Number(num) => number(*num),
+        Operand::Literal(lit) => number(lit.value),
+        Operand::Symbol(sym) => symbol(sym).to_string(),
+        Operand::Term(t) => term(t),
+        Operand::FunCall(fun_call) if fun_call.name == "sqrt" && fun_call.params.len() == 1 => {
+            format!("√({})", operand(&fun_call.params[0]))
+        }
+        Operand::FunCall(fun_call) if fun_call.name == "root" => match &fun_call.params[..] {
+            [Operand::Number(3.0), x] => format!("∛({})", operand(x)),
+            [Operand::Number(4.0), x] => format!("∜({})", operand(x)),
+            params => format!(
+                "root({})",
+                params.iter().map(operand).collect::<Vec<_>>().join(", ")
+            ),
+        },
+        Operand::FunCall(fun_call) => format!(
+            "{}({})",
+            fun_call.name,
+            fun_call
+                .params
+                .iter()
+                .map(operand)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        Operand::Conditional(cond) => format!(
+            "if({}, {}, {})",
+            guard(&cond.guard),
+            operand(&cond.then),
+            operand(&cond.otherwise)
+        ),
+        Operand::Series(series) => format!(
+            "{}({} = {}..{}, {})",
+            match series.kind {
+                SeriesKind::Sum => "∑",
+                SeriesKind::Product => "∏",
+            },
+            series.var,
+            operand(&series.from),
+            operand(&series.to),
+            operand(&series.body)
+        ),
+        Operand::Let(binding) => format!(
+            "let {} := {}; {}",
+            symbol(&binding.var),
+            operand(&binding.value),
+            operand(&binding.body)
+        ),
+        Operand::Factorial(op) if op.needs_parens_in_factorial() => format!("({})!", operand(op)),
+        Operand::Factorial(op) => format!("{}!", operand(op)),
+        Operand::Matrix(rows) => format!(
+            "[{}]",
+            rows.iter()
+                .map(|row| row.iter().map(operand).collect::<Vec<_>>().join(", "))
+                .collect::<Vec<_>>()
+                .join("; ")
+        ),
+    }
+}
+
+/// definition of a custom function or sequence, e.g. `f(x) := x² + 1`
+pub fn function(name: &str, fun: &Function) -> Option<String> {
+    match fun {
+        Function::Custom(fun) => Some(format!(
+            "{}({}) := {}",
+            name,
+            fun.args.join(", "),
+            operand(&fun.body)
+        )),
+        Function::Sequence(Sequence {
+            arg,
+            body: Some(body),
+            ..
+        }) => Some(format!("{}({}) := {}", name, arg, operand(body))),
+        Function::Piecewise(fun) => Some(
+            fun.clauses
+                .iter()
+                .map(|clause| {
+                    let condition = match &clause.guard {
+                        Some(guard) => format!("when {}", self::guard(guard)),
+                        None => "otherwise".to_string(),
+                    };
+                    format!(
+                        "{}({}) := {} {}",
+                        name,
+                        fun.args.join(", "),
+                        operand(&clause.body),
+                        condition
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+        _ => None,
+    }
+}
+
+/// renders an equation stored as `name`, e.g. `ohm: v = i·r`
+pub fn equation(name: &str, equation: &Equation) -> String {
+    format!(
+        "{}: {} = {}",
+        name,
+        operand(&equation.lhs),
+        operand(&equation.rhs)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_expression;
+
+    fn unicode(s: &str) -> String {
+        operand(&parse_expression(s).unwrap())
+    }
+
+    #[test]
+    fn unicode_operators() {
+        assert_eq!("3·x² + ½", unicode("3 * x^2 + 1/2"));
+        assert_eq!("a − b", unicode("a - b"));
+        assert_eq!("2·π·r", unicode("2 * pi * r"));
+        assert_eq!("1 / 7", unicode("1 / 7"));
+        assert_eq!("(a + b)·c", unicode("(a + b) * c"));
+    }
+
+    #[test]
+    fn unicode_exponents() {
+        assert_eq!("xⁿ", unicode("x^n"));
+        assert_eq!("x⁻¹", unicode("x^(-1)"));
+        assert_eq!("(x + 1)¹⁰", unicode("(x + 1)^10"));
+        assert_eq!("(−2)²", unicode("(-2)^2"));
+        assert_eq!("x^y", unicode("x^y"));
+        assert_eq!("2^(x + 1)", unicode("2^(x + 1)"));
+        assert_eq!("2^0.5", unicode("2^0.5"));
+    }
+
+    #[test]
+    fn unicode_functions() {
+        assert_eq!("√(x² + 1)", unicode("sqrt(x^2 + 1)"));
+        assert_eq!("∛(−8) + root(5, x)", unicode("root(3, -8) + root(5, x)"));
+        assert_eq!("f(x, −1)", unicode("f(x, -1)"));
+        assert_eq!("[x², 1; 0, ½]", unicode("[x^2, 1; 0, 1/2]"));
+    }
+}
@@ -0,0 +1,494 @@
+//! Quantities with units, e.g. `3 m + 20 cm` or `5 km / 2 h`.
+//!
+//! A number followed by a unit like `20 cm` is an implicit product of the number and the
+//! unit symbol. Symbols of the unit table, which are no variables, make an expression a
+//! quantity. Sums convert the right operand into the unit of the left one, products
+//! combine the units.
+
+use crate::ast::{FunCall, Number, Operand, Operation, Term};
+use crate::calc::{calc_function_call, calc_operand, calc_operation, check_domain, Env};
+use crate::number::Num;
+
+use std::fmt;
+use thiserror::Error;
+
+#[derive(Debug, PartialEq, Eq, Error)]
+pub enum UnitError {
+    #[error("Cannot combine `{lhs}` with `{rhs}` - the units have different dimensions")]
+    IncompatibleUnits { lhs: String, rhs: String },
+    #[error("Expected an argument without unit for `{name}`, but got `{unit}`")]
+    UnitInArgument { name: String, unit: String },
+    #[error("Expected an exponent without unit, but got `{0}`")]
+    UnitInExponent(String),
+    #[error("Cannot raise `{unit}` to the power {exp}")]
+    FractionalPower { unit: String, exp: String },
+    #[error("Variables hold numbers without unit, but `{sym}` would be in `{unit}`")]
+    UnitInVariable { sym: String, unit: String },
+}
+
+/// exponents of length, mass, time, electric current and temperature
+type Dimension = [i8; 5];
+
+#[derive(Debug, PartialEq)]
+struct UnitDef {
+    symbol: &'static str,
+    /// value of one unit in SI base units
+    factor: f64,
+    dimension: Dimension,
+}
+
+const fn unit(symbol: &'static str, factor: f64, dimension: Dimension) -> UnitDef {
+    UnitDef {
+        symbol,
+        factor,
+        dimension,
+    }
+}
+
+const LENGTH: Dimension = [1, 0, 0, 0, 0];
+const MASS: Dimension = [0, 1, 0, 0, 0];
+const TIME: Dimension = [0, 0, 1, 0, 0];
+const CURRENT: Dimension = [0, 0, 0, 1, 0];
+const TEMPERATURE: Dimension = [0, 0, 0, 0, 1];
+const VOLUME: Dimension = [3, 0, 0, 0, 0];
+const FREQUENCY: Dimension = [0, 0, -1, 0, 0];
+const FORCE: Dimension = [1, 1, -2, 0, 0];
+const ENERGY: Dimension = [2, 1, -2, 0, 0];
+const POWER: Dimension = [2, 1, -3, 0, 0];
+const PRESSURE: Dimension = [-1, 1, -2, 0, 0];
+const VOLTAGE: Dimension = [2, 1, -3, -1, 0];
+
+/// `in` is no unit, because it is a keyword
+const UNITS: &[UnitDef] = &[
+    unit("m", 1.0, LENGTH),
+    unit("km", 1e3, LENGTH),
+    unit("cm", 1e-2, LENGTH),
+    unit("mm", 1e-3, LENGTH),
+    unit("um", 1e-6, LENGTH),
+    unit("nm", 1e-9, LENGTH),
+    unit("ft", 0.3048, LENGTH),
+    unit("yd", 0.9144, LENGTH),
+    unit("mi", 1609.344, LENGTH),
+    unit("kg", 1.0, MASS),
+    unit("g", 1e-3, MASS),
+    unit("mg", 1e-6, MASS),
+    unit("lb", 0.453_592_37, MASS),
+    unit("oz", 0.028_349_523_125, MASS),
+    unit("s", 1.0, TIME),
+    unit("ms", 1e-3, TIME),
+    unit("min", 60.0, TIME),
+    unit("h", 3600.0, TIME),
+    unit("day", 86400.0, TIME),
+    unit("A", 1.0, CURRENT),
+    unit("mA", 1e-3, CURRENT),
+    unit("K", 1.0, TEMPERATURE),
+    unit("L", 1e-3, VOLUME),
+    unit("mL", 1e-6, VOLUME),
+    unit("Hz", 1.0, FREQUENCY),
+    unit("N", 1.0, FORCE),
+    unit("J", 1.0, ENERGY),
+    unit("kJ", 1e3, ENERGY),
+    unit("W", 1.0, POWER),
+    unit("kW", 1e3, POWER),
+    unit("Pa", 1.0, PRESSURE),
+    unit("kPa", 1e3, PRESSURE),
+    unit("bar", 1e5, PRESSURE),
+    unit("V", 1.0, VOLTAGE),
+];
+
+fn unit_def(symbol: &str) -> Option<&'static UnitDef> {
+    UNITS.iter().find(|def| def.symbol == symbol)
+}
+
+/// Product of units with integer exponents, e.g. `km/h` or `kg*m/s^2`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Unit {
+    factors: Vec<(&'static UnitDef, i32)>,
+}
+
+impl Unit {
+    /// the unit `symbol` of the unit table, e.g. `cm`
+    pub fn of(symbol: &str) -> Option<Unit> {
+        unit_def(symbol).map(|def| Unit {
+            factors: vec![(def, 1)],
+        })
+    }
+
+    /// true for numbers without unit
+    pub fn is_none(&self) -> bool {
+        self.factors.is_empty()
+    }
+
+    /// value of one unit in SI base units
+    fn factor(&self) -> Number {
+        self.factors
+            .iter()
+            .map(|(def, exp)| Number::from_f64(def.factor).pow(*exp as Number))
+            .product()
+    }
+
+    fn dimension(&self) -> Dimension {
+        let mut dimension = Dimension::default();
+        for (def, exp) in &self.factors {
+            for (dim, base) in dimension.iter_mut().zip(def.dimension) {
+                *dim += base * *exp as i8;
+            }
+        }
+        dimension
+    }
+
+    /// The product of both units and the factor converting the product of the values
+    /// into it. Units of `rhs` are converted into units of the same dimension in `self`,
+    /// so `km * m` is `km^2`.
+    fn mul(&self, rhs: &Unit) -> (Unit, Number) {
+        let mut factors = self.factors.clone();
+        let mut conversion = 1.0;
+        for (def, exp) in &rhs.factors {
+            match factors
+                .iter_mut()
+                .find(|(other, _)| other.dimension == def.dimension)
+            {
+                Some((other, other_exp)) => {
+                    conversion *= Number::from_f64(def.factor / other.factor).pow(*exp as Number);
+                    *other_exp += exp;
+                }
+                None => factors.push((def, *exp)),
+            }
+        }
+        factors.retain(|(_, exp)| *exp != 0);
+        (Unit { factors }, conversion)
+    }
+
+    fn powi(&self, exp: i32) -> Unit {
+        Unit {
+            factors: self
+                .factors
+                .iter()
+                .map(|(def, factor_exp)| (*def, factor_exp * exp))
+                .collect(),
+        }
+    }
+
+    /// the unit raised to `exp`, whose products with the exponents of the unit are integers
+    fn pow(&self, exp: Number) -> Result<Unit, UnitError> {
+        let factors = self
+            .factors
+            .iter()
+            .map(|(def, factor_exp)| {
+                let power = *factor_exp as Number * exp;
+                if power.fract() == 0.0 {
+                    Ok((*def, power as i32))
+                } else {
+                    Err(UnitError::FractionalPower {
+                        unit: self.to_string(),
+                        exp: exp.to_string(),
+                    })
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Unit { factors })
+    }
+}
+
+/// A number with a unit, result of an expression like `5 km / 2 h`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantity {
+    pub value: Number,
+    pub unit: Unit,
+}
+
+impl Quantity {
+    fn number(value: Number) -> Quantity {
+        Quantity {
+            value,
+            unit: Unit::default(),
+        }
+    }
+
+    /// The value in `unit`, `None` if the dimensions of the units differ.
+    /// ```
+    /// use rust_expression::{Calculator, Unit};
+    /// let mut c = Calculator::new();
+    /// let speed = c.execute("36 km / 1 h").unwrap();
+    /// let m_per_s = c.execute("1 m / 1 s").unwrap();
+    /// let m_per_s = &m_per_s.as_quantity().unwrap().unit;
+    /// assert_eq!(Some(10.0), speed.as_quantity().unwrap().convert(m_per_s));
+    /// assert_eq!(None, speed.as_quantity().unwrap().convert(&Unit::of("m").unwrap()));
+    /// ```
+    pub fn convert(&self, unit: &Unit) -> Option<Number> {
+        (self.unit.dimension() == unit.dimension())
+            .then(|| self.value * self.unit.factor() / unit.factor())
+    }
+}
+
+impl fmt::Display for Unit {
+    /// e.g. `kg*m/s^2`, or `s^-1` without units in the numerator
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_none() {
+            return f.write_str("1");
+        }
+        let has_numerator = self.factors.iter().any(|(_, exp)| *exp > 0);
+        let mut first = true;
+        for (def, exp) in self.factors.iter().filter(|(_, exp)| *exp > 0) {
+            if !first {
+                f.write_str("*")?;
+            }
+            first = false;
+            write_power(f, def.symbol, *exp)?;
+        }
+        for (def, exp) in self.factors.iter().filter(|(_, exp)| *exp < 0) {
+            if has_numerator {
+                f.write_str("/")?;
+                write_power(f, def.symbol, -exp)?;
+            } else {
+                if !first {
+                    f.write_str("*")?;
+                }
+                first = false;
+                write_power(f, def.symbol, *exp)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_power(f: &mut fmt::Formatter<'_>, symbol: &str, exp: i32) -> fmt::Result {
+    match exp {
+        1 => f.write_str(symbol),
+        exp => write!(f, "{}^{}", symbol, exp),
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.unit.is_none() {
+            write!(f, "{}", self.value)
+        } else {
+            write!(f, "{} {}", self.value, self.unit)
+        }
+    }
+}
+
+/// a symbol of the unit table is a unit, unless it is defined as variable
+fn is_unit(sym: &str, env: &dyn Env) -> bool {
+    unit_def(sym).is_some()
+        && env.get(sym).is_none()
+        && env.get_list(sym).is_none()
+        && env.get_matrix(sym).is_none()
+        && env.get_text(sym).is_none()
+}
+
+/// true if the operand has to be evaluated by [`calc_quantity`]
+pub fn has_units(op: &Operand, env: &dyn Env) -> bool {
+    op.free_symbols().iter().any(|sym| is_unit(sym, env))
+}
+
+/// Evaluates an operand with units, operands without units are evaluated as numbers.
+pub fn calc_quantity(op: &Operand, env: &dyn Env) -> Result<Quantity, crate::Error> {
+    match op {
+        Operand::Symbol(sym) if is_unit(sym, env) => Ok(Quantity {
+            value: 1.0,
+            unit: Unit::of(sym).unwrap_or_default(),
+        }),
+        Operand::Term(term) => calc_quantity_term(term, env),
+        Operand::FunCall(fun_call) if has_units(op, env) => calc_quantity_call(fun_call, env),
+        op => Ok(Quantity::number(calc_operand(op, env)?)),
+    }
+}
+
+fn calc_quantity_term(term: &Term, env: &dyn Env) -> Result<Quantity, crate::Error> {
+    let lhs = calc_quantity(&term.lhs, env)?;
+    let rhs = calc_quantity(&term.rhs, env)?;
+    let quantity = match term.op {
+        Operation::Add | Operation::Sub | Operation::Rem => {
+            let rhs = rhs
+                .convert(&lhs.unit)
+                .ok_or_else(|| UnitError::IncompatibleUnits {
+                    lhs: lhs.unit.to_string(),
+                    rhs: rhs.unit.to_string(),
+                })?;
+            Quantity {
+                value: calc_operation(term.op, lhs.value, rhs, env)?,
+                unit: lhs.unit,
+            }
+        }
+        Operation::Mul => {
+            let (unit, conversion) = lhs.unit.mul(&rhs.unit);
+            Quantity {
+                value: calc_operation(term.op, lhs.value, rhs.value, env)? * conversion,
+                unit,
+            }
+        }
+        Operation::Div => {
+            let (unit, conversion) = lhs.unit.mul(&rhs.unit.powi(-1));
+            Quantity {
+                value: calc_operation(term.op, lhs.value, rhs.value, env)? * conversion,
+                unit,
+            }
+        }
+        Operation::Pow => {
+            if !rhs.unit.is_none() {
+                return Err(UnitError::UnitInExponent(rhs.unit.to_string()).into());
+            }
+            Quantity {
+                unit: lhs.unit.pow(rhs.value)?,
+                value: calc_operation(term.op, lhs.value, rhs.value, env)?,
+            }
+        }
+    };
+    Ok(quantity)
+}
+
+/// a symbol naming a function is passed as function, e.g. `h` in `apply_twice(h, 1)`
+fn is_function(op: &Operand, env: &dyn Env) -> bool {
+    matches!(op, Operand::Symbol(sym) if env.get_fun(sym).is_some() && env.get(sym).is_none())
+}
+
+/// `abs` and `sqrt` keep the unit, other functions expect arguments without unit
+fn calc_quantity_call(fun_call: &FunCall, env: &dyn Env) -> Result<Quantity, crate::Error> {
+    if fun_call.params.iter().any(|param| is_function(param, env)) {
+        let params = fun_call
+            .params
+            .iter()
+            .map(|param| match is_function(param, env) {
+                true => Ok(param.clone()),
+                false => calc_quantity(param, env).and_then(|param| number(fun_call, param)),
+            })
+            .collect::<Result<_, _>>()?;
+        let fun_call = FunCall {
+            name: fun_call.name.clone(),
+            params,
+        };
+        return Ok(Quantity::number(calc_function_call(&fun_call, env)?));
+    }
+    let params = fun_call
+        .params
+        .iter()
+        .map(|param| calc_quantity(param, env))
+        .collect::<Result<Vec<_>, _>>()?;
+    match (fun_call.name.as_str(), &params[..]) {
+        ("abs", [param]) => {
+            return Ok(Quantity {
+                value: param.value.abs(),
+                unit: param.unit.clone(),
+            })
+        }
+        ("sqrt", [param]) => {
+            return Ok(Quantity {
+                unit: param.unit.pow(0.5)?,
+                value: check_domain("sqrt", &[param.value], param.value.sqrt(), env)?,
+            })
+        }
+        _ => {}
+    }
+    let params = params
+        .into_iter()
+        .map(|param| number(fun_call, param))
+        .collect::<Result<_, _>>()?;
+    let fun_call = FunCall {
+        name: fun_call.name.clone(),
+        params,
+    };
+    Ok(Quantity::number(calc_function_call(&fun_call, env)?))
+}
+
+/// the argument of `fun_call` as number, if it has no unit
+fn number(fun_call: &FunCall, param: Quantity) -> Result<Operand, crate::Error> {
+    if param.unit.is_none() {
+        Ok(Operand::Number(param.value))
+    } else {
+        Err(UnitError::UnitInArgument {
+            name: fun_call.name.clone(),
+            unit: param.unit.to_string(),
+        }
+        .into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::nearly_equal;
+    use crate::calc::{CalcError, TopLevelEnv};
+    use crate::parser::parse_expression;
+
+    fn quantity(expr: &str) -> Result<Quantity, crate::Error> {
+        calc_quantity(&parse_expression(expr).unwrap(), &TopLevelEnv::default())
+    }
+
+    #[test]
+    fn units_of_products() {
+        assert_eq!(
+            "kg*m/s^2",
+            quantity("1 kg * m / s^2").unwrap().unit.to_string()
+        );
+        assert_eq!("s^-1", quantity("10 / 2 s").unwrap().unit.to_string());
+        let area = quantity("2 km * 500 m").unwrap();
+        assert_eq!("1 km^2", area.to_string());
+        let ratio = quantity("1 km / 1 m").unwrap();
+        assert!(ratio.unit.is_none());
+        assert!(nearly_equal(1000.0, ratio.value, 16.0 * Number::EPSILON));
+    }
+
+    #[test]
+    fn dimension_errors() {
+        assert_eq!(
+            Err(crate::Error::UnitError(UnitError::IncompatibleUnits {
+                lhs: "m".to_string(),
+                rhs: "s".to_string()
+            })),
+            quantity("1 m + 2 s")
+        );
+        assert_eq!(
+            Err(crate::Error::UnitError(UnitError::FractionalPower {
+                unit: "m".to_string(),
+                exp: "0.5".to_string()
+            })),
+            quantity("sqrt(4 m)")
+        );
+        assert!(matches!(
+            quantity("sin(2 m)"),
+            Err(crate::Error::UnitError(UnitError::UnitInArgument { .. }))
+        ));
+    }
+
+    #[test]
+    fn domain_errors() {
+        assert_eq!(
+            Err(crate::Error::CalcError(CalcError::DivisionByZero)),
+            quantity("1 m / 0")
+        );
+        assert_eq!(
+            Err(crate::Error::CalcError(CalcError::DivisionByZero)),
+            quantity("0 m / 0 m")
+        );
+        assert_eq!(
+            Err(crate::Error::CalcError(CalcError::DivisionByZero)),
+            quantity("3 m % 0 cm")
+        );
+        assert_eq!(
+            Err(crate::Error::CalcError(CalcError::DomainError {
+                function: "sqrt".to_string(),
+                argument: "-1".to_string()
+            })),
+            quantity("sqrt(-1 m^2)")
+        );
+        assert!(matches!(
+            quantity("(-4 m^2) ^ 1.5"),
+            Err(crate::Error::CalcError(CalcError::DomainError { .. }))
+        ));
+        let mut env = TopLevelEnv::default();
+        env.set_ieee(true);
+        let infinite = calc_quantity(&parse_expression("1 m / 0").unwrap(), &env).unwrap();
+        assert_eq!(Number::INFINITY, infinite.value);
+    }
+
+    #[test]
+    fn variables_shadow_units() {
+        let mut env = TopLevelEnv::default();
+        let op = parse_expression("2 h").unwrap();
+        assert!(has_units(&op, &env));
+        env.put("h".to_string(), 3.0).unwrap();
+        assert!(!has_units(&op, &env));
+    }
+}
@@ -0,0 +1,76 @@
+//! Non-fatal warnings about successfully executed statements.
+
+use crate::ast::*;
+use crate::calc::{calc_operand, Env, ScopedEnv, TopLevelEnv};
+use crate::diagnostic::Diagnostic;
+use crate::Value;
+
+/// relative residual of a solution above which the solution is reported
+const MAX_RESIDUAL: Number = 1e-6;
+
+/// Warnings, which must be checked before `st` changes the environment.
+pub(crate) fn before(st: &Statement, env: &TopLevelEnv) -> Vec<Diagnostic> {
+    let name = match st {
+        Statement::Function { name, .. } => name,
+        Statement::FitLinear {
+            name: Some(name), ..
+        } => name,
+        _ => return Vec::new(),
+    };
+    match env.get_fun(name) {
+        Some(Function::BuildIn(_)) | Some(Function::Data(_)) => vec![Diagnostic::warning(
+            "W0002",
+            format!("Function `{}` shadows a build-in function", name),
+        )],
+        _ => Vec::new(),
+    }
+}
+
+fn not_finite(what: impl std::fmt::Display) -> Diagnostic {
+    Diagnostic::warning("W0001", format!("{} is not finite", what))
+}
+
+/// Warnings about the `value` of the executed statement `st`.
+pub(crate) fn after(st: &Statement, value: &Value, env: &TopLevelEnv) -> Vec<Diagnostic> {
+    let mut warnings = Vec::new();
+    match value {
+        Value::Number(num) if !num.is_finite() => warnings.push(not_finite("Result")),
+        Value::List(list) | Value::Solutions(list) if list.iter().any(|num| !num.is_finite()) => {
+            warnings.push(not_finite("Result"))
+        }
+        Value::Solved { variable, value } if !value.is_finite() => {
+            warnings.push(not_finite(format!("Solution for `{}`", variable)))
+        }
+        _ => {}
+    }
+    match st {
+        Statement::Assignment { sym, .. } => match env.get(sym) {
+            Some(num) if !num.is_finite() => {
+                warnings.push(not_finite(format!("Value of `{}`", sym)))
+            }
+            _ => {}
+        },
+        Statement::SolveFor { lhs, rhs, sym, .. } => {
+            if let Value::Solved { value, .. } = value {
+                let scope = ScopedEnv::new(env, [(sym.as_str(), *value)]);
+                if let (Ok(lhs), Ok(rhs)) = (
+                    calc_operand::<Number>(lhs, &scope),
+                    calc_operand::<Number>(rhs, &scope),
+                ) {
+                    let residual = (lhs - rhs).abs();
+                    if residual > MAX_RESIDUAL * (1.0 + lhs.abs().max(rhs.abs())) {
+                        warnings.push(Diagnostic::warning(
+                            "W0003",
+                            format!(
+                                "Solution for `{}` leaves a residual of {} between both sides",
+                                sym, residual
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+    warnings
+}
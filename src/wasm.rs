@@ -0,0 +1,273 @@
+//! Bindings for JavaScript with `wasm-bindgen`, e.g. for the web app.
+//!
+//! Results are plain objects and plots are typed arrays of screen coordinates,
+//! so web frontends need no glue for the Rust types.
+
+use crate::{Area, Axis, Calculator, Graph, Num, Number, Plot, Value};
+
+use wasm_bindgen::prelude::*;
+
+/// Result of [`JsCalculator::execute`].
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsResult {
+    kind: String,
+    text: String,
+    value: Option<f64>,
+    values: Vec<f64>,
+    code: Option<String>,
+}
+
+#[wasm_bindgen]
+impl JsResult {
+    /// kind of the value, e.g. `number` or `graph`, or `error`
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> String {
+        self.kind.clone()
+    }
+
+    /// the value or the error message for humans
+    #[wasm_bindgen(getter)]
+    pub fn text(&self) -> String {
+        self.text.clone()
+    }
+
+    /// the number of a result or solution
+    #[wasm_bindgen(getter)]
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+
+    /// the elements of a list or a matrix in row-major order
+    #[wasm_bindgen(getter)]
+    pub fn values(&self) -> Vec<f64> {
+        self.values.clone()
+    }
+
+    /// the stable code of an error, e.g. `C0001`
+    #[wasm_bindgen(getter)]
+    pub fn code(&self) -> Option<String> {
+        self.code.clone()
+    }
+}
+
+impl From<&Value> for JsResult {
+    fn from(value: &Value) -> Self {
+        let values = match value {
+            Value::Matrix(matrix) => matrix.elements(),
+            value => value.as_list().unwrap_or_default(),
+        };
+        JsResult {
+            kind: format!("{:?}", value.kind()).to_lowercase(),
+            text: value.to_string(),
+            value: value.as_number().map(Num::to_f64),
+            values: values.iter().map(|num| Num::to_f64(*num)).collect(),
+            code: None,
+        }
+    }
+}
+
+/// Plot of a graph in screen coordinates.
+#[wasm_bindgen]
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsPlot {
+    name: String,
+    points: Vec<f64>,
+    x_axis: Option<f64>,
+    y_axis: Option<f64>,
+    x_tics: Vec<f64>,
+    y_tics: Vec<f64>,
+}
+
+/// pairs of position and label of the major tics
+fn tics(axis: &Option<Axis>) -> Vec<f64> {
+    axis.iter()
+        .flat_map(|axis| &axis.tics)
+        .filter(|tic| !tic.minor)
+        .flat_map(|tic| [Num::to_f64(tic.pos), Num::to_f64(tic.label)])
+        .collect()
+}
+
+impl From<&Plot> for JsPlot {
+    fn from(plot: &Plot) -> Self {
+        JsPlot {
+            name: plot.name.clone(),
+            points: plot
+                .points
+                .iter()
+                .flat_map(|point| match point {
+                    Some((x, y)) => [Num::to_f64(*x), Num::to_f64(*y)],
+                    None => [f64::NAN, f64::NAN],
+                })
+                .collect(),
+            x_axis: plot.x_axis.as_ref().map(|axis| Num::to_f64(axis.pos)),
+            y_axis: plot.y_axis.as_ref().map(|axis| Num::to_f64(axis.pos)),
+            x_tics: tics(&plot.x_axis),
+            y_tics: tics(&plot.y_axis),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl JsPlot {
+    /// name of the plotted series, e.g. `f(x)`
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// pairs of `x` and `y`, both `NaN` where the function is not defined or jumps
+    #[wasm_bindgen(getter)]
+    pub fn points(&self) -> Vec<f64> {
+        self.points.clone()
+    }
+
+    /// `y` of the horizontal axis, if it is visible
+    #[wasm_bindgen(getter, js_name = xAxis)]
+    pub fn x_axis(&self) -> Option<f64> {
+        self.x_axis
+    }
+
+    /// `x` of the vertical axis, if it is visible
+    #[wasm_bindgen(getter, js_name = yAxis)]
+    pub fn y_axis(&self) -> Option<f64> {
+        self.y_axis
+    }
+
+    /// pairs of `x` and label of the tics on the horizontal axis
+    #[wasm_bindgen(getter, js_name = xTics)]
+    pub fn x_tics(&self) -> Vec<f64> {
+        self.x_tics.clone()
+    }
+
+    /// pairs of `y` and label of the tics on the vertical axis
+    #[wasm_bindgen(getter, js_name = yTics)]
+    pub fn y_tics(&self) -> Vec<f64> {
+        self.y_tics.clone()
+    }
+}
+
+/// [`Calculator`] for JavaScript, which keeps the graph of the last `plot`.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct JsCalculator {
+    calc: Calculator,
+    graph: Option<Graph>,
+}
+
+#[wasm_bindgen]
+impl JsCalculator {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> JsCalculator {
+        JsCalculator::default()
+    }
+
+    /// Executes a statement, errors are results of kind `error`.
+    pub fn execute(&mut self, line: &str) -> JsResult {
+        match self.calc.execute(line) {
+            Ok(Value::Graph(graph)) => {
+                let result = JsResult::from(&Value::Void);
+                self.graph = Some(graph);
+                JsResult {
+                    kind: "graph".to_string(),
+                    ..result
+                }
+            }
+            Ok(value) => JsResult::from(&value),
+            Err(err) => JsResult {
+                kind: "error".to_string(),
+                text: err.to_string(),
+                value: None,
+                values: Vec::new(),
+                code: Some(err.diagnostic().code.to_string()),
+            },
+        }
+    }
+
+    /// Names and keywords completing the name before `cursor`, a byte offset in `line`.
+    pub fn complete(&self, line: &str, cursor: usize) -> Vec<String> {
+        self.calc
+            .complete(line, cursor)
+            .into_iter()
+            .map(|completion| completion.text)
+            .collect()
+    }
+
+    /// The signature of the function `name`, e.g. `atan2(y, x)`.
+    pub fn hint(&self, name: &str) -> Option<String> {
+        self.calc.hint(name)
+    }
+
+    /// Plots the graph of the last `plot` statement in the area from `(x_min, y_min)` to
+    /// `(x_max, y_max)` onto a screen of `width` times `height` pixels,
+    /// `None` without a graph or for an empty area or screen.
+    pub fn plot(
+        &self,
+        x_min: f64,
+        y_min: f64,
+        x_max: f64,
+        y_max: f64,
+        width: f64,
+        height: f64,
+    ) -> Option<JsPlot> {
+        let area = Area::try_new(
+            Number::from_f64(x_min),
+            Number::from_f64(y_min),
+            Number::from_f64(x_max),
+            Number::from_f64(y_max),
+        )
+        .ok()?;
+        let screen =
+            Area::try_new(0.0, 0.0, Number::from_f64(width), Number::from_f64(height)).ok()?;
+        let plot = self.graph.as_ref()?.plot(&area, &screen).ok()?;
+        Some(JsPlot::from(&plot))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn execute_results() {
+        let mut calc = JsCalculator::new();
+        let result = calc.execute("1 + 2");
+        assert_eq!(
+            ("number", "3", Some(3.0)),
+            (&result.kind()[..], &result.text()[..], result.value())
+        );
+        let result = calc.execute("[1, 2; 3, 4]");
+        assert_eq!(
+            ("matrix", vec![1.0, 2.0, 3.0, 4.0]),
+            (&result.kind()[..], result.values())
+        );
+        let result = calc.execute("a + 1");
+        assert_eq!(
+            ("error", Some("C0001".to_string())),
+            (&result.kind()[..], result.code())
+        );
+    }
+
+    #[test]
+    fn completions_and_hints() {
+        let mut calc = JsCalculator::new();
+        calc.execute("speed := 3");
+        assert_eq!(vec!["speed"], calc.complete("2 * spe", 7));
+        assert_eq!(Some("atan2(y, x)".to_string()), calc.hint("atan2"));
+    }
+
+    #[test]
+    fn plot_last_graph() {
+        let mut calc = JsCalculator::new();
+        assert_eq!(None, calc.plot(-1.0, -1.0, 1.0, 1.0, 20.0, 10.0));
+        calc.execute("f(x) := x");
+        assert_eq!("graph", calc.execute("plot f").kind());
+        let plot = calc.plot(-1.0, -1.0, 1.0, 1.0, 20.0, 10.0).unwrap();
+        assert_eq!("f(x)", plot.name());
+        assert_eq!(&[0.0, 0.0], &plot.points()[..2]);
+        assert_eq!(Some(5.0), plot.x_axis());
+        assert_eq!(Some(10.0), plot.y_axis());
+        assert!(plot.x_tics().chunks(2).any(|tic| tic == [10.0, 0.0]));
+        assert_eq!(None, calc.plot(1.0, -1.0, 1.0, 1.0, 20.0, 10.0));
+    }
+}